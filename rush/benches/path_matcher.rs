@@ -0,0 +1,72 @@
+//! Times PathMatcher on a generated monorepo: `cargo bench --bench path_matcher`
+
+use rush_core::path_matcher::PathMatcher;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+const PRODUCTS: usize = 20;
+const COMPONENTS: usize = 10;
+const FILES: usize = 50;
+
+/// Products with components holding sources, build output and node_modules, with a
+/// .gitignore at the root and in every component. Returns the files.
+fn generate(root: &Path) -> Vec<PathBuf> {
+    fs::write(root.join(".gitignore"), "target/\n*.log\n.DS_Store\n").unwrap();
+    let mut files = Vec::new();
+    for product in 0..PRODUCTS {
+        for component in 0..COMPONENTS {
+            let component = root.join(format!("products/p{product}/c{component}"));
+            fs::create_dir_all(&component).unwrap();
+            fs::write(
+                component.join(".gitignore"),
+                "node_modules\ndist/\n!dist/keep.txt\n**/*.tmp\n",
+            )
+            .unwrap();
+            for directory in ["src/handlers", "dist", "node_modules/lib", "target/debug"] {
+                for file in 0..FILES {
+                    files.push(component.join(directory).join(format!("f{file}.rs")));
+                }
+            }
+        }
+    }
+    files
+}
+
+fn main() {
+    let root = tempfile::TempDir::new().unwrap();
+    let files = generate(root.path());
+
+    let started = Instant::now();
+    let gitignore = PathMatcher::from_gitignore(root.path());
+    println!("from_gitignore: {:?}", started.elapsed());
+
+    let started = Instant::now();
+    let ignored = files.iter().filter(|file| gitignore.matches(file)).count();
+    let elapsed = started.elapsed();
+    println!(
+        "matches: {} of {} paths ignored in {:?}, {:?} per path",
+        ignored,
+        files.len(),
+        elapsed,
+        elapsed / files.len() as u32
+    );
+
+    let watch = PathMatcher::new(
+        root.path(),
+        vec![
+            "products/*/c1/src/**/*.rs".to_string(),
+            "*.toml".to_string(),
+        ],
+    );
+    let started = Instant::now();
+    let watched = files.iter().filter(|file| watch.matches(file)).count();
+    let elapsed = started.elapsed();
+    println!(
+        "watch: {} of {} paths matched in {:?}, {:?} per path",
+        watched,
+        files.len(),
+        elapsed,
+        elapsed / files.len() as u32
+    );
+}
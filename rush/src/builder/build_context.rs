@@ -1,7 +1,7 @@
 use crate::builder::BuildType;
 use crate::container::ServicesSpec;
 use crate::toolchain::Platform;
-use crate::ToolchainContext;
+use crate::toolchain::ToolchainContext;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::str;
@@ -13,7 +13,13 @@ pub struct BuildContext {
     pub target: Platform,
     pub host: Platform,
     pub rust_target: String,
+    /// Docker architecture of the target, amd64 or arm64, e.g. for a
+    /// `kubernetes.io/arch` node selector
+    pub target_arch: String,
     pub toolchain: ToolchainContext,
+    /// sccache wrapping rustc, when RUST_BUILD_CACHE is sccache and it is installed
+    pub sccache: Option<String>,
+    pub sccache_dir: String,
     pub services: ServicesSpec,
 
     pub environment: String,
@@ -23,8 +29,17 @@ pub struct BuildContext {
     pub component: String,
     pub docker_registry: String,
     pub image_name: String,
+    /// Registry digest of the pushed image, `sha256:...`, when PIN_IMAGE_DIGESTS is set.
+    /// `image_name` then ends in `@sha256:...` too.
+    pub image_digest: Option<String>,
+
+    pub kube_context: String,
+    /// Per cluster values from `stack.clusters.yaml`
+    pub cluster: HashMap<String, String>,
 
     pub domains: HashMap<String, String>,
     pub env: HashMap<String, String>,
     pub secrets: HashMap<String, String>,
+    /// Whether the binary is built for a debugger, see `debug` in stack.spec.yaml
+    pub debug: bool,
 }
@@ -46,8 +46,13 @@ impl BuildScript {
                     }
                 }
             }
-            BuildType::RustBinary { .. } => {
-                match TEMPLATES.render("build/rust_binary.sh", &context) {
+            BuildType::RustBinary { cross, .. } => {
+                let template = if *cross {
+                    "build/rust_binary_cross.sh"
+                } else {
+                    "build/rust_binary.sh"
+                };
+                match TEMPLATES.render(template, &context) {
                     Ok(s) => s,
                     Err(e) => {
                         println!("Error: {}", e);
@@ -15,6 +15,9 @@ pub enum BuildType {
         location: String,
         dockerfile_path: String,
         context_dir: Option<String>,
+        /// Builds with cross in a container rather than with the host's toolchain
+        #[serde(default)]
+        cross: bool,
     },
     Script {
         location: String,
@@ -46,3 +49,46 @@ pub enum BuildType {
         namespace: String,
     },
 }
+
+impl BuildType {
+    /// The directory of the component's sources, relative to the product directory.
+    pub fn location(&self) -> Option<&str> {
+        match self {
+            BuildType::TrunkWasm { location, .. }
+            | BuildType::DixiousWasm { location, .. }
+            | BuildType::RustBinary { location, .. }
+            | BuildType::Zola { location, .. }
+            | BuildType::Book { location, .. }
+            | BuildType::Script { location, .. } => Some(location),
+            _ => None,
+        }
+    }
+
+    /// The Dockerfile of build types rush builds an image for, relative to the product directory.
+    pub fn dockerfile_path(&self) -> Option<&str> {
+        match self {
+            BuildType::TrunkWasm {
+                dockerfile_path, ..
+            }
+            | BuildType::DixiousWasm {
+                dockerfile_path, ..
+            }
+            | BuildType::RustBinary {
+                dockerfile_path, ..
+            }
+            | BuildType::Script {
+                dockerfile_path, ..
+            }
+            | BuildType::Zola {
+                dockerfile_path, ..
+            }
+            | BuildType::Book {
+                dockerfile_path, ..
+            }
+            | BuildType::Ingress {
+                dockerfile_path, ..
+            } => Some(dockerfile_path),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,186 @@
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+#[derive(Deserialize)]
+struct Metadata {
+    packages: Vec<Package>,
+    workspace_root: PathBuf,
+}
+
+#[derive(Deserialize)]
+struct Package {
+    manifest_path: PathBuf,
+    dependencies: Vec<Dependency>,
+}
+
+#[derive(Deserialize)]
+struct Dependency {
+    /// Set for path dependencies, which are the only ones that change with the sources
+    path: Option<PathBuf>,
+}
+
+/// The crates of a cargo workspace a Rust component is built from, read with
+/// `cargo metadata`, so that dev mode only rebuilds components a change can affect.
+#[derive(Debug)]
+pub struct CrateDependencies {
+    /// Directories of the component's crate and the local crates it depends on
+    closure: HashSet<PathBuf>,
+    /// Directories of every crate of the workspace
+    crates: Vec<PathBuf>,
+    /// Cargo.toml and Cargo.lock of the workspace
+    workspace_files: Vec<PathBuf>,
+}
+
+impl CrateDependencies {
+    /// Reads the workspace of the crate in `crate_dir`.
+    pub fn load(crate_dir: &Path) -> Result<Self, String> {
+        let crate_dir = std::fs::canonicalize(crate_dir)
+            .map_err(|e| format!("Failed to resolve {}: {}", crate_dir.display(), e))?;
+        let output = Command::new("cargo")
+            .args([
+                "metadata",
+                "--format-version",
+                "1",
+                "--no-deps",
+                "--offline",
+            ])
+            .current_dir(&crate_dir)
+            .output()
+            .map_err(|e| format!("Failed to run cargo metadata: {}", e))?;
+        if !output.status.success() {
+            return Err(format!(
+                "cargo metadata failed in {}: {}",
+                crate_dir.display(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        let metadata: Metadata = serde_json::from_slice(&output.stdout)
+            .map_err(|e| format!("Failed to parse cargo metadata: {}", e))?;
+        Ok(Self::from_metadata(metadata, crate_dir))
+    }
+
+    fn from_metadata(metadata: Metadata, crate_dir: PathBuf) -> Self {
+        let package_dir = |package: &Package| {
+            package
+                .manifest_path
+                .parent()
+                .map(Path::to_path_buf)
+                .unwrap_or_default()
+        };
+
+        let mut closure = HashSet::new();
+        let mut pending = vec![crate_dir];
+        while let Some(dir) = pending.pop() {
+            if !closure.insert(dir.clone()) {
+                continue;
+            }
+            // Path dependencies outside the workspace are not listed, so their own
+            // dependencies are not followed
+            if let Some(package) = metadata.packages.iter().find(|p| package_dir(p) == dir) {
+                pending.extend(package.dependencies.iter().filter_map(|d| d.path.clone()));
+            }
+        }
+
+        Self {
+            closure,
+            crates: metadata.packages.iter().map(package_dir).collect(),
+            workspace_files: vec![
+                metadata.workspace_root.join("Cargo.toml"),
+                metadata.workspace_root.join("Cargo.lock"),
+            ],
+        }
+    }
+
+    /// Whether a change to `file`, an absolute path, affects the component: `Some(true)`
+    /// for its crates and the workspace manifest, `Some(false)` for other crates of the
+    /// workspace and `None` for files outside any crate, e.g. its Dockerfile.
+    pub fn affects(&self, file: &Path) -> Option<bool> {
+        if self.workspace_files.iter().any(|f| f == file) {
+            return Some(true);
+        }
+        // The innermost crate owns the file, as crates may be nested in one another
+        let owner = self
+            .crates
+            .iter()
+            .chain(self.closure.iter())
+            .filter(|dir| file.starts_with(dir))
+            .max_by_key(|dir| dir.components().count())?;
+        Some(self.closure.contains(owner))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A workspace where `server` depends on `api`, which depends on `types`, and
+    /// `webui` is a sibling with a crate nested in it.
+    fn workspace() -> CrateDependencies {
+        let metadata: Metadata = serde_json::from_value(serde_json::json!({
+            "workspace_root": "/ws",
+            "packages": [
+                {
+                    "manifest_path": "/ws/server/Cargo.toml",
+                    "dependencies": [{ "path": "/ws/api" }, { "path": null }]
+                },
+                {
+                    "manifest_path": "/ws/api/Cargo.toml",
+                    "dependencies": [{ "path": "/ws/types" }]
+                },
+                { "manifest_path": "/ws/types/Cargo.toml", "dependencies": [] },
+                { "manifest_path": "/ws/webui/Cargo.toml", "dependencies": [] },
+                {
+                    "manifest_path": "/ws/webui/plugin/Cargo.toml",
+                    "dependencies": [{ "path": "/ws/server" }]
+                }
+            ]
+        }))
+        .unwrap();
+        CrateDependencies::from_metadata(metadata, PathBuf::from("/ws/server"))
+    }
+
+    #[test]
+    fn test_affects_the_crates_it_depends_on() {
+        let crates = workspace();
+        assert_eq!(
+            crates.affects(Path::new("/ws/server/src/main.rs")),
+            Some(true)
+        );
+        assert_eq!(crates.affects(Path::new("/ws/api/src/lib.rs")), Some(true));
+        assert_eq!(
+            crates.affects(Path::new("/ws/types/src/lib.rs")),
+            Some(true)
+        );
+    }
+
+    #[test]
+    fn test_does_not_affect_other_crates() {
+        let crates = workspace();
+        assert_eq!(
+            crates.affects(Path::new("/ws/webui/src/main.rs")),
+            Some(false)
+        );
+        // Owned by the nested crate, which depends on the component rather than the
+        // other way around
+        assert_eq!(
+            crates.affects(Path::new("/ws/webui/plugin/src/lib.rs")),
+            Some(false)
+        );
+    }
+
+    #[test]
+    fn test_affects_the_workspace_manifest() {
+        let crates = workspace();
+        assert_eq!(crates.affects(Path::new("/ws/Cargo.toml")), Some(true));
+        assert_eq!(crates.affects(Path::new("/ws/Cargo.lock")), Some(true));
+    }
+
+    #[test]
+    fn test_leaves_files_outside_crates_to_the_context() {
+        let crates = workspace();
+        assert_eq!(crates.affects(Path::new("/ws/Dockerfile")), None);
+        assert_eq!(crates.affects(Path::new("/elsewhere/main.rs")), None);
+    }
+}
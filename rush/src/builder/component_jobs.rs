@@ -0,0 +1,40 @@
+use serde::{Deserialize, Serialize};
+
+/// Kubernetes Jobs run around applying a component's manifests by `rush apply` and
+/// `rush deploy`, e.g. database migrations. Each is a Job template relative to the
+/// component's `k8s` directory, in a subdirectory so it is not applied with the other
+/// manifests, and rendered like them.
+///
+/// ```yaml
+/// jobs:
+///   pre_apply: [jobs/migrate.yaml]
+///   post_apply: [jobs/seed.yaml]
+///   timeout_seconds: 600
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ComponentJobs {
+    /// Run before the manifests are applied, which are not when one fails
+    #[serde(default)]
+    pub pre_apply: Vec<String>,
+    /// Run once the manifests are applied
+    #[serde(default)]
+    pub post_apply: Vec<String>,
+    /// How long each Job may take to complete
+    #[serde(default = "default_timeout_seconds")]
+    pub timeout_seconds: u64,
+}
+
+impl Default for ComponentJobs {
+    fn default() -> Self {
+        Self {
+            pre_apply: Vec::new(),
+            post_apply: Vec::new(),
+            timeout_seconds: default_timeout_seconds(),
+        }
+    }
+}
+
+fn default_timeout_seconds() -> u64 {
+    600
+}
@@ -1,5 +1,9 @@
+use super::{TagStrategy, SHARED_DIRECTORY};
+use crate::error::RushError;
 use log::trace;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
 use std::sync::Arc;
 use tera::Context;
 use tera::Tera;
@@ -19,25 +23,72 @@ pub struct Config {
     product_path: String,
     network_name: String,
     environment: String,
+    environments: Vec<String>,
+    protected_environments: Vec<String>,
+    clean_environments: Vec<String>,
     domain_template: String,
-    kube_context: String,
+    domain: String,
+    kube_contexts: Vec<String>,
     infrastructure_repository: String,
+    infrastructure_path: String,
+    infrastructure_branch: Option<String>,
+    rollout_mode: String,
+    flux_source_name: String,
+    flux_namespace: String,
+    network_policies: bool,
+    ingress_controller_namespace: String,
+    ingress_kind: String,
+    ingress_class: Option<String>,
+    gateway: String,
+    gateway_namespace: Option<String>,
+    cert_manager_issuer: Option<String>,
+    cert_manager_issuer_kind: String,
     docker_registry: String,
+    docker_context: Option<String>,
     root_path: String,
     vault_name: String,
     k8s_encoder: String,
+    kubeseal_cert: Option<String>,
+    kubeseal_controller_namespace: Option<String>,
+    kubeseal_controller_name: Option<String>,
     one_password_account: Option<String>,
+    one_password_vaults: HashMap<String, String>,
+    local_cluster: String,
+    local_cluster_name: String,
+    rust_build_cache: String,
+    terraform_directory: Option<String>,
+    terraform_binary: String,
+    deploy_webhooks: Vec<String>,
+    tag_strategy: TagStrategy,
+    push_concurrency: usize,
+    pin_image_digests: bool,
+    secret_leaks: String,
     start_port: u16,
+    variable_overrides: HashMap<String, String>,
 }
 
 impl Config {
     pub fn start_port(&self) -> u16 {
         self.start_port
     }
+    /// Variables given with `--var`, taking precedence over variables.yaml.
+    pub fn variable_overrides(&self) -> &HashMap<String, String> {
+        &self.variable_overrides
+    }
     pub fn k8s_encoder(&self) -> &str {
         &self.k8s_encoder
     }
 
+    /// Certificate file or URL kubeseal seals with, from KUBESEAL_CERT.
+    pub fn kubeseal_cert(&self) -> Option<&String> {
+        self.kubeseal_cert.as_ref()
+    }
+    pub fn kubeseal_controller_namespace(&self) -> Option<&String> {
+        self.kubeseal_controller_namespace.as_ref()
+    }
+    pub fn kubeseal_controller_name(&self) -> Option<&String> {
+        self.kubeseal_controller_name.as_ref()
+    }
     pub fn vault_name(&self) -> &str {
         &self.vault_name
     }
@@ -57,120 +108,331 @@ impl Config {
     pub fn environment(&self) -> &str {
         &self.environment
     }
+    /// The environments declared in `ENVIRONMENTS`, defaulting to local, dev, staging and prod.
+    pub fn environments(&self) -> &[String] {
+        &self.environments
+    }
+    /// Whether changes to the cluster of the current environment need a confirmation.
+    pub fn is_protected(&self) -> bool {
+        self.protected_environments.contains(&self.environment)
+    }
+    /// Whether images may only be pushed from a committed product directory.
+    pub fn requires_clean_tree(&self) -> bool {
+        self.clean_environments.contains(&self.environment)
+    }
     pub fn domain_template(&self) -> &str {
         &self.domain_template
     }
+    /// The first of the environment's kube contexts.
     pub fn kube_context(&self) -> &str {
-        &self.kube_context
+        &self.kube_contexts[0]
+    }
+    pub fn kube_contexts(&self) -> &[String] {
+        &self.kube_contexts
     }
     pub fn infrastructure_repository(&self) -> &str {
         &self.infrastructure_repository
     }
+    pub fn infrastructure_path(&self) -> &str {
+        &self.infrastructure_path
+    }
+    pub fn infrastructure_branch(&self) -> Option<&String> {
+        self.infrastructure_branch.as_ref()
+    }
+    /// Directory of the Terraform or OpenTofu configuration applied before deploy and rollout.
+    pub fn terraform_directory(&self) -> Option<&str> {
+        self.terraform_directory.as_deref()
+    }
+    pub fn terraform_binary(&self) -> &str {
+        &self.terraform_binary
+    }
+    /// Webhooks notified when a deploy or rollout succeeds or fails.
+    pub fn deploy_webhooks(&self) -> &[String] {
+        &self.deploy_webhooks
+    }
+    /// How the images are tagged, from <ENV>_TAG_STRATEGY or TAG_STRATEGY.
+    pub fn tag_strategy(&self) -> TagStrategy {
+        self.tag_strategy
+    }
+    /// How many images are pushed at the same time, from PUSH_CONCURRENCY.
+    pub fn push_concurrency(&self) -> usize {
+        self.push_concurrency
+    }
+    /// Whether rendered manifests refer to images by digest, from <ENV>_PIN_IMAGE_DIGESTS
+    /// or PIN_IMAGE_DIGESTS.
+    pub fn pin_image_digests(&self) -> bool {
+        self.pin_image_digests
+    }
+    /// What `apply` and `rollout` do about secrets in plain text in the manifests when
+    /// they are not encrypted, from <ENV>_SECRET_LEAKS or SECRET_LEAKS: fail, warn or
+    /// ignore. Protected environments fail by default, the others warn.
+    pub fn secret_leaks(&self) -> &str {
+        &self.secret_leaks
+    }
+    pub fn network_policies(&self) -> bool {
+        self.network_policies
+    }
+    pub fn ingress_controller_namespace(&self) -> &str {
+        &self.ingress_controller_namespace
+    }
+    pub fn ingress_kind(&self) -> &str {
+        &self.ingress_kind
+    }
+    pub fn ingress_class(&self) -> Option<&String> {
+        self.ingress_class.as_ref()
+    }
+    pub fn gateway(&self) -> &str {
+        &self.gateway
+    }
+    pub fn gateway_namespace(&self) -> Option<&String> {
+        self.gateway_namespace.as_ref()
+    }
+    pub fn cert_manager_issuer(&self) -> Option<&String> {
+        self.cert_manager_issuer.as_ref()
+    }
+    pub fn cert_manager_issuer_kind(&self) -> &str {
+        &self.cert_manager_issuer_kind
+    }
+    pub fn rollout_mode(&self) -> &str {
+        &self.rollout_mode
+    }
+    pub fn flux_source_name(&self) -> &str {
+        &self.flux_source_name
+    }
+    pub fn flux_namespace(&self) -> &str {
+        &self.flux_namespace
+    }
     pub fn docker_registry(&self) -> &str {
         &self.docker_registry
     }
+    /// Docker context, i.e. daemon, the environment builds and runs containers with,
+    /// from <ENV>_DOCKER_CONTEXT. The current context is used when unset.
+    pub fn docker_context(&self) -> Option<&str> {
+        self.docker_context.as_deref()
+    }
     pub fn one_password_account(&self) -> Option<&String> {
         self.one_password_account.as_ref()
     }
-    pub fn domain(&self, subdomain: Option<String>) -> String {
-        let ctx = DomainContext {
-            product_name: self.product_name.clone(),
-            product_uri: self.product_uri.clone(),
-            subdomain,
-        };
-        let context = Context::from_serialize(&ctx).expect("Could not create config context");
-        match Tera::one_off(&self.domain_template, &context, false) {
-            Ok(d) => d,
-            Err(e) => panic!("Could not render domain template: {}", e),
+    /// The 1Password vault holding the secrets of `environment`, from
+    /// <ENV>_ONE_PASSWORD_VAULT or ONE_PASSWORD_VAULT, named after the product by default.
+    pub fn one_password_vault(&self, environment: &str) -> Option<&str> {
+        self.one_password_vaults
+            .get(environment)
+            .map(|vault| vault.as_str())
+    }
+    /// The 1Password vault of every environment.
+    pub fn one_password_vaults(&self) -> &HashMap<String, String> {
+        &self.one_password_vaults
+    }
+    pub fn local_cluster(&self) -> &str {
+        &self.local_cluster
+    }
+    /// Compiler cache of Rust builds, none or sccache, from RUST_BUILD_CACHE.
+    pub fn rust_build_cache(&self) -> &str {
+        &self.rust_build_cache
+    }
+    pub fn local_cluster_name(&self) -> &str {
+        &self.local_cluster_name
+    }
+    /// The domain of the product, or of a component given its subdomain.
+    pub fn domain(&self, subdomain: Option<String>) -> Result<String, RushError> {
+        match subdomain {
+            None => Ok(self.domain.clone()),
+            subdomain => render_domain(
+                &self.domain_template,
+                &DomainContext {
+                    product_name: self.product_name.clone(),
+                    product_uri: self.product_uri.clone(),
+                    subdomain,
+                },
+            ),
         }
     }
+    /// The domain of the product, rendered when the config is loaded.
+    pub fn product_domain(&self) -> &str {
+        &self.domain
+    }
     pub fn root_path(&self) -> &str {
         &self.root_path
     }
 
+    /// The products in `products/`, named like on the command line. Directories are named
+    /// with the domain reversed (io.wonop.helloworld for helloworld.wonop.io), using `_`
+    /// in place of `.` where needed.
+    pub fn product_names(root_path: &Path) -> Vec<String> {
+        let mut names = std::fs::read_dir(root_path.join("products"))
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok())
+                    .filter(|entry| entry.file_name() != SHARED_DIRECTORY)
+                    .filter(|entry| entry.path().join("stack.spec.yaml").exists())
+                    .filter_map(|entry| entry.file_name().to_str().map(Self::product_name_of))
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        names.sort();
+        names
+    }
+
+    /// Infers the product from a directory inside `products/<product>/`.
+    pub fn product_name_from_path(root_path: &Path, path: &Path) -> Option<String> {
+        let relative = path.strip_prefix(root_path.join("products")).ok()?;
+        let dirname = relative.components().next()?.as_os_str().to_str()?;
+        if dirname == SHARED_DIRECTORY {
+            return None;
+        }
+        Some(Self::product_name_of(dirname))
+    }
+
+    /// The environments declared in rushd.yaml, e.g. ENVIRONMENTS: local,dev,qa,prod
+    pub fn environment_names() -> Vec<String> {
+        std::env::var("ENVIRONMENTS")
+            .unwrap_or_else(|_| "local,dev,staging,prod".to_string())
+            .split(',')
+            .map(|environment| environment.trim().to_string())
+            .filter(|environment| !environment.is_empty())
+            .collect()
+    }
+
+    fn product_name_of(dirname: &str) -> String {
+        dirname
+            .replace('_', ".")
+            .split('.')
+            .rev()
+            .collect::<Vec<_>>()
+            .join(".")
+    }
+
     pub fn new(
         root_path: &str,
         product_name: &str,
         environment: &str,
         docker_registry: &str,
         start_port: u16,
-    ) -> Result<Arc<Self>, String> {
+        variable_overrides: HashMap<String, String>,
+    ) -> Result<Arc<Self>, RushError> {
         let product_name = product_name.to_string();
         let environment = environment.to_string();
         let docker_registry = docker_registry.to_string();
 
-        let valid_environments = ["local", "dev", "prod", "staging"]
-            .iter()
-            .map(|e| e.to_string())
-            .collect::<Vec<_>>();
+        let environments = Self::environment_names();
         let product_uri = slug::slugify(&product_name).to_string();
         let product_uri = product_uri.to_lowercase();
-        if !valid_environments.contains(&environment) {
+        if !environments.contains(&environment) {
             eprintln!("Invalid environment: {}", environment);
-            eprintln!("Valid environments: {:#?}", valid_environments);
-            return Err(format!("Invalid environment: {}", environment));
+            eprintln!("Valid environments: {:#?}", environments);
+            return Err(RushError::Config(format!(
+                "Invalid environment: {}",
+                environment
+            )));
         }
 
-        let kube_context = match environment.as_str() {
-            "dev" => std::env::var("DEV_CTX").expect("DEV_CTX environment variable not found"),
-            "prod" => std::env::var("PROD_CTX").expect("PROD_CTX environment variable not found"),
-            "staging" => {
-                std::env::var("STAGING_CTX").expect("STAGING_CTX environment variable not found")
-            }
-            "local" => {
-                std::env::var("LOCAL_CTX").expect("LOCAL_CTX environment variable not found")
-            }
-            _ => panic!("Invalid environment"),
-        };
-
-        let vault_name = match environment.as_str() {
-            "dev" => std::env::var("DEV_VAULT").expect("DEV_VAULT environment variable not found"),
-            "prod" => {
-                std::env::var("PROD_VAULT").expect("PROD_VAULT environment variable not found")
-            }
-            "staging" => std::env::var("STAGING_VAULT")
-                .expect("STAGING_VAULT environment variable not found"),
-            "local" => {
-                std::env::var("LOCAL_VAULT").expect("LOCAL_VAULT environment variable not found")
-            }
-            _ => panic!("Invalid environment"),
+        // Settings of an environment are read from <ENV>_CTX, <ENV>_VAULT and so on
+        let environment_prefix = environment.to_uppercase().replace('-', "_");
+        let environment_setting = |name: String| {
+            std::env::var(&name)
+                .map_err(|_| RushError::Config(format!("{} not set in rushd.yaml", name)))
         };
 
-        let k8s_encoder = match environment.as_str() {
-            "dev" => std::env::var("K8S_ENCODER_DEV")
-                .expect("K8S_ENCODER_DEV environment variable not found"),
-            "prod" => std::env::var("K8S_ENCODER_PROD")
-                .expect("K8S_ENCODER_PROD environment variable not found"),
-            "staging" => std::env::var("K8S_ENCODER_STAGING")
-                .expect("K8S_ENCODER_STAGING environment variable not found"),
-            "local" => std::env::var("K8S_ENCODER_LOCAL")
-                .expect("K8S_ENCODER_LOCAL environment variable not found"),
-            _ => panic!("Invalid environment"),
-        };
+        let kube_context = environment_setting(format!("{}_CTX", environment_prefix))?;
+        // An environment may span several clusters, e.g. PROD_CTX: prod-eu,prod-us
+        let kube_contexts = kube_context
+            .split(',')
+            .map(|context| context.trim().to_string())
+            .filter(|context| !context.is_empty())
+            .collect::<Vec<_>>();
+        if kube_contexts.is_empty() {
+            return Err(RushError::Config(format!(
+                "No kube context configured for {}",
+                environment
+            )));
+        }
 
-        let domain_template =
-            match environment.as_str() {
-                "dev" => {
-                    std::env::var("DEV_DOMAIN").expect("DEV_DOMAIN environment variable not found")
-                }
-                "prod" => std::env::var("PROD_DOMAIN")
-                    .expect("PROD_DOMAIN environment variable not found"),
-                "staging" => std::env::var("STAGING_DOMAIN")
-                    .expect("STAGING_DOMAIN environment variable not found"),
-                "local" => std::env::var("LOCAL_DOMAIN")
-                    .expect("LOCAL_DOMAIN environment variable not found"),
-                _ => panic!("Invalid environment"),
-            };
+        let docker_context = std::env::var(format!("{}_DOCKER_CONTEXT", environment_prefix))
+            .ok()
+            .filter(|context| !context.is_empty());
+        let vault_name = environment_setting(format!("{}_VAULT", environment_prefix))?;
+        let k8s_encoder = environment_setting(format!("K8S_ENCODER_{}", environment_prefix))?;
+        let domain_template = environment_setting(format!("{}_DOMAIN", environment_prefix))?;
 
-        let infrastructure_repository = std::env::var("INFRASTRUCTURE_REPOSITORY")
-            .expect("INFRASTRUCTURE_REPOSITORY environment variable not found");
+        // Each environment may point to its own GitOps repository, falling back to the shared one
+        let infrastructure_repository =
+            std::env::var(format!("{}_INFRASTRUCTURE_REPOSITORY", environment_prefix))
+                .or_else(|_| std::env::var("INFRASTRUCTURE_REPOSITORY"))
+                .map_err(|_| {
+                    RushError::Config("INFRASTRUCTURE_REPOSITORY not set in rushd.yaml".to_string())
+                })?;
+        let infrastructure_path_template =
+            std::env::var(format!("{}_INFRASTRUCTURE_PATH", environment_prefix))
+                .or_else(|_| std::env::var("INFRASTRUCTURE_PATH"))
+                .unwrap_or_else(|_| "products/{{ product_name }}/{{ environment }}".to_string());
+        let infrastructure_branch =
+            std::env::var(format!("{}_INFRASTRUCTURE_BRANCH", environment_prefix))
+                .or_else(|_| std::env::var("INFRASTRUCTURE_BRANCH"))
+                .ok();
+        let rollout_mode =
+            std::env::var("INFRASTRUCTURE_ROLLOUT_MODE").unwrap_or_else(|_| "copy".to_string());
+        if !["copy", "flux"].contains(&rollout_mode.as_str()) {
+            return Err(RushError::Config(format!(
+                "Invalid rollout mode: {}",
+                rollout_mode
+            )));
+        }
+        let flux_source_name =
+            std::env::var("FLUX_SOURCE_NAME").unwrap_or_else(|_| "flux-system".to_string());
+        let flux_namespace =
+            std::env::var("FLUX_NAMESPACE").unwrap_or_else(|_| "flux-system".to_string());
+        // Cluster changes in these environments have to be confirmed, e.g. PROTECTED_ENVIRONMENTS: staging,prod
+        let protected_environments = std::env::var("PROTECTED_ENVIRONMENTS")
+            .unwrap_or_else(|_| "prod".to_string())
+            .split(',')
+            .map(|environment| environment.trim().to_string())
+            .filter(|environment| !environment.is_empty())
+            .collect::<Vec<_>>();
+        // Pushing images built from uncommitted changes is refused in these environments
+        let clean_environments = std::env::var("REQUIRE_CLEAN_ENVIRONMENTS")
+            .unwrap_or_else(|_| "prod".to_string())
+            .split(',')
+            .map(|environment| environment.trim().to_string())
+            .filter(|environment| !environment.is_empty())
+            .collect::<Vec<_>>();
+        let network_policies = std::env::var("K8S_NETWORK_POLICIES")
+            .map(|value| matches!(value.to_lowercase().as_str(), "true" | "1" | "yes"))
+            .unwrap_or(false);
+        let ingress_controller_namespace = std::env::var("INGRESS_CONTROLLER_NAMESPACE")
+            .unwrap_or_else(|_| "kube-system".to_string());
+        let ingress_kind = std::env::var("K8S_INGRESS").unwrap_or_else(|_| "none".to_string());
+        if !["none", "ingress", "httproute"].contains(&ingress_kind.as_str()) {
+            return Err(RushError::Config(format!(
+                "Invalid ingress kind: {}",
+                ingress_kind
+            )));
+        }
+        let ingress_class = std::env::var("K8S_INGRESS_CLASS").ok();
+        let gateway = std::env::var("K8S_GATEWAY").unwrap_or_else(|_| "gateway".to_string());
+        let gateway_namespace = std::env::var("K8S_GATEWAY_NAMESPACE").ok();
+        let cert_manager_issuer =
+            std::env::var(format!("{}_CERT_MANAGER_ISSUER", environment_prefix))
+                .or_else(|_| std::env::var("CERT_MANAGER_ISSUER"))
+                .ok();
+        let cert_manager_issuer_kind = std::env::var("CERT_MANAGER_ISSUER_KIND")
+            .unwrap_or_else(|_| "ClusterIssuer".to_string());
+        if !["Issuer", "ClusterIssuer"].contains(&cert_manager_issuer_kind.as_str()) {
+            return Err(RushError::Config(format!(
+                "Invalid cert-manager issuer kind: {}",
+                cert_manager_issuer_kind
+            )));
+        }
         // We assume in the rest of the code that the product path does not end with /
         let mut product_dirname = product_name
             .split('.')
             .rev()
             .collect::<Vec<&str>>()
             .join(".");
-        let products_dir = std::env::current_dir().unwrap().join("products");
+        let products_dir = std::env::current_dir()
+            .unwrap()
+            .join(root_path)
+            .join("products");
 
         // To support the Apple quirk that ".app" is an "App", we allow for using _ in the product name
         if let Ok(entries) = std::fs::read_dir(&products_dir) {
@@ -197,19 +459,20 @@ impl Config {
             {
                 product_dirname = normalized_name.0.clone();
             } else {
-                panic!(
-                    "Product path does not exist for product_dirname: {}",
-                    product_dirname
-                );
+                return Err(RushError::Config(format!(
+                    "Product {} not found in {}",
+                    product_name,
+                    products_dir.display()
+                )));
             }
         }
 
         let product_path = products_dir.join(&product_dirname);
         if !product_path.exists() {
-            panic!(
-                "Product path does not exist for product_dirname: {}",
-                product_dirname
-            );
+            return Err(RushError::Config(format!(
+                "Product path {} does not exist",
+                product_path.display()
+            )));
         }
 
         let product_path = product_path.to_str().unwrap().to_string();
@@ -217,6 +480,180 @@ impl Config {
         trace!("Product dirname: {}", product_dirname);
 
         let one_password_account = std::env::var("ONE_PASSWORD_ACCOUNT").ok();
+        let local_cluster =
+            std::env::var("LOCAL_CLUSTER").unwrap_or_else(|_| "minikube".to_string());
+        if !["minikube", "kind", "k3d"].contains(&local_cluster.as_str()) {
+            return Err(RushError::Config(format!(
+                "Invalid LOCAL_CLUSTER: {} (expected minikube, kind or k3d)",
+                local_cluster
+            )));
+        }
+        let local_cluster_name =
+            std::env::var("LOCAL_CLUSTER_NAME").unwrap_or_else(|_| "rush".to_string());
+        let rust_build_cache =
+            std::env::var("RUST_BUILD_CACHE").unwrap_or_else(|_| "none".to_string());
+
+        let mut path_context = Context::new();
+        path_context.insert("product_name", &product_name);
+        path_context.insert("product_uri", &product_uri);
+        path_context.insert("environment", &environment);
+        let infrastructure_path =
+            match Tera::one_off(&infrastructure_path_template, &path_context, false) {
+                Ok(path) => path.trim_matches('/').to_string(),
+                Err(e) => {
+                    return Err(RushError::Config(format!(
+                        "Could not render infrastructure path: {}",
+                        e
+                    )))
+                }
+            };
+
+        // E.g. PROD_ONE_PASSWORD_VAULT: "{{ product_name }} (restricted)", read for every
+        // environment, as a vault may be used for several
+        let mut one_password_vaults = HashMap::new();
+        for name in &environments {
+            let prefix = name.to_uppercase().replace('-', "_");
+            let template = std::env::var(format!("{}_ONE_PASSWORD_VAULT", prefix))
+                .or_else(|_| std::env::var("ONE_PASSWORD_VAULT"))
+                .unwrap_or_else(|_| "{{ product_name }}".to_string());
+            let mut vault_context = path_context.clone();
+            vault_context.insert("environment", name);
+            match Tera::one_off(&template, &vault_context, false) {
+                Ok(vault) if !vault.trim().is_empty() => {
+                    one_password_vaults.insert(name.clone(), vault.trim().to_string());
+                }
+                Ok(_) => {
+                    return Err(RushError::Config(format!(
+                        "The 1Password vault of {} is empty",
+                        name
+                    )))
+                }
+                Err(e) => {
+                    return Err(RushError::Config(format!(
+                        "Could not render the 1Password vault of {}: {}",
+                        name, e
+                    )))
+                }
+            }
+        }
+
+        // Relative to the product directory, e.g. terraform/{{ environment }}
+        let terraform_directory =
+            match std::env::var(format!("{}_TERRAFORM_DIRECTORY", environment_prefix))
+                .or_else(|_| std::env::var("TERRAFORM_DIRECTORY"))
+            {
+                Ok(template) if !template.is_empty() => {
+                    match Tera::one_off(&template, &path_context, false) {
+                        Ok(directory) => Some(
+                            Path::new(&product_path)
+                                .join(directory.trim())
+                                .to_string_lossy()
+                                .into_owned(),
+                        ),
+                        Err(e) => {
+                            return Err(RushError::Config(format!(
+                                "Could not render terraform directory: {}",
+                                e
+                            )))
+                        }
+                    }
+                }
+                _ => None,
+            };
+        // Relative to the product directory, unless a URL
+        let kubeseal_cert = std::env::var(format!("{}_KUBESEAL_CERT", environment_prefix))
+            .or_else(|_| std::env::var("KUBESEAL_CERT"))
+            .ok()
+            .filter(|cert| !cert.is_empty())
+            .map(|cert| {
+                if cert.contains("://") {
+                    cert
+                } else {
+                    Path::new(&product_path)
+                        .join(cert)
+                        .to_string_lossy()
+                        .into_owned()
+                }
+            });
+        let kubeseal_controller_namespace = std::env::var(format!(
+            "{}_KUBESEAL_CONTROLLER_NAMESPACE",
+            environment_prefix
+        ))
+        .or_else(|_| std::env::var("KUBESEAL_CONTROLLER_NAMESPACE"))
+        .ok();
+        let kubeseal_controller_name =
+            std::env::var(format!("{}_KUBESEAL_CONTROLLER_NAME", environment_prefix))
+                .or_else(|_| std::env::var("KUBESEAL_CONTROLLER_NAME"))
+                .ok();
+
+        let terraform_binary =
+            std::env::var("TERRAFORM_BINARY").unwrap_or_else(|_| "terraform".to_string());
+
+        // Slack, Discord or any URL taking a JSON POST, e.g. PROD_DEPLOY_WEBHOOKS: https://hooks.slack.com/...
+        let deploy_webhooks = std::env::var(format!("{}_DEPLOY_WEBHOOKS", environment_prefix))
+            .or_else(|_| std::env::var("DEPLOY_WEBHOOKS"))
+            .unwrap_or_default()
+            .split(',')
+            .map(|webhook| webhook.trim().to_string())
+            .filter(|webhook| !webhook.is_empty())
+            .collect::<Vec<_>>();
+
+        let tag_strategy_name = std::env::var(format!("{}_TAG_STRATEGY", environment_prefix))
+            .or_else(|_| std::env::var("TAG_STRATEGY"))
+            .unwrap_or_else(|_| "git-hash".to_string());
+        let tag_strategy = TagStrategy::parse(&tag_strategy_name).ok_or_else(|| {
+            RushError::Config(format!(
+                "Invalid tag strategy: {} (expected git-hash, semver, git-describe, date or branch)",
+                tag_strategy_name
+            ))
+        })?;
+
+        let push_concurrency = match std::env::var("PUSH_CONCURRENCY") {
+            Ok(value) => value
+                .parse::<usize>()
+                .ok()
+                .filter(|concurrency| *concurrency > 0)
+                .ok_or_else(|| RushError::Config(format!("Invalid PUSH_CONCURRENCY: {}", value)))?,
+            Err(_) => 4,
+        };
+
+        // Manifests then keep running the same image even when its tag is pushed again
+        let pin_image_digests = std::env::var(format!("{}_PIN_IMAGE_DIGESTS", environment_prefix))
+            .or_else(|_| std::env::var("PIN_IMAGE_DIGESTS"))
+            .map(|value| matches!(value.to_lowercase().as_str(), "true" | "1" | "yes"))
+            .unwrap_or(false);
+
+        let secret_leaks = std::env::var(format!("{}_SECRET_LEAKS", environment_prefix))
+            .or_else(|_| std::env::var("SECRET_LEAKS"))
+            .unwrap_or_else(|_| {
+                if protected_environments.contains(&environment) {
+                    "fail".to_string()
+                } else {
+                    "warn".to_string()
+                }
+            });
+        if !["fail", "warn", "ignore"].contains(&secret_leaks.as_str()) {
+            return Err(RushError::Config(format!(
+                "Invalid SECRET_LEAKS: {}",
+                secret_leaks
+            )));
+        }
+
+        if !["kubeseal", "noop"].contains(&k8s_encoder.as_str()) {
+            return Err(RushError::Config(format!(
+                "Invalid K8S_ENCODER_{}: {} (expected kubeseal or noop)",
+                environment_prefix, k8s_encoder
+            )));
+        }
+
+        let domain = render_domain(
+            &domain_template,
+            &DomainContext {
+                product_name: product_name.clone(),
+                product_uri: product_uri.clone(),
+                subdomain: None,
+            },
+        )?;
 
         let ret = Self {
             root_path: root_path.to_string(),
@@ -226,16 +663,61 @@ impl Config {
             product_path,
             network_name,
             environment,
+            environments,
+            protected_environments,
+            clean_environments,
             domain_template: domain_template.to_string(),
-            kube_context,
+            domain,
+            kube_contexts,
             infrastructure_repository,
+            infrastructure_path,
+            infrastructure_branch,
+            rollout_mode,
+            flux_source_name,
+            flux_namespace,
+            network_policies,
+            ingress_controller_namespace,
+            ingress_kind,
+            ingress_class,
+            gateway,
+            gateway_namespace,
+            cert_manager_issuer,
+            cert_manager_issuer_kind,
             docker_registry,
+            docker_context,
             vault_name,
             k8s_encoder,
+            kubeseal_cert,
+            kubeseal_controller_namespace,
+            kubeseal_controller_name,
             one_password_account,
+            one_password_vaults,
+            local_cluster,
+            local_cluster_name,
+            rust_build_cache,
+            terraform_directory,
+            terraform_binary,
+            deploy_webhooks,
+            tag_strategy,
+            push_concurrency,
+            pin_image_digests,
+            secret_leaks,
             start_port,
+            variable_overrides,
         };
 
         Ok(Arc::new(ret))
     }
 }
+
+fn render_domain(template: &str, domain_context: &DomainContext) -> Result<String, RushError> {
+    Context::from_serialize(domain_context)
+        .and_then(|context| Tera::one_off(template, &context, false))
+        .map_err(|e| {
+            // Tera puts the reason in the source of the error
+            let reason = std::error::Error::source(&e)
+                .map(|source| source.to_string())
+                .unwrap_or_else(|| e.to_string());
+            RushError::Config(format!("Invalid domain template {}: {}", template, reason))
+        })
+}
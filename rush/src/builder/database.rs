@@ -0,0 +1,82 @@
+use serde::{Deserialize, Serialize};
+
+/// Marks a component as a database, so that `rush db snapshot` and `rush db restore`
+/// can dump and load its data with the tools of its image. The commands run in the
+/// dev container with `sh -c`, so they see its environment, e.g. `POSTGRES_USER`. A
+/// dump writes to stdout and a restore reads from stdin.
+///
+/// ```yaml
+/// database:
+///   type: postgres
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DatabaseSpec {
+    #[serde(rename = "type")]
+    pub kind: DatabaseKind,
+    /// Overrides the dump command of `type`, required with `custom`
+    pub dump: Option<String>,
+    /// Overrides the restore command of `type`, required with `custom`
+    pub restore: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DatabaseKind {
+    /// The `POSTGRES_DB` database of the official postgres image
+    Postgres,
+    /// All databases of the official mysql or mariadb image
+    Mysql,
+    /// All databases of the official mongo image
+    Mongodb,
+    Custom,
+}
+
+impl DatabaseSpec {
+    /// The command writing a snapshot of the database to stdout.
+    pub fn dump_command(&self) -> Option<String> {
+        self.dump.clone().or_else(|| {
+            Some(match self.kind {
+                DatabaseKind::Postgres => {
+                    "pg_dump --clean --if-exists -U \"${POSTGRES_USER:-postgres}\" \"${POSTGRES_DB:-${POSTGRES_USER:-postgres}}\""
+                }
+                DatabaseKind::Mysql => {
+                    "mysqldump -uroot ${MYSQL_ROOT_PASSWORD:+-p\"$MYSQL_ROOT_PASSWORD\"} --all-databases"
+                }
+                DatabaseKind::Mongodb => {
+                    "mongodump --archive ${MONGO_INITDB_ROOT_USERNAME:+--authenticationDatabase admin -u \"$MONGO_INITDB_ROOT_USERNAME\" -p \"$MONGO_INITDB_ROOT_PASSWORD\"}"
+                }
+                DatabaseKind::Custom => return None,
+            }
+            .to_string())
+        })
+    }
+
+    /// The command loading a snapshot from stdin, replacing the current data.
+    pub fn restore_command(&self) -> Option<String> {
+        self.restore.clone().or_else(|| {
+            Some(match self.kind {
+                DatabaseKind::Postgres => {
+                    "psql -q -v ON_ERROR_STOP=1 -U \"${POSTGRES_USER:-postgres}\" \"${POSTGRES_DB:-${POSTGRES_USER:-postgres}}\""
+                }
+                DatabaseKind::Mysql => {
+                    "mysql -uroot ${MYSQL_ROOT_PASSWORD:+-p\"$MYSQL_ROOT_PASSWORD\"}"
+                }
+                DatabaseKind::Mongodb => {
+                    "mongorestore --archive --drop ${MONGO_INITDB_ROOT_USERNAME:+--authenticationDatabase admin -u \"$MONGO_INITDB_ROOT_USERNAME\" -p \"$MONGO_INITDB_ROOT_PASSWORD\"}"
+                }
+                DatabaseKind::Custom => return None,
+            }
+            .to_string())
+        })
+    }
+
+    /// The extension of the snapshot files.
+    pub fn extension(&self) -> &'static str {
+        match self.kind {
+            DatabaseKind::Postgres | DatabaseKind::Mysql => "sql",
+            DatabaseKind::Mongodb => "archive",
+            DatabaseKind::Custom => "dump",
+        }
+    }
+}
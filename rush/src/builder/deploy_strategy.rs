@@ -0,0 +1,40 @@
+use serde::{Deserialize, Serialize};
+
+/// How the Deployments of a component are switched over by `rush deploy`.
+///
+/// ```yaml
+/// deploy_strategy:
+///   type: canary
+///   steps: [10, 50]
+///   pause_seconds: 60
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum DeployStrategy {
+    /// Plain `kubectl apply`, leaving the rollout to Kubernetes.
+    #[default]
+    Rolling,
+    /// Runs a `-canary` copy of each Deployment next to the stable one, scaled so that
+    /// it receives roughly `steps[i]` percent of the traffic, before updating the stable
+    /// Deployment.
+    Canary {
+        #[serde(default = "default_canary_steps")]
+        steps: Vec<u8>,
+        #[serde(default = "default_pause_seconds")]
+        pause_seconds: u64,
+    },
+    /// Deploys a second `-blue`/`-green` copy of each Deployment and switches the
+    /// Services over once it is healthy.
+    BlueGreen {
+        #[serde(default)]
+        keep_previous: bool,
+    },
+}
+
+fn default_canary_steps() -> Vec<u8> {
+    vec![10, 50]
+}
+
+fn default_pause_seconds() -> u64 {
+    60
+}
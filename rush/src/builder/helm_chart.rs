@@ -0,0 +1,12 @@
+use serde::{Deserialize, Serialize};
+
+/// A Helm chart that a component is installed from when rolling out through Flux.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HelmChart {
+    pub repository: String,
+    pub chart: String,
+    pub version: Option<String>,
+    pub namespace: Option<String>,
+    pub values: Option<serde_yaml::Value>,
+}
@@ -0,0 +1,50 @@
+use serde_yaml::Value;
+use std::path::{Path, PathBuf};
+
+/// Path of the git-ignored overlay of a configuration file, such as rushd.local.yaml
+/// for rushd.yaml. Overlays let individuals tweak ports, the registry or silenced
+/// components without touching the committed files.
+pub fn local_file(path: &Path) -> PathBuf {
+    let stem = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .and_then(|name| name.strip_suffix(".yaml"))
+        .unwrap_or_default();
+    path.with_file_name(format!("{}.local.yaml", stem))
+}
+
+/// Merges `overlay` into `base`. Mappings are merged key by key at every level, while
+/// any other value, lists included, replaces the value it overlays.
+pub fn merge_overlay(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Mapping(base), Value::Mapping(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(base_value) => merge_overlay(base_value, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        // An empty overlay file parses as null
+        (_, Value::Null) => (),
+        (base, overlay) => *base = overlay,
+    }
+}
+
+/// Reads a YAML file with its local overlay, if there is one, merged on top.
+pub fn load_with_local_overrides(path: &Path) -> Result<Value, String> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let mut value: Value = serde_yaml::from_str(&contents)
+        .map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?;
+
+    let local_path = local_file(path);
+    if let Ok(contents) = std::fs::read_to_string(&local_path) {
+        let overlay = serde_yaml::from_str(&contents)
+            .map_err(|e| format!("Failed to parse {}: {}", local_path.display(), e))?;
+        merge_overlay(&mut value, overlay);
+    }
+    Ok(value)
+}
@@ -2,8 +2,16 @@ mod artefact;
 mod build_context;
 mod build_script;
 mod build_type;
+mod cargo_workspace;
+mod component_jobs;
 mod config;
+mod database;
+mod deploy_strategy;
+mod helm_chart;
+mod local_overrides;
+mod shared;
 mod spec;
+mod tag_strategy;
 mod templates;
 mod variables;
 
@@ -13,6 +21,14 @@ pub use artefact::Artefact;
 pub use build_context::BuildContext;
 pub use build_script::BuildScript;
 pub use build_type::BuildType;
+pub use cargo_workspace::CrateDependencies;
+pub use component_jobs::ComponentJobs;
 pub use config::Config;
-pub use spec::ComponentBuildSpec;
+pub use database::{DatabaseKind, DatabaseSpec};
+pub use deploy_strategy::DeployStrategy;
+pub use helm_chart::HelmChart;
+pub use local_overrides::{load_with_local_overrides, local_file, merge_overlay};
+pub use shared::{load_stack_spec, shared_file, SHARED_DIRECTORY};
+pub use spec::{ComponentBuildSpec, DOTENV_FILES};
+pub use tag_strategy::TagStrategy;
 pub use variables::Variables;
@@ -0,0 +1,89 @@
+use super::load_with_local_overrides;
+use serde_yaml::{Mapping, Value};
+use std::path::{Path, PathBuf};
+
+/// Directory next to the products holding the definitions they share. It contains
+/// the same files as a product, and a product's own definitions take precedence.
+pub const SHARED_DIRECTORY: &str = "_shared";
+
+/// How deep shared components may extend each other before it is treated as a cycle.
+const MAX_EXTENDS_DEPTH: usize = 16;
+
+/// Path of `file_name` in the shared directory of the repository `product_dir` belongs to.
+pub fn shared_file(product_dir: &Path, file_name: &str) -> PathBuf {
+    product_dir
+        .parent()
+        .unwrap_or(product_dir)
+        .join(SHARED_DIRECTORY)
+        .join(file_name)
+}
+
+/// Loads a product's `stack.spec.yaml`, with `stack.spec.local.yaml` merged on top, and
+/// every `extends: <component>` replaced by the shared component it names, overridden
+/// by the keys given next to it. Mappings such as `env` are merged key by key:
+///
+/// ```yaml
+/// database:
+///   extends: postgres
+///   env:
+///     POSTGRES_DB: backend
+/// ```
+pub fn load_stack_spec(product_dir: &Path) -> Result<Value, String> {
+    let stack = load_with_local_overrides(&product_dir.join("stack.spec.yaml"))?;
+
+    let shared_path = shared_file(product_dir, "stack.spec.yaml");
+    let shared = match std::fs::read_to_string(&shared_path) {
+        Ok(contents) => serde_yaml::from_str(&contents)
+            .map_err(|e| format!("Failed to parse {}: {}", shared_path.display(), e))?,
+        Err(_) => Value::Mapping(Mapping::new()),
+    };
+
+    let components = match stack {
+        Value::Mapping(components) => components,
+        other => return Ok(other),
+    };
+    let mut resolved = Mapping::new();
+    for (component_name, section) in components {
+        let section = resolve_extends(section, &shared, 0)
+            .map_err(|e| format!("{}: {}", component_name.as_str().unwrap_or_default(), e))?;
+        resolved.insert(component_name, section);
+    }
+    Ok(Value::Mapping(resolved))
+}
+
+fn resolve_extends(section: Value, shared: &Value, depth: usize) -> Result<Value, String> {
+    let mut section = match section {
+        Value::Mapping(section) => section,
+        other => return Ok(other),
+    };
+    let parent_name = match section.remove("extends") {
+        Some(Value::String(parent_name)) => parent_name,
+        Some(_) => return Err("extends must name a shared component".to_string()),
+        None => return Ok(Value::Mapping(section)),
+    };
+    if depth >= MAX_EXTENDS_DEPTH {
+        return Err(format!("extends of {} is cyclic", parent_name));
+    }
+
+    let parent = shared.get(&parent_name).cloned().ok_or_else(|| {
+        format!(
+            "extends unknown shared component {} (see products/{}/stack.spec.yaml)",
+            parent_name, SHARED_DIRECTORY
+        )
+    })?;
+    let mut merged = match resolve_extends(parent, shared, depth + 1)? {
+        Value::Mapping(parent) => parent,
+        _ => return Err(format!("Shared component {} is not a mapping", parent_name)),
+    };
+    for (key, value) in section {
+        match (merged.get_mut(&key), value) {
+            (Some(Value::Mapping(parent_value)), Value::Mapping(value)) => {
+                parent_value.extend(value)
+            }
+            (_, value) => {
+                merged.insert(key, value);
+            }
+        }
+    }
+    Ok(Value::Mapping(merged))
+}
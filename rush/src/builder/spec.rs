@@ -2,16 +2,29 @@ use super::Variables;
 use crate::builder::Artefact;
 use crate::builder::BuildContext;
 use crate::builder::Config;
-use crate::builder::{BuildScript, BuildType};
+use crate::builder::{
+    BuildScript, BuildType, ComponentJobs, DatabaseKind, DatabaseSpec, DeployStrategy, HelmChart,
+};
+use crate::container::volumes::is_volume_name;
 use crate::container::{ServiceSpec, ServicesSpec};
 use crate::dotenv_utils::load_dotenv;
+use crate::error::RushError;
 use crate::path_matcher::PathMatcher;
+use crate::toolchain::ToolchainContext;
 use crate::vault::Vault;
-use crate::ToolchainContext;
+use log::warn;
+use serde::Deserialize;
 use std::collections::HashMap;
 use std::sync::Arc;
 use std::sync::Mutex;
 
+/// The files in a component's location its container's environment is read from, in
+/// the order they override each other.
+pub const DOTENV_FILES: [&str; 3] = [".env", ".env.secrets", ".env.local"];
+
+/// Port of the gdbserver of a component with `debug`, unless it sets `debug_port`.
+pub const DEFAULT_DEBUG_PORT: u16 = 2345;
+
 #[derive(Debug, Clone)]
 pub struct ComponentBuildSpec {
     pub build_type: BuildType,
@@ -21,18 +34,43 @@ pub struct ComponentBuildSpec {
     pub depends_on: Vec<String>,
 
     pub build: Option<String>,
+    /// Flake whose dev shell the build script runs in with `nix develop`, e.g. `.#backend`
+    pub nix_flake: Option<String>,
     pub mount_point: Option<String>,
+    /// Path polled after `rush dev` starts the component to tell whether it serves,
+    /// e.g. `/api/health`. Defaults to the mount point
+    pub health_check: Option<String>,
     pub subdomain: Option<String>,
     pub artefacts: Option<std::collections::HashMap<String, String>>,
     pub artefact_output_dir: String,
     pub docker_extra_run_args: Vec<String>,
+    /// Further hostnames the dev container is reached under on the product's network
+    pub network_aliases: Vec<String>,
+    /// Existing Docker networks the dev container is attached to as well, e.g. a
+    /// database network shared by several products
+    pub extra_networks: Vec<String>,
     pub env: Option<HashMap<String, String>>, // TODO: Deprecated
     pub volumes: Option<HashMap<String, String>>,
+    /// Volumes of `stack.volumes.yaml` by their declared name, to container paths
+    pub named_volumes: HashMap<String, String>,
     pub port: Option<u16>,
     pub target_port: Option<u16>,
     pub k8s: Option<String>, // TODO: Refactor to k8s_dir
     pub priority: u64,
     pub watch: Option<Arc<PathMatcher>>,
+    pub helm: Option<HelmChart>,
+    pub deploy_strategy: DeployStrategy,
+    pub jobs: ComponentJobs,
+    /// The environments the component is built and deployed in, e.g. `[local, dev]`.
+    /// All of them when not set
+    pub environments: Option<Vec<String>>,
+    /// Whether `rush dev` builds the component unoptimized with debug info and runs a
+    /// gdbserver in its container for debuggers to attach to. Only for RustBinary
+    pub debug: bool,
+    /// Port of this machine the gdbserver of `debug` listens on
+    pub debug_port: u16,
+    /// How `rush db snapshot` and `rush db restore` dump and load the component's data
+    pub database: Option<DatabaseSpec>,
 
     // Set after loading
     pub config: Arc<Config>,
@@ -44,6 +82,8 @@ pub struct ComponentBuildSpec {
     // Environment and secrets from the component
     pub dotenv: HashMap<String, String>,
     pub dotenv_secrets: HashMap<String, String>,
+    /// Personal overrides from .env.local, which rush never writes
+    pub dotenv_local: HashMap<String, String>,
     pub domain: String,
 }
 
@@ -52,6 +92,27 @@ impl ComponentBuildSpec {
         format!("{}-{}", self.product_name, self.component_name)
     }
 
+    /// The variables passed to the component's container in the order they are
+    /// applied, so later entries override earlier ones. Each entry names its source.
+    pub fn environment_sources(&self) -> Vec<(String, String, &'static str)> {
+        let mut sources = Vec::new();
+        if let Some(env) = &self.env {
+            for (key, value) in env {
+                sources.push((key.clone(), value.clone(), "stack.spec.yaml"));
+            }
+        }
+        for (key, value) in &self.dotenv {
+            sources.push((key.clone(), value.clone(), ".env"));
+        }
+        for (key, value) in &self.dotenv_secrets {
+            sources.push((key.clone(), value.clone(), ".env.secrets"));
+        }
+        for (key, value) in &self.dotenv_local {
+            sources.push((key.clone(), value.clone(), ".env.local"));
+        }
+        sources
+    }
+
     pub fn set_services(&mut self, services: Arc<ServicesSpec>) {
         self.services = Some(services);
     }
@@ -72,377 +133,261 @@ impl ComponentBuildSpec {
         config: Arc<Config>,
         variables: Arc<Variables>,
         yaml_section: &serde_yaml::Value,
-    ) -> Self {
+    ) -> Result<Self, RushError> {
         let product_name = config.product_name();
-        let build_type = match yaml_section
-            .get("build_type")
-            .expect("build_type is required")
-            .as_str()
-            .unwrap()
-        {
+        let component_name = yaml_section
+            .get("component_name")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| RushError::spec("<unnamed>", "component_name is required"))?;
+        let error = |message: String| RushError::spec(component_name, message);
+        let section = ComponentSection::from_value(yaml_section).map_err(error)?;
+        let template = |input: &str| {
+            Self::process_template_string(input, &variables)
+                .map_err(|e| RushError::spec(component_name, e))
+        };
+        let optional_template = |input: &Option<String>| input.as_deref().map(template).transpose();
+        let templates = |inputs: &[String]| inputs.iter().map(|input| template(input)).collect();
+        let template_map = |map: &HashMap<String, String>| {
+            map.iter()
+                .map(|(name, value)| Ok((template(name)?, template(value)?)))
+                .collect::<Result<Vec<_>, RushError>>()
+        };
+        let port = |key: &str, port: &Option<PortValue>| match port {
+            Some(PortValue::Number(port)) => Ok(Some(*port)),
+            Some(PortValue::Template(port)) => {
+                let port = template(port)?;
+                port.parse::<u16>()
+                    .map(Some)
+                    .map_err(|_| error(format!("Could not parse {} {} as a port", key, port)))
+            }
+            None => Ok(None),
+        };
+
+        let build_type_name = section
+            .build_type
+            .clone()
+            .ok_or_else(|| error("build_type is required".to_string()))?;
+        let required = |key: &str, value: &Option<String>| {
+            value
+                .clone()
+                .ok_or_else(|| error(format!("{} is required for {}", key, build_type_name)))
+        };
+        let context_dir = || {
+            section
+                .context_dir
+                .clone()
+                .unwrap_or_else(|| ".".to_string())
+        };
+        let build_type = match build_type_name.as_str() {
             "TrunkWasm" => BuildType::TrunkWasm {
                 context_dir: None,
-                location: yaml_section
-                    .get("location")
-                    .expect("location is required for TrunkWasm")
-                    .as_str()
-                    .unwrap()
-                    .to_string(),
-                dockerfile_path: yaml_section
-                    .get("dockerfile")
-                    .expect("dockerfile_path is required")
-                    .as_str()
-                    .unwrap()
-                    .to_string(),
+                location: required("location", &section.location)?,
+                dockerfile_path: required("dockerfile", &section.dockerfile)?,
             },
             "DixiousWasm" => BuildType::DixiousWasm {
                 context_dir: None,
-                location: yaml_section
-                    .get("location")
-                    .expect("location is required for DixiousWasm")
-                    .as_str()
-                    .unwrap()
-                    .to_string(),
-                dockerfile_path: yaml_section
-                    .get("dockerfile")
-                    .expect("dockerfile_path is required")
-                    .as_str()
-                    .unwrap()
-                    .to_string(),
+                location: required("location", &section.location)?,
+                dockerfile_path: required("dockerfile", &section.dockerfile)?,
             },
             "RustBinary" => BuildType::RustBinary {
-                context_dir: Some(
-                    yaml_section
-                        .get("context_dir")
-                        .map_or(".".to_string(), |v| v.as_str().unwrap().to_string()),
-                ),
-                location: yaml_section
-                    .get("location")
-                    .expect("location is required for RustBinary")
-                    .as_str()
-                    .unwrap()
-                    .to_string(),
-                dockerfile_path: yaml_section
-                    .get("dockerfile")
-                    .expect("dockerfile_path is required")
-                    .as_str()
-                    .unwrap()
-                    .to_string(),
+                context_dir: Some(context_dir()),
+                location: required("location", &section.location)?,
+                dockerfile_path: required("dockerfile", &section.dockerfile)?,
+                cross: section.cross,
             },
             "Zola" => BuildType::Zola {
-                context_dir: Some(
-                    yaml_section
-                        .get("context_dir")
-                        .map_or(".".to_string(), |v| v.as_str().unwrap().to_string()),
-                ),
-                location: yaml_section
-                    .get("location")
-                    .expect("location is required for Book")
-                    .as_str()
-                    .unwrap()
-                    .to_string(),
-                dockerfile_path: yaml_section
-                    .get("dockerfile")
-                    .expect("dockerfile_path is required")
-                    .as_str()
-                    .unwrap()
-                    .to_string(),
+                context_dir: Some(context_dir()),
+                location: required("location", &section.location)?,
+                dockerfile_path: required("dockerfile", &section.dockerfile)?,
             },
             "Book" => BuildType::Book {
-                context_dir: Some(
-                    yaml_section
-                        .get("context_dir")
-                        .map_or(".".to_string(), |v| v.as_str().unwrap().to_string()),
-                ),
-                location: yaml_section
-                    .get("location")
-                    .expect("location is required for Book")
-                    .as_str()
-                    .unwrap()
-                    .to_string(),
-                dockerfile_path: yaml_section
-                    .get("dockerfile")
-                    .expect("dockerfile_path is required")
-                    .as_str()
-                    .unwrap()
-                    .to_string(),
+                context_dir: Some(context_dir()),
+                location: required("location", &section.location)?,
+                dockerfile_path: required("dockerfile", &section.dockerfile)?,
             },
             "Script" => BuildType::Script {
-                context_dir: Some(
-                    yaml_section
-                        .get("context_dir")
-                        .map_or(".".to_string(), |v| v.as_str().unwrap().to_string()),
-                ),
-                location: yaml_section
-                    .get("location")
-                    .expect("location is required for Script")
-                    .as_str()
-                    .unwrap()
-                    .to_string(),
-                dockerfile_path: yaml_section
-                    .get("dockerfile")
-                    .expect("dockerfile_path is required")
-                    .as_str()
-                    .unwrap()
-                    .to_string(),
+                context_dir: Some(context_dir()),
+                location: required("location", &section.location)?,
+                dockerfile_path: required("dockerfile", &section.dockerfile)?,
             },
             "Ingress" => BuildType::Ingress {
-                context_dir: Some(
-                    yaml_section
-                        .get("context_dir")
-                        .map_or(".".to_string(), |v| v.as_str().unwrap().to_string()),
-                ),
-                components: yaml_section
-                    .get("components")
-                    .expect("components are required for Ingress")
-                    .as_sequence()
-                    .unwrap()
-                    .iter()
-                    .map(|v| v.as_str().unwrap().to_string())
-                    .collect(),
-                dockerfile_path: yaml_section
-                    .get("dockerfile")
-                    .expect("dockerfile_path is required")
-                    .as_str()
-                    .unwrap()
-                    .to_string(),
+                context_dir: Some(context_dir()),
+                components: section
+                    .components
+                    .clone()
+                    .ok_or_else(|| error("components are required for Ingress".to_string()))?,
+                dockerfile_path: required("dockerfile", &section.dockerfile)?,
             },
             "Image" => BuildType::PureDockerImage {
-                image_name_with_tag: yaml_section
-                    .get("image")
-                    .expect("image is required for PureDockerImage")
-                    .as_str()
-                    .unwrap()
-                    .to_string(),
-                command: yaml_section
-                    .get("command")
-                    .map(|v| v.as_str().unwrap().to_string()),
-                entrypoint: yaml_section
-                    .get("entrypoint")
-                    .map(|v| v.as_str().unwrap().to_string()),
+                image_name_with_tag: required("image", &section.image)?,
+                command: section.command.clone(),
+                entrypoint: section.entrypoint.clone(),
             },
             "K8sOnly" => BuildType::PureKubernetes,
             "K8sInstall" => BuildType::KubernetesInstallation {
-                namespace: yaml_section
-                    .get("namespace")
-                    .expect("namespace is required for KubernetesInstallation")
-                    .as_str()
-                    .unwrap()
-                    .to_string(),
+                namespace: required("namespace", &section.namespace)?,
             },
 
-            _ => panic!("Invalid build_type"),
+            _ => {
+                return Err(error(format!("Invalid build_type {}", build_type_name)));
+            }
         };
 
-        let cwd = std::env::current_dir()
-            .expect("Failed to get current working directory")
-            .to_str()
-            .unwrap()
-            .to_string();
+        let cwd = std::env::current_dir()?.to_str().unwrap().to_string();
 
         // Loading environment
-        let location = match &build_type {
-            BuildType::TrunkWasm { location, .. } => Some(location.clone()),
-            BuildType::DixiousWasm { location, .. } => Some(location.clone()),
-            BuildType::RustBinary { location, .. } => Some(location.clone()),
-            BuildType::Zola { location, .. } => Some(location.clone()),
-            BuildType::Book { location, .. } => Some(location.clone()),
-            BuildType::Script { location, .. } => Some(location.clone()),
-            _ => None,
-        };
-        let component_path = match location {
-            Some(location) => {
-                let binding = std::path::Path::new(&cwd).join(location);
-                Some(binding.to_str().unwrap().to_string())
+        let component_path = build_type
+            .location()
+            .map(|location| std::path::Path::new(&cwd).join(location));
+        let (dotenv, dotenv_secrets, dotenv_local) =
+            load_component_dotenv(component_path.as_deref()).map_err(error)?;
+
+        let subdomain = optional_template(&section.subdomain)?;
+        let domain = config
+            .domain(subdomain.clone())
+            .map_err(|e| error(e.to_string()))?;
+
+        let environments: Option<Vec<String>> =
+            section.environments.as_deref().map(templates).transpose()?;
+        for environment in environments.iter().flatten() {
+            if !config.environments().contains(environment) {
+                return Err(error(format!(
+                    "Unknown environment {} in environments, expected one of {}",
+                    environment,
+                    config.environments().join(", ")
+                )));
             }
-            None => None,
-        };
+        }
 
-        let dotenv = match &component_path {
-            Some(path) => {
-                let dotenv_path = std::path::Path::new(&path).join(".env");
-                if dotenv_path.exists() {
-                    match load_dotenv(&dotenv_path) {
-                        Ok(env) => env,
-                        Err(e) => {
-                            panic!("Failed to load .env file: {}", e);
-                        }
-                    }
-                } else {
-                    HashMap::new()
-                }
+        // Sources that are names rather than paths mount the product's named volumes
+        let (named_volumes, volumes) = match &section.volumes {
+            Some(volumes) => {
+                let (named_volumes, volumes): (Vec<_>, Vec<_>) = template_map(volumes)?
+                    .into_iter()
+                    .partition(|(source, _)| is_volume_name(source));
+                (named_volumes, Some(volumes))
             }
-            None => HashMap::new(),
+            None => (Vec::new(), None),
         };
-        let dotenv_secrets = match &component_path {
-            Some(path) => {
-                let dotenv_secrets_path = std::path::Path::new(&path).join(".env.secrets");
-                if dotenv_secrets_path.exists() {
-                    match load_dotenv(&dotenv_secrets_path) {
-                        Ok(env) => env,
-                        Err(e) => {
-                            panic!("Failed to load .env file: {}", e);
-                        }
-                    }
-                } else {
-                    HashMap::new()
-                }
+
+        if section.debug && !matches!(build_type, BuildType::RustBinary { .. }) {
+            return Err(error(
+                "debug is only supported for RustBinary components".to_string(),
+            ));
+        }
+
+        if let Some(database) = &section.database {
+            if database.kind == DatabaseKind::Custom
+                && (database.dump.is_none() || database.restore.is_none())
+            {
+                return Err(error(
+                    "a custom database needs both dump and restore".to_string(),
+                ));
             }
-            None => HashMap::new(),
+        }
+
+        let watch = match &section.watch {
+            Some(paths) => Some(Arc::new(PathMatcher::new(
+                std::path::Path::new(&cwd),
+                templates(paths)?,
+            ))),
+            None => None,
         };
-        let subdomain = yaml_section
-            .get("subdomain")
-            .map(|v| Self::process_template_string(v.as_str().unwrap(), &variables));
-        let domain = config.domain(subdomain.clone());
-
-        let watch = yaml_section.get("watch").map(|v| {
-            let paths: Vec<String> = v
-                .as_sequence()
-                .unwrap()
-                .iter()
-                .map(|item| Self::process_template_string(item.as_str().unwrap(), &variables))
-                .collect();
-            Arc::new(PathMatcher::new(std::path::Path::new(&cwd), paths))
-        });
 
-        ComponentBuildSpec {
+        Ok(ComponentBuildSpec {
             build_type,
-            build: yaml_section
-                .get("build")
-                .map(|v| Self::process_template_string(v.as_str().unwrap(), &variables)),
-
-            color: yaml_section.get("color").map_or("blue".to_string(), |v| {
-                Self::process_template_string(v.as_str().unwrap(), &variables)
-            }),
-            depends_on: yaml_section.get("depends_on").map_or(Vec::new(), |v| {
-                v.as_sequence()
-                    .unwrap()
-                    .iter()
-                    .map(|item| Self::process_template_string(item.as_str().unwrap(), &variables))
-                    .collect()
-            }),
+            build: optional_template(&section.build)?,
+            nix_flake: optional_template(&section.nix_flake)?,
+            color: template(&section.color)?,
+            depends_on: templates(&section.depends_on)?,
             product_name: product_name.to_string(),
-            component_name: Self::process_template_string(
-                yaml_section
-                    .get("component_name")
-                    .expect("component_name is required")
-                    .as_str()
-                    .unwrap(),
-                &variables,
-            ),
-            mount_point: yaml_section
-                .get("mount_point")
-                .map(|v| Self::process_template_string(v.as_str().unwrap(), &variables)),
+            component_name: template(&section.component_name)?,
+            mount_point: optional_template(&section.mount_point)?,
+            health_check: optional_template(&section.health_check)?,
             subdomain,
-            artefacts: yaml_section.get("artefacts").map(|v| {
-                v.as_mapping()
-                    .unwrap()
-                    .iter()
-                    .map(|(k, val)| {
-                        (
-                            Self::process_template_string(k.as_str().unwrap(), &variables),
-                            Self::process_template_string(val.as_str().unwrap(), &variables),
-                        )
-                    })
-                    .collect()
-            }),
-            artefact_output_dir: yaml_section
-                .get("artefact_output_dir")
-                .map_or("target/rushd".to_string(), |v| {
-                    Self::process_template_string(v.as_str().unwrap(), &variables)
-                }),
-            docker_extra_run_args: yaml_section.get("docker_extra_run_args").map_or_else(
-                Vec::new,
-                |v| {
-                    v.as_sequence()
-                        .unwrap()
-                        .iter()
-                        .map(|item| {
-                            Self::process_template_string(item.as_str().unwrap(), &variables)
-                        })
-                        .collect()
-                },
-            ),
-            env: yaml_section.get("env").map(|v| {
-                v.as_mapping()
-                    .unwrap()
-                    .iter()
-                    .map(|(k, val)| {
-                        let v = Self::process_template_string(val.as_str().unwrap(), &variables);
-                        (
-                            Self::process_template_string(k.as_str().unwrap(), &variables),
-                            v,
-                        )
-                    })
-                    .collect()
-            }),
-            volumes: yaml_section.get("volumes").map(|v| {
-                v.as_mapping()
-                    .unwrap()
-                    .iter()
-                    .map(|(k, val)| {
+            artefacts: match &section.artefacts {
+                Some(artefacts) => Some(template_map(artefacts)?.into_iter().collect()),
+                None => None,
+            },
+            artefact_output_dir: template(&section.artefact_output_dir)?,
+            docker_extra_run_args: templates(&section.docker_extra_run_args)?,
+            network_aliases: templates(&section.network_aliases)?,
+            extra_networks: templates(&section.extra_networks)?,
+            env: match &section.env {
+                Some(env) => Some(template_map(env)?.into_iter().collect()),
+                None => None,
+            },
+            volumes: volumes.map(|volumes| {
+                volumes
+                    .into_iter()
+                    .map(|(host_path, container_path)| {
                         let absolute_path = std::path::Path::new(&cwd)
-                            .join(Self::process_template_string(
-                                k.as_str().unwrap(),
-                                &variables,
-                            ))
+                            .join(host_path)
                             .to_str()
                             .unwrap()
                             .to_string();
-                        (
-                            absolute_path,
-                            Self::process_template_string(val.as_str().unwrap(), &variables),
-                        )
+                        (absolute_path, container_path)
                     })
                     .collect()
             }),
-            port: yaml_section.get("port").map(|v| {
-                if let Some(port_str) = v.as_str() {
-                    let processed_str = Self::process_template_string(port_str, &variables);
-                    processed_str
-                        .parse::<u16>()
-                        .unwrap_or_else(|_| panic!("Could not parse {}", processed_str))
-                } else {
-                    v.as_u64().unwrap() as u16
-                }
-            }),
-            target_port: yaml_section.get("target_port").map(|v| {
-                if let Some(target_port_str) = v.as_str() {
-                    let processed_str = Self::process_template_string(target_port_str, &variables);
-                    processed_str
-                        .parse::<u16>()
-                        .unwrap_or_else(|_| panic!("Could not parse {}", processed_str))
-                } else {
-                    v.as_u64().unwrap() as u16
-                }
-            }),
-            k8s: yaml_section
-                .get("k8s")
-                .map(|v| Self::process_template_string(v.as_str().unwrap(), &variables)),
-            priority: yaml_section
-                .get("priority")
-                .map_or(100, |v| v.as_u64().unwrap()),
+            named_volumes: named_volumes.into_iter().collect(),
+            port: port("port", &section.port)?,
+            target_port: port("target_port", &section.target_port)?,
+            k8s: optional_template(&section.k8s)?,
+            priority: section.priority,
             watch,
+            helm: section.helm,
+            deploy_strategy: section.deploy_strategy,
+            jobs: section.jobs,
+            environments,
+            debug: section.debug,
+            debug_port: port("debug_port", &section.debug_port)?.unwrap_or(DEFAULT_DEBUG_PORT),
+            database: section.database,
             config,
-            variables,
+            variables: variables.clone(),
             services: None,
             tagged_image_name: None,
             dotenv,
             dotenv_secrets,
+            dotenv_local,
             domain,
             domains: None,
-        }
+        })
     }
 
-    fn process_template_string(input: &str, variables: &Arc<Variables>) -> String {
+    /// Reads the component's .env files again, relative to the current directory, e.g.
+    /// after `rush reload-env` regenerated them. Returns whether the environment of the
+    /// component's container changed.
+    pub fn reload_dotenv(&mut self) -> Result<bool, RushError> {
+        let component_path = self.build_type.location().map(std::path::Path::new);
+        let (dotenv, dotenv_secrets, dotenv_local) = load_component_dotenv(component_path)
+            .map_err(|e| RushError::spec(&self.component_name, e))?;
+        let changed = dotenv != self.dotenv
+            || dotenv_secrets != self.dotenv_secrets
+            || dotenv_local != self.dotenv_local;
+        self.dotenv = dotenv;
+        self.dotenv_secrets = dotenv_secrets;
+        self.dotenv_local = dotenv_local;
+        Ok(changed)
+    }
+
+    /// Whether the component is part of the product in the config's environment.
+    pub fn is_enabled(&self) -> bool {
+        self.environments.as_ref().is_none_or(|environments| {
+            environments
+                .iter()
+                .any(|environment| environment == self.config.environment())
+        })
+    }
+
+    fn process_template_string(input: &str, variables: &Arc<Variables>) -> Result<String, String> {
         if input.starts_with("{{") && input.ends_with("}}") {
             let var_name = input.trim_start_matches("{{").trim_end_matches("}}").trim();
             variables
                 .get(var_name)
-                .unwrap_or_else(|| panic!("Variable `{}` not found", var_name))
-                .to_string()
+                .ok_or_else(|| format!("Variable `{}` not found", var_name))
         } else {
-            input.to_string()
+            Ok(input.to_string())
         }
     }
 
@@ -513,13 +458,32 @@ impl ComponentBuildSpec {
         let product_name = self.product_name.clone();
         let product_uri = slug::slugify(&product_name);
 
+        let sccache = match (self.config.rust_build_cache(), toolchain.sccache()) {
+            ("sccache", Some(sccache)) => Some(sccache.to_string()),
+            ("sccache", None) => {
+                warn!("RUST_BUILD_CACHE is sccache, but sccache is not installed");
+                None
+            }
+            _ => None,
+        };
+        // Shared by all products, so dependencies are compiled once per machine
+        let sccache_dir = std::env::var("SCCACHE_DIR").unwrap_or_else(|_| {
+            format!(
+                "{}/.cache/rush/sccache",
+                std::env::var("HOME").unwrap_or_default()
+            )
+        });
+
         BuildContext {
             toolchain: (*toolchain).clone(),
+            sccache,
+            sccache_dir,
             build_type: self.build_type.clone(),
             location,
             target: toolchain.target().clone(),
             host: toolchain.host().clone(),
             rust_target: toolchain.target().to_rust_target(),
+            target_arch: toolchain.target().arch.to_docker_target(),
             services: services.unwrap_or_default(),
             environment: self.config.environment().to_string(),
             domain: self.domain.clone(),
@@ -528,9 +492,162 @@ impl ComponentBuildSpec {
             component: self.component_name.clone(),
             docker_registry: self.config.docker_registry().to_string(),
             image_name: self.tagged_image_name.clone().unwrap_or_default(),
+            image_digest: None,
+            kube_context: self.config.kube_context().to_string(),
+            cluster: HashMap::new(),
             secrets,
             domains,
             env: self.dotenv.clone(),
+            debug: false,
         }
     }
 }
+
+type DotenvFiles = (
+    HashMap<String, String>,
+    HashMap<String, String>,
+    HashMap<String, String>,
+);
+
+/// Loads the .env, .env.secrets and .env.local of the component in `component_path`,
+/// those missing being empty.
+fn load_component_dotenv(component_path: Option<&std::path::Path>) -> Result<DotenvFiles, String> {
+    let load = |file_name: &str| match component_path {
+        Some(path) if path.join(file_name).exists() => load_dotenv(&path.join(file_name))
+            .map_err(|e| format!("Failed to load {}: {}", file_name, e)),
+        _ => Ok(HashMap::new()),
+    };
+    let [dotenv, dotenv_secrets, dotenv_local] = DOTENV_FILES;
+    Ok((load(dotenv)?, load(dotenv_secrets)?, load(dotenv_local)?))
+}
+
+/// A component's section of stack.spec.yaml as written, before its templates are
+/// rendered. Besides the component_name, which of the fields a component needs depends
+/// on its build_type.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ComponentSection {
+    component_name: String,
+    build_type: Option<String>,
+    location: Option<String>,
+    dockerfile: Option<String>,
+    context_dir: Option<String>,
+    #[serde(default)]
+    cross: bool,
+    components: Option<Vec<String>>,
+    image: Option<String>,
+    command: Option<String>,
+    entrypoint: Option<String>,
+    namespace: Option<String>,
+    build: Option<String>,
+    nix_flake: Option<String>,
+    #[serde(default = "default_color")]
+    color: String,
+    #[serde(default)]
+    depends_on: Vec<String>,
+    mount_point: Option<String>,
+    health_check: Option<String>,
+    subdomain: Option<String>,
+    artefacts: Option<HashMap<String, String>>,
+    #[serde(default = "default_artefact_output_dir")]
+    artefact_output_dir: String,
+    #[serde(default)]
+    docker_extra_run_args: Vec<String>,
+    #[serde(default)]
+    network_aliases: Vec<String>,
+    #[serde(default)]
+    extra_networks: Vec<String>,
+    env: Option<HashMap<String, String>>,
+    volumes: Option<HashMap<String, String>>,
+    port: Option<PortValue>,
+    target_port: Option<PortValue>,
+    #[serde(alias = "k8s_dir")]
+    k8s: Option<String>,
+    #[serde(default = "default_priority")]
+    priority: u64,
+    watch: Option<Vec<String>>,
+    helm: Option<HelmChart>,
+    #[serde(default)]
+    deploy_strategy: DeployStrategy,
+    #[serde(default)]
+    jobs: ComponentJobs,
+    environments: Option<Vec<String>>,
+    #[serde(default)]
+    debug: bool,
+    debug_port: Option<PortValue>,
+    database: Option<DatabaseSpec>,
+}
+
+/// A port, written as a number or as a template rendering to one.
+#[derive(Debug)]
+enum PortValue {
+    Number(u16),
+    Template(String),
+}
+
+// Visited rather than untagged, as errors of untagged enums lose the path to the key
+impl<'de> Deserialize<'de> for PortValue {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct PortVisitor;
+
+        impl serde::de::Visitor<'_> for PortVisitor {
+            type Value = PortValue;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str("a port number or a template")
+            }
+
+            fn visit_u64<E: serde::de::Error>(self, port: u64) -> Result<PortValue, E> {
+                u16::try_from(port)
+                    .map(PortValue::Number)
+                    .map_err(|_| E::invalid_value(serde::de::Unexpected::Unsigned(port), &self))
+            }
+
+            fn visit_i64<E: serde::de::Error>(self, port: i64) -> Result<PortValue, E> {
+                Err(E::invalid_value(serde::de::Unexpected::Signed(port), &self))
+            }
+
+            fn visit_str<E: serde::de::Error>(self, template: &str) -> Result<PortValue, E> {
+                Ok(PortValue::Template(template.to_string()))
+            }
+        }
+
+        deserializer.deserialize_any(PortVisitor)
+    }
+}
+
+fn default_color() -> String {
+    "blue".to_string()
+}
+
+fn default_artefact_output_dir() -> String {
+    "target/rushd".to_string()
+}
+
+fn default_priority() -> u64 {
+    100
+}
+
+impl ComponentSection {
+    /// Deserializes the section, with errors naming the path of the key they are about,
+    /// e.g. `database.port`.
+    fn from_value(section: &serde_yaml::Value) -> Result<Self, String> {
+        // Unlike a Value, serde_yaml's deserializer tracks the path, so the section is
+        // written out and read again
+        let text = serde_yaml::to_string(section).map_err(|e| e.to_string())?;
+        Self::deserialize(serde_yaml::Deserializer::from_str(&text)).map_err(|e| {
+            // The location is in that text rather than in stack.spec.yaml
+            let message = e.to_string();
+            match e.location() {
+                Some(location) => message
+                    .trim_end_matches(&format!(
+                        " at line {} column {}",
+                        location.line(),
+                        location.column()
+                    ))
+                    .to_string(),
+                None => message,
+            }
+        })
+    }
+}
@@ -0,0 +1,141 @@
+use crate::toolchain::ToolchainContext;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::process::Command;
+
+/// How the images of a product are tagged, set with TAG_STRATEGY or <ENV>_TAG_STRATEGY.
+///
+/// All strategies add `-wip` when the product directory has uncommitted changes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TagStrategy {
+    /// The first 8 characters of the last commit touching the product, e.g. `1a2b3c4d`.
+    #[default]
+    GitHash,
+    /// The semantic version in the product's VERSION file, e.g. `1.4.2`.
+    Semver,
+    /// `git describe --tags --always`, e.g. `v1.4.2-3-g1a2b3c4`.
+    GitDescribe,
+    /// The commit date followed by the hash, e.g. `20261017-1a2b3c4d`.
+    Date,
+    /// The current branch as a slug followed by the hash, e.g. `feature-login-1a2b3c4d`.
+    Branch,
+}
+
+impl TagStrategy {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "git-hash" => Some(TagStrategy::GitHash),
+            "semver" => Some(TagStrategy::Semver),
+            "git-describe" => Some(TagStrategy::GitDescribe),
+            "date" => Some(TagStrategy::Date),
+            "branch" => Some(TagStrategy::Branch),
+            _ => None,
+        }
+    }
+
+    /// The tag of the product's images, where `git_hash` is the last commit touching
+    /// `product_path`.
+    pub fn tag(
+        &self,
+        toolchain: &ToolchainContext,
+        product_path: &str,
+        git_hash: &str,
+    ) -> Result<String, String> {
+        let short_hash = &git_hash[..8.min(git_hash.len())];
+        let tag = match self {
+            TagStrategy::GitHash => short_hash.to_string(),
+            TagStrategy::Semver => {
+                let path = Path::new(product_path).join("VERSION");
+                let version = std::fs::read_to_string(&path)
+                    .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+                let version = version.trim().trim_start_matches('v');
+                let parsed = semver::Version::parse(version)
+                    .map_err(|e| format!("{} is not a semantic version: {}", path.display(), e))?;
+                if !parsed.build.is_empty() {
+                    return Err(format!(
+                        "{} has build metadata, which image tags cannot hold",
+                        path.display()
+                    ));
+                }
+                parsed.to_string()
+            }
+            TagStrategy::GitDescribe => git(
+                toolchain,
+                product_path,
+                &["describe", "--tags", "--always", git_hash],
+            )?,
+            TagStrategy::Date => {
+                let date = git(
+                    toolchain,
+                    product_path,
+                    &[
+                        "show",
+                        "-s",
+                        "--format=%cd",
+                        "--date=format:%Y%m%d",
+                        git_hash,
+                    ],
+                )?;
+                format!("{}-{}", date, short_hash)
+            }
+            TagStrategy::Branch => {
+                let branch = git(
+                    toolchain,
+                    product_path,
+                    &["rev-parse", "--abbrev-ref", "HEAD"],
+                )?;
+                if branch == "HEAD" {
+                    return Err("The branch tag strategy needs a branch checked out".to_string());
+                }
+                format!("{}-{}", slug(&branch), short_hash)
+            }
+        };
+
+        let wip = toolchain.get_git_wip(product_path).unwrap_or_default();
+        Ok(sanitize(&format!("{}{}", tag, wip)))
+    }
+}
+
+fn git(toolchain: &ToolchainContext, directory: &str, args: &[&str]) -> Result<String, String> {
+    let output = Command::new(toolchain.git())
+        .args(args)
+        .current_dir(directory)
+        .output()
+        .map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        return Err(format!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Lower case letters and digits, with any other run of characters made a single `-`,
+/// short enough to leave room in the tag for the hash and suffixes.
+fn slug(name: &str) -> String {
+    let slug = name
+        .to_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    slug[..64.min(slug.len())].trim_end_matches('-').to_string()
+}
+
+/// Docker tags only hold `[A-Za-z0-9_.-]` and cannot start with `.` or `-`.
+fn sanitize(tag: &str) -> String {
+    tag.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || "_.-".contains(c) {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect::<String>()
+        .trim_start_matches(['.', '-'])
+        .to_string()
+}
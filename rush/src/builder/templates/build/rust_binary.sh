@@ -5,6 +5,15 @@ export DOMAIN_{{ d | envname }}="{{ v }}"
 {% for d,v in env -%}
 export {{ d | envname }}="{{ v }}"
 {% endfor %}
-
+{% if sccache -%}
+# sccache caches compiled crates across builds, which incremental builds would defeat
+export RUSTC_WRAPPER="{{ sccache }}"
+export SCCACHE_DIR="{{ sccache_dir }}"
+export CARGO_INCREMENTAL=0
+{% endif %}
 export SQLX_OFFLINE=true
-CARGO_TARGET_DIR=./target cargo build --release --target {{ rust_target }} --config "target.{{ rust_target }}.linker = '{{toolchain.cc}}'"
+{% if debug -%}
+# Unoptimized with debug info for a debugger, in the release directory Dockerfiles copy from
+DEBUG_PROFILE="--config profile.release.opt-level=0 --config profile.release.debug=true --config profile.release.strip=false --config profile.release.lto=false"
+{% endif -%}
+CARGO_TARGET_DIR=./target cargo build --release $DEBUG_PROFILE --target {{ rust_target }} --config "target.{{ rust_target }}.linker = '{{toolchain.cc}}'"
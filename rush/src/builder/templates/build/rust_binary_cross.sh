@@ -0,0 +1,18 @@
+cd {{ location }} || exit
+{% for d,v in domains -%}
+export DOMAIN_{{ d | envname }}="{{ v }}"
+{% endfor %}
+{% for d,v in env -%}
+export {{ d | envname }}="{{ v }}"
+{% endfor %}
+command -v cross >/dev/null || { echo "cross is not installed. Install it with: cargo install cross"; exit 1; }
+
+# cross picks the platform of its build images, which only exist for the host's architecture
+unset DOCKER_DEFAULT_PLATFORM CROSS_CONTAINER_OPTS
+
+export SQLX_OFFLINE=true
+{% if debug -%}
+# Unoptimized with debug info for a debugger, in the release directory Dockerfiles copy from
+DEBUG_PROFILE="--config profile.release.opt-level=0 --config profile.release.debug=true --config profile.release.strip=false --config profile.release.lto=false"
+{% endif -%}
+CARGO_TARGET_DIR=./target cross build --release $DEBUG_PROFILE --target {{ rust_target }}
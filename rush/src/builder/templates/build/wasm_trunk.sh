@@ -5,5 +5,10 @@ export DOMAIN_{{ d | envname }}="{{ v }}"
 {% for d,v in env -%}
 export {{ d | envname }}="{{ v }}"
 {% endfor %}
-
+{% if sccache -%}
+# sccache caches compiled crates across builds, which incremental builds would defeat
+export RUSTC_WRAPPER="{{ sccache }}"
+export SCCACHE_DIR="{{ sccache_dir }}"
+export CARGO_INCREMENTAL=0
+{% endif %}
 CARGO_TARGET_DIR=./target wasm-trunk build --release
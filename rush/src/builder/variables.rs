@@ -1,13 +1,13 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::path::PathBuf;
 use std::sync::Arc;
 
+/// Variables keyed by environment name, so any environment declared in rushd.yaml can have its own.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct VariablesFile {
-    pub dev: HashMap<String, String>,
-    pub staging: HashMap<String, String>,
-    pub prod: HashMap<String, String>,
-    pub local: HashMap<String, String>,
+    #[serde(flatten)]
+    pub environments: HashMap<String, HashMap<String, String>>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -17,38 +17,43 @@ pub struct Variables {
 }
 
 impl Variables {
-    pub fn new(path: &str, env: &str) -> Arc<Self> {
-        let contents = match std::fs::read_to_string(path) {
-            Ok(content) => content,
-            Err(_) => {
-                return Arc::new(Variables {
-                    values: VariablesFile {
-                        dev: HashMap::new(),
-                        staging: HashMap::new(),
-                        prod: HashMap::new(),
-                        local: HashMap::new(),
-                    },
-                    env: env.to_lowercase(),
-                })
-            }
+    /// Loads the variables from each file in turn, so later files override earlier ones,
+    /// and finally applies `overrides` to the current environment. Missing files are skipped.
+    pub fn new(paths: &[PathBuf], env: &str, overrides: &HashMap<String, String>) -> Arc<Self> {
+        let mut values = VariablesFile {
+            environments: HashMap::new(),
         };
+        for path in paths {
+            let contents = match std::fs::read_to_string(path) {
+                Ok(content) => content,
+                Err(_) => continue,
+            };
 
-        let variables =
-            serde_yaml::from_str(&contents).expect("Could not parse variables YAML file");
+            let variables: VariablesFile =
+                serde_yaml::from_str(&contents).expect("Could not parse variables YAML file");
+            for (environment, environment_values) in variables.environments {
+                values
+                    .environments
+                    .entry(environment)
+                    .or_default()
+                    .extend(environment_values);
+            }
+        }
 
-        Arc::new(Variables {
-            values: variables,
-            env: env.to_lowercase(),
-        })
+        let env = env.to_lowercase();
+        values
+            .environments
+            .entry(env.clone())
+            .or_default()
+            .extend(overrides.clone());
+
+        Arc::new(Variables { values, env })
     }
 
     pub fn get(&self, key: &str) -> Option<String> {
-        match self.env.as_str() {
-            "dev" => self.values.dev.get(key).cloned(),
-            "staging" => self.values.staging.get(key).cloned(),
-            "prod" => self.values.prod.get(key).cloned(),
-            "local" => self.values.local.get(key).cloned(),
-            _ => None,
-        }
+        self.values
+            .environments
+            .get(&self.env)
+            .and_then(|values| values.get(key).cloned())
     }
 }
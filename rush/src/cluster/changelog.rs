@@ -0,0 +1,131 @@
+use crate::builder::BuildType;
+use crate::container::docker::DockerImage;
+use std::path::Path;
+use std::process::Command;
+
+/// A commit listed in a changelog.
+#[derive(Debug, Clone)]
+pub struct ChangelogCommit {
+    pub hash: String,
+    pub author: String,
+    pub date: String,
+    pub subject: String,
+}
+
+/// The commits touching the files a component is built and deployed from.
+#[derive(Debug)]
+pub struct ComponentChangelog {
+    pub component_name: String,
+    pub commits: Vec<ChangelogCommit>,
+}
+
+/// Lists, per component, the commits since `since` that touch its Dockerfile, build
+/// context, source location or manifests. Components without any are left out.
+pub fn changelog(
+    git: &str,
+    product_directory: &str,
+    since: &str,
+    images: &[DockerImage],
+) -> Result<Vec<ComponentChangelog>, String> {
+    let known = Command::new(git)
+        .args(["cat-file", "-e", &format!("{}^{{commit}}", since)])
+        .current_dir(product_directory)
+        .output()
+        .map_err(|e| format!("Failed to run {}: {}", git, e))?;
+    if !known.status.success() {
+        return Err(format!(
+            "Commit {} is not in this repository, try fetching it first",
+            since
+        ));
+    }
+
+    let mut changelogs = Vec::new();
+    for image in images {
+        let paths = component_paths(image);
+        if paths.is_empty() {
+            continue;
+        }
+        let commits = commits_since(git, product_directory, since, &paths)?;
+        if !commits.is_empty() {
+            changelogs.push(ComponentChangelog {
+                component_name: image.component_name(),
+                commits,
+            });
+        }
+    }
+    changelogs.sort_by(|a, b| a.component_name.cmp(&b.component_name));
+    Ok(changelogs)
+}
+
+/// The paths, relative to the product directory, a component's image and manifests
+/// are made from. Pulled images only change with their manifests.
+fn component_paths(image: &DockerImage) -> Vec<String> {
+    let spec = image.spec();
+    let mut paths = Vec::new();
+    if let Some(dockerfile) = spec.build_type.dockerfile_path() {
+        // The build context is relative to the Dockerfile's directory
+        let dockerfile_directory = Path::new(dockerfile).parent().unwrap_or(Path::new(""));
+        let context_dir = image.describe().context_dir.unwrap_or_default();
+        paths.push(dockerfile.to_string());
+        paths.push(dockerfile_directory.join(context_dir).display().to_string());
+    }
+    match &spec.build_type {
+        BuildType::TrunkWasm { location, .. }
+        | BuildType::DixiousWasm { location, .. }
+        | BuildType::RustBinary { location, .. }
+        | BuildType::Script { location, .. }
+        | BuildType::Zola { location, .. }
+        | BuildType::Book { location, .. } => paths.push(location.clone()),
+        _ => {}
+    }
+    paths.extend(spec.k8s);
+    // An empty path is the product directory itself
+    paths
+        .into_iter()
+        .map(|path| {
+            if path.is_empty() {
+                ".".to_string()
+            } else {
+                path
+            }
+        })
+        .collect()
+}
+
+fn commits_since(
+    git: &str,
+    product_directory: &str,
+    since: &str,
+    paths: &[String],
+) -> Result<Vec<ChangelogCommit>, String> {
+    let output = Command::new(git)
+        .args([
+            "log",
+            "--format=%H%x1f%an%x1f%ad%x1f%s",
+            "--date=short",
+            &format!("{}..HEAD", since),
+            "--",
+        ])
+        .args(paths)
+        .current_dir(product_directory)
+        .output()
+        .map_err(|e| format!("Failed to run {}: {}", git, e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "git log failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(4, '\x1f');
+            Some(ChangelogCommit {
+                hash: fields.next()?.to_string(),
+                author: fields.next()?.to_string(),
+                date: fields.next()?.to_string(),
+                subject: fields.next()?.to_string(),
+            })
+        })
+        .collect())
+}
@@ -0,0 +1,139 @@
+use crate::cluster::documents::edit_workloads;
+use openssl::hash::MessageDigest;
+use openssl::pkey::PKey;
+use openssl::sha::Sha256;
+use openssl::sign::Signer;
+use serde_yaml::{Mapping, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::env::VarError;
+use std::path::{Path, PathBuf};
+
+pub const SECRETS_CHECKSUM_ANNOTATION: &str = "rush.io/secrets-checksum";
+/// Marks manifests rendered with `--fake-secrets`, which must not be applied.
+pub const FAKE_SECRETS_ANNOTATION: &str = "rush.io/fake-secrets";
+/// Left in the render directory of each component rendered with `--fake-secrets`, so
+/// that a later run without the flag still refuses to apply what is there. kubectl
+/// only applies `.yaml`, `.yml` and `.json` files, so it skips the marker.
+pub const FAKE_SECRETS_MARKER: &str = ".fake-secrets";
+
+/// Variable holding a key, set in the CI secrets or the user config and never in
+/// rushd.yaml, that makes [`secrets_checksum`] an HMAC. It must be the same wherever a
+/// product is deployed from, as a different key rolls the pods.
+pub const SECRETS_CHECKSUM_KEY: &str = "SECRETS_CHECKSUM_KEY";
+
+/// The key of [`secrets_checksum`], if [`SECRETS_CHECKSUM_KEY`] is set.
+pub fn secrets_checksum_key() -> Result<Option<Vec<u8>>, String> {
+    match std::env::var(SECRETS_CHECKSUM_KEY) {
+        Ok(key) if key.trim().is_empty() => Err(format!(
+            "{} is set but empty, unset it for an unkeyed checksum",
+            SECRETS_CHECKSUM_KEY
+        )),
+        Ok(key) => Ok(Some(key.into_bytes())),
+        Err(VarError::NotPresent) => Ok(None),
+        Err(e) => Err(format!("Invalid {}: {}", SECRETS_CHECKSUM_KEY, e)),
+    }
+}
+
+/// Hashes a component's secrets independently of their order. With a `key` it is an
+/// HMAC, so the annotation cannot be checked against guesses of the secrets.
+pub fn secrets_checksum(
+    key: Option<&[u8]>,
+    secrets: &HashMap<String, String>,
+) -> Result<String, String> {
+    let mut text = Vec::new();
+    for (name, value) in secrets.iter().collect::<BTreeMap<_, _>>() {
+        text.extend_from_slice(name.as_bytes());
+        text.push(b'=');
+        text.extend_from_slice(value.as_bytes());
+        text.push(b'\n');
+    }
+    let digest = match key {
+        Some(key) => PKey::hmac(key)
+            .and_then(|key| {
+                let mut signer = Signer::new(MessageDigest::sha256(), &key)?;
+                signer.update(&text)?;
+                signer.sign_to_vec()
+            })
+            .map_err(|e| format!("Failed to checksum the secrets: {}", e))?,
+        None => {
+            let mut hasher = Sha256::new();
+            hasher.update(&text);
+            hasher.finish().to_vec()
+        }
+    };
+    Ok(hex::encode(digest))
+}
+
+/// Adds an annotation to the pod template of every workload rendered in `directory`,
+/// so that changing its value makes Kubernetes roll the pods.
+pub fn annotate_pod_templates(
+    directory: &Path,
+    annotation: &str,
+    value: &str,
+) -> Result<(), String> {
+    edit_workloads(directory, |document| {
+        let annotations = &mut document["spec"]["template"]["metadata"]["annotations"];
+        if !annotations.is_mapping() {
+            *annotations = Value::Mapping(Mapping::new());
+        }
+        annotations[annotation] = Value::from(value);
+    })
+}
+
+/// Marks the render directory of a component as holding placeholders, see
+/// [`FAKE_SECRETS_MARKER`].
+pub fn mark_fake_secrets(directory: &Path) -> Result<(), String> {
+    let path = directory.join(FAKE_SECRETS_MARKER);
+    std::fs::write(&path, "Rendered with --fake-secrets, do not apply\n")
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// The first render directory below `directory` marked as holding placeholders.
+pub fn find_fake_secrets(directory: &Path) -> Option<PathBuf> {
+    let mut markers = ignore::WalkBuilder::new(directory)
+        .standard_filters(false)
+        .build()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_name() == FAKE_SECRETS_MARKER)
+        .filter_map(|entry| entry.path().parent().map(Path::to_path_buf))
+        .collect::<Vec<_>>();
+    markers.sort();
+    markers.into_iter().next()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secrets_checksum_ignores_order() {
+        let mut first = HashMap::new();
+        let mut second = HashMap::new();
+        for (name, value) in [("A", "1"), ("B", "2"), ("C", "3")] {
+            first.insert(name.to_string(), value.to_string());
+        }
+        for (name, value) in [("C", "3"), ("A", "1"), ("B", "2")] {
+            second.insert(name.to_string(), value.to_string());
+        }
+        assert_eq!(
+            secrets_checksum(None, &first).unwrap(),
+            secrets_checksum(None, &second).unwrap()
+        );
+        assert_eq!(
+            secrets_checksum(Some(b"key"), &first).unwrap(),
+            secrets_checksum(Some(b"key"), &second).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_secrets_checksum_depends_on_key() {
+        let secrets = HashMap::from([("A".to_string(), "1".to_string())]);
+        assert_eq!(
+            secrets_checksum(None, &secrets).unwrap(),
+            hex::encode(openssl::sha::sha256(b"A=1\n"))
+        );
+        let keyed = secrets_checksum(Some(b"key"), &secrets).unwrap();
+        assert_ne!(keyed, secrets_checksum(None, &secrets).unwrap());
+        assert_ne!(keyed, secrets_checksum(Some(b"other"), &secrets).unwrap());
+    }
+}
@@ -0,0 +1,94 @@
+use log::trace;
+use serde::Deserialize;
+use serde_yaml::Value;
+use std::fs;
+use std::path::Path;
+
+/// Reads every YAML document rendered for a component.
+pub(crate) fn load_documents(directory: &Path) -> Result<Vec<Value>, String> {
+    let mut paths = fs::read_dir(directory)
+        .map_err(|e| format!("Failed to read {}: {}", directory.display(), e))?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "yaml"))
+        .collect::<Vec<_>>();
+    paths.sort();
+
+    let mut documents = Vec::new();
+    for path in paths {
+        let contents = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+        for document in serde_yaml::Deserializer::from_str(&contents) {
+            let value = Value::deserialize(document)
+                .map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?;
+            if !value.is_null() {
+                documents.push(value);
+            }
+        }
+    }
+    Ok(documents)
+}
+
+pub(crate) fn kind_of(document: &Value) -> &str {
+    document["kind"].as_str().unwrap_or_default()
+}
+
+pub(crate) fn name_of(document: &Value) -> String {
+    document["metadata"]["name"]
+        .as_str()
+        .unwrap_or_default()
+        .to_string()
+}
+
+pub(crate) fn namespace_of(document: &Value) -> Option<String> {
+    document["metadata"]["namespace"]
+        .as_str()
+        .map(|namespace| namespace.to_string())
+}
+
+/// Rewrites the Deployments, StatefulSets and DaemonSets rendered in `directory` with
+/// `edit`, leaving the files without any as they are.
+pub(crate) fn edit_workloads(
+    directory: &Path,
+    mut edit: impl FnMut(&mut Value),
+) -> Result<(), String> {
+    let entries = fs::read_dir(directory)
+        .map_err(|e| format!("Failed to read {}: {}", directory.display(), e))?;
+    for path in entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "yaml"))
+    {
+        let contents = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+        let mut documents = Vec::new();
+        for document in serde_yaml::Deserializer::from_str(&contents) {
+            let value = Value::deserialize(document)
+                .map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?;
+            if !value.is_null() {
+                documents.push(value);
+            }
+        }
+
+        let mut changed = false;
+        for document in &mut documents {
+            if !["Deployment", "StatefulSet", "DaemonSet"].contains(&kind_of(document)) {
+                continue;
+            }
+            edit(document);
+            changed = true;
+        }
+        if !changed {
+            continue;
+        }
+
+        trace!("Rewriting workloads in {}", path.display());
+        let mut contents = String::new();
+        for document in &documents {
+            contents.push_str("---\n");
+            contents.push_str(&serde_yaml::to_string(document).map_err(|e| e.to_string())?);
+        }
+        fs::write(&path, contents)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    }
+
+    Ok(())
+}
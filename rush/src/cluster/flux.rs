@@ -0,0 +1,220 @@
+use crate::builder::HelmChart;
+use log::trace;
+use serde_json::json;
+use std::fs;
+use std::path::Path;
+
+/// Writes the resources Flux needs to reconcile a product from the
+/// infrastructure repository.
+///
+/// The layout produced in the infrastructure repository is:
+///
+/// ```text
+/// clusters/<environment>/<product_uri>.yaml      Flux Kustomization (or clusters/<cluster>)
+/// <target path>/
+///     kustomization.yaml                         lists the component directories
+///     <component>/kustomization.yaml             lists the component manifests
+///     <component>/helmrepository.yaml            only for components with a chart
+///     <component>/helmrelease.yaml               only for components with a chart
+/// ```
+pub struct FluxLayout {
+    target_path: String,
+    product_uri: String,
+    environment: String,
+    source_name: String,
+    namespace: String,
+}
+
+impl FluxLayout {
+    pub fn new(
+        target_path: &str,
+        product_uri: &str,
+        environment: &str,
+        source_name: &str,
+        namespace: &str,
+    ) -> Self {
+        FluxLayout {
+            target_path: target_path.to_string(),
+            product_uri: product_uri.to_string(),
+            environment: environment.to_string(),
+            source_name: source_name.to_string(),
+            namespace: namespace.to_string(),
+        }
+    }
+
+    /// Writes the Flux resources into `repository_root`. The plain manifests must
+    /// already have been copied into the product directory of the repository.
+    ///
+    /// When the environment spans several clusters, each cluster gets its own
+    /// `<target path>/<cluster>` directory and `clusters/<cluster>` Kustomization.
+    pub fn write(
+        &self,
+        repository_root: &Path,
+        cluster: Option<&str>,
+        helm_releases: &[(String, HelmChart)],
+    ) -> Result<(), String> {
+        let target_path = match cluster {
+            Some(cluster) => format!("{}/{}", self.target_path, cluster),
+            None => self.target_path.clone(),
+        };
+        let product_directory = repository_root.join(&target_path);
+        fs::create_dir_all(&product_directory).map_err(|e| e.to_string())?;
+
+        for (component_name, chart) in helm_releases {
+            let component_directory = product_directory.join(component_name);
+            fs::create_dir_all(&component_directory).map_err(|e| e.to_string())?;
+            self.write_helm_release(&component_directory, component_name, chart)?;
+        }
+
+        let mut component_directories = Vec::new();
+        for entry in fs::read_dir(&product_directory).map_err(|e| e.to_string())? {
+            let path = entry.map_err(|e| e.to_string())?.path();
+            if path.is_dir() {
+                self.write_component_kustomization(&path)?;
+                component_directories.push(file_name(&path));
+            }
+        }
+        component_directories.sort();
+
+        write_yaml(
+            &product_directory.join("kustomization.yaml"),
+            &json!({
+                "apiVersion": "kustomize.config.k8s.io/v1beta1",
+                "kind": "Kustomization",
+                "resources": component_directories,
+            }),
+        )?;
+
+        let cluster_directory = repository_root
+            .join("clusters")
+            .join(cluster.unwrap_or(&self.environment));
+        fs::create_dir_all(&cluster_directory).map_err(|e| e.to_string())?;
+        write_yaml(
+            &cluster_directory.join(format!("{}.yaml", self.product_uri)),
+            &json!({
+                "apiVersion": "kustomize.toolkit.fluxcd.io/v1",
+                "kind": "Kustomization",
+                "metadata": {
+                    "name": format!("{}-{}", self.product_uri, self.environment),
+                    "namespace": self.namespace,
+                },
+                "spec": {
+                    "interval": "10m",
+                    "path": format!("./{}", target_path),
+                    "prune": true,
+                    "sourceRef": {
+                        "kind": "GitRepository",
+                        "name": self.source_name,
+                    },
+                },
+            }),
+        )?;
+
+        Ok(())
+    }
+
+    fn write_component_kustomization(&self, component_directory: &Path) -> Result<(), String> {
+        let mut resources = fs::read_dir(component_directory)
+            .map_err(|e| e.to_string())?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.is_file()
+                    && path.extension().is_some_and(|ext| ext == "yaml")
+                    && file_name(path) != "kustomization.yaml"
+            })
+            .map(|path| file_name(&path))
+            .collect::<Vec<_>>();
+        resources.sort();
+
+        trace!(
+            "Writing kustomization for {} with {} resources",
+            component_directory.display(),
+            resources.len()
+        );
+        write_yaml(
+            &component_directory.join("kustomization.yaml"),
+            &json!({
+                "apiVersion": "kustomize.config.k8s.io/v1beta1",
+                "kind": "Kustomization",
+                "resources": resources,
+            }),
+        )
+    }
+
+    fn write_helm_release(
+        &self,
+        component_directory: &Path,
+        component_name: &str,
+        chart: &HelmChart,
+    ) -> Result<(), String> {
+        // Component directories are prefixed with their priority, e.g. `10_postgres`
+        let release_name = component_name
+            .split_once('_')
+            .map_or(component_name, |(_, name)| name);
+        let repository_name = format!("{}-{}", self.product_uri, chart.chart);
+        let target_namespace = chart
+            .namespace
+            .clone()
+            .unwrap_or_else(|| "default".to_string());
+
+        write_yaml(
+            &component_directory.join("helmrepository.yaml"),
+            &json!({
+                "apiVersion": "source.toolkit.fluxcd.io/v1",
+                "kind": "HelmRepository",
+                "metadata": {
+                    "name": repository_name,
+                    "namespace": self.namespace,
+                },
+                "spec": {
+                    "interval": "1h",
+                    "url": chart.repository,
+                },
+            }),
+        )?;
+
+        let mut chart_spec = json!({
+            "chart": chart.chart,
+            "sourceRef": {
+                "kind": "HelmRepository",
+                "name": repository_name,
+                "namespace": self.namespace,
+            },
+        });
+        if let Some(version) = &chart.version {
+            chart_spec["version"] = json!(version);
+        }
+
+        let mut release = json!({
+            "apiVersion": "helm.toolkit.fluxcd.io/v2",
+            "kind": "HelmRelease",
+            "metadata": {
+                "name": release_name,
+                "namespace": self.namespace,
+            },
+            "spec": {
+                "interval": "10m",
+                "targetNamespace": target_namespace,
+                "install": { "createNamespace": true },
+                "chart": { "spec": chart_spec },
+            },
+        });
+        if let Some(values) = &chart.values {
+            release["spec"]["values"] = serde_json::to_value(values).map_err(|e| e.to_string())?;
+        }
+
+        write_yaml(&component_directory.join("helmrelease.yaml"), &release)
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+fn write_yaml(path: &Path, value: &serde_json::Value) -> Result<(), String> {
+    let contents = serde_yaml::to_string(value).map_err(|e| e.to_string())?;
+    fs::write(path, contents).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
@@ -0,0 +1,139 @@
+use crate::cluster::checksum::SECRETS_CHECKSUM_ANNOTATION;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// How the rendered manifests differ from the golden files, by path relative to both
+/// directories.
+#[derive(Debug)]
+pub enum GoldenDifference {
+    /// Rendered, but there is no golden file for it
+    Added(PathBuf),
+    /// A golden file that is no longer rendered
+    Removed(PathBuf),
+    /// Rendered differently, with the lines removed and added, `-` and `+` in front
+    Changed(PathBuf, Vec<String>),
+}
+
+/// Compares the files in `rendered` with those in `golden`. A missing golden directory
+/// counts as empty.
+pub fn compare_golden(rendered: &Path, golden: &Path) -> Result<Vec<GoldenDifference>, String> {
+    let rendered_files = files(rendered)?;
+    let golden_files = files(golden)?;
+
+    let mut differences = Vec::new();
+    for path in rendered_files.union(&golden_files) {
+        match (rendered_files.contains(path), golden_files.contains(path)) {
+            (true, false) => differences.push(GoldenDifference::Added(path.clone())),
+            (false, true) => differences.push(GoldenDifference::Removed(path.clone())),
+            _ => {
+                let expected = read(&golden.join(path))?;
+                let actual = without_secrets_checksum(&read(&rendered.join(path))?);
+                if expected != actual {
+                    differences.push(GoldenDifference::Changed(
+                        path.clone(),
+                        line_diff(&expected, &actual),
+                    ));
+                }
+            }
+        }
+    }
+    Ok(differences)
+}
+
+/// Replaces the golden files with the rendered ones, without their secrets checksums.
+pub fn update_golden(rendered: &Path, golden: &Path) -> Result<(), String> {
+    if golden.exists() {
+        fs::remove_dir_all(golden)
+            .map_err(|e| format!("Failed to remove {}: {}", golden.display(), e))?;
+    }
+    for path in files(rendered)? {
+        let target = golden.join(&path);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        let content = without_secrets_checksum(&read(&rendered.join(&path))?);
+        fs::write(&target, content)
+            .map_err(|e| format!("Failed to write {}: {}", target.display(), e))?;
+    }
+    Ok(())
+}
+
+/// The files below `directory`, relative to it.
+fn files(directory: &Path) -> Result<BTreeSet<PathBuf>, String> {
+    let mut files = BTreeSet::new();
+    if !directory.exists() {
+        return Ok(files);
+    }
+    let mut pending = vec![directory.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        let entries = fs::read_dir(&current)
+            .map_err(|e| format!("Failed to read {}: {}", current.display(), e))?;
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let path = entry.path();
+            if path.is_dir() {
+                pending.push(path);
+            } else if let Ok(relative) = path.strip_prefix(directory) {
+                files.insert(relative.to_path_buf());
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// Masks the secrets checksum annotation, which depends on the secrets and on the key of
+/// whoever renders, so golden files neither differ between machines nor reveal it.
+fn without_secrets_checksum(content: &str) -> String {
+    let prefix = format!("{}:", SECRETS_CHECKSUM_ANNOTATION);
+    content
+        .split_inclusive('\n')
+        .map(|line| match line.trim_start().strip_prefix(&prefix) {
+            Some(_) => {
+                let indent = &line[..line.len() - line.trim_start().len()];
+                let newline = if line.ends_with('\n') { "\n" } else { "" };
+                format!("{}{} '<secrets checksum>'{}", indent, prefix, newline)
+            }
+            None => line.to_string(),
+        })
+        .collect()
+}
+
+fn read(path: &Path) -> Result<String, String> {
+    fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))
+}
+
+/// The lines of `expected` missing from `actual` and the other way round, in order,
+/// from their longest common subsequence.
+fn line_diff(expected: &str, actual: &str) -> Vec<String> {
+    let expected = expected.lines().collect::<Vec<_>>();
+    let actual = actual.lines().collect::<Vec<_>>();
+    // common[i][j] is the length of the common subsequence of expected[i..] and actual[j..]
+    let mut common = vec![vec![0usize; actual.len() + 1]; expected.len() + 1];
+    for i in (0..expected.len()).rev() {
+        for j in (0..actual.len()).rev() {
+            common[i][j] = if expected[i] == actual[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut lines = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < expected.len() || j < actual.len() {
+        if i < expected.len() && j < actual.len() && expected[i] == actual[j] {
+            i += 1;
+            j += 1;
+        } else if i < expected.len() && (j == actual.len() || common[i + 1][j] >= common[i][j + 1])
+        {
+            lines.push(format!("-{}", expected[i]));
+            i += 1;
+        } else {
+            lines.push(format!("+{}", actual[j]));
+            j += 1;
+        }
+    }
+    lines
+}
@@ -0,0 +1,132 @@
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// A single rollout of a product into an environment.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RolloutRecord {
+    pub product_name: String,
+    pub environment: String,
+    pub git_hash: String,
+    /// Tagged image name per component, e.g. `backend -> product-backend:1a2b3c4d`
+    pub images: HashMap<String, String>,
+    pub timestamp: String,
+    /// Environment the images were promoted from, if this was a promotion
+    pub promoted_from: Option<String>,
+    /// A copy of the manifests published, in a subdirectory per cluster, as a record of
+    /// what went into the environment
+    #[serde(default)]
+    pub manifests: Option<PathBuf>,
+}
+
+impl RolloutRecord {
+    pub fn new(
+        product_name: &str,
+        environment: &str,
+        git_hash: &str,
+        images: HashMap<String, String>,
+        promoted_from: Option<String>,
+        manifests: Option<PathBuf>,
+    ) -> Self {
+        RolloutRecord {
+            product_name: product_name.to_string(),
+            environment: environment.to_string(),
+            git_hash: git_hash.to_string(),
+            images,
+            timestamp: Utc::now().to_rfc3339(),
+            promoted_from,
+            manifests,
+        }
+    }
+
+    /// Returns the image tag recorded for a component.
+    pub fn tag_for(&self, component_name: &str) -> Option<&str> {
+        self.images
+            .get(component_name)
+            .and_then(|image| image.rsplit_once(':'))
+            .map(|(_, tag)| tag)
+    }
+}
+
+/// Append-only log of rollouts stored as one JSON record per line.
+pub struct DeploymentHistory {
+    path: PathBuf,
+}
+
+impl DeploymentHistory {
+    pub fn new(path: PathBuf) -> Self {
+        DeploymentHistory { path }
+    }
+
+    pub fn record(&self, record: &RolloutRecord) -> Result<(), String> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let line = serde_json::to_string(record).map_err(|e| e.to_string())?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(|e| format!("Failed to open {}: {}", self.path.display(), e))?;
+        writeln!(file, "{}", line).map_err(|e| e.to_string())
+    }
+
+    /// Returns all records, oldest first.
+    pub fn records(&self) -> Result<Vec<RolloutRecord>, String> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        let contents = fs::read_to_string(&self.path)
+            .map_err(|e| format!("Failed to read {}: {}", self.path.display(), e))?;
+        contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| serde_json::from_str(line).map_err(|e| e.to_string()))
+            .collect()
+    }
+
+    /// A new directory next to the history for the manifests of a rollout into
+    /// `environment`, see [`RolloutRecord::manifests`].
+    pub fn manifests_directory(&self, environment: &str) -> PathBuf {
+        self.path
+            .parent()
+            .unwrap_or(Path::new("."))
+            .join("rollouts")
+            .join(format!(
+                "{}-{}",
+                environment,
+                Utc::now().format("%Y%m%dT%H%M%S%.3f")
+            ))
+    }
+
+    /// Returns the most recent rollout into the given environment.
+    pub fn latest(&self, environment: &str) -> Result<Option<RolloutRecord>, String> {
+        Ok(self
+            .records()?
+            .into_iter()
+            .rev()
+            .find(|record| record.environment == environment))
+    }
+}
+
+/// Copies the files below `source` to `destination`, keeping their relative paths.
+pub fn copy_directory(source: &Path, destination: &Path) -> Result<(), String> {
+    fs::create_dir_all(destination)
+        .map_err(|e| format!("Failed to create {}: {}", destination.display(), e))?;
+    let entries =
+        fs::read_dir(source).map_err(|e| format!("Failed to read {}: {}", source.display(), e))?;
+    for entry in entries {
+        let entry = entry.map_err(|e| e.to_string())?;
+        let target = destination.join(entry.file_name());
+        if entry.path().is_dir() {
+            copy_directory(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), &target)
+                .map_err(|e| format!("Failed to copy {}: {}", entry.path().display(), e))?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,92 @@
+use crate::cluster::documents::edit_workloads;
+use log::warn;
+use serde_yaml::{Mapping, Value};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Where a product directory is mounted into the node of a local cluster.
+#[derive(Debug, Clone)]
+pub struct HostMount {
+    /// The product directory on this machine
+    pub host_directory: String,
+    /// The same directory on the node, e.g. `/rush/helloworld.wonop.io`
+    pub node_directory: String,
+}
+
+impl HostMount {
+    /// The node's path of a path on this machine, if it is in the mounted directory.
+    pub fn node_path(&self, host_path: &str) -> Option<String> {
+        let relative = Path::new(host_path)
+            .strip_prefix(&self.host_directory)
+            .ok()?;
+        Some(
+            Path::new(&self.node_directory)
+                .join(relative)
+                .display()
+                .to_string(),
+        )
+    }
+}
+
+/// Mounts the `volumes` of a component, from host paths to container paths as with
+/// `docker run -v`, into the containers of its workloads rendered in `directory`, as
+/// `hostPath` volumes of the node the product directory is mounted into.
+pub fn mount_host_paths(
+    directory: &Path,
+    mount: &HostMount,
+    volumes: &HashMap<String, String>,
+) -> Result<(), String> {
+    let mut volumes = volumes
+        .iter()
+        .filter_map(
+            |(host_path, container_path)| match mount.node_path(host_path) {
+                Some(node_path) => Some((node_path, container_path)),
+                None => {
+                    warn!(
+                        "Not mounting {} into the cluster, as it is outside {}",
+                        host_path, mount.host_directory
+                    );
+                    None
+                }
+            },
+        )
+        .collect::<Vec<_>>();
+    if volumes.is_empty() {
+        return Ok(());
+    }
+    // Named by their position, so they have to come in the same order every time
+    volumes.sort();
+
+    edit_workloads(directory, |document| {
+        let pod = &mut document["spec"]["template"]["spec"];
+        for (index, (node_path, _)) in volumes.iter().enumerate() {
+            let mut host_path = Mapping::new();
+            host_path.insert("path".into(), node_path.as_str().into());
+            let mut volume = Mapping::new();
+            volume.insert("name".into(), format!("rush-host-path-{}", index).into());
+            volume.insert("hostPath".into(), Value::Mapping(host_path));
+            push(&mut pod["volumes"], Value::Mapping(volume));
+        }
+
+        let Some(containers) = pod["containers"].as_sequence_mut() else {
+            return;
+        };
+        for container in containers {
+            for (index, (_, container_path)) in volumes.iter().enumerate() {
+                let mut volume_mount = Mapping::new();
+                volume_mount.insert("name".into(), format!("rush-host-path-{}", index).into());
+                volume_mount.insert("mountPath".into(), container_path.as_str().into());
+                push(&mut container["volumeMounts"], Value::Mapping(volume_mount));
+            }
+        }
+    })
+}
+
+fn push(list: &mut Value, item: Value) {
+    if !list.is_sequence() {
+        *list = Value::Sequence(Vec::new());
+    }
+    if let Some(list) = list.as_sequence_mut() {
+        list.push(item);
+    }
+}
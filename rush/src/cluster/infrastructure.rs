@@ -1,4 +1,5 @@
-use crate::builder::Config;
+use crate::builder::{Config, HelmChart};
+use crate::cluster::FluxLayout;
 use crate::toolchain::ToolchainContext;
 use crate::utils::{run_command, run_command_in_window};
 use colored::Colorize;
@@ -10,8 +11,11 @@ use std::sync::Arc;
 pub struct InfrastructureRepo {
     repository_url: String,
     local_path: PathBuf, // Changed back to PathBuf
-    environment: String,
-    product_name: String,
+    target_path: String,
+    branch: Option<String>,
+    cluster: Option<String>,
+    rollout_mode: String,
+    flux_layout: FluxLayout,
     toolchain: Arc<ToolchainContext>,
 }
 
@@ -19,13 +23,30 @@ impl InfrastructureRepo {
     pub fn new(config: Arc<Config>, toolchain: Arc<ToolchainContext>) -> Self {
         Self {
             repository_url: config.infrastructure_repository().to_string(),
-            local_path: PathBuf::from(config.root_path()).join(".infra"), // Already using PathBuf
-            environment: config.environment().to_string(),
-            product_name: config.product_name().to_string(),
+            // Environments may use different repositories, so each gets its own checkout
+            local_path: PathBuf::from(config.root_path())
+                .join(format!(".infra-{}", config.environment())),
+            target_path: config.infrastructure_path().to_string(),
+            branch: config.infrastructure_branch().cloned(),
+            cluster: None,
+            rollout_mode: config.rollout_mode().to_string(),
+            flux_layout: FluxLayout::new(
+                config.infrastructure_path(),
+                config.product_uri(),
+                config.environment(),
+                config.flux_source_name(),
+                config.flux_namespace(),
+            ),
             toolchain,
         }
     }
 
+    /// Places the manifests of the following rollouts in a subdirectory for the
+    /// given cluster, for environments spanning several clusters.
+    pub fn set_cluster(&mut self, cluster: Option<&str>) {
+        self.cluster = cluster.map(|cluster| cluster.to_string());
+    }
+
     pub async fn checkout(&self) -> Result<String, String> {
         let git = self.toolchain.git();
         let window_size = 10; // Example window size, adjust as needed
@@ -46,22 +67,30 @@ impl InfrastructureRepo {
             let args = vec!["-C", self.local_path.to_str().unwrap(), "clean", "-fd"];
             run_command(/*window_size,*/ formatted_label.clone(), git, args).await?;
 
-            let args = vec!["-C", self.local_path.to_str().unwrap(), "pull"]; // Adjusted args for pull operation using PathBuf
+            let mut args = vec!["-C", self.local_path.to_str().unwrap(), "pull"]; // Adjusted args for pull operation using PathBuf
+            if let Some(branch) = &self.branch {
+                args.push("origin");
+                args.push(branch);
+            }
             run_command(/*window_size,*/ formatted_label, git, args).await
         } else {
             let formatted_label = "git".white(); // Label for clone operation
-            let args = vec![
-                "clone",
-                &self.repository_url,
-                self.local_path.to_str().unwrap(),
-            ]; // Args for clone operation using PathBuf
+            let mut args = vec!["clone"];
+            if let Some(branch) = &self.branch {
+                args.push("--branch");
+                args.push(branch);
+            }
+            args.push(&self.repository_url);
+            args.push(self.local_path.to_str().unwrap()); // Args for clone operation using PathBuf
             run_command_in_window(window_size, &formatted_label, git, args).await
         }
     }
 
     pub async fn copy_manifests(&self, source_directory: &PathBuf) -> Result<(), String> {
-        let target_subdirectory = format!("products/{}/{}", self.product_name, self.environment);
-        let target_directory = self.local_path.join(target_subdirectory); // Directly using PathBuf
+        let mut target_directory = self.local_path.join(&self.target_path); // Directly using PathBuf
+        if let Some(cluster) = &self.cluster {
+            target_directory = target_directory.join(cluster);
+        }
 
         // Delete target directory if it exists
         if target_directory.exists() {
@@ -96,6 +125,19 @@ impl InfrastructureRepo {
         Ok(())
     }
 
+    /// Writes the Flux Kustomization and HelmRelease resources next to the copied
+    /// manifests. Does nothing when the repository uses the plain copy mode.
+    pub async fn write_flux_resources(
+        &self,
+        helm_releases: &[(String, HelmChart)],
+    ) -> Result<(), String> {
+        if self.rollout_mode != "flux" {
+            return Ok(());
+        }
+        self.flux_layout
+            .write(&self.local_path, self.cluster.as_deref(), helm_releases)
+    }
+
     pub async fn commit_and_push(&self, commit_message: &str) -> Result<String, String> {
         let git = self.toolchain.git();
         let window_size = 10; // Example window size, adjust as needed
@@ -121,7 +163,15 @@ impl InfrastructureRepo {
         .await?;
 
         let formatted_label_push = "git".white(); // Example label, adjust as needed
-        let args_push = vec!["-C", self.local_path.to_str().unwrap(), "push"];
+        let push_refspec = self
+            .branch
+            .as_ref()
+            .map(|branch| format!("HEAD:{}", branch));
+        let mut args_push = vec!["-C", self.local_path.to_str().unwrap(), "push"];
+        if let Some(refspec) = &push_refspec {
+            args_push.push("origin");
+            args_push.push(refspec);
+        }
 
         run_command(/*window_size, &*/ formatted_label_push, git, args_push).await
     }
@@ -0,0 +1,260 @@
+use crate::cluster::documents::{kind_of, load_documents, name_of, namespace_of};
+use crate::cluster::K8ClusterManifests;
+use crate::container::ServicesSpec;
+use log::{trace, warn};
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::fs;
+
+/// Directory inside the manifest output directory holding the generated routes.
+/// Component directories are always prefixed with their priority, so this cannot clash.
+const INGRESS_DIRECTORY: &str = "ingress";
+
+/// A path on a domain routed to a Kubernetes service.
+struct Route {
+    mount_point: String,
+    service_name: String,
+    service_port: u64,
+}
+
+/// Generates the routing for every service with a mount point, either as
+/// `networking.k8s.io` Ingress objects or as Gateway API HTTPRoutes.
+pub struct IngressGenerator {
+    kind: String,
+    ingress_class: Option<String>,
+    gateway: String,
+    gateway_namespace: Option<String>,
+    issuer: Option<(String, String)>,
+}
+
+impl IngressGenerator {
+    pub fn new(
+        kind: &str,
+        ingress_class: Option<&String>,
+        gateway: &str,
+        gateway_namespace: Option<&String>,
+    ) -> Self {
+        IngressGenerator {
+            kind: kind.to_string(),
+            ingress_class: ingress_class.cloned(),
+            gateway: gateway.to_string(),
+            gateway_namespace: gateway_namespace.cloned(),
+            issuer: None,
+        }
+    }
+
+    /// Requests TLS certificates for the generated domains from a cert-manager
+    /// issuer of the given kind (`Issuer` or `ClusterIssuer`).
+    pub fn with_cert_manager(mut self, issuer: &str, issuer_kind: &str) -> Self {
+        self.issuer = Some((issuer.to_string(), issuer_kind.to_string()));
+        self
+    }
+
+    pub fn write(
+        &self,
+        manifests: &K8ClusterManifests,
+        services: &ServicesSpec,
+    ) -> Result<(), String> {
+        // Routes have to live next to the services they point at
+        let mut routes: BTreeMap<(String, String), Vec<Route>> = BTreeMap::new();
+        for (domain, domain_services) in services {
+            for service in domain_services {
+                let mount_point = match &service.mount_point {
+                    Some(mount_point) => mount_point,
+                    None => continue,
+                };
+                let component = manifests.components().iter().find(|component| {
+                    !component.is_installation() && component.spec().component_name == service.name
+                });
+                let component = match component {
+                    Some(component) => component,
+                    None => continue,
+                };
+
+                let documents = load_documents(component.output_directory())?;
+                let kubernetes_service = match documents
+                    .iter()
+                    .find(|document| kind_of(document) == "Service")
+                {
+                    Some(kubernetes_service) => kubernetes_service,
+                    None => {
+                        warn!(
+                            "{} has a mount point but no Service manifest, skipping its route",
+                            service.name
+                        );
+                        continue;
+                    }
+                };
+
+                let service_port = kubernetes_service["spec"]["ports"][0]["port"]
+                    .as_u64()
+                    .unwrap_or(service.target_port as u64);
+                let namespace =
+                    namespace_of(kubernetes_service).unwrap_or_else(|| "default".to_string());
+                routes
+                    .entry((domain.clone(), namespace))
+                    .or_default()
+                    .push(Route {
+                        mount_point: mount_point.clone(),
+                        service_name: name_of(kubernetes_service),
+                        service_port,
+                    });
+            }
+        }
+
+        if routes.is_empty() {
+            return Ok(());
+        }
+        let directory = manifests.output_directory().join(INGRESS_DIRECTORY);
+        fs::create_dir_all(&directory).map_err(|e| e.to_string())?;
+
+        let mut contents = String::new();
+        for ((domain, namespace), mut domain_routes) in routes {
+            // Most specific mount point first
+            domain_routes.sort_by_key(|route| std::cmp::Reverse(route.mount_point.len()));
+            let mut resources = Vec::new();
+            match self.kind.as_str() {
+                "httproute" => {
+                    resources.push(self.http_route(&domain, &namespace, &domain_routes));
+                    // The gateway terminates TLS, so the secret belongs in its namespace
+                    let certificate_namespace =
+                        self.gateway_namespace.as_ref().unwrap_or(&namespace);
+                    resources.extend(self.certificate(&domain, certificate_namespace));
+                }
+                _ => resources.push(self.ingress(&domain, &namespace, &domain_routes)),
+            }
+            for resource in resources {
+                contents.push_str("---\n");
+                contents.push_str(&serde_yaml::to_string(&resource).map_err(|e| e.to_string())?);
+            }
+        }
+
+        let path = directory.join(format!("{}.yaml", self.kind));
+        trace!("Writing routes to {}", path.display());
+        fs::write(&path, contents).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+
+    fn ingress(&self, domain: &str, namespace: &str, routes: &[Route]) -> serde_json::Value {
+        let paths = routes
+            .iter()
+            .map(|route| {
+                json!({
+                    "path": route.mount_point,
+                    "pathType": "Prefix",
+                    "backend": {
+                        "service": {
+                            "name": route.service_name,
+                            "port": { "number": route.service_port },
+                        },
+                    },
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let mut ingress = json!({
+            "apiVersion": "networking.k8s.io/v1",
+            "kind": "Ingress",
+            "metadata": {
+                "name": resource_name(domain),
+                "namespace": namespace,
+            },
+            "spec": {
+                "rules": [{
+                    "host": domain,
+                    "http": { "paths": paths },
+                }],
+            },
+        });
+        if let Some(ingress_class) = &self.ingress_class {
+            ingress["spec"]["ingressClassName"] = json!(ingress_class);
+        }
+        if let Some((issuer, issuer_kind)) = &self.issuer {
+            // cert-manager's ingress-shim creates the Certificate from these
+            let annotation = match issuer_kind.as_str() {
+                "Issuer" => "cert-manager.io/issuer",
+                _ => "cert-manager.io/cluster-issuer",
+            };
+            ingress["metadata"]["annotations"] = json!({ annotation: issuer });
+            ingress["spec"]["tls"] = json!([{
+                "hosts": [domain],
+                "secretName": tls_secret_name(domain),
+            }]);
+        }
+        ingress
+    }
+
+    fn certificate(&self, domain: &str, namespace: &str) -> Option<serde_json::Value> {
+        let (issuer, issuer_kind) = self.issuer.as_ref()?;
+        Some(json!({
+            "apiVersion": "cert-manager.io/v1",
+            "kind": "Certificate",
+            "metadata": {
+                "name": resource_name(domain),
+                "namespace": namespace,
+            },
+            "spec": {
+                "secretName": tls_secret_name(domain),
+                "dnsNames": [domain],
+                "issuerRef": {
+                    "name": issuer,
+                    "kind": issuer_kind,
+                },
+            },
+        }))
+    }
+
+    fn http_route(&self, domain: &str, namespace: &str, routes: &[Route]) -> serde_json::Value {
+        let rules = routes
+            .iter()
+            .map(|route| {
+                json!({
+                    "matches": [{
+                        "path": { "type": "PathPrefix", "value": route.mount_point },
+                    }],
+                    "backendRefs": [{
+                        "name": route.service_name,
+                        "port": route.service_port,
+                    }],
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let mut parent = json!({ "name": self.gateway });
+        if let Some(gateway_namespace) = &self.gateway_namespace {
+            parent["namespace"] = json!(gateway_namespace);
+        }
+
+        json!({
+            "apiVersion": "gateway.networking.k8s.io/v1",
+            "kind": "HTTPRoute",
+            "metadata": {
+                "name": resource_name(domain),
+                "namespace": namespace,
+            },
+            "spec": {
+                "parentRefs": [parent],
+                "hostnames": [domain],
+                "rules": rules,
+            },
+        })
+    }
+}
+
+fn tls_secret_name(domain: &str) -> String {
+    format!("{}-tls", resource_name(domain))
+}
+
+fn resource_name(domain: &str) -> String {
+    domain
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect::<String>()
+        .trim_matches('-')
+        .to_string()
+}
@@ -0,0 +1,140 @@
+use crate::cluster::documents::{kind_of, name_of, namespace_of};
+use crate::progress::suspend;
+use crate::utils::{quiet, run_command, spawn_command};
+use colored::Colorize;
+use log::warn;
+use serde::Deserialize;
+use serde_yaml::Value;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::Command;
+
+/// Runs the Jobs of a rendered manifest: replaces them, as a Job's pod template cannot
+/// be changed, streams their logs and waits for them to complete, failing when any
+/// fails or does not complete within `timeout`.
+pub async fn run_jobs(
+    kubectl: &str,
+    context: &str,
+    manifest_path: &str,
+    timeout: Duration,
+) -> Result<(), String> {
+    let contents = std::fs::read_to_string(manifest_path)
+        .map_err(|e| format!("Failed to read {}: {}", manifest_path, e))?;
+    let mut jobs = Vec::new();
+    for document in serde_yaml::Deserializer::from_str(&contents) {
+        let document = Value::deserialize(document)
+            .map_err(|e| format!("Failed to parse {}: {}", manifest_path, e))?;
+        if kind_of(&document) == "Job" {
+            jobs.push((name_of(&document), namespace_of(&document)));
+        }
+    }
+    if jobs.is_empty() {
+        warn!("{} holds no Job", manifest_path);
+    }
+
+    run_command(
+        "job".white().bold(),
+        kubectl,
+        vec![
+            "--context",
+            context,
+            "delete",
+            "-f",
+            manifest_path,
+            "--ignore-not-found",
+            "--wait=true",
+        ],
+    )
+    .await?;
+    run_command(
+        "job".white().bold(),
+        kubectl,
+        vec!["--context", context, "apply", "-f", manifest_path],
+    )
+    .await?;
+
+    for (name, namespace) in &jobs {
+        wait_for_job(kubectl, context, name, namespace.as_deref(), timeout).await?;
+    }
+    Ok(())
+}
+
+async fn wait_for_job(
+    kubectl: &str,
+    context: &str,
+    name: &str,
+    namespace: Option<&str>,
+    timeout: Duration,
+) -> Result<(), String> {
+    let target = format!("job/{}", name);
+    let mut namespace_args = Vec::new();
+    if let Some(namespace) = namespace {
+        namespace_args.extend(["-n".to_string(), namespace.to_string()]);
+    }
+
+    let mut logs_args = vec![
+        "--context".to_string(),
+        context.to_string(),
+        "logs".to_string(),
+        "-f".to_string(),
+        target.clone(),
+        format!("--pod-running-timeout={}s", timeout.as_secs()),
+    ];
+    logs_args.extend(namespace_args.clone());
+    let mut logs = spawn_command(kubectl, &logs_args)
+        .map_err(|e| format!("Failed to run {}: {}", kubectl, e))?;
+    let streaming = {
+        let label = name.cyan().bold();
+        let mut lines = BufReader::new(logs.stdout.take().expect("stdout is piped")).lines();
+        tokio::spawn(async move {
+            while let Ok(Some(line)) = lines.next_line().await {
+                if !quiet() {
+                    suspend(|| println!("       {}  |   {}", label, line.trim_end()));
+                }
+            }
+        })
+    };
+
+    let deadline = Instant::now() + timeout;
+    let result = loop {
+        let output = Command::new(kubectl)
+            .args(["--context", context, "get", &target, "-o"])
+            .arg(r#"jsonpath={.status.conditions[?(@.status=="True")].type}"#)
+            .args(&namespace_args)
+            .output()
+            .await
+            .map_err(|e| format!("Failed to run {}: {}", kubectl, e))?;
+        let conditions = String::from_utf8_lossy(&output.stdout);
+        if conditions
+            .split_whitespace()
+            .any(|condition| condition == "Failed")
+        {
+            break Err(format!("Job {} failed", name));
+        }
+        if conditions
+            .split_whitespace()
+            .any(|condition| condition == "Complete")
+        {
+            break Ok(());
+        }
+        if Instant::now() >= deadline {
+            break Err(format!(
+                "Job {} did not complete within {}s",
+                name,
+                timeout.as_secs()
+            ));
+        }
+        tokio::time::sleep(Duration::from_secs(2)).await;
+    };
+
+    // The last lines of the logs may still be on their way
+    let abort = streaming.abort_handle();
+    if tokio::time::timeout(Duration::from_secs(5), streaming)
+        .await
+        .is_err()
+    {
+        abort.abort();
+    }
+    let _ = logs.kill().await;
+    result
+}
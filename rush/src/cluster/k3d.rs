@@ -0,0 +1,80 @@
+use crate::cluster::local_cluster::node_container_ip;
+use crate::cluster::LocalCluster;
+use crate::toolchain::ToolchainContext;
+use crate::utils::run_command;
+use async_trait::async_trait;
+use colored::Colorize;
+use std::sync::Arc;
+
+pub struct K3d {
+    toolchain: Arc<ToolchainContext>,
+    cluster_name: String,
+}
+
+impl K3d {
+    pub fn new(toolchain: Arc<ToolchainContext>, cluster_name: &str) -> Self {
+        K3d {
+            toolchain,
+            cluster_name: cluster_name.to_string(),
+        }
+    }
+
+    fn executable(&self) -> Result<String, String> {
+        self.toolchain
+            .k3d()
+            .ok_or_else(|| "k3d executable not found. Please install it.".to_string())
+    }
+
+    async fn cluster_command(&self, command: &str) -> Result<String, String> {
+        let k3d_executable = self.executable()?;
+        run_command(
+            "k3d".white().bold(),
+            &k3d_executable,
+            vec!["cluster", command, &self.cluster_name],
+        )
+        .await
+    }
+}
+
+#[async_trait]
+impl LocalCluster for K3d {
+    fn kind(&self) -> &str {
+        "k3d"
+    }
+
+    async fn create(&self) -> Result<String, String> {
+        self.cluster_command("create").await
+    }
+
+    async fn start(&self) -> Result<String, String> {
+        self.cluster_command("start").await
+    }
+
+    async fn stop(&self) -> Result<String, String> {
+        self.cluster_command("stop").await
+    }
+
+    async fn delete(&self) -> Result<String, String> {
+        self.cluster_command("delete").await
+    }
+
+    async fn enable_ingress(&self) -> Result<String, String> {
+        // k3d clusters come with Traefik
+        Ok(String::new())
+    }
+
+    async fn load_image(&self, image: &str) -> Result<String, String> {
+        let k3d_executable = self.executable()?;
+        run_command(
+            "k3d".white().bold(),
+            &k3d_executable,
+            vec!["image", "import", image, "--cluster", &self.cluster_name],
+        )
+        .await
+    }
+
+    async fn get_ip(&self) -> Result<String, String> {
+        let container = format!("k3d-{}-server-0", self.cluster_name);
+        node_container_ip(self.toolchain.docker(), &container)
+    }
+}
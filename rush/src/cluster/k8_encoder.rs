@@ -1,13 +1,116 @@
-use log::{info, trace};
+use log::{info, trace, warn};
 use std::fs;
+use std::path::PathBuf;
 use std::process::Command;
+use std::sync::OnceLock;
+use std::time::Duration;
 
-pub trait K8Encoder {
+/// Encodes a rendered manifest in place, called from several threads at once.
+pub trait K8Encoder: Send + Sync {
     fn encode_file(&self, path: &str) -> Result<(), String>;
 }
 
+/// Sealed secrets controllers renew their key every 30 days, and a certificate cached
+/// for longer is fetched again.
+const CERTIFICATE_MAX_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// Where kubeseal finds the public certificate of the sealed secrets controller.
+#[derive(Debug, Clone, Default)]
+pub struct KubesealSettings {
+    /// A certificate file or URL, from KUBESEAL_CERT. Sealing then needs no cluster access
+    pub certificate: Option<String>,
+    pub controller_namespace: Option<String>,
+    pub controller_name: Option<String>,
+    /// The context the certificate is fetched from when none is configured
+    pub context: String,
+    /// The file the fetched certificate is cached in, e.g.
+    /// `~/.cache/rush/kubeseal/helloworld-wonop-io-staging.pem`
+    pub cache_file: PathBuf,
+}
+
 // Implementation of the K8Encoder trait
-pub struct SealedSecretsEncoder;
+pub struct SealedSecretsEncoder {
+    settings: KubesealSettings,
+    /// Fetched once, on the first secret sealed
+    certificate: OnceLock<Result<String, String>>,
+}
+
+impl SealedSecretsEncoder {
+    pub fn new(settings: KubesealSettings) -> Self {
+        Self {
+            settings,
+            certificate: OnceLock::new(),
+        }
+    }
+
+    /// The configured certificate, or the one cached for the environment, which is
+    /// fetched from the controller when missing or stale.
+    fn certificate(&self) -> Result<String, String> {
+        self.certificate
+            .get_or_init(|| {
+                if let Some(certificate) = &self.settings.certificate {
+                    return Ok(certificate.clone());
+                }
+                let cache_file = &self.settings.cache_file;
+                let cache_age = fs::metadata(cache_file)
+                    .and_then(|metadata| metadata.modified())
+                    .ok()
+                    .and_then(|modified| modified.elapsed().ok());
+                if cache_age.is_some_and(|age| age < CERTIFICATE_MAX_AGE) {
+                    return Ok(cache_file.display().to_string());
+                }
+                match self.fetch_certificate() {
+                    Ok(()) => Ok(cache_file.display().to_string()),
+                    // An older key still seals secrets the controller can decrypt
+                    Err(e) if cache_age.is_some() => {
+                        warn!("{}, sealing with the cached certificate", e);
+                        Ok(cache_file.display().to_string())
+                    }
+                    Err(e) => Err(format!(
+                        "{}. Set KUBESEAL_CERT to seal secrets without access to the cluster",
+                        e
+                    )),
+                }
+            })
+            .clone()
+    }
+
+    fn fetch_certificate(&self) -> Result<(), String> {
+        info!(
+            "Fetching the sealed secrets certificate from {}",
+            self.settings.context
+        );
+        let mut command = Command::new("kubeseal");
+        command
+            .arg("--fetch-cert")
+            .arg("--context")
+            .arg(&self.settings.context);
+        if let Some(namespace) = &self.settings.controller_namespace {
+            command.arg("--controller-namespace").arg(namespace);
+        }
+        if let Some(name) = &self.settings.controller_name {
+            command.arg("--controller-name").arg(name);
+        }
+        let output = command
+            .output()
+            .map_err(|e| format!("Failed to execute kubeseal: {}", e))?;
+        if !output.status.success() || output.stdout.is_empty() {
+            return Err(format!(
+                "Failed to fetch the sealed secrets certificate from {}: {}",
+                self.settings.context,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+
+        let cache_file = &self.settings.cache_file;
+        if let Some(directory) = cache_file.parent() {
+            fs::create_dir_all(directory)
+                .map_err(|e| format!("Failed to create {}: {}", directory.display(), e))?;
+        }
+        fs::write(cache_file, &output.stdout)
+            .map_err(|e| format!("Failed to write {}: {}", cache_file.display(), e))
+    }
+}
 
 impl K8Encoder for SealedSecretsEncoder {
     fn encode_file(&self, path: &str) -> Result<(), String> {
@@ -27,9 +130,10 @@ impl K8Encoder for SealedSecretsEncoder {
         let temp_file = format!("{}.tmp.yaml", path);
         trace!("Encoding file {}", path);
 
-        // Run kubeseal command
-        // TODO: Add certitiicate path as an argument
+        // Sealing with the certificate needs no cluster access
         let output = Command::new("kubeseal")
+            .arg("--cert")
+            .arg(self.certificate()?)
             .arg("--format")
             .arg("yaml")
             .arg("-w")
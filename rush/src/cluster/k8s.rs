@@ -3,13 +3,16 @@ use crate::builder::Artefact;
 use crate::builder::BuildContext;
 use crate::builder::BuildType;
 use crate::builder::ComponentBuildSpec;
-use crate::cluster::run_command;
 use crate::toolchain::ToolchainContext;
+use crate::utils::run_command;
 use colored::Colorize;
-use log::{error, trace};
+use log::trace;
+use openssl::sha::Sha256;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
 pub struct K8ManifestArtefact {
     pub artefact: Artefact,
@@ -32,14 +35,17 @@ impl K8ManifestArtefact {
         self.artefact.render(context)
     }
 
-    pub fn render_to_file(&self, context: &BuildContext) {
+    pub fn render_to_file(&self, context: &BuildContext) -> Result<(), String> {
         self.artefact.render_to_file(context);
         match self.encoder.encode_file(&self.artefact.output_path) {
-            Ok(_) => trace!("Encoded file {}", self.artefact.output_path),
-            Err(e) => {
-                error!("Failed to encode file {}: {}", self.artefact.output_path, e);
-                panic!("Encoding failed");
+            Ok(_) => {
+                trace!("Encoded file {}", self.artefact.output_path);
+                Ok(())
             }
+            Err(e) => Err(format!(
+                "Failed to encode {}: {}",
+                self.artefact.output_path, e
+            )),
         }
     }
 
@@ -48,6 +54,40 @@ impl K8ManifestArtefact {
     }
 }
 
+/// How many manifests are rendered at the same time. Encoding mostly waits for kubeseal,
+/// so this is not bound to the number of CPUs.
+const RENDER_WORKERS: usize = 8;
+
+/// Renders and encodes each manifest with its context, [`RENDER_WORKERS`] at a time.
+/// Every manifest has an output path of its own, so the files written do not depend on
+/// the order. Results are in the order of `manifests`, each with the time it took.
+pub fn render_in_parallel(
+    manifests: &[(&K8ManifestArtefact, &BuildContext)],
+) -> Vec<(Result<(), String>, Duration)> {
+    let workers = RENDER_WORKERS.min(manifests.len());
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(vec![None; manifests.len()]);
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some((manifest, context)) = manifests.get(index) else {
+                    break;
+                };
+                let started = Instant::now();
+                let result = manifest.render_to_file(context);
+                results.lock().unwrap()[index] = Some((result, started.elapsed()));
+            });
+        }
+    });
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|result| result.expect("Every manifest is rendered"))
+        .collect()
+}
+
 pub struct K8ClusterManifests {
     components: Vec<K8ComponentManifests>,
     toolchain: Option<Arc<ToolchainContext>>,
@@ -76,11 +116,14 @@ impl K8ClusterManifests {
         input_directory: PathBuf,
     ) {
         let output_directory = self.output_directory.join(name);
+        // Outside the output directory, which is applied as a whole
+        let jobs_directory = self.jobs_directory().join(name);
         self.components.push(K8ComponentManifests::new(
             name,
             spec,
             input_directory,
             output_directory,
+            jobs_directory,
             self.toolchain.clone(),
             self.encoder.clone(),
         ));
@@ -90,10 +133,36 @@ impl K8ClusterManifests {
         &self.output_directory
     }
 
+    /// Where the jobs of the components are rendered, next to the manifests.
+    pub fn jobs_directory(&self) -> PathBuf {
+        self.output_directory.with_file_name("k8s-jobs")
+    }
+
     pub fn components(&self) -> &Vec<K8ComponentManifests> {
         &self.components
     }
 
+    pub fn into_components(self) -> Vec<K8ComponentManifests> {
+        self.components
+    }
+
+    /// Reads the templates again, picking up changes made since they were loaded.
+    pub fn reload_templates(&mut self) -> Result<(), String> {
+        for component in &mut self.components {
+            for manifest in component
+                .manifests
+                .iter_mut()
+                .chain(&mut component.pre_apply_jobs)
+                .chain(&mut component.post_apply_jobs)
+            {
+                let artefact = &mut manifest.artefact;
+                artefact.template = std::fs::read_to_string(&artefact.input_path)
+                    .map_err(|e| format!("Failed to read {}: {}", artefact.input_path, e))?;
+            }
+        }
+        Ok(())
+    }
+
     pub fn update_encoder(&mut self, encoder: Arc<dyn K8Encoder>) {
         self.encoder = encoder.clone();
         for component in &mut self.components {
@@ -107,8 +176,11 @@ pub struct K8ComponentManifests {
     spec: Arc<Mutex<ComponentBuildSpec>>,
     is_installation: bool,
     manifests: Vec<K8ManifestArtefact>,
+    pre_apply_jobs: Vec<K8ManifestArtefact>,
+    post_apply_jobs: Vec<K8ManifestArtefact>,
     input_directory: PathBuf,
     output_directory: PathBuf,
+    jobs_directory: PathBuf,
     toolchain: Option<Arc<ToolchainContext>>,
     namespace: String,
     encoder: Arc<dyn K8Encoder>,
@@ -120,6 +192,7 @@ impl K8ComponentManifests {
         spec: Arc<Mutex<ComponentBuildSpec>>,
         input_directory: PathBuf,
         output_directory: PathBuf,
+        jobs_directory: PathBuf,
         toolchain: Option<Arc<ToolchainContext>>,
         encoder: Arc<dyn K8Encoder>,
     ) -> Self {
@@ -130,11 +203,24 @@ impl K8ComponentManifests {
         } else {
             (false, "default".to_string())
         };
+        let jobs = spec.lock().unwrap().jobs.clone();
+        let job_artefact = |path: &String| {
+            K8ManifestArtefact::new(
+                input_directory.join(path).display().to_string(),
+                jobs_directory.join(path).display().to_string(),
+                encoder.clone(),
+            )
+        };
+        let pre_apply_jobs = jobs.pre_apply.iter().map(job_artefact).collect();
+        let post_apply_jobs = jobs.post_apply.iter().map(job_artefact).collect();
         let mut ret = K8ComponentManifests {
             name: name.to_string(),
             manifests: Vec::new(),
+            pre_apply_jobs,
+            post_apply_jobs,
             input_directory: input_directory.clone(),
             output_directory: output_directory.clone(),
+            jobs_directory,
             toolchain,
             is_installation,
             spec,
@@ -193,10 +279,50 @@ impl K8ComponentManifests {
         &self.manifests
     }
 
+    /// Where the component's Jobs are rendered, see [`ComponentJobs`](crate::builder::ComponentJobs).
+    pub fn jobs_directory(&self) -> &PathBuf {
+        &self.jobs_directory
+    }
+
+    /// The Jobs run before the manifests are applied.
+    pub fn pre_apply_jobs(&self) -> &Vec<K8ManifestArtefact> {
+        &self.pre_apply_jobs
+    }
+
+    /// The Jobs run once the manifests are applied.
+    pub fn post_apply_jobs(&self) -> &Vec<K8ManifestArtefact> {
+        &self.post_apply_jobs
+    }
+
+    /// The manifests followed by the Jobs, everything rendered for the component.
+    pub fn rendered(&self) -> impl Iterator<Item = &K8ManifestArtefact> {
+        self.manifests
+            .iter()
+            .chain(&self.pre_apply_jobs)
+            .chain(&self.post_apply_jobs)
+    }
+
     pub fn name(&self) -> &str {
         &self.name
     }
 
+    /// Hash of everything the manifests are rendered from: the context, which holds the
+    /// spec, image tag and secrets, and the templates. Equal fingerprints render equal
+    /// manifests, short of the random parts kubeseal adds.
+    pub fn fingerprint(&self, context: &BuildContext) -> String {
+        let mut hasher = Sha256::new();
+        // Through a Value, whose maps are sorted, unlike the HashMaps of the context
+        let context = serde_json::to_value(context).expect("Build context is serializable");
+        hasher.update(context.to_string().as_bytes());
+        for manifest in self.rendered() {
+            hasher.update(manifest.artefact.input_path.as_bytes());
+            hasher.update(b"\n");
+            hasher.update(manifest.artefact.template.as_bytes());
+            hasher.update(b"\n");
+        }
+        hex::encode(hasher.finish())
+    }
+
     pub fn add_manifest(&mut self, manifest: Artefact) {
         self.manifests.push(K8ManifestArtefact::from_artefact(
             manifest,
@@ -206,12 +332,17 @@ impl K8ComponentManifests {
 
     pub fn update_encoder(&mut self, encoder: Arc<dyn K8Encoder>) {
         self.encoder = encoder.clone();
-        for manifest in &mut self.manifests {
+        for manifest in self
+            .manifests
+            .iter_mut()
+            .chain(&mut self.pre_apply_jobs)
+            .chain(&mut self.post_apply_jobs)
+        {
             manifest.update_encoder(encoder.clone());
         }
     }
 
-    pub async fn apply(&self) -> Result<(), String> {
+    pub async fn apply(&self, context: &str) -> Result<(), String> {
         let toolchain = match &self.toolchain {
             Some(toolchain) => toolchain.clone(),
             None => panic!("Cannot launch docker image without a toolchain"),
@@ -222,14 +353,14 @@ impl K8ComponentManifests {
             run_command(
                 "kubectl apply".white(),
                 toolchain.kubectl(),
-                vec!["apply", "-f", &output_path],
+                vec!["--context", context, "apply", "-f", &output_path],
             )
             .await?;
         }
         Ok(())
     }
 
-    pub async fn unapply(&self) -> Result<(), String> {
+    pub async fn unapply(&self, context: &str) -> Result<(), String> {
         let toolchain = match &self.toolchain {
             Some(toolchain) => toolchain.clone(),
             None => panic!("Cannot launch docker image without a toolchain"),
@@ -240,7 +371,7 @@ impl K8ComponentManifests {
             run_command(
                 "kubectl delete".white(),
                 toolchain.kubectl(),
-                vec!["delete", "-f", &output_path],
+                vec!["--context", context, "delete", "-f", &output_path],
             )
             .await?;
         }
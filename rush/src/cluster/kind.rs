@@ -0,0 +1,107 @@
+use crate::cluster::local_cluster::node_container_ip;
+use crate::cluster::LocalCluster;
+use crate::toolchain::ToolchainContext;
+use crate::utils::run_command;
+use async_trait::async_trait;
+use colored::Colorize;
+use std::sync::Arc;
+
+/// ingress-nginx set up for the ports kind maps to the host.
+const INGRESS_NGINX_MANIFEST: &str =
+    "https://raw.githubusercontent.com/kubernetes/ingress-nginx/main/deploy/static/provider/kind/deploy.yaml";
+
+pub struct Kind {
+    toolchain: Arc<ToolchainContext>,
+    cluster_name: String,
+}
+
+impl Kind {
+    pub fn new(toolchain: Arc<ToolchainContext>, cluster_name: &str) -> Self {
+        Kind {
+            toolchain,
+            cluster_name: cluster_name.to_string(),
+        }
+    }
+
+    fn executable(&self) -> Result<String, String> {
+        self.toolchain
+            .kind()
+            .ok_or_else(|| "kind executable not found. Please install it.".to_string())
+    }
+
+    fn control_plane_container(&self) -> String {
+        format!("{}-control-plane", self.cluster_name)
+    }
+}
+
+#[async_trait]
+impl LocalCluster for Kind {
+    fn kind(&self) -> &str {
+        "kind"
+    }
+
+    async fn create(&self) -> Result<String, String> {
+        let kind_executable = self.executable()?;
+        run_command(
+            "kind".white().bold(),
+            &kind_executable,
+            vec!["create", "cluster", "--name", &self.cluster_name],
+        )
+        .await
+    }
+
+    async fn start(&self) -> Result<String, String> {
+        // kind has no start command; the cluster lives in a docker container
+        let container = self.control_plane_container();
+        run_command(
+            "kind".white().bold(),
+            self.toolchain.docker(),
+            vec!["start", &container],
+        )
+        .await
+    }
+
+    async fn stop(&self) -> Result<String, String> {
+        let container = self.control_plane_container();
+        run_command(
+            "kind".white().bold(),
+            self.toolchain.docker(),
+            vec!["stop", &container],
+        )
+        .await
+    }
+
+    async fn delete(&self) -> Result<String, String> {
+        let kind_executable = self.executable()?;
+        run_command(
+            "kind".white().bold(),
+            &kind_executable,
+            vec!["delete", "cluster", "--name", &self.cluster_name],
+        )
+        .await
+    }
+
+    async fn enable_ingress(&self) -> Result<String, String> {
+        let context = format!("kind-{}", self.cluster_name);
+        run_command(
+            "kind".white().bold(),
+            self.toolchain.kubectl(),
+            vec!["apply", "--context", &context, "-f", INGRESS_NGINX_MANIFEST],
+        )
+        .await
+    }
+
+    async fn load_image(&self, image: &str) -> Result<String, String> {
+        let kind_executable = self.executable()?;
+        run_command(
+            "kind".white().bold(),
+            &kind_executable,
+            vec!["load", "docker-image", image, "--name", &self.cluster_name],
+        )
+        .await
+    }
+
+    async fn get_ip(&self) -> Result<String, String> {
+        node_container_ip(self.toolchain.docker(), &self.control_plane_container())
+    }
+}
@@ -0,0 +1,60 @@
+use async_trait::async_trait;
+
+/// A Kubernetes cluster running on the developer's machine.
+///
+/// Implementations wrap the CLI of the respective tool (minikube, kind, k3d)
+/// and are selected through the `LOCAL_CLUSTER` setting in rushd.yaml.
+#[async_trait]
+pub trait LocalCluster: Send + Sync {
+    /// Name of the tool backing the cluster, used for labels and messages.
+    fn kind(&self) -> &str;
+
+    /// Creates the cluster if it does not already exist.
+    async fn create(&self) -> Result<String, String>;
+
+    /// Starts a previously created cluster.
+    async fn start(&self) -> Result<String, String>;
+
+    /// Stops the cluster without deleting it.
+    async fn stop(&self) -> Result<String, String>;
+
+    /// Deletes the cluster and all of its resources.
+    async fn delete(&self) -> Result<String, String>;
+
+    /// Installs an ingress controller, so that the product's Ingress routes requests.
+    async fn enable_ingress(&self) -> Result<String, String>;
+
+    /// Loads a locally built image into the cluster's container runtime.
+    async fn load_image(&self, image: &str) -> Result<String, String>;
+
+    /// Returns the IP address under which the cluster is reachable from the host.
+    async fn get_ip(&self) -> Result<String, String>;
+}
+
+/// Looks up the IP address of a docker container that hosts a cluster node.
+pub(crate) fn node_container_ip(docker: &str, container_name: &str) -> Result<String, String> {
+    let output = std::process::Command::new(docker)
+        .args([
+            "inspect",
+            "-f",
+            "{{range .NetworkSettings.Networks}}{{.IPAddress}}{{end}}",
+            container_name,
+        ])
+        .output()
+        .map_err(|e| format!("Failed to inspect {}: {}", container_name, e))?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "Failed to get IP of {}: {}",
+            container_name,
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+
+    let ip = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if ip.is_empty() {
+        Err(format!("Container {} has no IP address", container_name))
+    } else {
+        Ok(ip)
+    }
+}
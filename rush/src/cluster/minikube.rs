@@ -0,0 +1,157 @@
+use crate::cluster::LocalCluster;
+use crate::toolchain::ToolchainContext;
+use crate::utils::{run_command, spawn_command};
+use async_trait::async_trait;
+use colored::Colorize;
+use log::trace;
+use std::process::Command;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, BufReader};
+use tokio::process::Child;
+
+pub struct Minikube {
+    toolchain: Arc<ToolchainContext>,
+}
+
+impl Minikube {
+    pub fn new(toolchain: Arc<ToolchainContext>) -> Self {
+        Minikube { toolchain }
+    }
+
+    fn executable(&self) -> Result<String, String> {
+        self.toolchain
+            .minikube()
+            .ok_or_else(|| "Minikube executable not found. Please install it.".to_string())
+    }
+
+    /// Mounts `host_directory` at `node_directory` of the minikube node over 9p with
+    /// `minikube mount`, for as long as the returned process runs.
+    pub async fn mount(&self, host_directory: &str, node_directory: &str) -> Result<Child, String> {
+        let minikube_executable = self.executable()?;
+        let mount = format!("{}:{}", host_directory, node_directory);
+        let mut child = spawn_command(&minikube_executable, &["mount".to_string(), mount])
+            .map_err(|e| format!("Failed to run minikube mount: {}", e))?;
+
+        let mut lines = BufReader::new(child.stdout.take().expect("stdout is piped")).lines();
+        let mounted = tokio::time::timeout(Duration::from_secs(60), async {
+            while let Ok(Some(line)) = lines.next_line().await {
+                trace!("minikube mount: {}", line);
+                if line.contains("Successfully mounted") {
+                    return true;
+                }
+            }
+            false
+        })
+        .await;
+        match mounted {
+            Ok(true) => {
+                // Kept reading, so minikube never blocks on a full pipe
+                tokio::spawn(async move {
+                    while let Ok(Some(line)) = lines.next_line().await {
+                        trace!("minikube mount: {}", line);
+                    }
+                });
+                Ok(child)
+            }
+            Ok(false) => {
+                let mut stderr = String::new();
+                if let Some(mut output) = child.stderr.take() {
+                    let _ = output.read_to_string(&mut stderr).await;
+                }
+                Err(format!(
+                    "Failed to mount {} into minikube: {}",
+                    host_directory,
+                    stderr.trim()
+                ))
+            }
+            Err(_) => {
+                let _ = child.kill().await;
+                Err(format!(
+                    "Mounting {} into minikube did not finish within a minute",
+                    host_directory
+                ))
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl LocalCluster for Minikube {
+    fn kind(&self) -> &str {
+        "minikube"
+    }
+
+    async fn create(&self) -> Result<String, String> {
+        // Minikube creates the cluster on first start
+        self.start().await
+    }
+
+    async fn start(&self) -> Result<String, String> {
+        let minikube_executable = self.executable()?;
+        run_command(
+            "minikube".white().bold(),
+            &minikube_executable,
+            vec!["start"],
+        )
+        .await
+    }
+
+    async fn stop(&self) -> Result<String, String> {
+        let minikube_executable = self.executable()?;
+        run_command(
+            "minikube".white().bold(),
+            &minikube_executable,
+            vec!["stop"],
+        )
+        .await
+    }
+
+    async fn delete(&self) -> Result<String, String> {
+        let minikube_executable = self.executable()?;
+        run_command(
+            "minikube".white().bold(),
+            &minikube_executable,
+            vec!["delete"],
+        )
+        .await
+    }
+
+    async fn enable_ingress(&self) -> Result<String, String> {
+        let minikube_executable = self.executable()?;
+        run_command(
+            "minikube".white().bold(),
+            &minikube_executable,
+            vec!["addons", "enable", "ingress"],
+        )
+        .await
+    }
+
+    async fn load_image(&self, image: &str) -> Result<String, String> {
+        let minikube_executable = self.executable()?;
+        run_command(
+            "minikube".white().bold(),
+            &minikube_executable,
+            vec!["image", "load", image],
+        )
+        .await
+    }
+
+    async fn get_ip(&self) -> Result<String, String> {
+        let minikube_executable = self.executable()?;
+
+        let output = Command::new(minikube_executable)
+            .arg("ip")
+            .output()
+            .map_err(|e| format!("Failed to get minikube IP: {}", e))?;
+
+        if !output.status.success() {
+            Err(format!(
+                "Failed to get minikube IP: {}",
+                String::from_utf8_lossy(&output.stderr)
+            ))
+        } else {
+            Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+        }
+    }
+}
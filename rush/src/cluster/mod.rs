@@ -1,83 +1,46 @@
+mod changelog;
+mod checksum;
+mod documents;
+mod flux;
+mod golden;
+mod history;
+mod host_path;
 mod infrastructure;
+mod ingress;
+mod jobs;
+mod k3d;
 mod k8_encoder;
 mod k8s;
-
-use crate::toolchain::ToolchainContext;
-use crate::utils::run_command;
-use colored::Colorize;
-use std::process::Command;
-use std::sync::Arc;
-
+mod kind;
+mod local_cluster;
+mod minikube;
+mod network_policy;
+mod readiness;
+mod secret_leaks;
+mod strategy;
+mod terraform;
+
+pub use changelog::{changelog, ChangelogCommit, ComponentChangelog};
+pub use checksum::{
+    annotate_pod_templates, find_fake_secrets, mark_fake_secrets, secrets_checksum,
+    secrets_checksum_key, FAKE_SECRETS_ANNOTATION, FAKE_SECRETS_MARKER,
+    SECRETS_CHECKSUM_ANNOTATION, SECRETS_CHECKSUM_KEY,
+};
+pub use flux::FluxLayout;
+pub use golden::{compare_golden, update_golden, GoldenDifference};
+pub use history::{copy_directory, DeploymentHistory, RolloutRecord};
+pub use host_path::{mount_host_paths, HostMount};
 pub use infrastructure::InfrastructureRepo;
-pub use k8_encoder::{K8Encoder, NoopEncoder, SealedSecretsEncoder};
-pub use k8s::K8ClusterManifests;
-
-pub struct Minikube {
-    toolchain: Arc<ToolchainContext>,
-}
-
-impl Minikube {
-    pub fn new(toolchain: Arc<ToolchainContext>) -> Self {
-        Minikube { toolchain }
-    }
-
-    pub async fn start(&self) -> Result<String, String> {
-        let minikube_executable = self
-            .toolchain
-            .minikube()
-            .ok_or_else(|| "Minikube executable not found. Please install it.".to_string())?;
-        run_command(
-            "minikube".white().bold(),
-            &minikube_executable,
-            vec!["start"],
-        )
-        .await
-    }
-
-    pub async fn stop(&self) -> Result<String, String> {
-        let minikube_executable = self
-            .toolchain
-            .minikube()
-            .ok_or_else(|| "Minikube executable not found. Please install it.".to_string())?;
-        run_command(
-            "minikube".white().bold(),
-            &minikube_executable,
-            vec!["stop"],
-        )
-        .await
-    }
-
-    pub async fn delete(&self) -> Result<String, String> {
-        let minikube_executable = self
-            .toolchain
-            .minikube()
-            .ok_or_else(|| "Minikube executable not found. Please install it.".to_string())?;
-        run_command(
-            "minikube".white().bold(),
-            &minikube_executable,
-            vec!["delete"],
-        )
-        .await
-    }
-
-    pub async fn get_ip(&self) -> Result<String, String> {
-        let minikube_executable = match self.toolchain.minikube() {
-            Some(minikube_executable) => minikube_executable,
-            None => return Err("Minikube executable not found. Please install it.".to_string()),
-        };
-
-        let output = Command::new(minikube_executable)
-            .arg("ip")
-            .output()
-            .expect("Failed to get minikube IP");
-
-        if !output.status.success() {
-            Err(format!(
-                "Failed to get minikube IP: {}",
-                String::from_utf8_lossy(&output.stderr)
-            ))
-        } else {
-            Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
-        }
-    }
-}
+pub use ingress::IngressGenerator;
+pub use jobs::run_jobs;
+pub use k3d::K3d;
+pub use k8_encoder::{K8Encoder, KubesealSettings, NoopEncoder, SealedSecretsEncoder};
+pub use k8s::{render_in_parallel, K8ClusterManifests, K8ComponentManifests};
+pub use kind::Kind;
+pub use local_cluster::LocalCluster;
+pub use minikube::Minikube;
+pub use network_policy::write_network_policies;
+pub use readiness::{restart_workloads, wait_for_manifest, wait_for_namespace};
+pub use secret_leaks::{find_secret_leaks, SecretLeak};
+pub use strategy::StrategyDeployer;
+pub use terraform::Terraform;
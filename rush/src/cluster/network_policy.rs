@@ -0,0 +1,168 @@
+use crate::builder::BuildType;
+use crate::cluster::documents::{kind_of, load_documents, name_of, namespace_of};
+use crate::cluster::K8ClusterManifests;
+use log::trace;
+use serde_json::json;
+use serde_yaml::{Mapping, Value};
+use std::collections::HashMap;
+use std::fs;
+
+const POLICY_FILE: &str = "network-policy.yaml";
+
+/// Pods of a rendered workload that a policy can select.
+struct Workload {
+    name: String,
+    namespace: String,
+    labels: Mapping,
+    ports: Vec<u64>,
+}
+
+/// Writes a default-deny NetworkPolicy for every workload of the product that only
+/// admits traffic from the components depending on it. Components with a mount
+/// point or subdomain additionally admit traffic from the ingress controller.
+pub fn write_network_policies(
+    manifests: &K8ClusterManifests,
+    ingress_controller_namespace: &str,
+) -> Result<(), String> {
+    let components = manifests
+        .components()
+        .iter()
+        .filter(|component| !component.is_installation())
+        .collect::<Vec<_>>();
+
+    let mut workloads: HashMap<String, Vec<Workload>> = HashMap::new();
+    for component in &components {
+        let documents = load_documents(component.output_directory())?;
+        workloads.insert(
+            component.spec().component_name,
+            documents.iter().filter_map(workload_of).collect(),
+        );
+    }
+
+    // Traffic flows from a component to everything it depends on
+    let mut sources: HashMap<String, Vec<String>> = HashMap::new();
+    for component in &components {
+        let spec = component.spec();
+        let mut targets = spec.depends_on.clone();
+        if let BuildType::Ingress {
+            components: routed, ..
+        } = &spec.build_type
+        {
+            targets.extend(routed.iter().cloned());
+        }
+        for target in targets {
+            sources
+                .entry(target)
+                .or_default()
+                .push(spec.component_name.clone());
+        }
+    }
+
+    for component in &components {
+        let spec = component.spec();
+        let exposed = spec.mount_point.is_some() || spec.subdomain.is_some();
+        let peers = sources
+            .get(&spec.component_name)
+            .map(|names| {
+                names
+                    .iter()
+                    .filter_map(|name| workloads.get(name))
+                    .flatten()
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+
+        let mut contents = String::new();
+        for workload in workloads.get(&spec.component_name).into_iter().flatten() {
+            let policy = policy_for(workload, &peers, exposed, ingress_controller_namespace);
+            contents.push_str("---\n");
+            contents.push_str(&serde_yaml::to_string(&policy).map_err(|e| e.to_string())?);
+        }
+        if contents.is_empty() {
+            continue;
+        }
+
+        let path = component.output_directory().join(POLICY_FILE);
+        trace!("Writing network policies to {}", path.display());
+        fs::write(&path, contents)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    }
+
+    Ok(())
+}
+
+fn workload_of(document: &Value) -> Option<Workload> {
+    if !["Deployment", "StatefulSet", "DaemonSet"].contains(&kind_of(document)) {
+        return None;
+    }
+
+    let template = &document["spec"]["template"];
+    let labels = template["metadata"]["labels"].as_mapping()?.clone();
+    let ports = template["spec"]["containers"]
+        .as_sequence()
+        .into_iter()
+        .flatten()
+        .flat_map(|container| container["ports"].as_sequence().into_iter().flatten())
+        .filter_map(|port| port["containerPort"].as_u64())
+        .collect();
+
+    Some(Workload {
+        name: name_of(document),
+        namespace: namespace_of(document).unwrap_or_else(|| "default".to_string()),
+        labels,
+        ports,
+    })
+}
+
+fn policy_for(
+    workload: &Workload,
+    peers: &[&Workload],
+    exposed: bool,
+    ingress_controller_namespace: &str,
+) -> serde_json::Value {
+    let ports = workload
+        .ports
+        .iter()
+        .map(|port| json!({ "port": port, "protocol": "TCP" }))
+        .collect::<Vec<_>>();
+
+    let mut rules = Vec::new();
+    if !peers.is_empty() {
+        let from = peers
+            .iter()
+            .map(|peer| {
+                json!({
+                    "podSelector": { "matchLabels": peer.labels },
+                    "namespaceSelector": {
+                        "matchLabels": { "kubernetes.io/metadata.name": peer.namespace },
+                    },
+                })
+            })
+            .collect::<Vec<_>>();
+        rules.push(json!({ "from": from, "ports": ports }));
+    }
+    if exposed {
+        rules.push(json!({
+            "from": [{
+                "namespaceSelector": {
+                    "matchLabels": { "kubernetes.io/metadata.name": ingress_controller_namespace },
+                },
+            }],
+            "ports": ports,
+        }));
+    }
+
+    json!({
+        "apiVersion": "networking.k8s.io/v1",
+        "kind": "NetworkPolicy",
+        "metadata": {
+            "name": format!("{}-ingress", workload.name),
+            "namespace": workload.namespace,
+        },
+        "spec": {
+            "podSelector": { "matchLabels": workload.labels },
+            "policyTypes": ["Ingress"],
+            "ingress": rules,
+        },
+    })
+}
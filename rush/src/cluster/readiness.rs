@@ -0,0 +1,139 @@
+use crate::cluster::documents::{kind_of, load_documents, name_of, namespace_of};
+use crate::utils::run_command;
+use colored::Colorize;
+use log::{trace, warn};
+use serde::Deserialize;
+use serde_yaml::Value;
+use std::path::Path;
+
+const WAIT_TIMEOUT: &str = "--timeout=120s";
+
+/// Waits until a namespace is active so that namespaced resources can be applied to it.
+pub async fn wait_for_namespace(
+    kubectl: &str,
+    context: &str,
+    namespace: &str,
+) -> Result<(), String> {
+    let target = format!("namespace/{}", namespace);
+    run_command(
+        "wait".white().bold(),
+        kubectl,
+        vec![
+            "--context",
+            context,
+            "wait",
+            "--for=jsonpath={.status.phase}=Active",
+            &target,
+            WAIT_TIMEOUT,
+        ],
+    )
+    .await
+    .map(|_| ())
+}
+
+/// Waits for the resources of an applied manifest that later manifests depend on:
+/// CRDs must be established, namespaces active and operator deployments available.
+pub async fn wait_for_manifest(
+    kubectl: &str,
+    context: &str,
+    manifest_path: &str,
+    default_namespace: &str,
+) -> Result<(), String> {
+    let contents = std::fs::read_to_string(manifest_path)
+        .map_err(|e| format!("Failed to read {}: {}", manifest_path, e))?;
+
+    for document in serde_yaml::Deserializer::from_str(&contents) {
+        let document = match Value::deserialize(document) {
+            Ok(document) => document,
+            Err(e) => {
+                warn!("Not waiting for {}: {}", manifest_path, e);
+                return Ok(());
+            }
+        };
+        let kind = document["kind"].as_str().unwrap_or_default();
+        let name = match document["metadata"]["name"].as_str() {
+            Some(name) => name,
+            None => continue,
+        };
+        let namespace = document["metadata"]["namespace"]
+            .as_str()
+            .unwrap_or(default_namespace);
+
+        match kind {
+            "CustomResourceDefinition" => {
+                trace!("Waiting for CRD {}", name);
+                let target = format!("crd/{}", name);
+                run_command(
+                    "wait".white().bold(),
+                    kubectl,
+                    vec![
+                        "--context",
+                        context,
+                        "wait",
+                        "--for=condition=Established",
+                        &target,
+                        WAIT_TIMEOUT,
+                    ],
+                )
+                .await?;
+            }
+            "Namespace" => wait_for_namespace(kubectl, context, name).await?,
+            "Deployment" => {
+                trace!("Waiting for deployment {} in {}", name, namespace);
+                let target = format!("deployment/{}", name);
+                run_command(
+                    "wait".white().bold(),
+                    kubectl,
+                    vec![
+                        "--context",
+                        context,
+                        "rollout",
+                        "status",
+                        &target,
+                        "-n",
+                        namespace,
+                        WAIT_TIMEOUT,
+                    ],
+                )
+                .await?;
+            }
+            _ => (),
+        }
+    }
+
+    Ok(())
+}
+
+/// Restarts the Deployments, StatefulSets and DaemonSets rendered in `directory`, so that
+/// their pods run an image rebuilt under the same tag, e.g. one loaded into minikube.
+pub async fn restart_workloads(
+    kubectl: &str,
+    context: &str,
+    directory: &Path,
+    default_namespace: &str,
+) -> Result<(), String> {
+    for document in load_documents(directory)? {
+        let kind = kind_of(&document);
+        if !["Deployment", "StatefulSet", "DaemonSet"].contains(&kind) {
+            continue;
+        }
+        let target = format!("{}/{}", kind.to_lowercase(), name_of(&document));
+        let namespace = namespace_of(&document).unwrap_or_else(|| default_namespace.to_string());
+        trace!("Restarting {} in {}", target, namespace);
+        run_command(
+            "restart".white().bold(),
+            kubectl,
+            vec![
+                "--context",
+                context,
+                "rollout",
+                "restart",
+                &target,
+                "-n",
+                &namespace,
+            ],
+        )
+        .await?;
+    }
+    Ok(())
+}
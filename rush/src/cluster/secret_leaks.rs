@@ -0,0 +1,96 @@
+use ignore::WalkBuilder;
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Shorter secret values, e.g. `true` or a port, are all over manifests anyway.
+const MIN_SECRET_LENGTH: usize = 8;
+
+/// Credentials recognisable by their format, whatever vault they came from.
+const SECRET_PATTERNS: &[(&str, &str)] = &[
+    ("a private key", r"-----BEGIN [A-Z ]*PRIVATE KEY-----"),
+    ("an AWS access key", r"\b(?:AKIA|ASIA)[0-9A-Z]{16}\b"),
+    ("a GitHub token", r"\bgh[pousr]_[A-Za-z0-9]{36,}\b"),
+    ("a Slack token", r"\bxox[abprs]-[A-Za-z0-9-]{10,}"),
+    ("a Stripe secret key", r"\b[rs]k_live_[A-Za-z0-9]{16,}"),
+    ("a Google API key", r"\bAIza[0-9A-Za-z_-]{35}\b"),
+];
+
+/// A secret in plain text in a rendered manifest.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SecretLeak {
+    pub path: PathBuf,
+    pub line: usize,
+    /// Names the secret or the kind of credential, never its value
+    pub what: String,
+}
+
+impl fmt::Display for SecretLeak {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.path.display(), self.line, self.what)
+    }
+}
+
+fn patterns() -> &'static [(&'static str, Regex)] {
+    static PATTERNS: OnceLock<Vec<(&str, Regex)>> = OnceLock::new();
+    PATTERNS.get_or_init(|| {
+        SECRET_PATTERNS
+            .iter()
+            .map(|(what, pattern)| (*what, Regex::new(pattern).expect("Valid secret pattern")))
+            .collect()
+    })
+}
+
+/// Finds the values of `secrets`, keyed by the name they are reported with, and
+/// anything that looks like a credential in the manifests rendered to `directory`.
+/// Meant for manifests whose secrets are not encrypted, as then nothing but their
+/// base64 encoding in the `Secret`s keeps them from being read in the cluster or the
+/// infrastructure repository.
+pub fn find_secret_leaks(directory: &Path, secrets: &BTreeMap<String, String>) -> Vec<SecretLeak> {
+    let secrets = secrets
+        .iter()
+        .filter(|(_, value)| value.trim().len() >= MIN_SECRET_LENGTH)
+        .collect::<Vec<_>>();
+    let mut files = WalkBuilder::new(directory)
+        .standard_filters(false)
+        .build()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            entry
+                .file_type()
+                .is_some_and(|file_type| file_type.is_file())
+        })
+        .map(|entry| entry.into_path())
+        .collect::<Vec<_>>();
+    files.sort();
+
+    let mut leaks = Vec::new();
+    for path in files {
+        // Binary files are not manifests
+        let Ok(contents) = std::fs::read_to_string(&path) else {
+            continue;
+        };
+        for (index, line) in contents.lines().enumerate() {
+            let mut leak = |what: String| {
+                leaks.push(SecretLeak {
+                    path: path.clone(),
+                    line: index + 1,
+                    what,
+                })
+            };
+            for (name, value) in &secrets {
+                if line.contains(value.trim()) {
+                    leak(format!("the secret {}", name));
+                }
+            }
+            for (what, pattern) in patterns() {
+                if pattern.is_match(line) {
+                    leak(format!("what looks like {}", what));
+                }
+            }
+        }
+    }
+    leaks
+}
@@ -0,0 +1,431 @@
+use crate::builder::DeployStrategy;
+use crate::cluster::documents::{kind_of, load_documents, name_of, namespace_of};
+use crate::utils::run_command;
+use colored::{ColoredString, Colorize};
+use log::{trace, warn};
+use serde_yaml::{Mapping, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const TRACK_LABEL: &str = "rush.io/track";
+const COLOR_LABEL: &str = "rush.io/color";
+const ROLLOUT_TIMEOUT: &str = "--timeout=300s";
+
+/// Orchestrates the canary and blue/green switch-over of a single component.
+///
+/// The additional objects a strategy needs are derived from the component's
+/// rendered manifests and written to `work_directory` before they are applied.
+pub struct StrategyDeployer {
+    kubectl: String,
+    context: String,
+    work_directory: PathBuf,
+}
+
+impl StrategyDeployer {
+    pub fn new(kubectl: &str, context: &str, work_directory: PathBuf) -> Self {
+        StrategyDeployer {
+            kubectl: kubectl.to_string(),
+            context: context.to_string(),
+            work_directory,
+        }
+    }
+
+    pub async fn deploy(
+        &self,
+        rendered_directory: &Path,
+        strategy: &DeployStrategy,
+    ) -> Result<(), String> {
+        if self.work_directory.exists() {
+            fs::remove_dir_all(&self.work_directory).map_err(|e| e.to_string())?;
+        }
+        fs::create_dir_all(&self.work_directory).map_err(|e| e.to_string())?;
+
+        let documents = load_documents(rendered_directory)?;
+        match strategy {
+            DeployStrategy::Rolling => self.apply(rendered_directory).await.map(|_| ()),
+            DeployStrategy::Canary {
+                steps,
+                pause_seconds,
+            } => {
+                self.canary(rendered_directory, &documents, steps, *pause_seconds)
+                    .await
+            }
+            DeployStrategy::BlueGreen { keep_previous } => {
+                self.blue_green(&documents, *keep_previous).await
+            }
+        }
+    }
+
+    async fn canary(
+        &self,
+        rendered_directory: &Path,
+        documents: &[Value],
+        steps: &[u8],
+        pause_seconds: u64,
+    ) -> Result<(), String> {
+        let deployments = documents
+            .iter()
+            .filter(|doc| kind_of(doc) == "Deployment")
+            .collect::<Vec<_>>();
+
+        for weight in steps.iter().filter(|weight| **weight > 0 && **weight < 100) {
+            let canaries = deployments
+                .iter()
+                .map(|deployment| canary_deployment(deployment, *weight))
+                .collect::<Vec<_>>();
+            let path = self.write("canary.yaml", &canaries)?;
+            println!("Canary at {}% for {} deployment(s)", weight, canaries.len());
+            self.apply(&path).await?;
+
+            for canary in &canaries {
+                if let Err(e) = self.verify(canary, pause_seconds).await {
+                    warn!("Canary failed, removing it: {}", e);
+                    self.delete(&path).await.ok();
+                    return Err(format!("Canary failed at {}%: {}", weight, e));
+                }
+            }
+        }
+
+        self.apply(rendered_directory).await?;
+        for deployment in &deployments {
+            self.rollout_status(deployment).await?;
+        }
+
+        let canary_path = self.work_directory.join("canary.yaml");
+        if canary_path.exists() {
+            self.delete(&canary_path).await?;
+        }
+        Ok(())
+    }
+
+    async fn blue_green(&self, documents: &[Value], keep_previous: bool) -> Result<(), String> {
+        let (deployments, rest): (Vec<&Value>, Vec<&Value>) = documents
+            .iter()
+            .partition(|doc| kind_of(doc) == "Deployment");
+        let (services, resources): (Vec<&Value>, Vec<&Value>) =
+            rest.into_iter().partition(|doc| kind_of(doc) == "Service");
+
+        let active_color = match services.first() {
+            Some(service) => self.active_color(service).await,
+            None => None,
+        };
+        let next_color = match active_color.as_deref() {
+            Some("blue") => "green",
+            _ => "blue",
+        };
+        println!(
+            "Deploying {} (active: {})",
+            next_color.bold(),
+            active_color.as_deref().unwrap_or("none")
+        );
+
+        if !resources.is_empty() {
+            let resources = resources.into_iter().cloned().collect::<Vec<_>>();
+            let path = self.write("resources.yaml", &resources)?;
+            self.apply(&path).await?;
+        }
+
+        let colored = deployments
+            .iter()
+            .map(|deployment| colored_deployment(deployment, next_color))
+            .collect::<Vec<_>>();
+        let deployments_path = self.write(&format!("deployment-{}.yaml", next_color), &colored)?;
+        self.apply(&deployments_path).await?;
+
+        for deployment in &colored {
+            if let Err(e) = self.rollout_status(deployment).await {
+                warn!("{} deployment failed, removing it: {}", next_color, e);
+                self.delete(&deployments_path).await.ok();
+                return Err(format!("{} deployment is not healthy: {}", next_color, e));
+            }
+        }
+
+        let switched = services
+            .iter()
+            .map(|service| colored_service(service, next_color))
+            .collect::<Vec<_>>();
+        if !switched.is_empty() {
+            let path = self.write("service.yaml", &switched)?;
+            self.apply(&path).await?;
+        }
+
+        for deployment in &deployments {
+            let name = previous_deployment_name(deployment, active_color.as_deref());
+            let namespace = namespace_of(deployment);
+            let mut args = match (active_color.is_some(), keep_previous) {
+                (_, false) => vec!["delete", "deployment", &name, "--ignore-not-found"],
+                (true, true) => continue,
+                // The deployment from before blue/green has no color to switch back to, so
+                // it is scaled down rather than left serving nothing with all its replicas
+                (false, true) => {
+                    if !self.exists(&name, namespace.as_deref()).await? {
+                        continue;
+                    }
+                    vec!["scale", "deployment", &name, "--replicas=0"]
+                }
+            };
+            if let Some(namespace) = &namespace {
+                args.extend(["-n", namespace]);
+            }
+            self.kubectl("blue/green".white().bold(), args).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Waits for a deployment to become available and stay free of container
+    /// restarts for `pause_seconds`.
+    async fn verify(&self, deployment: &Value, pause_seconds: u64) -> Result<(), String> {
+        self.rollout_status(deployment).await?;
+        tokio::time::sleep(Duration::from_secs(pause_seconds)).await;
+
+        let selector = selector_of(deployment);
+        let mut args = vec![
+            "get",
+            "pods",
+            "-l",
+            &selector,
+            "-o",
+            "jsonpath={.items[*].status.containerStatuses[*].restartCount}",
+        ];
+        let namespace = namespace_of(deployment);
+        if let Some(namespace) = &namespace {
+            args.extend(["-n", namespace]);
+        }
+        let restarts = self.kubectl("verify".white().bold(), args).await?;
+        let restarts = restarts
+            .split_whitespace()
+            .filter_map(|count| count.parse::<u64>().ok())
+            .sum::<u64>();
+        if restarts > 0 {
+            return Err(format!(
+                "{} restarted {} time(s)",
+                name_of(deployment),
+                restarts
+            ));
+        }
+        Ok(())
+    }
+
+    async fn rollout_status(&self, deployment: &Value) -> Result<String, String> {
+        let target = format!("deployment/{}", name_of(deployment));
+        let mut args = vec!["rollout", "status", &target, ROLLOUT_TIMEOUT];
+        let namespace = namespace_of(deployment);
+        if let Some(namespace) = &namespace {
+            args.extend(["-n", namespace]);
+        }
+        self.kubectl("rollout".white().bold(), args).await
+    }
+
+    async fn exists(&self, deployment: &str, namespace: Option<&str>) -> Result<bool, String> {
+        let mut args = vec![
+            "get",
+            "deployment",
+            deployment,
+            "--ignore-not-found",
+            "-o",
+            "name",
+        ];
+        if let Some(namespace) = namespace {
+            args.extend(["-n", namespace]);
+        }
+        let found = self.kubectl("blue/green".white().bold(), args).await?;
+        Ok(!found.trim().is_empty())
+    }
+
+    async fn active_color(&self, service: &Value) -> Option<String> {
+        let jsonpath = format!(
+            "jsonpath={{.spec.selector.{}}}",
+            COLOR_LABEL.replace('.', "\\.")
+        );
+        let name = name_of(service);
+        let mut args = vec!["get", "service", &name, "-o", &jsonpath];
+        let namespace = namespace_of(service);
+        if let Some(namespace) = &namespace {
+            args.extend(["-n", namespace]);
+        }
+        self.kubectl("blue/green".white().bold(), args)
+            .await
+            .ok()
+            .map(|color| color.trim().to_string())
+            .filter(|color| !color.is_empty())
+    }
+
+    async fn apply(&self, path: &Path) -> Result<String, String> {
+        let path = path.display().to_string();
+        self.kubectl("apply".white().bold(), vec!["apply", "-R", "-f", &path])
+            .await
+    }
+
+    async fn delete(&self, path: &Path) -> Result<String, String> {
+        let path = path.display().to_string();
+        self.kubectl(
+            "delete".white().bold(),
+            vec!["delete", "--ignore-not-found", "-f", &path],
+        )
+        .await
+    }
+
+    async fn kubectl(&self, label: ColoredString, args: Vec<&str>) -> Result<String, String> {
+        let args = ["--context", self.context.as_str()]
+            .into_iter()
+            .chain(args)
+            .collect();
+        run_command(label, &self.kubectl, args).await
+    }
+
+    fn write(&self, file_name: &str, documents: &[Value]) -> Result<PathBuf, String> {
+        let path = self.work_directory.join(file_name);
+        let mut contents = String::new();
+        for document in documents {
+            contents.push_str("---\n");
+            contents.push_str(&serde_yaml::to_string(document).map_err(|e| e.to_string())?);
+        }
+        trace!("Writing {}", path.display());
+        fs::write(&path, contents)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        Ok(path)
+    }
+}
+
+fn selector_of(deployment: &Value) -> String {
+    deployment["spec"]["selector"]["matchLabels"]
+        .as_mapping()
+        .map(|labels| {
+            labels
+                .iter()
+                .filter_map(|(key, value)| Some(format!("{}={}", key.as_str()?, value.as_str()?)))
+                .collect::<Vec<_>>()
+                .join(",")
+        })
+        .unwrap_or_default()
+}
+
+/// Copies a deployment under a new name and adds a label to its selector and pods.
+fn labelled_copy(deployment: &Value, suffix: &str, label: &str, value: &str) -> Value {
+    let mut copy = deployment.clone();
+    copy["metadata"]["name"] = Value::from(format!("{}-{}", name_of(deployment), suffix));
+    add_label(&mut copy["spec"]["selector"]["matchLabels"], label, value);
+    add_label(
+        &mut copy["spec"]["template"]["metadata"]["labels"],
+        label,
+        value,
+    );
+    copy
+}
+
+/// The stable pods keep matching the Services, so the canary receives a share of
+/// the traffic proportional to its replica count.
+fn canary_deployment(deployment: &Value, weight: u8) -> Value {
+    let stable_replicas = deployment["spec"]["replicas"].as_u64().unwrap_or(1);
+    let mut canary = labelled_copy(deployment, "canary", TRACK_LABEL, "canary");
+    canary["spec"]["replicas"] = Value::from(canary_replicas(stable_replicas, weight));
+    canary
+}
+
+/// The deployment the Services selected before the switch: the one of the previous
+/// color, or on the first switch the one applied before blue/green, which has none.
+fn previous_deployment_name(deployment: &Value, active_color: Option<&str>) -> String {
+    match active_color {
+        Some(color) => format!("{}-{}", name_of(deployment), color),
+        None => name_of(deployment),
+    }
+}
+
+/// The replicas that make the canary `weight` percent of all pods, at least one.
+fn canary_replicas(stable_replicas: u64, weight: u8) -> u64 {
+    let weight = weight as u64;
+    (stable_replicas * weight).div_ceil(100 - weight).max(1)
+}
+
+fn colored_deployment(deployment: &Value, color: &str) -> Value {
+    labelled_copy(deployment, color, COLOR_LABEL, color)
+}
+
+fn colored_service(service: &Value, color: &str) -> Value {
+    let mut service = service.clone();
+    add_label(&mut service["spec"]["selector"], COLOR_LABEL, color);
+    service
+}
+
+fn add_label(labels: &mut Value, label: &str, value: &str) {
+    if !labels.is_mapping() {
+        *labels = Value::Mapping(Mapping::new());
+    }
+    labels[label] = Value::from(value);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn deployment(replicas: u64) -> Value {
+        let mut deployment: Value = serde_yaml::from_str(
+            "
+kind: Deployment
+metadata:
+  name: backend
+spec:
+  selector:
+    matchLabels:
+      app: backend
+  template:
+    metadata:
+      labels:
+        app: backend
+",
+        )
+        .unwrap();
+        deployment["spec"]["replicas"] = Value::from(replicas);
+        deployment
+    }
+
+    #[test]
+    fn test_canary_replicas() {
+        assert_eq!(canary_replicas(3, 25), 1);
+        assert_eq!(canary_replicas(3, 50), 3);
+        assert_eq!(canary_replicas(4, 80), 16);
+        assert_eq!(canary_replicas(10, 1), 1);
+        assert_eq!(canary_replicas(0, 50), 1);
+    }
+
+    #[test]
+    fn test_canary_deployment() {
+        let canary = canary_deployment(&deployment(3), 50);
+        assert_eq!(name_of(&canary), "backend-canary");
+        assert_eq!(canary["spec"]["replicas"].as_u64(), Some(3));
+        assert_eq!(selector_of(&canary), "app=backend,rush.io/track=canary");
+        assert_eq!(
+            canary["spec"]["template"]["metadata"]["labels"][TRACK_LABEL].as_str(),
+            Some("canary")
+        );
+    }
+
+    #[test]
+    fn test_colored_deployment_and_service() {
+        let green = colored_deployment(&deployment(2), "green");
+        assert_eq!(name_of(&green), "backend-green");
+        assert_eq!(green["spec"]["replicas"].as_u64(), Some(2));
+        assert_eq!(selector_of(&green), "app=backend,rush.io/color=green");
+
+        let service: Value =
+            serde_yaml::from_str("kind: Service\nspec:\n  selector:\n    app: backend\n").unwrap();
+        let service = colored_service(&service, "green");
+        assert_eq!(service["spec"]["selector"]["app"].as_str(), Some("backend"));
+        assert_eq!(
+            service["spec"]["selector"][COLOR_LABEL].as_str(),
+            Some("green")
+        );
+    }
+
+    #[test]
+    fn test_previous_deployment_name() {
+        let deployment = deployment(1);
+        assert_eq!(
+            previous_deployment_name(&deployment, Some("blue")),
+            "backend-blue"
+        );
+        assert_eq!(previous_deployment_name(&deployment, None), "backend");
+    }
+}
@@ -0,0 +1,107 @@
+use crate::utils::run_command;
+use colored::Colorize;
+use log::{debug, trace};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Runs `plan` and `apply` of a Terraform or OpenTofu configuration for an environment,
+/// provisioning the resources next to the cluster, such as buckets and DNS records.
+///
+/// Each environment gets its own workspace, so their states stay apart.
+pub struct Terraform {
+    binary: String,
+    directory: PathBuf,
+    environment: String,
+}
+
+impl Terraform {
+    pub fn new(binary: &str, directory: &str, environment: &str) -> Self {
+        Terraform {
+            binary: binary.to_string(),
+            directory: PathBuf::from(directory),
+            environment: environment.to_string(),
+        }
+    }
+
+    /// Plans with `variables` as input variables, then applies exactly that plan.
+    pub async fn plan_and_apply(&self, variables: &HashMap<String, String>) -> Result<(), String> {
+        if !self.directory.is_dir() {
+            return Err(format!(
+                "Terraform directory {} does not exist",
+                self.directory.display()
+            ));
+        }
+        let chdir = format!("-chdir={}", self.directory.display());
+        self.run(vec![&chdir, "init", "-input=false"]).await?;
+        self.run(vec![
+            &chdir,
+            "workspace",
+            "select",
+            "-or-create",
+            &self.environment,
+        ])
+        .await?;
+
+        // Both files hold the secrets, so they live in the git-ignored .terraform and are
+        // removed again whatever the outcome
+        let working_directory = self.directory.join(".terraform");
+        let var_file = working_directory.join(format!("rush-{}.tfvars.json", self.environment));
+        let plan_file = working_directory.join(format!("rush-{}.tfplan", self.environment));
+        write_private(
+            &var_file,
+            &serde_json::to_string(variables).map_err(|e| e.to_string())?,
+        )?;
+        let var_file_arg = format!("-var-file={}", var_file.display());
+        let plan_file_arg = format!("-out={}", plan_file.display());
+        let plan = self
+            .run(vec![
+                &chdir,
+                "plan",
+                "-input=false",
+                &var_file_arg,
+                &plan_file_arg,
+            ])
+            .await;
+        let _ = std::fs::remove_file(&var_file);
+
+        let result = match plan {
+            Ok(_) => self
+                .run(vec![
+                    &chdir,
+                    "apply",
+                    "-input=false",
+                    &plan_file.display().to_string(),
+                ])
+                .await
+                .map(|_| ()),
+            Err(e) => Err(e),
+        };
+        let _ = std::fs::remove_file(&plan_file);
+        result
+    }
+
+    async fn run(&self, args: Vec<&str>) -> Result<String, String> {
+        trace!("Running {} {}", self.binary, args.join(" "));
+        run_command(self.binary.white().bold(), &self.binary, args)
+            .await
+            .map_err(|e| format!("{} failed: {}", self.binary, e))
+    }
+}
+
+/// Writes a file only the current user can read.
+fn write_private(path: &Path, contents: &str) -> Result<(), String> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options
+        .open(path)
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    std::io::Write::write_all(&mut file, contents.as_bytes())
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    debug!("Wrote Terraform variables to {}", path.display());
+    Ok(())
+}
@@ -0,0 +1,206 @@
+use crate::builder::shared_file;
+use jsonschema::{error::ValidationErrorKind, JSONSchema};
+use serde_json::Value;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+const STACK_SPEC_SCHEMA: &str = include_str!("../schemas/stack.spec.schema.json");
+const RUSHD_SCHEMA: &str = include_str!("../schemas/rushd.schema.json");
+const STACK_ENV_SCHEMA: &str = include_str!("../schemas/stack.env.schema.json");
+const STACK_ENV_SECRETS_SCHEMA: &str = include_str!("../schemas/stack.env.secrets.schema.json");
+
+/// A problem found in one of the configuration files.
+#[derive(Debug)]
+pub struct ConfigIssue {
+    pub file: String,
+    pub line: Option<usize>,
+    /// JSON pointer to the offending value, empty for syntax errors
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{}: ", self.file, line)?,
+            None => write!(f, "{}: ", self.file)?,
+        }
+        if !self.path.is_empty() {
+            write!(f, "{}: ", self.path)?;
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+/// Validates `rushd.yaml` and the configuration files of a product for the given environment.
+pub fn validate_product(
+    root_dir: &Path,
+    product_path: &Path,
+    environment: &str,
+) -> Vec<ConfigIssue> {
+    let mut issues = validate_file(&root_dir.join("rushd.yaml"), RUSHD_SCHEMA, true);
+    issues.extend(validate_file(
+        &product_path.join("stack.spec.yaml"),
+        STACK_SPEC_SCHEMA,
+        true,
+    ));
+    issues.extend(validate_file(
+        &product_path.join("stack.env.base.yaml"),
+        STACK_ENV_SCHEMA,
+        false,
+    ));
+    issues.extend(validate_file(
+        &product_path.join(format!("stack.env.{}.yaml", environment)),
+        STACK_ENV_SCHEMA,
+        false,
+    ));
+    issues.extend(validate_file(
+        &product_path.join("stack.env.secrets.yaml"),
+        STACK_ENV_SECRETS_SCHEMA,
+        false,
+    ));
+
+    // Definitions shared between products
+    for (file_name, schema) in [
+        ("stack.spec.yaml", STACK_SPEC_SCHEMA),
+        ("stack.env.base.yaml", STACK_ENV_SCHEMA),
+        ("stack.env.secrets.yaml", STACK_ENV_SECRETS_SCHEMA),
+    ] {
+        issues.extend(validate_file(
+            &shared_file(product_path, file_name),
+            schema,
+            false,
+        ));
+    }
+    issues.extend(validate_file(
+        &shared_file(product_path, &format!("stack.env.{}.yaml", environment)),
+        STACK_ENV_SCHEMA,
+        false,
+    ));
+    issues
+}
+
+/// Validates a YAML file against one of the schemas above. Missing files are
+/// reported only when `required` is set.
+fn validate_file(path: &Path, schema: &str, required: bool) -> Vec<ConfigIssue> {
+    let file = path.display().to_string();
+    let issue = |line, message| ConfigIssue {
+        file: file.clone(),
+        line,
+        path: String::new(),
+        message,
+    };
+
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(_) if !required => return Vec::new(),
+        Err(e) => return vec![issue(None, format!("Could not read file: {}", e))],
+    };
+    let document: serde_yaml::Value = match serde_yaml::from_str(&contents) {
+        Ok(document) => document,
+        Err(e) => {
+            let line = e.location().map(|location| location.line());
+            return vec![issue(line, e.to_string())];
+        }
+    };
+    // Tagged values such as `!Static "x"` become `{"!Static": "x"}`
+    let instance = match serde_json::to_value(&document) {
+        Ok(Value::Null) => Value::Object(Default::default()),
+        Ok(instance) => instance,
+        Err(e) => return vec![issue(None, e.to_string())],
+    };
+
+    let schema: Value = serde_json::from_str(schema).expect("Bundled schema is not valid JSON");
+    let compiled = JSONSchema::compile(&schema).expect("Bundled schema is not a valid JSON Schema");
+    let errors = match compiled.validate(&instance) {
+        Ok(_) => return Vec::new(),
+        Err(errors) => errors,
+    };
+    errors
+        .map(|error| {
+            let mut segments = error.instance_path.clone().into_vec();
+            // Point at the first unexpected key rather than the object holding it
+            if let ValidationErrorKind::AdditionalProperties { unexpected } = &error.kind {
+                segments.extend(unexpected.first().cloned());
+            }
+            ConfigIssue {
+                file: file.clone(),
+                line: line_of(&contents, &segments),
+                path: error.instance_path.to_string(),
+                message: error.to_string(),
+            }
+        })
+        .collect()
+}
+
+/// Finds the line of the value at `segments` by walking the YAML text. This
+/// understands the block style used throughout rush's configuration files and
+/// falls back to the closest ancestor it could find.
+fn line_of(contents: &str, segments: &[String]) -> Option<usize> {
+    let lines = contents.lines().collect::<Vec<_>>();
+    let mut start = 0;
+    let mut parent_indent: Option<usize> = None;
+    let mut parent_is_item = false;
+    let mut found = None;
+
+    for segment in segments {
+        let mut item = 0;
+        let mut matched = None;
+        for (number, line) in lines.iter().enumerate().skip(start) {
+            let trimmed = line.trim_start();
+            if trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with("---") {
+                continue;
+            }
+            let indent = line.len() - trimmed.len();
+            let is_item = trimmed.starts_with("- ") || trimmed == "-";
+            if let Some(parent_indent) = parent_indent {
+                // Sequences may sit at the same indentation as their key, while the
+                // next dash at the indentation of an item ends that item
+                let sibling = indent == parent_indent && (!is_item || parent_is_item);
+                if (indent < parent_indent || sibling) && !(parent_is_item && number == start) {
+                    break;
+                }
+            }
+
+            if let Ok(index) = segment.parse::<usize>() {
+                if is_item {
+                    if item == index {
+                        matched = Some((number, indent, true));
+                        break;
+                    }
+                    item += 1;
+                }
+                continue;
+            }
+
+            // Keys of a sequence item may follow its dash on the same line
+            let key = trimmed.trim_start_matches("- ");
+            let key_indent = indent + (trimmed.len() - key.len());
+            let is_key = [
+                format!("{}:", segment),
+                format!("\"{}\":", segment),
+                format!("'{}':", segment),
+            ]
+            .iter()
+            .any(|prefix| key.starts_with(prefix.as_str()));
+            if is_key && parent_indent.map_or(key_indent == 0, |parent| key_indent > parent) {
+                matched = Some((number, key_indent, false));
+                break;
+            }
+        }
+
+        match matched {
+            Some((number, indent, is_item)) => {
+                found = Some(number + 1);
+                parent_indent = Some(indent);
+                parent_is_item = is_item;
+                // The first key of a sequence item shares its line
+                start = if is_item { number } else { number + 1 };
+            }
+            None => break,
+        }
+    }
+
+    found
+}
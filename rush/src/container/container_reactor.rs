@@ -1,29 +1,67 @@
-use super::docker::DockerImage;
+use super::docker::{DockerImage, Pushed};
+use super::endpoints::{report_endpoints, Endpoint};
+use super::events::{Event, EventSink};
+use super::gc::{self, BuiltImage};
+use super::notifications::DeployNotification;
+use super::profile::{BuildProfiler, ProfileStep};
+use super::proxy::{dev_certificate, DevProxy};
+use super::registry::RegistryClient;
+use super::snapshots::Snapshots;
+use super::stats::{print_usage, ResourceMonitor};
 use super::status::Status;
+use super::status_page::StatusBoard;
+use super::summary::{ExecutionSummary, StepStatus};
+use super::volumes::ProductVolumes;
+use super::warm_start::WarmStart;
 use crate::builder::BuildType;
 use crate::builder::ComponentBuildSpec;
 use crate::builder::Config;
+use crate::builder::DatabaseSpec;
+use crate::builder::DeployStrategy;
 use crate::builder::Variables;
+use crate::builder::DOTENV_FILES;
+use crate::builder::{load_stack_spec, shared_file};
+use crate::cluster::render_in_parallel;
+use crate::cluster::restart_workloads;
+use crate::cluster::run_jobs;
 use crate::cluster::InfrastructureRepo;
+use crate::cluster::IngressGenerator;
 use crate::cluster::K8ClusterManifests;
+use crate::cluster::K8ComponentManifests;
 use crate::cluster::K8Encoder;
+use crate::cluster::LocalCluster;
+use crate::cluster::StrategyDeployer;
+use crate::cluster::Terraform;
+use crate::cluster::{
+    annotate_pod_templates, find_fake_secrets, mark_fake_secrets, secrets_checksum,
+    secrets_checksum_key, FAKE_SECRETS_ANNOTATION, SECRETS_CHECKSUM_ANNOTATION,
+};
+use crate::cluster::{copy_directory, DeploymentHistory, RolloutRecord};
+use crate::cluster::{find_secret_leaks, mount_host_paths, HostMount};
+use crate::cluster::{wait_for_manifest, wait_for_namespace, write_network_policies};
+use crate::container::graph::find_cycle;
 use crate::container::service_spec::{ServiceSpec, ServicesSpec};
+use crate::error::RushError;
 use crate::path_matcher::PathMatcher;
+use crate::progress::Progress;
+use crate::public_env_defs::PublicEnvironmentDefinitions;
 use crate::toolchain::ToolchainContext;
+use crate::utils::closest_match;
 use crate::utils::run_command;
 use crate::utils::Directory;
 use crate::vault::EncodeSecrets;
-use crate::vault::Vault;
+use crate::vault::{get_secrets, Vault};
 use colored::Colorize;
 use glob::glob;
+use ignore::WalkBuilder;
 use log::{debug, error, trace, warn};
-use notify::{Config as NotifyConfig, RecommendedWatcher, RecursiveMode, Watcher};
-use std::io::Write;
+use notify::event::ModifyKind;
+use notify::{Config as NotifyConfig, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
 use std::path::PathBuf;
 use std::sync::Arc;
 use std::sync::Mutex;
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
     sync::mpsc::{self, Receiver},
 };
 use tokio::sync::broadcast;
@@ -47,8 +85,206 @@ pub struct ContainerReactor {
     cluster_manifests: K8ClusterManifests,
     infrastructure_repo: InfrastructureRepo,
     vault: Arc<Mutex<dyn Vault + Send>>,
+    git_hash: String,
+    history: DeploymentHistory,
+    kube_context: String,
+    cluster_values: HashMap<String, HashMap<String, String>>,
 
     changed_files: Arc<Mutex<Vec<PathBuf>>>,
+    /// Directories created below the watched paths since the dev loop last looked
+    created_directories: Arc<Mutex<Vec<PathBuf>>>,
+    /// Fingerprints of the manifests last rendered per component, see
+    /// [`K8ComponentManifests::fingerprint`](crate::cluster::K8ComponentManifests::fingerprint)
+    manifest_fingerprints: HashMap<String, String>,
+    /// Components taken out of stack.spec.yaml during `rush minikube dev`, still to be
+    /// deleted from the cluster
+    removed_manifests: Vec<K8ComponentManifests>,
+    /// The secrets the manifests were last rendered with, by `component/KEY`
+    rendered_secrets: BTreeMap<String, String>,
+    events: EventSink,
+    summary: ExecutionSummary,
+    status_board: Option<StatusBoard>,
+    profiler: Option<BuildProfiler>,
+    resource_usage_interval: Option<std::time::Duration>,
+    host_mount: Option<HostMount>,
+    fake_secrets: bool,
+    proxy_port: Option<u16>,
+    /// The HTTPS port of the proxy and the file its certificate is cached in
+    proxy_tls: Option<(u16, PathBuf)>,
+    /// Components whose environment `rush reload-env` changed, restarted without a rebuild
+    env_reloads: HashSet<String>,
+    volumes: ProductVolumes,
+    /// The images of the previous `rush dev`, for starting unchanged components from them
+    warm_start: Option<WarmStart>,
+    /// Components started from the image of the previous session rather than rebuilt
+    warm_started: HashSet<String>,
+    /// Components `--redirect` replaces, to the host and port that serve them
+    redirects: BTreeMap<String, (String, u16)>,
+    /// Kept to load the components again when the stack files change during `rush dev`
+    k8s_encoder: Arc<dyn K8Encoder>,
+    silenced_components: Vec<String>,
+    /// Watches the files of the dev loop, with the paths it watches so far
+    watcher: Option<RecommendedWatcher>,
+    watching: HashSet<PathBuf>,
+}
+
+/// When the Jobs of a component run, see [`ComponentJobs`](crate::builder::ComponentJobs).
+#[derive(Clone, Copy)]
+enum JobPhase {
+    PreApply,
+    PostApply,
+}
+
+/// The files the components of `rush dev` are loaded from, besides their own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum StackFile {
+    /// stack.spec.yaml and the files it is rendered with
+    Spec,
+    /// The public environment definitions the .env files are generated from
+    Environment,
+}
+
+const SPEC_FILES: [&str; 5] = [
+    "stack.spec.yaml",
+    "stack.spec.local.yaml",
+    "variables.yaml",
+    "stack.volumes.yaml",
+    "stack.clusters.yaml",
+];
+
+/// Which stack file `path` is, if it is one of those in the product or the shared
+/// directory, given in `directories`.
+fn stack_file(
+    path: &std::path::Path,
+    directories: &[PathBuf],
+    environment: &str,
+) -> Option<StackFile> {
+    if !path
+        .parent()
+        .is_some_and(|parent| directories.iter().any(|directory| directory == parent))
+    {
+        return None;
+    }
+    let name = path.file_name()?.to_str()?;
+    if SPEC_FILES.contains(&name) {
+        Some(StackFile::Spec)
+    } else if name == "stack.env.base.yaml" || name == format!("stack.env.{}.yaml", environment) {
+        Some(StackFile::Environment)
+    } else {
+        None
+    }
+}
+
+/// Deletes the manifests and jobs rendered for `component`.
+fn remove_rendered(component: &K8ComponentManifests) {
+    for directory in [component.output_directory(), component.jobs_directory()] {
+        if directory.exists() {
+            if let Err(e) = std::fs::remove_dir_all(directory) {
+                warn!("Failed to delete {}: {}", directory.display(), e);
+            }
+        }
+    }
+}
+
+/// The directories and files below `start` that are in, or are, one of `roots`, with
+/// whether they are directories. The walk skips what git ignores and does not descend
+/// where no root is.
+fn walk_watched(start: &std::path::Path, roots: &[PathBuf]) -> Vec<(PathBuf, bool)> {
+    let within_roots = |path: &std::path::Path| roots.iter().any(|root| path.starts_with(root));
+    let walk_roots = roots.to_vec();
+    WalkBuilder::new(start)
+        .hidden(false)
+        .require_git(false)
+        .filter_entry(move |entry| {
+            entry.file_name() != ".git"
+                && walk_roots
+                    .iter()
+                    .any(|root| root.starts_with(entry.path()) || entry.path().starts_with(root))
+        })
+        .build()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| within_roots(entry.path()))
+        .map(|entry| {
+            let is_directory = entry
+                .file_type()
+                .is_some_and(|file_type| file_type.is_dir());
+            (entry.into_path(), is_directory)
+        })
+        .collect()
+}
+
+fn step_status<T, E>(result: &Result<T, E>) -> StepStatus {
+    match result {
+        Ok(_) => StepStatus::Ok,
+        Err(_) => StepStatus::Failed,
+    }
+}
+
+/// Fails on components an Ingress routes to that do not exist or have no service,
+/// which would otherwise be left out of its upstreams without a word. Components not
+/// enabled in the environment are left out on purpose.
+fn validate_ingress_routes(
+    images: &[DockerImage],
+    services: &HashMap<String, Vec<ServiceSpec>>,
+    disabled_components: &HashSet<String>,
+) -> Result<(), RushError> {
+    let names = images
+        .iter()
+        .map(|image| image.component_name())
+        .collect::<Vec<_>>();
+    let names = names.iter().map(String::as_str).collect::<Vec<_>>();
+    for image in images {
+        let BuildType::Ingress { components, .. } = image.spec().build_type else {
+            continue;
+        };
+        for component in &components {
+            if disabled_components.contains(component) {
+                continue;
+            }
+            if !names.contains(&component.as_str()) {
+                let message = match closest_match(component, &names) {
+                    Some(name) => format!(
+                        "routes to unknown component {}, did you mean {}?",
+                        component, name
+                    ),
+                    None => format!("routes to unknown component {}", component),
+                };
+                return Err(RushError::spec(&image.component_name(), message));
+            }
+            let has_service = services
+                .values()
+                .flatten()
+                .any(|service| &service.name == component);
+            if !has_service {
+                return Err(RushError::spec(
+                    &image.component_name(),
+                    format!(
+                        "routes to {}, which exposes no port. Set its port and target_port",
+                        component
+                    ),
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Digest of a pushed `image_name:tag`, failing when the tag has not been pushed.
+async fn image_digest(
+    registry: &RegistryClient,
+    tagged_image_name: &str,
+) -> Result<String, String> {
+    let (image_name, tag) = tagged_image_name
+        .rsplit_once(':')
+        .ok_or_else(|| format!("{} has no tag to pin", tagged_image_name))?;
+    match registry.manifest(image_name, tag).await? {
+        Some(manifest) => Ok(manifest.digest),
+        None => Err(format!(
+            "{}/{} has not been pushed, so its digest cannot be pinned",
+            registry.host(),
+            tagged_image_name
+        )),
+    }
 }
 
 enum BreakType {
@@ -58,8 +294,11 @@ enum BreakType {
     FileChanged,
 }
 
+/// How long `rush dev` waits for the target of a `--redirect` to accept a connection.
+const REDIRECT_CONNECT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(2);
+
 impl ContainerReactor {
-    async fn delete_network(&self) -> Result<(), String> {
+    pub async fn delete_network(&self) -> Result<(), String> {
         let toolchain = match &self.toolchain {
             Some(toolchain) => toolchain,
             None => return Err("Toolchain not found".to_string()),
@@ -150,6 +389,277 @@ impl ContainerReactor {
         &self.cluster_manifests
     }
 
+    /// What the reactor has built, pushed and applied so far.
+    pub fn summary(&self) -> &ExecutionSummary {
+        &self.summary
+    }
+
+    /// Sends the reactor's lifecycle events to `events`, see `--events`.
+    pub fn set_events(&mut self, events: EventSink) {
+        self.events = events;
+    }
+
+    /// Records how long the rebuilds of the dev loop take, see `rush profile build`.
+    pub fn set_profiler(&mut self, profiler: BuildProfiler) {
+        for image in &mut self.images {
+            image.set_profiler(profiler.clone());
+        }
+        self.profiler = Some(profiler);
+    }
+
+    fn profile(&self, step: ProfileStep, started: std::time::Instant) {
+        if let Some(profiler) = &self.profiler {
+            profiler.record(None, step, started.elapsed());
+        }
+    }
+
+    /// Prints the CPU and memory of the dev containers every `interval`, see
+    /// `rush dev --stats`.
+    pub fn set_resource_usage_interval(&mut self, interval: std::time::Duration) {
+        self.resource_usage_interval = Some(interval);
+    }
+
+    /// Starts the components whose inputs are unchanged since the previous `rush dev`
+    /// from their images instead of building them, unless `rebuild`.
+    pub fn enable_warm_start(&mut self, rebuild: bool) {
+        let mut warm_start = WarmStart::load(std::path::Path::new(&self.product_directory));
+        if rebuild {
+            warm_start.forget();
+        }
+        self.warm_start = Some(warm_start);
+    }
+
+    /// Routes the product's domains and mount points to the components with the built-in
+    /// proxy on `port`, and on `tls` with a self-signed certificate, instead of running
+    /// the ingress components, see `rush dev --proxy`.
+    pub fn set_proxy(&mut self, port: u16, tls: Option<(u16, PathBuf)>) {
+        for image in &mut self.images {
+            if matches!(image.spec().build_type, BuildType::Ingress { .. }) {
+                image.set_ignore_in_devmode(true);
+            }
+        }
+        self.proxy_port = Some(port);
+        self.proxy_tls = tls;
+    }
+
+    /// Renders the components' `volumes` as `hostPath` volumes of the local cluster
+    /// the product directory is mounted into, see `rush minikube dev`.
+    pub fn set_host_mount(&mut self, host_mount: HostMount) {
+        self.host_mount = Some(host_mount);
+    }
+
+    /// Marks the rendered manifests as holding placeholders rather than secrets and
+    /// refuses to deploy them, other than to the local cluster of `rush minikube dev`,
+    /// see `--fake-secrets`.
+    pub fn set_fake_secrets(&mut self) {
+        self.fake_secrets = true;
+    }
+
+    /// Fails on manifests with placeholders for secrets: those this run renders with
+    /// `--fake-secrets`, and those an earlier run left in the output directory.
+    fn check_deployable(&self) -> Result<(), String> {
+        if self.host_mount.is_some() {
+            return Ok(());
+        }
+        if self.fake_secrets {
+            return Err(
+                "The manifests hold placeholders rather than secrets and cannot be deployed. Run without --fake-secrets"
+                    .to_string(),
+            );
+        }
+        let _guard = Directory::chdir(&self.product_directory);
+        if let Some(directory) = find_fake_secrets(self.cluster_manifests.output_directory()) {
+            return Err(format!(
+                "{} was rendered with --fake-secrets and holds placeholders rather than secrets. Render the manifests again without it",
+                directory.display()
+            ));
+        }
+        Ok(())
+    }
+
+    /// Looks for secrets in plain text in the rendered manifests when K8S_ENCODER is
+    /// `noop`, failing or warning as SECRET_LEAKS says.
+    fn check_secret_leaks(&self, directory: &std::path::Path) -> Result<(), String> {
+        if self.config.k8s_encoder() != "noop"
+            || self.fake_secrets
+            || self.config.secret_leaks() == "ignore"
+        {
+            return Ok(());
+        }
+        let leaks = find_secret_leaks(directory, &self.rendered_secrets);
+        if leaks.is_empty() {
+            return Ok(());
+        }
+        let leaks = leaks
+            .iter()
+            .map(|leak| format!("  {}", leak))
+            .collect::<Vec<_>>()
+            .join("\n");
+        if self.config.secret_leaks() == "warn" {
+            eprintln!(
+                "{} The manifests of {} are not encrypted and hold secrets in plain text:\n{}",
+                "Warning:".yellow().bold(),
+                self.config.environment(),
+                leaks
+            );
+            return Ok(());
+        }
+        Err(format!(
+            "The manifests of {} are not encrypted and hold secrets in plain text:\n{}\nEncrypt them with K8S_ENCODER_{}: kubeseal, or set SECRET_LEAKS: warn to deploy them anyway",
+            self.config.environment(),
+            leaks,
+            self.config.environment().to_uppercase().replace('-', "_")
+        ))
+    }
+
+    /// Loads the built images into a local cluster, so the manifests run them without
+    /// a push.
+    pub async fn load_into(&mut self, cluster: &dyn LocalCluster) -> Result<(), String> {
+        let components = self.images.iter().map(|image| image.component_name());
+        self.load_components_into(cluster, &components.collect())
+            .await
+    }
+
+    async fn load_components_into(
+        &self,
+        cluster: &dyn LocalCluster,
+        components: &HashSet<String>,
+    ) -> Result<(), String> {
+        let images = self
+            .images
+            .iter()
+            .filter(|image| components.contains(&image.component_name()))
+            .collect::<Vec<_>>();
+        let progress = Progress::new("Loading", images.len());
+        for image in images {
+            progress.start(&image.identifier());
+            match image.load_into(cluster).await {
+                Ok(_) => progress.finish(&image.identifier(), "OK".white().bold()),
+                Err(e) => {
+                    progress.fail(&image.identifier());
+                    return Err(e);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Runs the product in `cluster` until Ctrl-C, see `rush minikube dev`: builds the
+    /// images, loads them into the cluster and applies the manifests. Once files change,
+    /// it does so again for the affected components and restarts their workloads, as
+    /// their images keep their tag.
+    pub async fn run_in_cluster(&mut self, cluster: &dyn LocalCluster) -> Result<(), String> {
+        let test_if_files_changed = self.setup_file_watcher()?;
+        // Components built since the last successful deploy
+        let mut pending = HashSet::new();
+        let mut deployed = false;
+        loop {
+            pending.extend(
+                self.images
+                    .iter()
+                    .filter(|image| !image.should_ignore_in_devmode())
+                    .filter(|image| {
+                        image.should_rebuild() || self.env_reloads.contains(&image.component_name())
+                    })
+                    .map(|image| image.component_name()),
+            );
+            match self.deploy_to_cluster(cluster, &pending, deployed).await {
+                Ok(()) => {
+                    pending.clear();
+                    deployed = true;
+                    println!(
+                        "{}",
+                        format!(
+                            "Running in {}, watching for changes, stop with Ctrl-C",
+                            cluster.kind()
+                        )
+                        .bold()
+                    );
+                }
+                Err(e) => {
+                    eprintln!("{}", e);
+                    println!(
+                        "{}",
+                        "Waiting for changes to try again, stop with Ctrl-C".yellow()
+                    );
+                }
+            }
+
+            let ctrl_c = tokio::signal::ctrl_c();
+            tokio::pin!(ctrl_c);
+            loop {
+                tokio::select! {
+                    _ = &mut ctrl_c => return Ok(()),
+                    _ = tokio::time::sleep(std::time::Duration::from_millis(500)) => (),
+                }
+                if !test_if_files_changed() {
+                    continue;
+                }
+                let significant = self.test_if_siginificant_change().await;
+                // Taking a component out leaves nothing to build, so it is deleted here
+                self.delete_removed_manifests().await;
+                if significant {
+                    break;
+                }
+            }
+        }
+    }
+
+    async fn deploy_to_cluster(
+        &mut self,
+        cluster: &dyn LocalCluster,
+        components: &HashSet<String>,
+        restart: bool,
+    ) -> Result<(), String> {
+        // The build reports its errors itself
+        self.build()
+            .await
+            .map_err(|_| "The build failed".to_string())?;
+        self.load_components_into(cluster, components).await?;
+        self.apply().await?;
+        if !restart {
+            return Ok(());
+        }
+
+        let toolchain = self.toolchain.clone().ok_or("Toolchain not found")?;
+        let _guard = Directory::chdir(&self.product_directory);
+        for component in self.cluster_manifests.components() {
+            if component.is_installation()
+                || !components.contains(component.name())
+                || !component.output_directory().exists()
+            {
+                continue;
+            }
+            restart_workloads(
+                toolchain.kubectl(),
+                &self.kube_context,
+                component.output_directory(),
+                component.namespace(),
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// Serves the dev status page on `port` of `address`, see `rush dev --status-port`.
+    pub async fn serve_status_page(
+        &mut self,
+        address: std::net::IpAddr,
+        port: u16,
+    ) -> Result<(), String> {
+        let status_board = StatusBoard::default();
+        for image in &mut self.images {
+            if image.should_ignore_in_devmode() {
+                continue;
+            }
+            status_board.add_component(&image.component_name(), image.port());
+            image.set_status_board(status_board.clone());
+        }
+        status_board.serve(address, port).await?;
+        self.status_board = Some(status_board);
+        Ok(())
+    }
+
     pub fn get_image(&self, component_name: &str) -> Option<&DockerImage> {
         self.images
             .iter()
@@ -164,14 +674,15 @@ impl ContainerReactor {
         k8s_encoder: Arc<dyn K8Encoder>,
         redirected_components: HashMap<String, (String, u16)>,
         silence_components: Vec<String>,
-    ) -> Result<Self, String> {
+    ) -> Result<Self, RushError> {
         let git_hash = match toolchain.get_git_folder_hash(config.product_path()) {
             Ok(hash) => hash,
             Err(e) => {
-                return Err(e);
+                return Err(e.into());
             }
         };
 
+        let silenced_components = silence_components.clone();
         let silence_components = silence_components.iter().collect::<HashSet<_>>();
 
         let binding = config.clone();
@@ -181,26 +692,44 @@ impl ContainerReactor {
 
         // TODO: Move to config
         if git_hash.is_empty() {
-            return Err("No git hash found for {}".to_string());
+            return Err(RushError::Other(format!(
+                "No git hash found for {}",
+                product_path
+            )));
         }
 
-        let tag = git_hash[..8].to_string();
-        let tag = match toolchain.get_git_wip(product_path) {
-            Ok(wip) => format!("{}{}", tag, wip),
-            Err(_e) => tag,
+        let tag = config
+            .tag_strategy()
+            .tag(&toolchain, product_path, &git_hash)?;
+        // Keeps arm64 images of a commit from overwriting the amd64 ones in the registry
+        let tag = match toolchain.target().arch.to_docker_target().as_str() {
+            "amd64" => tag,
+            arch => format!("{}-{}", tag, arch),
         };
 
         let _guard = Directory::chdir(product_path);
 
-        let variables = Variables::new("variables.yaml", config.environment());
+        let variables = Variables::new(
+            &[
+                shared_file(std::path::Path::new(product_path), "variables.yaml"),
+                std::path::PathBuf::from("variables.yaml"),
+            ],
+            config.environment(),
+            config.variable_overrides(),
+        );
+        // Values that differ between the clusters of an environment, keyed by kube context
+        let cluster_values: HashMap<String, HashMap<String, String>> =
+            match std::fs::read_to_string("stack.clusters.yaml") {
+                Ok(contents) => serde_yaml::from_str(&contents)
+                    .map_err(|e| format!("Could not parse stack.clusters.yaml: {}", e))?,
+                Err(_) => HashMap::new(),
+            };
+        let kube_context = config.kube_context().to_string();
+        let volumes = ProductVolumes::load(std::path::Path::new("."), config.product_uri())?;
 
-        let stack_config = match std::fs::read_to_string("stack.spec.yaml") {
-            Ok(config) => config,
-            Err(e) => return Err(format!("Failed to read stack config: {}", e)),
-        };
+        let stack_config_value = load_stack_spec(std::path::Path::new(product_path))?;
 
         let mut next_port = config.start_port();
-        let stack_config_value: serde_yaml::Value = serde_yaml::from_str(&stack_config).unwrap();
         let mut images = Vec::new();
 
         let mut cluster_manifests = {
@@ -208,11 +737,12 @@ impl ContainerReactor {
             K8ClusterManifests::new(
                 product_directory.join("k8s"),
                 Some(toolchain.clone()),
-                k8s_encoder,
+                k8s_encoder.clone(),
             )
         };
 
         let mut all_component_specs = Vec::new();
+        let mut disabled_components = HashSet::new();
 
         if let serde_yaml::Value::Mapping(config_map) = stack_config_value {
             for (component_name, yaml_section) in config_map {
@@ -233,7 +763,17 @@ impl ContainerReactor {
                     config.clone(),
                     variables.clone(),
                     &yaml_section_clone,
-                )));
+                )?));
+                if !component_spec.lock().unwrap().is_enabled() {
+                    let spec = component_spec.lock().unwrap();
+                    debug!(
+                        "Leaving out {}, which is not enabled in {}",
+                        spec.component_name,
+                        config.environment()
+                    );
+                    disabled_components.insert(spec.component_name.clone());
+                    continue;
+                }
 
                 let build_type = {
                     let (k8s, priority, build_type) = {
@@ -246,9 +786,9 @@ impl ContainerReactor {
                             let component_name: String = match component_name.as_str() {
                                 Some(name) => name.to_string(),
                                 None => {
-                                    return Err(
-                                        "Could not convert component name to string".to_string()
-                                    )
+                                    return Err(RushError::Other(
+                                        "Could not convert component name to string".to_string(),
+                                    ))
                                 }
                             };
                             let component_name = format!("{}_{}", priority, component_name);
@@ -265,6 +805,21 @@ impl ContainerReactor {
                 };
 
                 let mut image: DockerImage = component_spec.clone().try_into()?;
+                let mut named_volumes = Vec::new();
+                for (name, container_path) in image.spec().named_volumes {
+                    let volume = volumes.docker_name(&name).ok_or_else(|| {
+                        RushError::spec(
+                            &image.component_name(),
+                            format!(
+                                "volume {} is not declared in stack.volumes.yaml. To mount the directory instead, write ./{}",
+                                name, name
+                            ),
+                        )
+                    })?;
+                    named_volumes.push((volume, container_path));
+                }
+                named_volumes.sort();
+                image.set_named_volumes(named_volumes);
                 match build_type {
                     BuildType::PureDockerImage { .. } => (),
                     _ => {
@@ -325,6 +880,18 @@ impl ContainerReactor {
                 }
             }
         }
+        validate_ingress_routes(&images, &services, &disabled_components)?;
+        // The launch order would otherwise be cut at an arbitrary point of the cycle
+        let dependencies = images
+            .iter()
+            .map(|image| (image.component_name(), image.spec().depends_on))
+            .collect::<BTreeMap<_, _>>();
+        if let Some(cycle) = find_cycle(&dependencies) {
+            return Err(RushError::spec(
+                &cycle[0],
+                format!("depends_on forms a cycle: {}", cycle.join(" -> ")),
+            ));
+        }
         log::trace!("Generating domain list");
         let mut component_to_domain = HashMap::new();
         for component_spec in &mut all_component_specs {
@@ -349,6 +916,11 @@ impl ContainerReactor {
         let (terminate_sender, terminate_receiver) = broadcast::channel(16);
 
         let infrastructure_repo = InfrastructureRepo::new(config.clone(), toolchain.clone());
+        let history = DeploymentHistory::new(
+            std::path::Path::new(product_path)
+                .join("target")
+                .join("history.jsonl"),
+        );
 
         Ok(ContainerReactor {
             config,
@@ -367,67 +939,212 @@ impl ContainerReactor {
             cluster_manifests,
             infrastructure_repo,
             vault,
+            git_hash,
+            history,
+            kube_context,
+            cluster_values,
             changed_files: Arc::new(Mutex::new(Vec::new())),
+            created_directories: Arc::new(Mutex::new(Vec::new())),
+            manifest_fingerprints: HashMap::new(),
+            removed_manifests: Vec::new(),
+            rendered_secrets: BTreeMap::new(),
+            events: EventSink::default(),
+            summary: ExecutionSummary::default(),
+            status_board: None,
+            profiler: None,
+            resource_usage_interval: None,
+            host_mount: None,
+            fake_secrets: false,
+            proxy_port: None,
+            proxy_tls: None,
+            env_reloads: HashSet::new(),
+            volumes,
+            warm_start: None,
+            warm_started: HashSet::new(),
+            redirects: redirected_components.into_iter().collect(),
+            k8s_encoder,
+            silenced_components,
+            watcher: None,
+            watching: HashSet::new(),
         })
         //        Ok(Self::new(&product_name, &product_path, images, toolchain))
     }
 
+    /// Builds the images one after the other and pushes each as soon as it is built,
+    /// at most PUSH_CONCURRENCY at a time. A failed push does not stop the others, which
+    /// are all reported together.
+    #[tracing::instrument(skip_all, fields(product = %self.config.product_name(), environment = %self.config.environment()))]
     pub async fn build_and_push(&mut self) -> Result<(), String> {
         let _guard = Directory::chdir(&self.product_directory);
 
+        let progress = Progress::new("Build & push", self.images.len());
+        let product_name = self.config.product_name();
+        let pushes_allowed = Arc::new(tokio::sync::Semaphore::new(self.config.push_concurrency()));
+        // Without it, every image is pushed with docker push as it is
+        let registry = RegistryClient::new(self.config.docker_registry())
+            .ok()
+            .map(Arc::new);
+        let mut pushes = Vec::new();
+        let mut build_error = None;
         for image in &mut self.images {
-            print!("Build & push {}  ..... ", image.identifier());
-            std::io::stdout().flush().expect("Failed to flush stdout");
-            match image.build_and_push().await {
-                Ok(_) => println!(
-                    "Build & push {}  ..... [  {}  ]",
-                    image.identifier(),
-                    "OK".white().bold()
-                ),
+            progress.start(&image.identifier());
+            let component_name = image.component_name();
+            let identifier = image.identifier();
+            self.events.emit(
+                product_name,
+                Event::BuildStarted {
+                    component: &component_name,
+                    image: &identifier,
+                    push: true,
+                },
+            );
+            let started = std::time::Instant::now();
+            if let Err(e) = image.build().await {
+                self.events.emit(
+                    product_name,
+                    Event::BuildFinished {
+                        component: &component_name,
+                        image: &identifier,
+                        push: true,
+                        success: false,
+                        duration_ms: started.elapsed().as_millis(),
+                    },
+                );
+                self.summary
+                    .record("push", &identifier, StepStatus::Failed, started.elapsed());
+                progress.fail(&identifier);
+                build_error = Some(e);
+                break;
+            }
+
+            // run_command blocks the thread it runs on while the runtime reads the output,
+            // so pushes get threads of their own rather than the runtime's workers
+            let image = image.clone();
+            let pushes_allowed = pushes_allowed.clone();
+            let registry = registry.clone();
+            let runtime = tokio::runtime::Handle::current();
+            let push = tokio::task::spawn_blocking(move || {
+                runtime.block_on(async move {
+                    let _permit = pushes_allowed.acquire_owned().await;
+                    image.push(registry.as_deref()).await
+                })
+            });
+            pushes.push((component_name, identifier, started, push));
+        }
+
+        let mut failures = Vec::new();
+        for (component_name, identifier, started, push) in pushes {
+            let result = match push.await {
+                Ok(result) => result,
+                Err(e) => Err(format!("Push of {} panicked: {}", identifier, e)),
+            };
+            self.events.emit(
+                product_name,
+                Event::BuildFinished {
+                    component: &component_name,
+                    image: &identifier,
+                    push: true,
+                    success: result.is_ok(),
+                    duration_ms: started.elapsed().as_millis(),
+                },
+            );
+            self.summary
+                .record("push", &identifier, step_status(&result), started.elapsed());
+            match result {
+                Ok(Pushed::Uploaded) => progress.finish(&identifier, "OK".white().bold()),
+                Ok(Pushed::Unchanged) => progress.skip(&identifier, "UNCHANGED".yellow().bold()),
+                Ok(Pushed::Skipped) => progress.skip(&identifier, "SKIPPED".yellow().bold()),
                 Err(e) => {
-                    println!(
-                        "Build & push {}  ..... [ {} ]",
-                        image.identifier(),
-                        "FAIL".red().bold()
-                    );
-                    println!();
-                    println!("{}", e);
-                    println!();
-                    println!("{}", "Build was unsuccessful".red().bold());
-                    return Err(e);
+                    progress.fail(&identifier);
+                    failures.push((identifier, e));
                 }
             }
         }
+        drop(progress);
+
+        if let Some(e) = build_error {
+            println!();
+            println!("{}", e);
+            println!();
+            println!("{}", "Build was unsuccessful".red().bold());
+            return Err(e);
+        }
+        if !failures.is_empty() {
+            println!();
+            for (identifier, e) in &failures {
+                println!("{}", identifier.bold());
+                println!("{}", e);
+            }
+            println!();
+            println!("{}", "Push was unsuccessful".red().bold());
+            return Err(format!(
+                "Failed to push {}",
+                failures
+                    .iter()
+                    .map(|(identifier, _)| identifier.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
         Ok(())
     }
 
-    pub async fn select_kubernetes_context(&self, context: &str) -> Result<(), String> {
+    /// Makes `context` the one every kubectl command of the reactor passes with
+    /// `--context`, leaving the current context of the kubeconfig alone.
+    pub fn set_kube_context(&mut self, context: &str) {
+        self.kube_context = context.to_string();
+    }
+
+    /// Fails unless the kubeconfig has `context`, as kubectl would otherwise only fail
+    /// halfway through a deploy.
+    pub async fn verify_kubernetes_context(&self, context: &str) -> Result<(), String> {
         let toolchain = match &self.toolchain {
             Some(toolchain) => toolchain,
             None => return Err("Toolchain not found".to_string()),
         };
 
-        let kubectx = toolchain.kubectx();
-
-        match run_command(
-            "Selecting Kubernetes context".white().bold(),
-            kubectx,
-            vec![context],
+        let contexts = run_command(
+            "Verifying Kubernetes context".white().bold(),
+            toolchain.kubectl(),
+            vec!["config", "get-contexts", "-o", "name"],
         )
-        .await
-        {
-            Ok(_) => {
-                println!("Kubernetes context set to: {}", context);
-                Ok(())
-            }
-            Err(e) => {
-                eprintln!("Failed to set Kubernetes context: {}", e);
-                Err(e.to_string())
-            }
+        .await?;
+        if !contexts.lines().any(|line| line.trim() == context) {
+            return Err(format!(
+                "Kubernetes context {} is not in the kubeconfig",
+                context
+            ));
         }
+
+        Ok(())
+    }
+
+    /// Makes the given cluster the target of the following commands.
+    async fn switch_cluster(&mut self, context: &str) -> Result<(), String> {
+        self.verify_kubernetes_context(context).await?;
+        self.set_kube_context(context);
+        Ok(())
     }
 
+    /// Applies the rendered manifests. Environments with several clusters get their
+    /// manifests rendered and applied once per cluster.
+    #[tracing::instrument(skip_all, fields(product = %self.config.product_name(), environment = %self.config.environment()))]
     pub async fn apply(&mut self) -> Result<(), String> {
+        self.check_deployable()?;
+        let contexts = self.config.kube_contexts().to_vec();
+        if contexts.len() == 1 {
+            return self.apply_output().await;
+        }
+
+        for context in &contexts {
+            self.switch_cluster(context).await?;
+            self.build_manifests().await?;
+            self.apply_output().await?;
+        }
+        Ok(())
+    }
+
+    async fn apply_output(&mut self) -> Result<(), String> {
         let toolchain = match self.toolchain.clone() {
             Some(toolchain) => toolchain,
             None => return Err("Toolchain not found".to_string()),
@@ -447,24 +1164,111 @@ impl ContainerReactor {
             &output_dir
         };
 
-        match run_command(
+        self.check_deployable()?;
+        self.check_secret_leaks(self.cluster_manifests.output_directory())?;
+        let components = self
+            .cluster_manifests
+            .components()
+            .iter()
+            .filter(|component| !component.is_installation())
+            .map(|component| component.name().to_string())
+            .collect::<Vec<_>>();
+        for component in &components {
+            self.run_jobs(component, JobPhase::PreApply).await?;
+        }
+
+        let started = std::time::Instant::now();
+        let result = run_command(
             "apply".white().bold(),
             kubectl,
-            vec!["apply", "-R", "-f", &output_dir],
+            vec![
+                "--context",
+                &self.kube_context,
+                "apply",
+                "-R",
+                "-f",
+                output_dir,
+            ],
         )
-        .await
-        {
-            Ok(_) => (),
-            Err(e) => {
-                eprintln!("Failed to apply manifests: {}", e);
-                return Err(e.to_string());
-            }
+        .await;
+        self.summary.record(
+            "apply",
+            &self.kube_context,
+            step_status(&result),
+            started.elapsed(),
+        );
+        if let Err(e) = result {
+            eprintln!("Failed to apply manifests: {}", e);
+            return Err(e.to_string());
+        }
+        self.events.emit(
+            self.config.product_name(),
+            Event::DeployApplied {
+                context: &self.kube_context,
+                component: None,
+            },
+        );
+
+        for component in &components {
+            self.run_jobs(component, JobPhase::PostApply).await?;
         }
+        Ok(())
+    }
 
+    /// Runs the Jobs a component declares for `phase`, failing on the first that fails.
+    async fn run_jobs(&mut self, component_name: &str, phase: JobPhase) -> Result<(), String> {
+        let toolchain = match self.toolchain.clone() {
+            Some(toolchain) => toolchain,
+            None => return Err("Toolchain not found".to_string()),
+        };
+        let Some(component) = self
+            .cluster_manifests
+            .components()
+            .iter()
+            .find(|component| component.name() == component_name)
+        else {
+            return Ok(());
+        };
+        let spec = component.spec();
+        let (names, jobs) = match phase {
+            JobPhase::PreApply => (&spec.jobs.pre_apply, component.pre_apply_jobs()),
+            JobPhase::PostApply => (&spec.jobs.post_apply, component.post_apply_jobs()),
+        };
+        let jobs = names
+            .iter()
+            .cloned()
+            .zip(jobs.iter().map(|job| job.artefact.output_path.clone()))
+            .collect::<Vec<_>>();
+        let timeout = std::time::Duration::from_secs(spec.jobs.timeout_seconds);
+
+        for (name, path) in jobs {
+            println!("Running {} of {}", name, component_name);
+            let started = std::time::Instant::now();
+            let result = run_jobs(toolchain.kubectl(), &self.kube_context, &path, timeout).await;
+            self.summary
+                .record("job", &name, step_status(&result), started.elapsed());
+            result.map_err(|e| format!("{} of {} failed: {}", name, component_name, e))?;
+        }
         Ok(())
     }
 
+    #[tracing::instrument(skip_all, fields(product = %self.config.product_name(), environment = %self.config.environment()))]
     pub async fn unapply(&mut self) -> Result<(), String> {
+        let contexts = self.config.kube_contexts().to_vec();
+        if contexts.len() == 1 {
+            return self.unapply_output().await;
+        }
+
+        for context in &contexts {
+            self.switch_cluster(context).await?;
+            // Deleting only needs the names of the resources
+            self.render_manifests(false).await?;
+            self.unapply_output().await?;
+        }
+        Ok(())
+    }
+
+    async fn unapply_output(&mut self) -> Result<(), String> {
         let toolchain = match self.toolchain.clone() {
             Some(toolchain) => toolchain,
             None => return Err("Toolchain not found".to_string()),
@@ -503,7 +1307,7 @@ impl ContainerReactor {
             match run_command(
                 "delete".white().bold(),
                 kubectl,
-                vec!["delete", "-f", &**arg],
+                vec!["--context", &self.kube_context, "delete", "-f", &**arg],
             )
             .await
             {
@@ -519,58 +1323,348 @@ impl ContainerReactor {
         Ok(())
     }
 
+    #[tracing::instrument(skip_all, fields(product = %self.config.product_name(), environment = %self.config.environment()))]
     pub async fn rollout(&mut self) -> Result<(), String> {
+        self.check_deployable()?;
         self.build_and_push().await?;
-        self.build_manifests().await?;
-
-        let _guard = Directory::chdir(&self.product_directory);
-        self.infrastructure_repo.checkout().await?;
-
-        let source_directory = self.cluster_manifests.output_directory();
-        self.infrastructure_repo
-            .copy_manifests(source_directory)
-            .await?;
-
-        self.infrastructure_repo
-            .commit_and_push(&format!(
+        self.provision_infrastructure().await?;
+        let manifests = self
+            .publish_to_clusters(&format!(
                 "Deploying {} for {}",
                 self.config.environment(),
                 self.config.product_name()
             ))
             .await?;
 
-        Ok(())
+        self.record_rollout(None, manifests)
     }
 
-    pub async fn deploy(&mut self) -> Result<(), String> {
-        self.build_and_push().await?;
-        self.build_manifests().await?;
-        self.apply().await?;
-
-        Ok(())
-    }
+    /// Applies the product's Terraform or OpenTofu configuration, if TERRAFORM_DIRECTORY
+    /// is set. The secrets of the `terraform` component in the vault are its variables.
+    #[tracing::instrument(skip_all, fields(product = %self.config.product_name(), environment = %self.config.environment()))]
+    pub async fn provision_infrastructure(&mut self) -> Result<(), String> {
+        let Some(directory) = self.config.terraform_directory() else {
+            return Ok(());
+        };
+        let started = std::time::Instant::now();
+        let variables = get_secrets(
+            &self.vault,
+            self.config.product_name(),
+            "terraform",
+            self.config.environment(),
+        )
+        .await
+        .unwrap_or_default();
 
-    pub async fn install_manifests(&mut self) -> Result<(), String> {
-        let toolchain = match self.toolchain.clone() {
-            Some(toolchain) => toolchain,
-            None => return Err("Toolchain not found".to_string()),
+        let result = Terraform::new(
+            self.config.terraform_binary(),
+            directory,
+            self.config.environment(),
+        )
+        .plan_and_apply(&variables)
+        .await;
+        let status = if result.is_ok() {
+            StepStatus::Ok
+        } else {
+            StepStatus::Failed
         };
-        let _guard = Directory::chdir(&self.product_directory);
+        self.summary
+            .record("terraform", "infrastructure", status, started.elapsed());
+        result
+    }
 
-        let kubectl = toolchain.kubectl();
-        for component in self.cluster_manifests.components() {
-            if !component.is_installation() {
+    /// Re-deploys the exact image tags of a previous rollout into the current
+    /// environment. The images are expected to already exist in the registry.
+    #[tracing::instrument(skip_all, fields(product = %self.config.product_name(), environment = %self.config.environment()))]
+    pub async fn promote(&mut self, record: &RolloutRecord) -> Result<(), String> {
+        self.check_deployable()?;
+        for image in &mut self.images {
+            if matches!(image.spec().build_type, BuildType::PureDockerImage { .. }) {
                 continue;
             }
-
-            let name = component.name();
-            let namespace = component.namespace();
+            let component_name = image.component_name();
+            match record.tag_for(&component_name) {
+                Some(tag) => {
+                    image.set_tag(tag.to_string());
+                    image
+                        .spec_handle()
+                        .lock()
+                        .unwrap()
+                        .set_tagged_image_name(image.tagged_image_name());
+                }
+                None => warn!(
+                    "No image recorded for {} in {}, keeping current tag",
+                    component_name, record.environment
+                ),
+            }
+        }
+
+        // Rendered again, as the namespaces, domains and sealed secrets of the manifests
+        // kept from the source rollout are those of its environment
+        let manifests = self
+            .publish_to_clusters(&format!(
+                "Promoting {} from {} to {}",
+                self.config.product_name(),
+                record.environment,
+                self.config.environment()
+            ))
+            .await?;
+
+        self.git_hash = record.git_hash.clone();
+        self.record_rollout(Some(record.environment.clone()), manifests)
+    }
+
+    pub fn history(&self) -> &DeploymentHistory {
+        &self.history
+    }
+
+    /// Tagged image name per component, leaving out images rush does not build.
+    fn deployed_images(&self) -> HashMap<String, String> {
+        self.images
+            .iter()
+            .filter(|image| !matches!(image.spec().build_type, BuildType::PureDockerImage { .. }))
+            .map(|image| (image.component_name(), image.tagged_image_name()))
+            .collect()
+    }
+
+    /// Notifies the DEPLOY_WEBHOOKS of the outcome of a deploy or rollout. Components
+    /// count as changed when their image differs from the `previous` rollout.
+    pub async fn notify_deploy(
+        &self,
+        action: &str,
+        previous: Option<&RolloutRecord>,
+        result: &Result<(), String>,
+    ) {
+        let webhooks = self.config.deploy_webhooks();
+        if webhooks.is_empty() {
+            return;
+        }
+        let images = self
+            .deployed_images()
+            .into_iter()
+            .collect::<BTreeMap<_, _>>();
+        let changed_components = images
+            .iter()
+            .filter(|(component, image)| {
+                previous.is_none_or(|previous| previous.images.get(*component) != Some(image))
+            })
+            .map(|(component, _)| component.clone())
+            .collect();
+        let notification = DeployNotification {
+            action: action.to_string(),
+            product: self.config.product_name().to_string(),
+            environment: self.config.environment().to_string(),
+            git_hash: self.git_hash.clone(),
+            success: result.is_ok(),
+            images,
+            changed_components,
+            error: result.as_ref().err().cloned(),
+        };
+        notification.send(webhooks).await;
+    }
+
+    fn record_rollout(
+        &self,
+        promoted_from: Option<String>,
+        manifests: PathBuf,
+    ) -> Result<(), String> {
+        let images = self.deployed_images();
+        let record = RolloutRecord::new(
+            self.config.product_name(),
+            self.config.environment(),
+            &self.git_hash,
+            images,
+            promoted_from,
+            Some(manifests),
+        );
+        self.history.record(&record)
+    }
+
+    /// Renders and publishes the manifests of every cluster of the environment, keeping
+    /// a copy of them per cluster in the returned directory.
+    async fn publish_to_clusters(&mut self, commit_message: &str) -> Result<PathBuf, String> {
+        let manifests = self.history.manifests_directory(self.config.environment());
+        let contexts = self.config.kube_contexts().to_vec();
+        for (index, context) in contexts.iter().enumerate() {
+            if contexts.len() > 1 {
+                self.kube_context = context.clone();
+                self.infrastructure_repo.set_cluster(Some(context));
+            }
+            self.build_manifests().await?;
+            let output_directory = {
+                let _guard = Directory::chdir(&self.product_directory);
+                self.cluster_manifests.output_directory().canonicalize()
+            }
+            .map_err(|e| format!("Failed to resolve the rendered manifests: {}", e))?;
+            // Numbered, so they sort in the order of the clusters
+            let cluster_directory = format!("{:02}-{}", index, context.replace('/', "_"));
+            copy_directory(&output_directory, &manifests.join(cluster_directory))?;
+            self.publish_manifests(&output_directory, commit_message)
+                .await?;
+        }
+        Ok(manifests)
+    }
+
+    async fn publish_manifests(
+        &self,
+        source_directory: &std::path::Path,
+        commit_message: &str,
+    ) -> Result<(), String> {
+        let _guard = Directory::chdir(&self.product_directory);
+        self.check_deployable()?;
+        if let Some(directory) = find_fake_secrets(source_directory) {
+            return Err(format!(
+                "{} was rendered with --fake-secrets and cannot be published",
+                directory.display()
+            ));
+        }
+        self.check_secret_leaks(source_directory)?;
+        self.infrastructure_repo.checkout().await?;
+
+        self.infrastructure_repo
+            .copy_manifests(&source_directory.to_path_buf())
+            .await?;
+
+        let helm_releases = self
+            .images
+            .iter()
+            .filter_map(|image| {
+                let spec = image.spec();
+                spec.helm
+                    .map(|chart| (format!("{}_{}", spec.priority, spec.component_name), chart))
+            })
+            .collect::<Vec<_>>();
+        self.infrastructure_repo
+            .write_flux_resources(&helm_releases)
+            .await?;
+
+        self.infrastructure_repo
+            .commit_and_push(commit_message)
+            .await?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all, fields(product = %self.config.product_name(), environment = %self.config.environment()))]
+    pub async fn deploy(&mut self) -> Result<(), String> {
+        self.check_deployable()?;
+        self.build_and_push().await?;
+        self.provision_infrastructure().await?;
+
+        let has_strategies = self.cluster_manifests.components().iter().any(|component| {
+            !component.is_installation()
+                && component.spec().deploy_strategy != DeployStrategy::Rolling
+        });
+        let contexts = self.config.kube_contexts().to_vec();
+        for context in &contexts {
+            if contexts.len() > 1 {
+                self.switch_cluster(context).await?;
+            }
+            self.build_manifests().await?;
+            if has_strategies {
+                self.apply_with_strategies().await?;
+            } else {
+                self.apply_output().await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Applies the components one by one, letting components with a canary or
+    /// blue/green strategy orchestrate their own switch-over.
+    async fn apply_with_strategies(&mut self) -> Result<(), String> {
+        let toolchain = match self.toolchain.clone() {
+            Some(toolchain) => toolchain,
+            None => return Err("Toolchain not found".to_string()),
+        };
+        let _guard = Directory::chdir(&self.product_directory);
+        self.check_deployable()?;
+        self.check_secret_leaks(self.cluster_manifests.output_directory())?;
+
+        let strategy_directory = PathBuf::from("./target/k8s-strategies");
+        let components = self
+            .cluster_manifests
+            .components()
+            .iter()
+            .filter(|component| !component.is_installation())
+            .map(|component| component.name().to_string())
+            .collect::<Vec<_>>();
+        for component_name in &components {
+            self.run_jobs(component_name, JobPhase::PreApply).await?;
+            let Some(component) = self
+                .cluster_manifests
+                .components()
+                .iter()
+                .find(|component| component.name() == component_name)
+            else {
+                continue;
+            };
+
+            let strategy = component.spec().deploy_strategy;
+            let started = std::time::Instant::now();
+            let result = if strategy == DeployStrategy::Rolling {
+                component.apply(&self.kube_context).await
+            } else {
+                println!("Deploying {} with {:?}", component.name(), strategy);
+                let deployer = StrategyDeployer::new(
+                    toolchain.kubectl(),
+                    &self.kube_context,
+                    strategy_directory.join(component.name()),
+                );
+                deployer
+                    .deploy(component.output_directory(), &strategy)
+                    .await
+            };
+            self.summary.record(
+                "apply",
+                component.name(),
+                step_status(&result),
+                started.elapsed(),
+            );
+            result?;
+            self.events.emit(
+                self.config.product_name(),
+                Event::DeployApplied {
+                    context: &self.kube_context,
+                    component: Some(component.name()),
+                },
+            );
+            self.run_jobs(component_name, JobPhase::PostApply).await?;
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all, fields(product = %self.config.product_name(), environment = %self.config.environment()))]
+    pub async fn install_manifests(&mut self) -> Result<(), String> {
+        self.check_deployable()?;
+        let toolchain = match self.toolchain.clone() {
+            Some(toolchain) => toolchain,
+            None => return Err("Toolchain not found".to_string()),
+        };
+        let _guard = Directory::chdir(&self.product_directory);
+
+        let kubectl = toolchain.kubectl();
+        for component in self.cluster_manifests.components() {
+            if !component.is_installation() {
+                continue;
+            }
+
+            let name = component.name();
+            let namespace = component.namespace();
             print!("Installing {} in {}  ..... ", name, namespace);
 
             match run_command(
                 "install".white().bold(),
                 kubectl,
-                vec!["create", "namespace", namespace],
+                vec![
+                    "--context",
+                    &self.kube_context,
+                    "create",
+                    "namespace",
+                    namespace,
+                ],
             )
             .await
             {
@@ -581,12 +1675,15 @@ impl ContainerReactor {
                     //return Err(e.to_string());
                 }
             }
+            wait_for_namespace(kubectl, &self.kube_context, namespace).await?;
 
             for manifest in component.manifests() {
                 match run_command(
                     "install".white().bold(),
                     kubectl,
                     vec![
+                        "--context",
+                        &self.kube_context,
                         "apply",
                         "-n",
                         namespace,
@@ -602,6 +1699,15 @@ impl ContainerReactor {
                         return Err(e.to_string());
                     }
                 }
+
+                // Later manifests may consume the CRDs and operators installed here
+                wait_for_manifest(
+                    kubectl,
+                    &self.kube_context,
+                    &manifest.artefact.input_path,
+                    namespace,
+                )
+                .await?;
             }
 
             println!(
@@ -615,6 +1721,7 @@ impl ContainerReactor {
         Ok(())
     }
 
+    #[tracing::instrument(skip_all, fields(product = %self.config.product_name(), environment = %self.config.environment()))]
     pub async fn uninstall_manifests(&mut self) -> Result<(), String> {
         let toolchain = match self.toolchain.clone() {
             Some(toolchain) => toolchain,
@@ -638,6 +1745,8 @@ impl ContainerReactor {
                     "uninstall".white().bold(),
                     kubectl,
                     vec![
+                        "--context",
+                        &self.kube_context,
                         "delete",
                         "-n",
                         namespace,
@@ -657,177 +1766,1044 @@ impl ContainerReactor {
             match run_command(
                 "uninstall".white().bold(),
                 kubectl,
-                vec!["delete", "namespace", namespace],
+                vec![
+                    "--context",
+                    &self.kube_context,
+                    "delete",
+                    "namespace",
+                    namespace,
+                ],
+            )
+            .await
+            {
+                Ok(_) => (),
+                Err(e) => {
+                    eprintln!("Failed to delete namespace: {}", e);
+                }
+            }
+
+            println!(
+                "\rUninstalling {} in {}  ..... [  {}  ]",
+                name,
+                namespace,
+                "OK".white().bold()
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Renders the manifests, referring to the images by digest when PIN_IMAGE_DIGESTS is set.
+    pub async fn build_manifests(&mut self) -> Result<(), String> {
+        self.render_manifests(self.config.pin_image_digests()).await
+    }
+
+    /// Renders the manifests with every built image tagged `tag` instead of the commit,
+    /// so they only change with the templates and specs, e.g. to compare with golden files.
+    pub async fn build_manifests_with_tag(&mut self, tag: &str) -> Result<(), String> {
+        for image in &mut self.images {
+            if matches!(image.spec().build_type, BuildType::PureDockerImage { .. }) {
+                continue;
+            }
+            image.set_tag(tag.to_string());
+            image
+                .spec_handle()
+                .lock()
+                .unwrap()
+                .set_tagged_image_name(image.tagged_image_name());
+        }
+        // Rendered from scratch, so nothing of an earlier render is kept
+        self.manifest_fingerprints.clear();
+        self.render_manifests(false).await
+    }
+
+    #[tracing::instrument(skip_all, fields(product = %self.config.product_name(), environment = %self.config.environment()))]
+    async fn render_manifests(&mut self, pin_image_digests: bool) -> Result<(), String> {
+        let _guard = Directory::chdir(&self.product_directory);
+        self.cluster_manifests.reload_templates()?;
+        let output_dir = self.cluster_manifests.output_directory();
+        // Until this process has rendered them, the files there may be from anything
+        if self.manifest_fingerprints.is_empty() && output_dir.exists() {
+            std::fs::remove_dir_all(output_dir).expect("Failed to delete output directory");
+        }
+        // Otherwise those of components taken out would be applied along with the rest
+        self.prune_rendered()?;
+
+        let components = self
+            .cluster_manifests
+            .components()
+            .iter()
+            .filter(|component| !component.is_installation())
+            .collect::<Vec<_>>();
+        // Created up front, so a registry that cannot be reached fails before rendering
+        let registry = if pin_image_digests {
+            Some(RegistryClient::new(self.config.docker_registry())?)
+        } else {
+            None
+        };
+        let checksum_key = secrets_checksum_key()?;
+
+        let progress = Progress::new("Creating K8s", components.len());
+        let mut prepared = Vec::new();
+        for component in components {
+            let render_dir = component.output_directory();
+            progress.start(&render_dir.display().to_string());
+            let started = std::time::Instant::now();
+            let current_dir = std::env::current_dir().unwrap();
+            let spec = component.spec();
+
+            let secrets = get_secrets(
+                &self.vault,
+                &spec.product_name,
+                &spec.component_name,
+                spec.config.environment(),
+            )
+            .await
+            .unwrap_or_default();
+            let checksum = secrets_checksum(checksum_key.as_deref(), &secrets)?;
+            let prefix = format!("{}/", component.name());
+            self.rendered_secrets
+                .retain(|name, _| !name.starts_with(&prefix));
+            for (key, value) in &secrets {
+                self.rendered_secrets
+                    .insert(format!("{}/{}", component.name(), key), value.clone());
+            }
+            // Encoding secrets
+            let secrets = self.secrets_encoder.encode_secrets(secrets);
+
+            let mut ctx = spec.generate_build_context(self.toolchain.clone(), secrets);
+            ctx.kube_context = self.kube_context.clone();
+            ctx.cluster = self
+                .cluster_values
+                .get(&self.kube_context)
+                .cloned()
+                .unwrap_or_default();
+            // Pulled images are not in our registry and keep their tag
+            if let Some(registry) = &registry {
+                if spec.build_type.dockerfile_path().is_some() {
+                    let digest = image_digest(registry, &ctx.image_name).await?;
+                    ctx.image_name = format!("{}@{}", ctx.image_name, digest);
+                    ctx.image_digest = Some(digest);
+                }
+            }
+
+            // Left as they are when nothing they are rendered from changed since last time
+            let fingerprint = component.fingerprint(&ctx);
+            if render_dir.exists()
+                && self.manifest_fingerprints.get(component.name()) == Some(&fingerprint)
+            {
+                progress.skip(
+                    &render_dir.display().to_string(),
+                    "UNCHANGED".yellow().bold(),
+                );
+                self.summary.skip("render", component.name());
+                continue;
+            }
+            self.manifest_fingerprints.remove(component.name());
+            if render_dir.exists() {
+                std::fs::remove_dir_all(render_dir).expect("Failed to delete render directory");
+            }
+            std::fs::create_dir_all(render_dir).expect("Failed to create render directory");
+            let jobs_dir = component.jobs_directory();
+            if jobs_dir.exists() {
+                std::fs::remove_dir_all(jobs_dir).expect("Failed to delete jobs directory");
+            }
+            for job in component
+                .pre_apply_jobs()
+                .iter()
+                .chain(component.post_apply_jobs())
+            {
+                if let Some(job_dir) = std::path::Path::new(&job.artefact.output_path).parent() {
+                    std::fs::create_dir_all(job_dir).expect("Failed to create jobs directory");
+                }
+            }
+            prepared.push((component, ctx, checksum, fingerprint, started.elapsed()));
+        }
+
+        // Secrets and digests are looked up one component at a time above, as the vault
+        // is shared, while the rendering and encoding run in parallel
+        let manifests = prepared
+            .iter()
+            .flat_map(|(component, ctx, _, _, _)| {
+                component.rendered().map(move |manifest| (manifest, ctx))
+            })
+            .collect::<Vec<_>>();
+        let mut results = render_in_parallel(&manifests).into_iter();
+        let mut failures = Vec::new();
+        for (component, _, checksum, fingerprint, prepared_in) in &prepared {
+            let render_dir = component.output_directory().display().to_string();
+            let mut errors = Vec::new();
+            // The manifests of a component render side by side, so it took the longest of them
+            let mut longest = std::time::Duration::ZERO;
+            for (result, took) in results.by_ref().take(component.rendered().count()) {
+                longest = longest.max(took);
+                if let Err(e) = result {
+                    errors.push(e);
+                }
+            }
+            // Rolls the pods whenever the secrets they consume change
+            if errors.is_empty() {
+                if let Err(e) = annotate_pod_templates(
+                    component.output_directory(),
+                    SECRETS_CHECKSUM_ANNOTATION,
+                    checksum,
+                ) {
+                    errors.push(e);
+                }
+            }
+            if errors.is_empty() && self.fake_secrets {
+                if let Err(e) = annotate_pod_templates(
+                    component.output_directory(),
+                    FAKE_SECRETS_ANNOTATION,
+                    "true",
+                )
+                .and_then(|_| mark_fake_secrets(component.output_directory()))
+                {
+                    errors.push(e);
+                }
+            }
+            if let (true, Some(host_mount), Some(volumes)) = (
+                errors.is_empty(),
+                &self.host_mount,
+                &component.spec().volumes,
+            ) {
+                if let Err(e) = mount_host_paths(component.output_directory(), host_mount, volumes)
+                {
+                    errors.push(e);
+                }
+            }
+
+            let duration = *prepared_in + longest;
+            if errors.is_empty() {
+                progress.finish(&render_dir, "OK".white().bold());
+                self.summary
+                    .record("render", component.name(), StepStatus::Ok, duration);
+                self.manifest_fingerprints
+                    .insert(component.name().to_string(), fingerprint.clone());
+            } else {
+                progress.fail(&render_dir);
+                self.summary
+                    .record("render", component.name(), StepStatus::Failed, duration);
+                failures.extend(errors);
+            }
+        }
+        drop(progress);
+        if !failures.is_empty() {
+            return Err(failures.join("\n"));
+        }
+
+        if self.config.ingress_kind() != "none" {
+            let mut generator = IngressGenerator::new(
+                self.config.ingress_kind(),
+                self.config.ingress_class(),
+                self.config.gateway(),
+                self.config.gateway_namespace(),
+            );
+            if let Some(issuer) = self.config.cert_manager_issuer() {
+                generator =
+                    generator.with_cert_manager(issuer, self.config.cert_manager_issuer_kind());
+            }
+            generator.write(&self.cluster_manifests, &self.services)?;
+        }
+
+        if self.config.network_policies() {
+            write_network_policies(
+                &self.cluster_manifests,
+                self.config.ingress_controller_namespace(),
+            )?;
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all, fields(product = %self.config.product_name(), environment = %self.config.environment()))]
+    pub async fn build(&mut self) -> Result<(), String> {
+        {
+            let _guard = Directory::chdir(&self.product_directory);
+
+            let progress = Progress::new("Building", self.images.len());
+            let product_name = self.config.product_name();
+            for image in &mut self.images {
+                image.set_was_recently_rebuild(false);
+                if image.should_ignore_in_devmode() {
+                    progress.skip(&image.identifier(), "IGNORED".red().bold());
+                    self.summary.skip("build", &image.identifier());
+                    continue;
+                }
+                let env_reloaded = self.env_reloads.remove(&image.component_name());
+                let warm_started = self.warm_started.remove(&image.component_name());
+                if !image.should_rebuild() && warm_started {
+                    // Started from the image the previous session built from the same inputs
+                    image.set_was_recently_rebuild(true);
+                    progress.skip(&image.identifier(), "UNCHANGED".yellow().bold());
+                    self.summary.skip("build", &image.identifier());
+                    continue;
+                }
+                if !image.should_rebuild() && env_reloaded {
+                    // Relaunched with the environment `rush reload-env` changed
+                    image.set_was_recently_rebuild(true);
+                    progress.skip(&image.identifier(), "RELOADED".yellow().bold());
+                    self.summary.skip("build", &image.identifier());
+                    continue;
+                }
+                if !image.should_rebuild() {
+                    progress.skip(&image.identifier(), "SKIPPED".yellow().bold());
+                    self.summary.skip("build", &image.identifier());
+                    continue;
+                }
+
+                progress.start(&image.identifier());
+                image.set_was_recently_rebuild(true);
+
+                let component_name = image.component_name();
+                let identifier = image.identifier();
+                self.events.emit(
+                    product_name,
+                    Event::BuildStarted {
+                        component: &component_name,
+                        image: &identifier,
+                        push: false,
+                    },
+                );
+                // Taken before the build, as the files the build writes could change it
+                let fingerprint = match &self.warm_start {
+                    Some(_) => image.fingerprint().await,
+                    None => None,
+                };
+                let started = std::time::Instant::now();
+                let result = image.build().await;
+                self.events.emit(
+                    product_name,
+                    Event::BuildFinished {
+                        component: &component_name,
+                        image: &identifier,
+                        push: false,
+                        success: result.is_ok(),
+                        duration_ms: started.elapsed().as_millis(),
+                    },
+                );
+                self.summary.record(
+                    "build",
+                    &identifier,
+                    step_status(&result),
+                    started.elapsed(),
+                );
+                match result {
+                    Ok(_) => {
+                        image.set_should_rebuild(false);
+                        if let (Some(warm_start), Some(fingerprint)) =
+                            (&mut self.warm_start, fingerprint)
+                        {
+                            warm_start.record(
+                                &component_name,
+                                fingerprint,
+                                image.tagged_image_name(),
+                            );
+                        }
+                        progress.finish(&image.identifier(), "OK".white().bold())
+                    }
+                    Err(e) => {
+                        progress.fail(&image.identifier());
+                        drop(progress);
+                        println!();
+                        println!("{}", e);
+                        println!();
+                        println!("{}", "Build was unsuccessful".red().bold());
+                        return Err(e);
+                    }
+                }
+            }
+        }
+
+        // The images are not pushed yet, so there are no digests to pin
+        let started = std::time::Instant::now();
+        self.render_manifests(false).await?;
+        self.profile(ProfileStep::Manifests, started);
+
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all, fields(product = %self.config.product_name(), environment = %self.config.environment()))]
+    pub async fn launch(&mut self) -> Result<(), String> {
+        trace!("Starting launch process");
+
+        self.enable_debugging()?;
+        // Requests left behind while no dev loop was running are stale
+        let _ = std::fs::remove_file(self.env_reload_file());
+        self.setup_environment().await?;
+
+        self.reuse_previous_images().await;
+        self.check_redirects().await;
+
+        let test_if_files_changed = self.setup_file_watcher()?;
+        let proxy = self.start_proxy().await?;
+        let resource_monitor = self.monitor_resources();
+        let mut endpoint_report: Option<tokio::task::JoinHandle<()>> = None;
+        if let Some(profiler) = &self.profiler {
+            profiler.start_rebuild(Vec::new());
+        }
+
+        let mut break_type = BreakType::Running;
+        while matches!(break_type, BreakType::Running | BreakType::FileChanged) {
+            // Invalidating cache
+            let started = std::time::Instant::now();
+            self.kill_and_clean(false).await;
+            self.profile(ProfileStep::Stop, started);
+            trace!("Cleaned up previous resources");
+
+            trace!("Building images");
+            if let Err(e) = self
+                .build_and_handle_errors(&mut break_type, &test_if_files_changed)
+                .await
+            {
+                continue;
+            }
+
+            trace!("Preparing launch");
+
+            let (max_label_length, longest_paths) = self.prepare_for_launch();
+            trace!("Launching images");
+            self.launch_images(max_label_length, longest_paths).await;
+            if let Some(profiler) = &self.profiler {
+                profiler.finish_rebuild();
+            }
+            if let Some(endpoint_report) = endpoint_report.take() {
+                endpoint_report.abort();
+            }
+            let endpoints = self.endpoints();
+            if !endpoints.is_empty() {
+                endpoint_report = Some(tokio::spawn(report_endpoints(endpoints)));
+            }
+            trace!("Monitoring images");
+
+            break_type = self.monitor_and_handle_events(&test_if_files_changed).await;
+        }
+
+        if let Some(resource_monitor) = resource_monitor {
+            resource_monitor.abort();
+        }
+        if let Some(endpoint_report) = endpoint_report {
+            endpoint_report.abort();
+        }
+        for server in proxy {
+            server.abort();
+        }
+        self.cleanup().await;
+
+        trace!("Launch process completed");
+        Ok(())
+    }
+
+    /// Builds the components with `debug` for a debugger, failing when two of them would
+    /// publish their gdbserver on the same port.
+    fn enable_debugging(&mut self) -> Result<(), String> {
+        let mut debug_ports: HashMap<u16, String> = HashMap::new();
+        for image in &mut self.images {
+            let spec = image.spec();
+            if !spec.debug || image.should_ignore_in_devmode() {
+                continue;
+            }
+            if let Some(other) = debug_ports.insert(spec.debug_port, spec.component_name.clone()) {
+                return Err(format!(
+                    "{} and {} both debug on port {}, set debug_port on one of them",
+                    other, spec.component_name, spec.debug_port
+                ));
+            }
+            image.set_debug();
+        }
+        Ok(())
+    }
+
+    /// The file `rush reload-env` leaves its requests to the running `rush dev` in, one
+    /// component per line or `*` for all of them.
+    fn env_reload_file(&self) -> PathBuf {
+        std::path::Path::new(&self.product_directory)
+            .join("target")
+            .join("reload-env")
+    }
+
+    /// Asks the running `rush dev` of the product to read the .env files of
+    /// `component_name`, or of every component, again and to restart the containers
+    /// whose environment changed.
+    pub fn request_env_reload(&self, component_name: Option<&str>) -> Result<(), String> {
+        if let Some(component_name) = component_name {
+            if self.get_image(component_name).is_none() {
+                return Err(format!("Unknown component {}", component_name));
+            }
+        }
+        let file = self.env_reload_file();
+        if let Some(directory) = file.parent() {
+            std::fs::create_dir_all(directory)
+                .map_err(|e| format!("Failed to create {}: {}", directory.display(), e))?;
+        }
+        // Appended, so requests made before the dev loop picks them up add up
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&file)
+            .and_then(|mut file| {
+                use std::io::Write;
+                writeln!(file, "{}", component_name.unwrap_or("*"))
+            })
+            .map_err(|e| format!("Failed to write {}: {}", file.display(), e))
+    }
+
+    /// Handles the requests of `rush reload-env`, which the dev loop then handles like a
+    /// change to the files of the components whose environment changed.
+    fn handle_env_reload_requests(&mut self) -> bool {
+        let file = self.env_reload_file();
+        let Ok(requests) = std::fs::read_to_string(&file) else {
+            return false;
+        };
+        let _ = std::fs::remove_file(&file);
+        let requests = requests
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>();
+
+        let reloaded = self.reload_environments(&requests);
+        if reloaded.is_empty() {
+            return false;
+        }
+        if let Some(profiler) = &self.profiler {
+            profiler.start_rebuild(reloaded);
+        }
+        true
+    }
+
+    /// Reads the .env files of the components in `requests`, or of all of them for `*`,
+    /// again and marks those whose environment changed to be restarted without a rebuild.
+    fn reload_environments(&mut self, requests: &[&str]) -> Vec<String> {
+        let _guard = Directory::chdir(&self.product_directory);
+        let mut reloaded = Vec::new();
+        for image in &self.images {
+            let component_name = image.component_name();
+            if image.should_ignore_in_devmode()
+                || !requests
+                    .iter()
+                    .any(|request| *request == "*" || *request == component_name)
+            {
+                continue;
+            }
+            match image.spec_handle().lock().unwrap().reload_dotenv() {
+                Ok(true) => {
+                    println!("Restarting '{}' with its new environment", component_name);
+                    reloaded.push(component_name);
+                }
+                Ok(false) => trace!("The environment of {} is unchanged", component_name),
+                Err(e) => eprintln!("{}", e),
+            }
+        }
+        self.env_reloads.extend(reloaded.iter().cloned());
+        reloaded
+    }
+
+    /// Writes the components' .env files again from the public environment definitions,
+    /// as `rush dev` does when it starts.
+    fn generate_dotenv_files(&self) {
+        let public_environment = PublicEnvironmentDefinitions::for_config(&self.config);
+        if let Err(e) = public_environment.generate_dotenv_files() {
+            eprintln!("{}", format!("Could not write the .env files: {}", e).red());
+            return;
+        }
+        match public_environment.validate_dotenv_files() {
+            Ok(issues) => {
+                for issue in issues {
+                    eprintln!("{}", issue.red());
+                }
+            }
+            Err(e) => eprintln!("{}", format!("Could not check the .env files: {}", e).red()),
+        }
+    }
+
+    /// Loads the components again after a change of stack.spec.yaml or the files it is
+    /// rendered with. Components taken out are stopped, those added or configured
+    /// differently are marked for a rebuild, and the others keep running. Returns the
+    /// components to rebuild; while the stack files do not load, the components stay
+    /// as they are.
+    async fn reload_stack_spec(&mut self) -> Vec<String> {
+        let Some(toolchain) = self.toolchain.clone() else {
+            return Vec::new();
+        };
+        let reloaded = match Self::from_product_dir(
+            self.config.clone(),
+            toolchain,
+            self.vault.clone(),
+            self.secrets_encoder.clone(),
+            self.k8s_encoder.clone(),
+            self.redirects.clone().into_iter().collect(),
+            self.silenced_components.clone(),
+        ) {
+            Ok(reloaded) => reloaded,
+            Err(e) => {
+                eprintln!(
+                    "{}",
+                    format!("Keeping the components as they are: {}", e).red()
+                );
+                return Vec::new();
+            }
+        };
+
+        let mut previous = std::mem::take(&mut self.images)
+            .into_iter()
+            .map(|image| (image.component_name(), image))
+            .collect::<HashMap<_, _>>();
+        let mut changed = Vec::new();
+        for mut image in reloaded.images {
+            let component_name = image.component_name();
+            if self.proxy_port.is_some()
+                && matches!(image.spec().build_type, BuildType::Ingress { .. })
+            {
+                image.set_ignore_in_devmode(true);
+            }
+            match previous.remove(&component_name) {
+                Some(current) if current.dev_settings() == image.dev_settings() => {
+                    self.images.push(current);
+                    continue;
+                }
+                Some(_) => println!("Reconfiguring '{}'", component_name),
+                None => println!("Adding '{}'", component_name),
+            }
+            if let Some(profiler) = &self.profiler {
+                image.set_profiler(profiler.clone());
+            }
+            if let Some(status_board) = self.status_board.as_ref() {
+                if !image.should_ignore_in_devmode() {
+                    status_board.add_component(&component_name, image.port());
+                    image.set_status_board(status_board.clone());
+                }
+            }
+            image.set_should_rebuild(true);
+            self.images.push(image);
+            changed.push(component_name);
+        }
+        for (component_name, image) in previous {
+            println!("Removing '{}'", component_name);
+            image.kill_and_clean().await;
+            if let Some(status_board) = &self.status_board {
+                status_board.remove_component(&component_name);
+            }
+        }
+
+        self.services = reloaded.services;
+        let previous_manifests =
+            std::mem::replace(&mut self.cluster_manifests, reloaded.cluster_manifests);
+        self.forget_removed_manifests(previous_manifests);
+        self.cluster_values = reloaded.cluster_values;
+        self.volumes = reloaded.volumes;
+        if let Err(e) = self.create_volumes().await {
+            eprintln!("{}", e.red());
+        }
+        if let Err(e) = self.enable_debugging() {
+            eprintln!("{}", e.red());
+        }
+        if let Err(e) = self.watch_paths() {
+            eprintln!("{}", e.red());
+        }
+        changed
+    }
+
+    /// Drops what was rendered for the components `previous` has and the current
+    /// manifests do not. Those rendered by this process may be in the cluster, so their
+    /// manifests are kept for `delete_removed_manifests`.
+    fn forget_removed_manifests(&mut self, previous: K8ClusterManifests) {
+        let current = self
+            .cluster_manifests
+            .components()
+            .iter()
+            .map(|component| component.name().to_string())
+            .collect::<HashSet<_>>();
+        let _guard = Directory::chdir(&self.product_directory);
+        for component in previous.into_components() {
+            if current.contains(component.name()) {
+                continue;
+            }
+            if self
+                .manifest_fingerprints
+                .remove(component.name())
+                .is_some()
+            {
+                self.removed_manifests.push(component);
+            } else {
+                remove_rendered(&component);
+            }
+        }
+    }
+
+    /// Deletes what was rendered for components the manifests no longer have, and the
+    /// Ingress, which is written again after the components.
+    fn prune_rendered(&mut self) -> Result<(), String> {
+        let components = self
+            .cluster_manifests
+            .components()
+            .iter()
+            .chain(&self.removed_manifests)
+            .collect::<Vec<_>>();
+        self.manifest_fingerprints.retain(|name, _| {
+            components
+                .iter()
+                .any(|component| component.name() == name.as_str())
+        });
+        let output_dir = self.cluster_manifests.output_directory();
+        for (directory, kept) in [
+            (
+                output_dir.clone(),
+                components
+                    .iter()
+                    .filter_map(|component| component.output_directory().file_name())
+                    .collect::<HashSet<_>>(),
+            ),
+            (
+                self.cluster_manifests.jobs_directory(),
+                components
+                    .iter()
+                    .filter_map(|component| component.jobs_directory().file_name())
+                    .collect(),
+            ),
+        ] {
+            let Ok(entries) = std::fs::read_dir(&directory) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                if kept.contains(entry.file_name().as_os_str()) {
+                    continue;
+                }
+                let path = entry.path();
+                debug!("Deleting {}", path.display());
+                let removed = if path.is_dir() {
+                    std::fs::remove_dir_all(&path)
+                } else {
+                    std::fs::remove_file(&path)
+                };
+                removed.map_err(|e| format!("Failed to delete {}: {}", path.display(), e))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Deletes the components taken out of stack.spec.yaml from the cluster, along with
+    /// their rendered manifests.
+    async fn delete_removed_manifests(&mut self) {
+        let _guard = Directory::chdir(&self.product_directory);
+        for component in std::mem::take(&mut self.removed_manifests) {
+            let component_name = component.spec().component_name;
+            println!("Deleting '{}' from the cluster", component_name);
+            if let Err(e) = component.unapply(&self.kube_context).await {
+                eprintln!(
+                    "{} could not delete '{}': {}",
+                    "Warning:".yellow().bold(),
+                    component_name,
+                    e
+                );
+            }
+            remove_rendered(&component);
+        }
+    }
+
+    /// Serves the proxy of `set_proxy`, if any, returning its tasks.
+    async fn start_proxy(&self) -> Result<Vec<tokio::task::JoinHandle<()>>, String> {
+        let Some(port) = self.proxy_port else {
+            return Ok(Vec::new());
+        };
+        let proxy = DevProxy::new(&self.services);
+        let mut servers = vec![proxy.serve(port).await?];
+        if let Some((tls_port, certificate_file)) = &self.proxy_tls {
+            let identity = dev_certificate(certificate_file, &proxy.domains())?;
+            servers.push(proxy.serve_tls(*tls_port, identity).await?);
+        }
+        Ok(servers)
+    }
+
+    async fn setup_environment(&mut self) -> Result<(), String> {
+        let _ = self.create_network().await;
+        trace!("Created Docker network");
+        self.check_extra_networks().await?;
+        self.create_volumes().await
+    }
+
+    /// Creates the named volumes the dev containers mount.
+    async fn create_volumes(&self) -> Result<(), String> {
+        let Some(toolchain) = &self.toolchain else {
+            return Ok(());
+        };
+        let names = self
+            .images
+            .iter()
+            .filter(|image| !image.should_ignore_in_devmode())
+            .flat_map(|image| image.spec().named_volumes.into_keys())
+            .collect();
+        self.volumes.create(toolchain.docker(), &names).await
+    }
+
+    /// Removes the product's volumes no longer in `stack.volumes.yaml`, or all of them,
+    /// returning their names, see `rush volumes prune`.
+    pub async fn prune_volumes(&self, all: bool, dry_run: bool) -> Result<Vec<String>, String> {
+        let toolchain = self.toolchain.as_ref().ok_or("Toolchain not found")?;
+        self.volumes.prune(toolchain.docker(), all, dry_run).await
+    }
+
+    /// Dumps the database of the running dev container of `component_name` into
+    /// `target/snapshots/`, returning the snapshot's path.
+    pub async fn snapshot_database(
+        &self,
+        component_name: &str,
+        name: Option<&str>,
+    ) -> Result<PathBuf, String> {
+        let (spec, database) = self.database_component(component_name)?;
+        let toolchain = self.toolchain.as_ref().ok_or("Toolchain not found")?;
+        Snapshots::new(std::path::Path::new(&self.product_directory))
+            .snapshot(
+                toolchain.docker(),
+                &spec.docker_local_name(),
+                component_name,
+                &database,
+                name,
             )
             .await
-            {
-                Ok(_) => (),
-                Err(e) => {
-                    eprintln!("Failed to delete namespace: {}", e);
-                }
-            }
+    }
 
-            println!(
-                "\rUninstalling {} in {}  ..... [  {}  ]",
+    /// Loads the snapshot `name`, or the latest one, into the database of the running
+    /// dev container of `component_name`, returning the snapshot's path.
+    pub async fn restore_database(
+        &self,
+        component_name: &str,
+        name: Option<&str>,
+    ) -> Result<PathBuf, String> {
+        let (spec, database) = self.database_component(component_name)?;
+        let toolchain = self.toolchain.as_ref().ok_or("Toolchain not found")?;
+        Snapshots::new(std::path::Path::new(&self.product_directory))
+            .restore(
+                toolchain.docker(),
+                &spec.docker_local_name(),
+                component_name,
+                &database,
                 name,
-                namespace,
-                "OK".white().bold()
-            );
-        }
+            )
+            .await
+    }
 
-        Ok(())
+    fn database_component(
+        &self,
+        component_name: &str,
+    ) -> Result<(ComponentBuildSpec, DatabaseSpec), String> {
+        let image = self
+            .get_image(component_name)
+            .ok_or_else(|| format!("Unknown component {}", component_name))?;
+        let spec = image.spec();
+        let database = spec.database.clone().ok_or_else(|| {
+            format!(
+                "{} is not a database. Mark it with database: {{type: postgres}} in stack.spec.yaml",
+                component_name
+            )
+        })?;
+        Ok((spec, database))
     }
 
-    pub async fn build_manifests(&mut self) -> Result<(), String> {
+    /// Skips the build of the components whose fingerprint matches an image the previous
+    /// `rush dev` built, tagging that image with the current tag when it changed.
+    async fn reuse_previous_images(&mut self) {
+        let (Some(warm_start), Some(toolchain)) = (&self.warm_start, &self.toolchain) else {
+            return;
+        };
+        let docker = toolchain.docker();
         let _guard = Directory::chdir(&self.product_directory);
-        let output_dir = self.cluster_manifests.output_directory();
-        if output_dir.exists() {
-            std::fs::remove_dir_all(output_dir).expect("Failed to delete output directory");
-        }
-
-        for component in self.cluster_manifests.components() {
-            if component.is_installation() {
+        for image in &mut self.images {
+            if image.should_ignore_in_devmode() || !image.should_rebuild() {
                 continue;
             }
-
-            let render_dir = component.output_directory();
-            std::fs::create_dir_all(render_dir).expect("Failed to create render directory");
-            print!("Creating K8s {}  ..... ", render_dir.display());
-            let current_dir = std::env::current_dir().unwrap();
-            let spec = component.spec();
-
-            let secrets = {
-                let vault = self.vault.lock().unwrap();
-                vault
-                    .get(
-                        &spec.product_name,
-                        &spec.component_name,
-                        &spec.config.environment().to_string(),
-                    )
-                    .await
-                    .unwrap_or_default()
+            let component_name = image.component_name();
+            let Some(fingerprint) = image.fingerprint().await else {
+                continue;
             };
-            // Encoding secrets
-            let secrets = self.secrets_encoder.encode_secrets(secrets);
-
-            let ctx = spec.generate_build_context(self.toolchain.clone(), secrets);
-            for manifest in component.manifests() {
-                manifest.render_to_file(&ctx);
+            let Some(previous) = warm_start.image(&component_name, &fingerprint) else {
+                debug!("{} changed since the previous session", component_name);
+                continue;
+            };
+            let inspect = vec!["image", "inspect", previous];
+            if run_command("check".white().bold(), docker, inspect)
+                .await
+                .is_err()
+            {
+                debug!("The previous image of {} is gone", component_name);
+                continue;
             }
+            let current = image.tagged_image_name();
+            if previous != current
+                && run_command("docker".into(), docker, vec!["tag", previous, &current])
+                    .await
+                    .is_err()
+            {
+                continue;
+            }
+            trace!("Starting {} from {}", component_name, previous);
+            image.set_should_rebuild(false);
+            self.warm_started.insert(component_name);
+        }
+    }
 
+    /// Warns about `--redirect` targets that do not accept connections, before the
+    /// components depending on them start, as otherwise they and the ingress only fail
+    /// to reach them, e.g. with 502s.
+    async fn check_redirects(&self) {
+        for (component_name, (host, port)) in &self.redirects {
+            // The containers reach this machine as host.docker.internal
+            let address = match host.as_str() {
+                "host.docker.internal" => format!("127.0.0.1:{}", port),
+                _ => format!("{}:{}", host, port),
+            };
+            let connection = tokio::time::timeout(
+                REDIRECT_CONNECT_TIMEOUT,
+                tokio::net::TcpStream::connect(&address),
+            )
+            .await;
+            let error = match connection {
+                Ok(Ok(_)) => {
+                    trace!("{} is redirected to {}", component_name, address);
+                    continue;
+                }
+                Ok(Err(e)) => e.to_string(),
+                Err(_) => "timed out".to_string(),
+            };
+            let dependents = self
+                .images
+                .iter()
+                .filter(|image| image.depends_on().contains(component_name))
+                .map(|image| image.component_name())
+                .collect::<Vec<_>>();
+            let affected = match dependents.is_empty() {
+                true => "The ingress".to_string(),
+                false => format!("{} and the ingress", dependents.join(", ")),
+            };
             println!(
-                "\rCreating K8s {}  ..... [  {}  ]",
-                render_dir.display(),
-                "OK".white().bold()
+                "{} {} is redirected to {}, which cannot be reached: {}. {} will fail to reach it until something listens there. Check the port of --redirect",
+                "Warning:".yellow().bold(),
+                component_name.bold(),
+                address,
+                error,
+                affected
             );
         }
-
-        Ok(())
     }
 
-    pub async fn build(&mut self) -> Result<(), String> {
-        {
-            let _guard = Directory::chdir(&self.product_directory);
-
-            for image in &mut self.images {
-                image.set_was_recently_rebuild(false);
-                if image.should_ignore_in_devmode() {
-                    println!(
-                        "{}  ..... [  {}  ]",
-                        image.identifier(),
-                        "IGNORED".red().bold()
-                    );
-                    continue;
-                }
-                if !image.should_rebuild() {
-                    println!(
-                        "{}  ..... [  {}  ]",
-                        image.identifier(),
-                        "SKIPPED".yellow().bold()
-                    );
+    /// Fails on `extra_networks` that do not exist, as rush only creates the product's
+    /// own network.
+    async fn check_extra_networks(&self) -> Result<(), String> {
+        let Some(toolchain) = &self.toolchain else {
+            return Ok(());
+        };
+        let mut checked = HashSet::new();
+        for image in &self.images {
+            if image.should_ignore_in_devmode() {
+                continue;
+            }
+            let spec = image.spec();
+            for network in &spec.extra_networks {
+                if !checked.insert(network.clone()) {
                     continue;
                 }
-
-                print!("Building {}  ..... ", image.identifier());
-                std::io::stdout().flush().expect("Failed to flush stdout");
-                image.set_was_recently_rebuild(true);
-
-                match image.build().await {
-                    Ok(_) => {
-                        image.set_should_rebuild(false);
-                        println!(
-                            "Building {}  ..... [  {}  ]",
-                            image.identifier(),
-                            "OK".white().bold()
-                        )
-                    }
-                    Err(e) => {
-                        println!(
-                            "Building {}  ..... [ {} ]",
-                            image.identifier(),
-                            "FAIL".red().bold()
-                        );
-                        println!();
-                        println!("{}", e);
-                        println!();
-                        println!("{}", "Build was unsuccessful".red().bold());
-                        return Err(e);
-                    }
+                let args = vec!["network", "inspect", network.as_str()];
+                if crate::utils::run_command("check".white().bold(), toolchain.docker(), args)
+                    .await
+                    .is_err()
+                {
+                    return Err(format!(
+                        "The Docker network {} in the extra_networks of {} does not exist. Create it with: docker network create {}",
+                        network, spec.component_name, network
+                    ));
                 }
             }
         }
-
-        self.build_manifests().await?;
-
         Ok(())
     }
 
-    pub async fn launch(&mut self) -> Result<(), String> {
-        trace!("Starting launch process");
-
-        self.setup_environment().await?;
-
-        let (_watcher, test_if_files_changed) = self.setup_file_watcher()?;
-
-        let mut break_type = BreakType::Running;
-        while matches!(break_type, BreakType::Running | BreakType::FileChanged) {
-            // Invalidating cache
-            self.kill_and_clean(false).await;
-            trace!("Cleaned up previous resources");
-
-            println!("Step A");
-            if let Err(e) = self
-                .build_and_handle_errors(&mut break_type, &test_if_files_changed)
-                .await
-            {
-                continue;
+    /// The URLs of the components running in dev mode with a mount point or health
+    /// check: through the ingress routing to them if there is one, or else on their
+    /// own port.
+    fn endpoints(&self) -> Vec<Endpoint> {
+        let running = self
+            .images
+            .iter()
+            .filter(|image| !image.should_ignore_in_devmode())
+            .map(|image| image.spec())
+            .collect::<Vec<_>>();
+        let ingress_port = |component_name: &str| {
+            let proxied = self
+                .services
+                .values()
+                .flatten()
+                .any(|service| service.name == component_name);
+            if let Some(port) = self.proxy_port.filter(|_| proxied) {
+                return Some(port);
             }
+            running.iter().find_map(|spec| match &spec.build_type {
+                BuildType::Ingress { components, .. }
+                    if components.iter().any(|name| name == component_name) =>
+                {
+                    spec.port
+                }
+                _ => None,
+            })
+        };
 
-            println!("Step B");
-
-            let (max_label_length, longest_paths) = self.prepare_for_launch();
-            println!("Step C");
-            self.launch_images(max_label_length, longest_paths).await;
-            println!("Step D");
-
-            break_type = self.monitor_and_handle_events(&test_if_files_changed).await;
+        let mut endpoints = Vec::new();
+        for spec in &running {
+            let Some(path) = spec.health_check.as_ref().or(spec.mount_point.as_ref()) else {
+                continue;
+            };
+            let endpoint = match (ingress_port(&spec.component_name), spec.port) {
+                (Some(port), _) if spec.mount_point.is_some() => {
+                    Endpoint::through_ingress(&spec.component_name, &spec.domain, port, path)
+                }
+                (_, Some(port)) => Endpoint::on_port(&spec.component_name, port, path),
+                _ => continue,
+            };
+            endpoints.push(endpoint);
         }
-
-        self.cleanup().await;
-
-        trace!("Launch process completed");
-        Ok(())
+        endpoints
     }
 
-    async fn setup_environment(&mut self) -> Result<(), String> {
-        let _ = self.create_network().await;
-        trace!("Created Docker network");
-        Ok(())
+    /// Samples the resources of the dev containers for the status page and, with
+    /// `--stats`, the terminal, until the returned task is aborted.
+    fn monitor_resources(&self) -> Option<tokio::task::JoinHandle<()>> {
+        if self.resource_usage_interval.is_none() && self.status_board.is_none() {
+            return None;
+        }
+        let components = self
+            .images
+            .iter()
+            .filter(|image| !image.should_ignore_in_devmode())
+            .map(|image| (image.spec().docker_local_name(), image.component_name()))
+            .collect();
+        let mut monitor = ResourceMonitor::new(self.toolchain.as_ref()?.docker(), components);
+        let print_interval = self.resource_usage_interval;
+        let status_board = self.status_board.clone();
+        Some(tokio::spawn(async move {
+            let mut interval = tokio::time::interval(
+                print_interval.unwrap_or(tokio::time::Duration::from_secs(5)),
+            );
+            loop {
+                interval.tick().await;
+                let usage = match monitor.sample().await {
+                    Ok(usage) => usage,
+                    Err(e) => {
+                        debug!("{}", e);
+                        continue;
+                    }
+                };
+                if let Some(status_board) = &status_board {
+                    status_board.set_resources(&usage);
+                }
+                if print_interval.is_some() && !usage.is_empty() {
+                    print_usage(&usage);
+                }
+            }
+        }))
     }
 
-    fn setup_file_watcher(&self) -> Result<(RecommendedWatcher, impl Fn() -> bool), String> {
+    fn setup_file_watcher(&mut self) -> Result<impl Fn() -> bool, String> {
         let (watch_tx, watch_rx) = std::sync::mpsc::channel();
-        let mut watcher = match RecommendedWatcher::new(watch_tx, NotifyConfig::default()) {
+        let watcher = match RecommendedWatcher::new(watch_tx, NotifyConfig::default()) {
             Ok(w) => {
                 trace!("Created file watcher");
                 w
@@ -837,41 +2813,52 @@ impl ContainerReactor {
                 return Err(e.to_string());
             }
         };
-
-        let path = self.product_directory.clone();
-        match watcher.watch(path.as_ref(), RecursiveMode::Recursive) {
-            Ok(_) => trace!("Started watching directory: {}", path),
-            Err(e) => {
-                error!("Failed to watch directory: {}", e);
-                return Err(e.to_string());
-            }
-        }
+        self.watcher = Some(watcher);
+        self.watching.clear();
+        self.watch_paths()?;
 
         let product_directory = std::path::Path::new(&self.product_directory);
-        let gitignore = PathMatcher::from_gitignore(product_directory);
+        let product_directory = product_directory
+            .canonicalize()
+            .unwrap_or_else(|_| product_directory.to_path_buf());
+        let gitignore = PathMatcher::from_gitignore(&product_directory);
+        let stack_directories = self.stack_directories();
+        let environment = self.config.environment().to_string();
         let changed_files = self.changed_files.clone();
-        Ok((watcher, move || {
+        let created_directories = self.created_directories.clone();
+        Ok(move || {
             if let Ok(event) = watch_rx.try_recv() {
                 match event {
                     Ok(event) => {
-                        let other_events = watch_rx.try_iter();
-                        let all_events = std::iter::once(Ok(event)).chain(other_events);
-                        let paths = all_events
-                            .filter_map(|event| {
-                                if let Ok(event) = event {
-                                    if event.paths.is_empty() {
-                                        None
-                                    } else {
-                                        Some(event.paths)
-                                    }
-                                } else {
-                                    None
+                        let mut paths = Vec::new();
+                        let mut directories = Vec::new();
+                        let other_events = watch_rx.try_iter().filter_map(|event| event.ok());
+                        for event in std::iter::once(event).chain(other_events) {
+                            let created = matches!(
+                                event.kind,
+                                EventKind::Create(_) | EventKind::Modify(ModifyKind::Name(_))
+                            );
+                            for path in event.paths {
+                                // stack.spec.local.yaml and the like are usually ignored
+                                if stack_file(&path, &stack_directories, &environment).is_none()
+                                    && gitignore.matches(&path)
+                                {
+                                    continue;
                                 }
-                            })
-                            .flatten()
-                            .filter(|path| !gitignore.matches(path))
-                            .filter(|path| path.is_file())
-                            .collect::<Vec<_>>();
+                                if path.is_file() {
+                                    paths.push(path);
+                                } else if created && path.is_dir() {
+                                    directories.push(path);
+                                }
+                            }
+                        }
+                        if !directories.is_empty() {
+                            trace!("Directories created: {:#?}", directories);
+                            created_directories.lock().unwrap().extend(directories);
+                            if paths.is_empty() {
+                                return true;
+                            }
+                        }
 
                         let mut unique_paths = std::collections::HashSet::new();
                         let paths = paths
@@ -895,7 +2882,122 @@ impl ContainerReactor {
                 }
             }
             false
-        }))
+        })
+    }
+
+    /// Watches the directories of [`watched_paths`](Self::watched_paths) that git does not
+    /// ignore, and the product and shared directories for the stack files, that are not
+    /// watched yet. Called again once stack.spec.yaml changed, as added components bring
+    /// their own paths.
+    fn watch_paths(&mut self) -> Result<(), String> {
+        // Only where a change can cause a rebuild, as watching target/ or node_modules/
+        // of a big product keeps a CPU busy. Each directory is watched on its own, as a
+        // recursive watch would take in the ignored ones below it
+        let product_directory = std::path::Path::new(&self.product_directory);
+        let product_directory = product_directory
+            .canonicalize()
+            .unwrap_or_else(|_| product_directory.to_path_buf());
+        let roots = self.watched_paths();
+        let paths = walk_watched(&product_directory, &roots)
+            .into_iter()
+            .filter(|(path, is_directory)| *is_directory || roots.contains(path))
+            .map(|(path, _)| path)
+            .chain(self.stack_directories())
+            .collect();
+        self.watch(paths)
+    }
+
+    /// Watches the directories created below the watched paths since the last call. The
+    /// files already in them count as changed, as they may have been written before.
+    fn watch_created_directories(&mut self) -> Result<(), String> {
+        let created = std::mem::take(&mut *self.created_directories.lock().unwrap());
+        if created.is_empty() {
+            return Ok(());
+        }
+        let roots = self.watched_paths();
+        let mut directories = Vec::new();
+        for directory in created {
+            // One removed and created again lost its watch with it
+            self.watching.retain(|path| !path.starts_with(&directory));
+            for (path, is_directory) in walk_watched(&directory, &roots) {
+                if is_directory {
+                    directories.push(path);
+                } else {
+                    self.changed_files.lock().unwrap().push(path);
+                }
+            }
+        }
+        self.watch(directories)
+    }
+
+    fn watch(&mut self, paths: Vec<PathBuf>) -> Result<(), String> {
+        let Some(watcher) = self.watcher.as_mut() else {
+            return Ok(());
+        };
+        for path in paths {
+            if self.watching.contains(&path) {
+                continue;
+            }
+            match watcher.watch(&path, RecursiveMode::NonRecursive) {
+                Ok(_) => trace!("Started watching: {}", path.display()),
+                Err(e) => {
+                    error!("Failed to watch {}: {}", path.display(), e);
+                    return Err(e.to_string());
+                }
+            }
+            self.watching.insert(path);
+        }
+        Ok(())
+    }
+
+    /// The product directory and the shared one, where they exist, which hold the
+    /// stack files.
+    fn stack_directories(&self) -> Vec<PathBuf> {
+        let product_directory = std::path::Path::new(&self.product_directory);
+        [
+            product_directory.to_path_buf(),
+            shared_file(product_directory, "stack.spec.yaml")
+                .parent()
+                .map(|path| path.to_path_buf())
+                .unwrap_or_default(),
+        ]
+        .into_iter()
+        .filter_map(|path| path.canonicalize().ok())
+        .collect()
+    }
+
+    /// The parts of the product directory holding the contexts and `watch` globs of the
+    /// components run in dev mode, without those inside another one. Paths that do not
+    /// exist yet are watched from the closest directory that does.
+    fn watched_paths(&self) -> Vec<PathBuf> {
+        let _guard = Directory::chdir(&self.product_directory);
+        let product_directory = std::path::Path::new(&self.product_directory);
+        let product_directory = product_directory
+            .canonicalize()
+            .unwrap_or_else(|_| product_directory.to_path_buf());
+
+        let mut paths = self
+            .images
+            .iter()
+            .filter(|image| !image.should_ignore_in_devmode())
+            .flat_map(|image| image.watched_paths())
+            .filter_map(|path| {
+                path.ancestors()
+                    .find(|ancestor| ancestor.exists())
+                    .and_then(|existing| existing.canonicalize().ok())
+            })
+            .filter(|path| path.starts_with(&product_directory))
+            .collect::<Vec<_>>();
+        // Sorted, the paths inside a directory come right after it
+        paths.sort();
+        let mut watched: Vec<PathBuf> = Vec::new();
+        for path in paths {
+            if !watched.last().is_some_and(|last| path.starts_with(last)) {
+                watched.push(path);
+            }
+        }
+        debug!("Watching {:#?}", watched);
+        watched
     }
 
     async fn build_and_handle_errors(
@@ -973,7 +3075,7 @@ impl ContainerReactor {
         let dependency_graph = self
             .images
             .iter()
-            .map(|image| (image.image_name().to_string(), image.depends_on().clone()))
+            .map(|image| (image.dependency_name(), image.depends_on().clone()))
             .collect::<HashMap<String, Vec<String>>>();
 
         let longest_paths = self.compute_longest_paths(&dependency_graph);
@@ -1024,7 +3126,7 @@ impl ContainerReactor {
             .enumerate()
             .map(move |(id, image)| {
                 let priority = longest_paths
-                    .get(image.image_name())
+                    .get(&image.dependency_name())
                     .cloned()
                     .unwrap_or_default();
 
@@ -1051,13 +3153,31 @@ impl ContainerReactor {
             self.statuses_receivers.insert(image_id, status_receiver);
             self.statuses
                 .insert(image.component_name(), Status::Awaiting);
+            if let Some(status_board) = &self.status_board {
+                status_board.set_status(&image.component_name(), Status::Awaiting.name());
+            }
+            let started = std::time::Instant::now();
             let handle = image.launch(
                 max_label_length,
                 self.terminate_receiver.resubscribe(),
                 status_sender,
             );
+            self.events.emit(
+                self.config.product_name(),
+                Event::ImageLaunched {
+                    component: &image.component_name(),
+                    image: &image.tagged_image_name(),
+                },
+            );
             self.handles.insert(image_id, handle);
             tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+            if let Some(profiler) = &self.profiler {
+                profiler.record(
+                    Some(&image.component_name()),
+                    ProfileStep::ContainerStart,
+                    started.elapsed(),
+                );
+            }
         }
     }
 
@@ -1082,6 +3202,9 @@ impl ContainerReactor {
                     if self.handle_file_changes(test_if_files_changed, &mut stopping, &mut stop_time).await {
                         return BreakType::FileChanged;
                     }
+                    if self.handle_restart_requests() || self.handle_env_reload_requests() {
+                        return BreakType::FileChanged;
+                    }
                     self.update_image_statuses();
 
                     all_finished = self.statuses.values().all(|status| matches!(status, Status::Finished(_)));
@@ -1127,13 +3250,42 @@ impl ContainerReactor {
     }
 
     async fn test_if_siginificant_change(&mut self) -> bool {
-        let mut significant_change = false;
+        if let Err(e) = self.watch_created_directories() {
+            warn!("Failed to watch the new directories: {}", e);
+        }
+        let mut changed_components = Vec::new();
         let changed_files = {
             let mut changed_files = self.changed_files.lock().unwrap();
             let ret = changed_files.clone();
             changed_files.clear();
             ret
         };
+        // The .env files only need the containers restarted, which rush rewrites on
+        // every command
+        let (dotenv_files, changed_files): (Vec<_>, Vec<_>) =
+            changed_files.into_iter().partition(|path| {
+                path.file_name()
+                    .is_some_and(|name| DOTENV_FILES.iter().any(|dotenv| name == *dotenv))
+            });
+        // The stack files change what the components are, so those are loaded again
+        let stack_directories = self.stack_directories();
+        let (stack_files, changed_files): (Vec<_>, Vec<_>) =
+            changed_files.into_iter().partition(|path| {
+                stack_file(path, &stack_directories, self.config.environment()).is_some()
+            });
+        let stack_files = stack_files
+            .iter()
+            .filter_map(|path| stack_file(path, &stack_directories, self.config.environment()))
+            .collect::<HashSet<_>>();
+        if !stack_files.is_empty() {
+            self.generate_dotenv_files();
+        }
+        if stack_files.contains(&StackFile::Spec) {
+            changed_components.extend(self.reload_stack_spec().await);
+        }
+        if !dotenv_files.is_empty() || stack_files.contains(&StackFile::Environment) {
+            changed_components.extend(self.reload_environments(&["*"]));
+        }
         {
             let _guard = Directory::chdir(&self.product_directory);
 
@@ -1142,14 +3294,20 @@ impl ContainerReactor {
                     continue;
                 }
                 if image.is_any_file_in_context(&changed_files) {
-                    significant_change = true;
+                    changed_components.push(image.component_name());
                     println!("Image '{}' was affected by change", image.component_name());
                     image.set_should_rebuild(true);
                 }
             }
         }
 
-        significant_change
+        if changed_components.is_empty() {
+            return false;
+        }
+        if let Some(profiler) = &self.profiler {
+            profiler.start_rebuild(changed_components);
+        }
+        true
     }
 
     async fn handle_file_changes(
@@ -1174,6 +3332,32 @@ impl ContainerReactor {
         }
     }
 
+    /// Marks the components restarted from the status page for a rebuild, which the
+    /// dev loop then handles like a change to their files.
+    fn handle_restart_requests(&mut self) -> bool {
+        let requests = match &self.status_board {
+            Some(status_board) => status_board.take_restart_requests(),
+            None => return false,
+        };
+        let mut restart = false;
+        for image in &mut self.images {
+            if requests.contains(&image.component_name()) {
+                println!(
+                    "Restarting '{}' from the status page",
+                    image.component_name()
+                );
+                image.set_should_rebuild(true);
+                restart = true;
+            }
+        }
+        if restart {
+            if let Some(profiler) = &self.profiler {
+                profiler.start_rebuild(requests);
+            }
+        }
+        restart
+    }
+
     async fn handle_image_completion(&mut self) -> bool {
         let any_finished = self
             .statuses
@@ -1306,6 +3490,20 @@ impl ContainerReactor {
                     if previous_status.map_or(true, |prev| *prev != status) {
                         self.statuses
                             .insert(component_name.to_string(), status.clone());
+                        if let Some(status_board) = &self.status_board {
+                            status_board.set_status(&component_name, status.name());
+                        }
+                        self.events.emit(
+                            self.config.product_name(),
+                            Event::StatusChanged {
+                                component: &component_name,
+                                status: status.name(),
+                                exit_code: match status {
+                                    Status::Finished(code) => Some(code),
+                                    _ => None,
+                                },
+                            },
+                        );
 
                         match status {
                             Status::InProgress => println!("Image {} is running", id),
@@ -1327,7 +3525,11 @@ impl ContainerReactor {
     pub async fn kill_and_clean(&self, force_all: bool) {
         trace!("Starting kill and cleanup process");
         for image in &self.images {
-            if force_all || image.should_rebuild() {
+            if force_all
+                || image.should_rebuild()
+                || self.env_reloads.contains(&image.component_name())
+                || self.warm_started.contains(&image.component_name())
+            {
                 log::info!("Cleaning up image: {}", image.identifier());
                 image.kill_and_clean().await;
             }
@@ -1344,4 +3546,81 @@ impl ContainerReactor {
         }
         trace!("Cleanup process completed");
     }
+
+    /// Removes the images built locally for the product's components.
+    pub async fn remove_images(&self) {
+        for image in &self.images {
+            image.remove_image().await;
+        }
+    }
+
+    /// Removes stale images of the product: the locally built ones and, with `remote`,
+    /// the tags pushed to DOCKER_REGISTRY. The current tag and the `keep` most recent
+    /// ones of each component are kept, as are the images of the latest rollout into
+    /// every environment and, before touching the registry, those running in
+    /// `kube_contexts`.
+    pub async fn collect_garbage(
+        &self,
+        keep: usize,
+        remote: bool,
+        kube_contexts: &[String],
+        dry_run: bool,
+    ) -> Result<(), String> {
+        let toolchain = self
+            .toolchain
+            .clone()
+            .expect("Toolchain is required for collecting garbage");
+        let images = self
+            .images
+            .iter()
+            .filter(|image| image.describe().build_type.dockerfile_path().is_some())
+            .map(|image| BuiltImage {
+                image_name: image.image_name().to_string(),
+                tag: image.describe().tag.unwrap_or_default(),
+            })
+            .collect::<Vec<_>>();
+
+        let client = match remote {
+            true => Some(RegistryClient::new(self.config.docker_registry())?),
+            false => None,
+        };
+
+        let mut in_use = HashSet::new();
+        let mut latest = HashMap::new();
+        for record in self.history.records()? {
+            latest.insert(record.environment.clone(), record);
+        }
+        for record in latest.values() {
+            in_use.extend(record.images.values().map(|image| gc::image_key(image)));
+        }
+
+        let removed = gc::remove_local_images(
+            toolchain.docker(),
+            self.config.docker_registry(),
+            &images,
+            keep,
+            &in_use,
+            dry_run,
+        )
+        .await?;
+        println!("{} local image(s)", removed);
+
+        if let Some(client) = client {
+            in_use.extend(gc::images_in_clusters(toolchain.kubectl(), kube_contexts).await?);
+            let deleted = gc::prune_registry(&client, &images, keep, &in_use, dry_run).await?;
+            println!("{} tag(s) in {}", deleted, client.host());
+        }
+        Ok(())
+    }
+
+    /// Removes the Kubernetes manifests rendered by earlier builds.
+    pub fn remove_k8s_output(&self) -> Result<(), String> {
+        let _guard = Directory::chdir(&self.product_directory);
+        let output_dir = self.cluster_manifests.output_directory();
+        if output_dir.exists() {
+            std::fs::remove_dir_all(output_dir)
+                .map_err(|e| format!("Failed to delete {}: {}", output_dir.display(), e))?;
+        }
+        Ok(())
+    }
 }
@@ -3,18 +3,25 @@ use std::path::PathBuf;
 use std::sync::mpsc::{self, Sender};
 use tokio::sync::broadcast::Receiver as BroadcastReceiver;
 
+use super::profile::{BuildProfiler, ProfileStep};
+use super::registry::RegistryClient;
 use super::status::Status;
+use super::status_page::StatusBoard;
+use super::warm_start;
 use crate::builder::BuildContext;
 use crate::builder::BuildType;
 use crate::builder::ComponentBuildSpec;
 use crate::builder::Config;
-use crate::utils::{handle_stream, run_command, run_command_in_window};
-use crate::vault::Vault;
-use crate::Directory;
+use crate::builder::CrateDependencies;
+use crate::cluster::LocalCluster;
+use crate::utils::Directory;
+use crate::utils::{handle_stream, run_command, run_command_in_window, spawn_command};
+use crate::vault::{get_secrets, Vault};
 use crate::{toolchain::ToolchainContext, utils::DockerCrossCompileGuard};
 use colored::Colorize;
 use log::{debug, error, info, trace, warn};
-use std::collections::HashMap;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
 use std::path::Path;
 use std::sync::Arc;
 use std::sync::Mutex;
@@ -27,6 +34,21 @@ impl TryInto<DockerImage> for Arc<Mutex<ComponentBuildSpec>> {
     }
 }
 
+/// The parts of an image that `rush describe images` reports.
+#[derive(Debug, Serialize)]
+pub struct ImageDescription {
+    pub component_name: String,
+    pub image_name: String,
+    pub tag: Option<String>,
+    pub build_type: BuildType,
+    pub depends_on: Vec<String>,
+    pub context_dir: Option<String>,
+    pub port: Option<u16>,
+    pub target_port: Option<u16>,
+    pub should_rebuild: bool,
+    pub ignored_in_devmode: bool,
+}
+
 #[derive(Debug, Clone)]
 pub struct DockerImage {
     image_name: String,
@@ -52,9 +74,102 @@ pub struct DockerImage {
     dev_ignore_image: bool,
     silence_output: bool,
     was_recently_rebuild: bool,
+    status_board: Option<StatusBoard>,
+    profiler: Option<BuildProfiler>,
+    debug: bool,
+    /// Docker volumes of `stack.volumes.yaml` mounted into the container, to container paths
+    named_volumes: Vec<(String, String)>,
+    /// The crates of a Rust component, read on the first change, or why they could not be
+    crate_dependencies: Option<Result<Arc<CrateDependencies>, String>>,
+}
+
+/// What [`DockerImage::push`] did.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pushed {
+    /// Uploaded with `docker push`
+    Uploaded,
+    /// The registry already had the image's content, which only got the image's tag
+    Unchanged,
+    /// Not deployed to Kubernetes, so there is nothing to push
+    Skipped,
 }
 
+/// How long `rush dev` tries to start the gdbserver of a component with `debug` while its
+/// container comes up.
+const GDBSERVER_ATTEMPTS: u32 = 20;
+
 impl DockerImage {
+    pub fn describe(&self) -> ImageDescription {
+        ImageDescription {
+            component_name: self.component_name(),
+            image_name: self.image_name.clone(),
+            tag: self.tag.clone(),
+            build_type: self.spec().build_type,
+            depends_on: self.depends_on.clone(),
+            context_dir: self.context_dir.clone(),
+            port: self.port,
+            target_port: self.target_port,
+            should_rebuild: self.should_rebuild,
+            ignored_in_devmode: self.dev_ignore_image,
+        }
+    }
+
+    /// What `rush dev` builds and runs the component from, to tell which components a
+    /// changed stack.spec.yaml or variables.yaml changed. The tag is left out, as it
+    /// changes with any edit of the product.
+    pub fn dev_settings(&self) -> String {
+        let spec = self.spec();
+        fn sorted(map: &Option<HashMap<String, String>>) -> Option<BTreeMap<&String, &String>> {
+            map.as_ref().map(|map| map.iter().collect())
+        }
+        // The services only end up in the configuration of an ingress
+        let services = match spec.build_type {
+            BuildType::Ingress { .. } => spec.services.as_ref().map(|services| {
+                services
+                    .iter()
+                    .map(|(domain, services)| (domain, format!("{:?}", services)))
+                    .collect::<BTreeMap<_, _>>()
+            }),
+            _ => None,
+        };
+        format!(
+            "{:?}",
+            (
+                (
+                    &spec.build_type,
+                    &spec.build,
+                    &spec.nix_flake,
+                    sorted(&spec.artefacts),
+                    &spec.artefact_output_dir,
+                    &spec.docker_extra_run_args,
+                    &spec.network_aliases,
+                    &spec.extra_networks,
+                    sorted(&spec.env),
+                    sorted(&spec.volumes),
+                ),
+                (
+                    &spec.mount_point,
+                    &spec.health_check,
+                    &spec.domain,
+                    &spec.depends_on,
+                    spec.watch.as_ref().map(|watch| watch.patterns()),
+                    spec.debug,
+                    spec.debug_port,
+                    &spec.database,
+                    &spec.color,
+                    services,
+                ),
+                (
+                    self.port,
+                    self.target_port,
+                    &self.named_volumes,
+                    self.dev_ignore_image,
+                    self.silence_output,
+                ),
+            )
+        )
+    }
+
     pub fn was_recently_rebuild(&self) -> bool {
         self.was_recently_rebuild
     }
@@ -67,10 +182,48 @@ impl DockerImage {
         &self.depends_on
     }
 
+    /// The name other components list this one by in their `depends_on`, which for
+    /// images pulled as they are differs from the image name.
+    pub fn dependency_name(&self) -> String {
+        let spec = self.spec.lock().unwrap();
+        format!("{}-{}", spec.product_name, spec.component_name)
+    }
+
     pub fn set_silence_output(&mut self, silence_output: bool) {
         self.silence_output = silence_output;
     }
 
+    /// Reports the output of the running container to the dev status page.
+    pub fn set_status_board(&mut self, status_board: StatusBoard) {
+        self.status_board = Some(status_board);
+    }
+
+    pub fn set_profiler(&mut self, profiler: BuildProfiler) {
+        self.profiler = Some(profiler);
+    }
+
+    fn profile(&self, step: ProfileStep, started: std::time::Instant) {
+        if let Some(profiler) = &self.profiler {
+            profiler.record(Some(&self.component_name()), step, started.elapsed());
+        }
+    }
+
+    /// Builds the binary for a debugger and runs a gdbserver next to it, see `debug` in
+    /// stack.spec.yaml. The image is tagged apart, so it is never mistaken for a release.
+    pub fn set_debug(&mut self) {
+        if self.debug {
+            return;
+        }
+        self.debug = true;
+        if let Some(tag) = &self.tag {
+            self.tag = Some(format!("{}-debug", tag));
+        }
+    }
+
+    pub fn set_named_volumes(&mut self, named_volumes: Vec<(String, String)>) {
+        self.named_volumes = named_volumes;
+    }
+
     pub fn should_ignore_in_devmode(&self) -> bool {
         self.dev_ignore_image
     }
@@ -165,14 +318,21 @@ impl DockerImage {
                     )
                 });
 
+            // EXPOSE takes several ports, each with an optional protocol, e.g. 8000/tcp
             let exposes = dockerfile_contents
                 .lines()
                 .map(|line| line.trim())
                 .filter(|line| line.starts_with("EXPOSE"))
-                .map(|line| line.trim_start_matches("EXPOSE").trim().to_string())
+                .flat_map(|line| line.trim_start_matches("EXPOSE").split_whitespace())
+                .map(|port| port.to_string())
                 .collect::<Vec<_>>();
 
-            let port = exposes.first().map(|port| port.parse::<u16>().unwrap());
+            let port = exposes.first().map(|port| {
+                let number = port.split('/').next().unwrap_or(port);
+                number.parse::<u16>().unwrap_or_else(|_| {
+                    panic!("Invalid EXPOSE port '{}' in {}", port, dockerfile_path)
+                })
+            });
             let target_port = port;
             debug!(
                 "Parsed from Dockerfile - Port: {:?}, Target Port: {:?}, Exposes: {:?}",
@@ -251,6 +411,11 @@ impl DockerImage {
             dev_ignore_image: false,
             silence_output: false,
             was_recently_rebuild: false,
+            status_board: None,
+            profiler: None,
+            debug: false,
+            named_volumes: Vec::new(),
+            crate_dependencies: None,
         })
     }
 
@@ -304,6 +469,26 @@ impl DockerImage {
             .generate_build_context(self.toolchain.clone(), secrets)
     }
 
+    /// The context the build script and artefacts are rendered with, including the
+    /// component's secrets.
+    async fn build_context(&self) -> BuildContext {
+        let (product_name, component_name, environment) = {
+            let spec = self.spec.lock().unwrap();
+            (
+                spec.product_name.clone(),
+                spec.component_name.clone(),
+                spec.config.environment().to_string(),
+            )
+        };
+        let vault = self.vault.as_ref().expect("Vault not set");
+        let secrets = get_secrets(vault, &product_name, &component_name, &environment)
+            .await
+            .unwrap_or_default();
+        let mut ctx = self.generate_build_context(secrets);
+        ctx.debug = self.debug;
+        ctx
+    }
+
     pub fn build_script(&self, ctx: &BuildContext) -> Option<String> {
         let ret = self.spec.lock().unwrap().build_script(ctx);
 
@@ -320,6 +505,10 @@ impl DockerImage {
         self.spec.lock().unwrap().clone()
     }
 
+    pub fn spec_handle(&self) -> Arc<Mutex<ComponentBuildSpec>> {
+        self.spec.clone()
+    }
+
     pub fn component_name(&self) -> String {
         self.spec.lock().unwrap().component_name.clone()
     }
@@ -364,6 +553,7 @@ impl DockerImage {
 
         debug!("Launching docker image: {}", self.identifier());
         let silent = self.silence_output;
+        let status_board = self.status_board.clone();
         tokio::spawn(async move {
             let spec = task.spec.lock().unwrap().clone();
             let env_guard = DockerImage::create_cross_compile_guard(&spec.build_type, &toolchain);
@@ -381,8 +571,25 @@ impl DockerImage {
                 "--name".to_string(),
                 spec.docker_local_name(),
                 "--network".to_string(),
-                network_name,
             ];
+            if spec.extra_networks.is_empty() {
+                args.push(network_name);
+                for alias in &spec.network_aliases {
+                    args.push("--network-alias".to_string());
+                    args.push(alias.clone());
+                }
+            } else {
+                // With several networks, the aliases have to name the network they are for
+                let mut network = format!("name={}", network_name);
+                for alias in &spec.network_aliases {
+                    network.push_str(&format!(",alias={}", alias));
+                }
+                args.push(network);
+                for extra_network in &spec.extra_networks {
+                    args.push("--network".to_string());
+                    args.push(extra_network.clone());
+                }
+            }
 
             if let Some(entrypoint) = entrypoint {
                 args.push("--entrypoint".to_string());
@@ -394,6 +601,17 @@ impl DockerImage {
                     args.push(format!("{}:{}", port, target_port));
                 }
             }
+            if task.debug {
+                // gdbserver attaches with ptrace, which Docker's default seccomp profile denies
+                args.extend([
+                    "--cap-add".to_string(),
+                    "SYS_PTRACE".to_string(),
+                    "--security-opt".to_string(),
+                    "seccomp=unconfined".to_string(),
+                    "-p".to_string(),
+                    format!("{0}:{0}", spec.debug_port),
+                ]);
+            }
 
             if let Some(env_vars) = &spec.env {
                 for (key, value) in env_vars {
@@ -412,12 +630,22 @@ impl DockerImage {
                 args.push(format!("{}={}", key, value));
             }
 
+            // Merged last, so developers can override any other value
+            for (key, value) in &spec.dotenv_local {
+                args.push("-e".to_string());
+                args.push(format!("{}={}", key, value));
+            }
+
             if let Some(volumes) = &spec.volumes {
                 for (host_path, container_path) in volumes {
                     args.push("-v".to_string());
                     args.push(format!("{}:{}", host_path, container_path));
                 }
             }
+            for (volume, container_path) in &task.named_volumes {
+                args.push("-v".to_string());
+                args.push(format!("{}:{}", volume, container_path));
+            }
 
             for arg in &spec.docker_extra_run_args {
                 args.push(arg.clone());
@@ -433,11 +661,7 @@ impl DockerImage {
                 spec.component_name,
                 args.join(" ")
             );
-            let mut child_process_result = Command::new(toolchain.docker())
-                .args(args)
-                .stdout(std::process::Stdio::piped())
-                .stderr(std::process::Stdio::piped())
-                .spawn();
+            let mut child_process_result = spawn_command(toolchain.docker(), &args);
 
             let _ = status_sender.send(Status::InProgress);
             match child_process_result {
@@ -462,6 +686,16 @@ impl DockerImage {
                     let lines = Arc::new(Mutex::new(Vec::new()));
                     let lines_clone = lines.clone();
                     let formatted_label_clone = formatted_label.clone();
+                    let component_name = spec.component_name.clone();
+
+                    if task.debug {
+                        tokio::spawn(start_gdbserver(
+                            toolchain.docker().to_string(),
+                            spec.docker_local_name(),
+                            spec.component_name.clone(),
+                            spec.debug_port,
+                        ));
+                    }
 
                     // TODO: Make startupcompleted depend on observed output
                     let _ = status_sender.send(Status::StartupCompleted);
@@ -472,6 +706,9 @@ impl DockerImage {
                                     let mut lines = lines_clone.lock().unwrap();
                                     lines.push(line.trim_end().to_string());
                                     let clean_line = line.trim_end().replace(['\r', '\n'], "");
+                                    if let Some(status_board) = &status_board {
+                                        status_board.push_log(&component_name, &clean_line);
+                                    }
                                     if !silent {
                                         println!("{} |   {}", formatted_label_clone, clean_line);
                                         std::io::stdout().flush().unwrap();
@@ -648,53 +885,238 @@ impl DockerImage {
         self.clean().await;
     }
 
-    pub async fn push(&self) -> Result<(), String> {
+    /// Removes the locally built image, so the next build starts from scratch.
+    pub async fn remove_image(&self) {
+        let toolchain = match &self.toolchain {
+            Some(toolchain) => toolchain.clone(),
+            None => {
+                error!("Cannot remove docker image without a toolchain");
+                return;
+            }
+        };
+        let tag = self.tagged_image_name();
+        let check_args = vec!["images", "-q", &tag];
+        match run_command("check".white().bold(), toolchain.docker(), check_args).await {
+            Ok(output) if !output.trim().is_empty() => {
+                match run_command(
+                    "rmi".white().bold(),
+                    toolchain.docker(),
+                    vec!["rmi", "-f", &tag],
+                )
+                .await
+                {
+                    Ok(_) => info!("Removed Docker image {}", tag),
+                    Err(e) => warn!("Failed to remove Docker image {}: {}", tag, e),
+                }
+            }
+            Ok(_) => trace!("No image found for {}. Skipping removal.", tag),
+            Err(e) => warn!("Failed to check for image {}: {}", tag, e),
+        }
+    }
+
+    #[tracing::instrument(skip_all, fields(component = %self.component_name()))]
+    /// Pushes the image, unless `registry` already has its content, e.g. as only the tag
+    /// changed since the last push. The registry then only gets the new tag.
+    pub async fn push(&self, registry: Option<&RegistryClient>) -> Result<Pushed, String> {
         let toolchain = match &self.toolchain {
             Some(toolchain) => toolchain.clone(),
             None => panic!("Cannot launch docker image without a toolchain"),
         };
 
+        let Some(docker_tag) = self.tag_for_registry(&toolchain).await? else {
+            return Ok(Pushed::Skipped);
+        };
+        if let Some(registry) = registry {
+            match self
+                .tag_in_registry(&toolchain, registry, &docker_tag)
+                .await
+            {
+                Ok(true) => return Ok(Pushed::Unchanged),
+                Ok(false) => (),
+                Err(e) => debug!(
+                    "Pushing {} without checking the registry: {}",
+                    docker_tag, e
+                ),
+            }
+        }
+
+        run_command(
+            "push".white().bold(),
+            toolchain.docker(),
+            vec!["push", &docker_tag],
+        )
+        .await
+        .map(|_| Pushed::Uploaded)
+    }
+
+    /// Tags the manifest of the image in the registry with the image's tag, if the
+    /// registry still has the digest docker recorded when the image was last pushed or
+    /// pulled. Returns false when it does not, so the image needs a push.
+    async fn tag_in_registry(
+        &self,
+        toolchain: &ToolchainContext,
+        registry: &RegistryClient,
+        docker_tag: &str,
+    ) -> Result<bool, String> {
+        let (repository, tag) = docker_tag
+            .rsplit_once(':')
+            .ok_or_else(|| format!("{} has no tag", docker_tag))?;
+        let repo_digests = run_command(
+            "check".white().bold(),
+            toolchain.docker(),
+            vec![
+                "image",
+                "inspect",
+                "--format",
+                "{{json .RepoDigests}}",
+                docker_tag,
+            ],
+        )
+        .await?;
+        let repo_digests = serde_json::from_str::<Vec<String>>(repo_digests.trim())
+            .map_err(|e| format!("Unexpected digests of {}: {}", docker_tag, e))?;
+        let prefix = format!("{}@", repository);
+        let Some(digest) = repo_digests
+            .iter()
+            .find_map(|repo_digest| repo_digest.strip_prefix(&prefix))
+        else {
+            return Ok(false);
+        };
+        // It may have been deleted since, e.g. by `rush gc --remote`
+        if registry.digest(&self.image_name, digest).await?.is_none() {
+            return Ok(false);
+        }
+        if registry.digest(&self.image_name, tag).await?.as_deref() != Some(digest) {
+            registry.tag(&self.image_name, digest, tag).await?;
+        }
+        trace!("{} is in the registry as {}", docker_tag, digest);
+        Ok(true)
+    }
+
+    /// Tags the image with the registry, as the manifests refer to it, unless it is not
+    /// deployed to Kubernetes. Returns the tag.
+    async fn tag_for_registry(
+        &self,
+        toolchain: &ToolchainContext,
+    ) -> Result<Option<String>, String> {
         let spec = self.spec.lock().unwrap().clone();
         // Nothing to do for components that does not have a k8s
         if spec.k8s.is_none() || spec.build_type == BuildType::PureKubernetes {
-            return Ok(());
+            return Ok(None);
         }
         if let BuildType::KubernetesInstallation { .. } = spec.build_type {
-            return Ok(());
+            return Ok(None);
         }
 
         let tag = self.tagged_image_name();
         let docker_registry = self.config.docker_registry();
         let docker_tag = format!("{}/{}", docker_registry, tag);
-        match run_command(
+        run_command(
             "tag".white().bold(),
             toolchain.docker(),
             vec!["tag", &tag, &docker_tag],
         )
-        .await
-        {
-            Ok(_) => (),
-            Err(e) => return Err(e),
-        }
+        .await?;
+        Ok(Some(docker_tag))
+    }
 
-        match run_command(
-            "push".white().bold(),
-            toolchain.docker(),
-            vec!["push", &docker_tag],
-        )
-        .await
+    /// Loads the image into a local cluster under the name the manifests pull, in
+    /// place of pushing it.
+    #[tracing::instrument(skip_all, fields(component = %self.component_name()))]
+    pub async fn load_into(&self, cluster: &dyn LocalCluster) -> Result<(), String> {
+        let toolchain = match &self.toolchain {
+            Some(toolchain) => toolchain.clone(),
+            None => panic!("Cannot load docker image without a toolchain"),
+        };
+        // Pulled images are not built here
+        if self
+            .spec
+            .lock()
+            .unwrap()
+            .build_type
+            .dockerfile_path()
+            .is_none()
         {
-            Ok(_) => Ok(()),
-            Err(e) => Err(e),
+            return Ok(());
+        }
+        match self.tag_for_registry(&toolchain).await? {
+            Some(docker_tag) => cluster.load_image(&docker_tag).await.map(|_| ()),
+            None => Ok(()),
         }
     }
 
+    #[tracing::instrument(skip_all, fields(component = %self.component_name()))]
     pub async fn build_and_push(&self) -> Result<(), String> {
         self.build().await?;
-        self.push().await
+        self.push(None).await.map(|_| ())
+    }
+
+    /// The absolute directories of the Dockerfile and of the build context, or None for
+    /// components without a Dockerfile. Relative paths are taken from the working
+    /// directory, which is the product directory.
+    fn context_directories(&self, spec: &ComponentBuildSpec) -> Option<(PathBuf, PathBuf)> {
+        let dockerfile_path = spec.build_type.dockerfile_path()?;
+        let dockerfile_path = std::fs::canonicalize(dockerfile_path).unwrap_or_else(|_| {
+            panic!(
+                "Failed to get absolute dockerfile path for {:?}",
+                dockerfile_path
+            )
+        });
+        let dockerfile_dir = dockerfile_path
+            .parent()
+            .expect("Failed to get dockerfile directory")
+            .to_path_buf();
+
+        let context_dir = match &self.context_dir {
+            Some(context_dir) => std::fs::canonicalize(dockerfile_dir.join(context_dir))
+                .expect("Failed to get absolute context directory path"),
+            None => dockerfile_dir.clone(),
+        };
+        Some((dockerfile_dir, context_dir))
+    }
+
+    /// A hash of everything the image is built from, for [`warm_start::WarmStart`]: the build
+    /// settings and context, and the files of the Dockerfile and context directories and
+    /// of `watch`. None for components without a Dockerfile.
+    pub async fn fingerprint(&self) -> Option<String> {
+        let spec = self.spec();
+        let (dockerfile_dir, context_dir) = self.context_directories(&spec)?;
+        let mut ctx = self.build_context().await;
+        // The tag changes with every commit, while the image may not
+        ctx.image_name = String::new();
+        let settings = serde_json::json!({
+            "context": serde_json::to_value(&ctx).ok()?,
+            "build": spec.build,
+            "artefacts": spec.artefacts,
+            "nix_flake": spec.nix_flake,
+            "context_dir": self.context_dir,
+        });
+        let mut directories = vec![dockerfile_dir, context_dir];
+        if let Some(watch) = &spec.watch {
+            directories.extend(watch.directories());
+        }
+        directories.sort();
+        directories.dedup();
+        Some(warm_start::fingerprint(&settings.to_string(), &directories))
+    }
+
+    /// Where a change can make [`is_any_file_in_context`](Self::is_any_file_in_context)
+    /// true: the Dockerfile and context directories and what the `watch` globs can match.
+    pub fn watched_paths(&self) -> Vec<PathBuf> {
+        let spec = self.spec.lock().unwrap();
+        let mut paths = spec
+            .watch
+            .as_ref()
+            .map(|watch| watch.directories())
+            .unwrap_or_default();
+        if let Some((dockerfile_dir, context_dir)) = self.context_directories(&spec) {
+            paths.push(dockerfile_dir);
+            paths.push(context_dir);
+        }
+        paths
     }
 
-    pub fn is_any_file_in_context(&self, file_paths: &Vec<PathBuf>) -> bool {
+    pub fn is_any_file_in_context(&mut self, file_paths: &Vec<PathBuf>) -> bool {
         let spec = self.spec.lock().unwrap();
 
         if let Some(watch) = &spec.watch {
@@ -705,57 +1127,70 @@ impl DockerImage {
             }
         }
 
-        let dockerfile_path = match &spec.build_type {
-            BuildType::TrunkWasm {
-                dockerfile_path, ..
-            }
-            | BuildType::DixiousWasm {
-                dockerfile_path, ..
-            }
-            | BuildType::RustBinary {
-                dockerfile_path, ..
-            }
-            | BuildType::Book {
-                dockerfile_path, ..
-            }
-            | BuildType::Zola {
-                dockerfile_path, ..
-            }
-            | BuildType::Script {
-                dockerfile_path, ..
-            }
-            | BuildType::Ingress {
-                dockerfile_path, ..
-            } => std::fs::canonicalize(dockerfile_path).expect(
-                format!(
-                    "Failed to get absolute dockerfile path for {:?}",
-                    dockerfile_path
-                )
-                .as_str(),
-            ),
-            _ => return false, // If there's no Dockerfile, the files can't be in context
+        // If there's no Dockerfile, the files can't be in context
+        let Some((dockerfile_dir, context_dir)) = self.context_directories(&spec) else {
+            return false;
         };
 
-        let dockerfile_dir = dockerfile_path
-            .parent()
-            .expect("Failed to get dockerfile directory");
-
-        let context_dir = match &self.context_dir {
-            Some(context_dir) => std::fs::canonicalize(dockerfile_dir.join(context_dir))
-                .expect("Failed to get absolute context directory path"),
-            None => dockerfile_dir.to_path_buf(),
+        // Rust components only rebuild for changes to the crates they depend on, rather
+        // than for anything in the context directory, e.g. a crate of a sibling component
+        let crate_location = match &spec.build_type {
+            BuildType::TrunkWasm { location, .. }
+            | BuildType::DixiousWasm { location, .. }
+            | BuildType::RustBinary { location, .. } => Some(location.clone()),
+            _ => None,
         };
+        drop(spec);
+        let crate_dependencies = crate_location
+            .and_then(|location| self.crate_dependencies(Path::new(&location), file_paths));
 
         file_paths.iter().any(|file_path| {
             if let Ok(absolute_file_path) = std::fs::canonicalize(file_path) {
-                absolute_file_path.starts_with(&context_dir)
-                    || absolute_file_path.starts_with(dockerfile_dir)
+                let affects_crates = crate_dependencies
+                    .as_ref()
+                    .and_then(|crates| crates.affects(&absolute_file_path));
+                match affects_crates {
+                    Some(affects) => affects,
+                    None => {
+                        absolute_file_path.starts_with(&context_dir)
+                            || absolute_file_path.starts_with(&dockerfile_dir)
+                    }
+                }
             } else {
                 false
             }
         })
     }
 
+    /// The crates of the Rust component in `location`. `cargo metadata` runs for the
+    /// first change and again once a Cargo.toml or Cargo.lock changed, rather than for
+    /// every change.
+    fn crate_dependencies(
+        &mut self,
+        location: &Path,
+        file_paths: &[PathBuf],
+    ) -> Option<Arc<CrateDependencies>> {
+        let manifest_changed = file_paths.iter().any(|path| {
+            path.file_name()
+                .is_some_and(|name| name == "Cargo.toml" || name == "Cargo.lock")
+        });
+        if self.crate_dependencies.is_none() || manifest_changed {
+            self.crate_dependencies = Some(CrateDependencies::load(location).map(Arc::new));
+        }
+        match self.crate_dependencies.as_ref()? {
+            Ok(crate_dependencies) => Some(crate_dependencies.clone()),
+            Err(e) => {
+                debug!(
+                    "Watching the whole context of {}: {}",
+                    self.component_name(),
+                    e
+                );
+                None
+            }
+        }
+    }
+
+    #[tracing::instrument(skip_all, fields(component = %self.component_name()))]
     pub async fn build(&self) -> Result<(), String> {
         let toolchain = match &self.toolchain {
             Some(toolchain) => toolchain.clone(),
@@ -792,7 +1227,7 @@ impl DockerImage {
             None => ".".to_string(),
         };
 
-        let _env_guard = DockerImage::create_cross_compile_guard(
+        let env_guard = DockerImage::create_cross_compile_guard(
             &self.spec.lock().unwrap().build_type,
             &toolchain,
         );
@@ -807,20 +1242,7 @@ impl DockerImage {
             .to_str()
             .expect("Failed to convert dockerfile name to str");
 
-        let secrets = self
-            .vault
-            .as_ref()
-            .expect("Vault not set")
-            .lock()
-            .unwrap()
-            .get(
-                &spec.product_name,
-                &spec.component_name,
-                &spec.config.environment().to_string(),
-            )
-            .await
-            .unwrap_or_default();
-        let ctx = self.generate_build_context(secrets);
+        let ctx = self.build_context().await;
 
         // Creating artefacts if needed
         let artefacts = spec.build_artefacts();
@@ -830,15 +1252,27 @@ impl DockerImage {
                 .expect("Failed to create artefact output directory");
 
             let _dir_raii = Directory::chpath(artefact_output_dir);
+            let started = std::time::Instant::now();
             for (_k, artefact) in artefacts {
                 artefact.render_to_file(&ctx);
             }
+            self.profile(ProfileStep::Artefacts, started);
         }
 
         // Cross compiling if needed
         if let Some(build_command) = &self.build_script(&ctx) {
             let start_time = std::time::Instant::now();
-            match run_command_in_window(10, "build", "sh", vec!["-c", build_command]).await {
+            // A nix dev shell provides the toolchain the same way on every machine
+            let (command, args) = match &spec.nix_flake {
+                Some(flake) => (
+                    "nix",
+                    vec!["develop", flake, "--command", "sh", "-c", build_command],
+                ),
+                None => ("sh", vec!["-c", build_command]),
+            };
+            let result = run_command_in_window(10, "build", command, args).await;
+            self.profile(ProfileStep::BuildScript, start_time);
+            match result {
                 Ok(_) => {
                     let duration = start_time.elapsed();
                     info!("Build command completed in {:?}", duration);
@@ -854,10 +1288,64 @@ impl DockerImage {
         let _dir_raii = Directory::chpath(dockerfile_dir);
 
         let tag = self.tagged_image_name();
-        let build_command_args = vec!["build", "-t", &tag, "-f", dockerfile_name, &context_dir];
-        match run_command_in_window(10, "docker", toolchain.docker(), build_command_args).await {
+        // Dockerfiles copy the binary from target/${RUST_TARGET}/release
+        let rust_target = format!("RUST_TARGET={}", ctx.rust_target);
+        let build_command_args = vec![
+            "build",
+            "--platform",
+            env_guard.target(),
+            "--build-arg",
+            &rust_target,
+            "-t",
+            &tag,
+            "-f",
+            dockerfile_name,
+            &context_dir,
+        ];
+        let started = std::time::Instant::now();
+        let result =
+            run_command_in_window(10, "docker", toolchain.docker(), build_command_args).await;
+        self.profile(ProfileStep::DockerBuild, started);
+        match result {
             Ok(_) => Ok(()),
             Err(e) => Err(e),
         }
     }
 }
+
+/// Starts a gdbserver in the container of a component with `debug`, once it runs. The
+/// server does not attach by itself, so the component keeps running until a debugger
+/// attaches to it, e.g. with `target extended-remote localhost:2345` and `attach 1`.
+async fn start_gdbserver(docker: String, container: String, component_name: String, port: u16) {
+    let address = format!("0.0.0.0:{}", port);
+    for _ in 0..GDBSERVER_ATTEMPTS {
+        let output = Command::new(&docker)
+            .args(["exec", "-d", &container, "gdbserver", "--multi", &address])
+            .output()
+            .await;
+        match output {
+            Ok(output) if output.status.success() => {
+                crate::progress::suspend(|| {
+                    println!(
+                        "{} debugger can attach to localhost:{} (gdbserver, the binary is process 1)",
+                        component_name.bold(),
+                        port
+                    )
+                });
+                return;
+            }
+            Ok(output) if String::from_utf8_lossy(&output.stderr).contains("not found") => {
+                crate::progress::suspend(|| {
+                    eprintln!(
+                        "{} has debug set, but its image has no gdbserver. Install it in the Dockerfile, e.g. with apt-get install gdbserver",
+                        component_name.bold()
+                    )
+                });
+                return;
+            }
+            // The container is still starting
+            _ => tokio::time::sleep(std::time::Duration::from_millis(500)).await,
+        }
+    }
+    warn!("Could not start the gdbserver of {}", component_name);
+}
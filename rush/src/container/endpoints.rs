@@ -0,0 +1,113 @@
+use colored::Colorize;
+use reqwest::header::HOST;
+use std::time::{Duration, Instant};
+
+/// How long a component has to start serving before it is reported as not serving.
+const READINESS_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// A URL a dev component serves on, through the ingress or its own port.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Endpoint {
+    pub component: String,
+    /// The URL as a browser opens it, e.g. `http://helloworld.wonop.io.localhost:9000/api`
+    pub url: String,
+    /// Where the URL is requested, as the domain need not resolve to this machine
+    address: String,
+    host: Option<String>,
+}
+
+impl Endpoint {
+    /// `path` of `domain`, served by the ingress on `port` of this machine.
+    pub fn through_ingress(component: &str, domain: &str, port: u16, path: &str) -> Self {
+        Self {
+            component: component.to_string(),
+            url: format!("http://{}:{}{}", domain, port, path),
+            address: format!("http://127.0.0.1:{}{}", port, path),
+            host: Some(domain.to_string()),
+        }
+    }
+
+    /// `path` served by the component itself on `port` of this machine.
+    pub fn on_port(component: &str, port: u16, path: &str) -> Self {
+        let url = format!("http://localhost:{}{}", port, path);
+        Self {
+            component: component.to_string(),
+            address: url.clone(),
+            url,
+            host: None,
+        }
+    }
+
+    /// Requests the URL until it answers without a server error, or until the timeout,
+    /// returning the last answer.
+    pub async fn wait_until_serving(&self, client: &reqwest::Client) -> EndpointStatus {
+        let deadline = Instant::now() + READINESS_TIMEOUT;
+        loop {
+            let mut request = client.get(&self.address).timeout(Duration::from_secs(5));
+            if let Some(host) = &self.host {
+                request = request.header(HOST, host);
+            }
+            let status = match request.send().await {
+                Ok(response) if response.status().is_server_error() => {
+                    EndpointStatus::Failing(response.status().to_string())
+                }
+                Ok(response) => return EndpointStatus::Serving(response.status().to_string()),
+                Err(e) if e.is_timeout() => EndpointStatus::Unreachable("timed out".to_string()),
+                Err(_) => EndpointStatus::Unreachable("unreachable".to_string()),
+            };
+            if Instant::now() >= deadline {
+                return status;
+            }
+            tokio::time::sleep(Duration::from_millis(500)).await;
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EndpointStatus {
+    /// Answered with a success, redirect or client error, e.g. `200 OK`
+    Serving(String),
+    /// Answered with a server error, e.g. `502 Bad Gateway`
+    Failing(String),
+    /// Did not answer
+    Unreachable(String),
+}
+
+/// Waits for every endpoint to serve and prints which do, with their URLs.
+pub async fn report_endpoints(endpoints: Vec<Endpoint>) {
+    let client = reqwest::Client::new();
+    let statuses = futures::future::join_all(
+        endpoints
+            .iter()
+            .map(|endpoint| endpoint.wait_until_serving(&client)),
+    )
+    .await;
+
+    let component_width = endpoints
+        .iter()
+        .map(|endpoint| endpoint.component.len())
+        .max()
+        .unwrap_or_default();
+    let url_width = endpoints
+        .iter()
+        .map(|endpoint| endpoint.url.len())
+        .max()
+        .unwrap_or_default();
+    println!("\n{}", "Endpoints".bold());
+    for (endpoint, status) in endpoints.iter().zip(statuses) {
+        let status = match status {
+            EndpointStatus::Serving(status) => status.green().bold(),
+            EndpointStatus::Failing(status) | EndpointStatus::Unreachable(status) => {
+                status.red().bold()
+            }
+        };
+        println!(
+            "  {:<component_width$}  {:<url_width$}  {}",
+            endpoint.component,
+            endpoint.url,
+            status,
+            component_width = component_width,
+            url_width = url_width
+        );
+    }
+}
@@ -0,0 +1,103 @@
+use chrono::Utc;
+use log::warn;
+use serde::Serialize;
+use std::fs::File;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+/// A lifecycle event of the reactor, written as one JSON object per line by `--events`:
+///
+/// ```json
+/// {"timestamp":"2024-05-02T09:12:44.120Z","product":"helloworld.wonop.io","event":"build_finished","component":"backend","image":"backend:abc123","push":false,"success":true,"duration_ms":5120}
+/// ```
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event<'a> {
+    BuildStarted {
+        component: &'a str,
+        image: &'a str,
+        push: bool,
+    },
+    BuildFinished {
+        component: &'a str,
+        image: &'a str,
+        push: bool,
+        success: bool,
+        duration_ms: u128,
+    },
+    ImageLaunched {
+        component: &'a str,
+        image: &'a str,
+    },
+    StatusChanged {
+        component: &'a str,
+        status: &'a str,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        exit_code: Option<i32>,
+    },
+    DeployApplied {
+        context: &'a str,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        component: Option<&'a str>,
+    },
+}
+
+#[derive(Serialize)]
+struct Record<'a> {
+    timestamp: String,
+    product: &'a str,
+    #[serde(flatten)]
+    event: Event<'a>,
+}
+
+/// Where events are written to. Disabled unless `--events` is given; clones share the
+/// same output so several reactors can write to one stream.
+#[derive(Clone, Default)]
+pub struct EventSink {
+    output: Option<Arc<Mutex<Box<dyn Write + Send>>>>,
+}
+
+impl EventSink {
+    /// Opens `target` for writing events, `-` being stdout.
+    pub fn open(target: &str) -> Result<Self, String> {
+        let output: Box<dyn Write + Send> = match target {
+            "-" => Box::new(std::io::stdout()),
+            path => Box::new(
+                File::create(path)
+                    .map_err(|e| format!("Could not open event stream {}: {}", path, e))?,
+            ),
+        };
+        Ok(Self {
+            output: Some(Arc::new(Mutex::new(output))),
+        })
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.output.is_some()
+    }
+
+    /// Writes `event` as a line of JSON. Failures are logged rather than interrupting
+    /// the operation the event is about.
+    pub fn emit(&self, product: &str, event: Event) {
+        let output = match &self.output {
+            Some(output) => output,
+            None => return,
+        };
+        let record = Record {
+            timestamp: Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+            product,
+            event,
+        };
+        let line = match serde_json::to_string(&record) {
+            Ok(line) => line,
+            Err(e) => {
+                warn!("Could not serialize event: {}", e);
+                return;
+            }
+        };
+        let mut output = output.lock().unwrap();
+        if let Err(e) = writeln!(output, "{}", line).and_then(|_| output.flush()) {
+            warn!("Could not write event: {}", e);
+        }
+    }
+}
@@ -0,0 +1,225 @@
+use super::registry::RegistryClient;
+use colored::Colorize;
+use log::{debug, warn};
+use std::collections::HashSet;
+use tokio::process::Command;
+
+/// An image built by rush, with the tag of the current build.
+pub struct BuiltImage {
+    pub image_name: String,
+    pub tag: String,
+}
+
+/// Removes the local images of the given names except for the current tag, the `keep`
+/// most recent ones and those in `in_use`, a set of `image_name:tag`. Returns how many
+/// were (or, with `dry_run`, would be) removed.
+pub async fn remove_local_images(
+    docker: &str,
+    docker_registry: &str,
+    images: &[BuiltImage],
+    keep: usize,
+    in_use: &HashSet<String>,
+    dry_run: bool,
+) -> Result<usize, String> {
+    let output = Command::new(docker)
+        .args([
+            "images",
+            "--format",
+            "{{.Repository}}\t{{.Tag}}\t{{.CreatedAt}}",
+        ])
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run {}: {}", docker, e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "Failed to list the docker images: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    let listing = String::from_utf8_lossy(&output.stdout).to_string();
+
+    let mut removed = 0;
+    for image in images {
+        // Pushed images are also tagged with the registry in front of the name
+        let pushed_name = format!("{}/{}", docker_registry, image.image_name);
+        let mut references = Vec::new();
+        for line in listing.lines() {
+            let mut fields = line.splitn(3, '\t');
+            let (Some(repository), Some(tag), Some(created)) =
+                (fields.next(), fields.next(), fields.next())
+            else {
+                continue;
+            };
+            if (repository == image.image_name || repository == pushed_name) && tag != "<none>" {
+                references.push((created.to_string(), repository.to_string(), tag.to_string()));
+            }
+        }
+        // Newest first; docker prints the creation time as `2024-05-01 10:00:00 +0200 CEST`
+        references.sort_by(|a, b| b.0.cmp(&a.0));
+        let kept = kept_tags(
+            image,
+            references.iter().map(|(_, _, tag)| tag.as_str()),
+            keep,
+            in_use,
+        );
+
+        for (_, repository, tag) in &references {
+            if kept.contains(tag.as_str()) {
+                continue;
+            }
+            let reference = format!("{}:{}", repository, tag);
+            if dry_run {
+                println!("{} {}", "Would remove".yellow(), reference);
+                removed += 1;
+                continue;
+            }
+            // Without -f, so images used by containers are left alone
+            let output = Command::new(docker)
+                .args(["rmi", &reference])
+                .output()
+                .await
+                .map_err(|e| format!("Failed to run {}: {}", docker, e))?;
+            if output.status.success() {
+                println!("{} {}", "Removed".green(), reference);
+                removed += 1;
+            } else {
+                warn!(
+                    "Failed to remove {}: {}",
+                    reference,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                );
+            }
+        }
+    }
+    Ok(removed)
+}
+
+/// Deletes the pushed tags of the images from the registry, keeping the same tags as
+/// [`remove_local_images`]. Manifests are deleted by digest, which removes every tag
+/// pointing at them, so a digest is only deleted when none of its tags is kept.
+pub async fn prune_registry(
+    registry: &RegistryClient,
+    images: &[BuiltImage],
+    keep: usize,
+    in_use: &HashSet<String>,
+    dry_run: bool,
+) -> Result<usize, String> {
+    let mut removed = 0;
+    for image in images {
+        // Tags of unknown age come first, so they count as recent and are kept
+        let tags = registry.remote_tags(&image.image_name).await?;
+        let kept = kept_tags(
+            image,
+            tags.iter().map(|remote| remote.tag.as_str()),
+            keep,
+            in_use,
+        );
+
+        let kept_digests = tags
+            .iter()
+            .filter(|remote| kept.contains(remote.tag.as_str()))
+            .map(|remote| remote.digest.clone())
+            .collect::<HashSet<_>>();
+        // Newest first, with the tags sharing a manifest together
+        let mut stale: Vec<(String, Vec<String>)> = Vec::new();
+        for remote in &tags {
+            if kept_digests.contains(&remote.digest) {
+                continue;
+            }
+            match stale
+                .iter_mut()
+                .find(|(digest, _)| *digest == remote.digest)
+            {
+                Some((_, stale_tags)) => stale_tags.push(remote.tag.clone()),
+                None => stale.push((remote.digest.clone(), vec![remote.tag.clone()])),
+            }
+        }
+
+        for (digest, tags) in stale {
+            let references = tags
+                .iter()
+                .map(|tag| format!("{}/{}:{}", registry.host(), image.image_name, tag))
+                .collect::<Vec<_>>()
+                .join(", ");
+            if dry_run {
+                println!("{} {}", "Would delete".yellow(), references);
+            } else {
+                registry.delete(&image.image_name, &digest).await?;
+                println!("{} {}", "Deleted".green(), references);
+            }
+            removed += tags.len();
+        }
+    }
+    Ok(removed)
+}
+
+/// The images the workloads of the clusters run, as `image_name:tag`. Fails when a
+/// cluster cannot be asked, as nothing could be removed safely then.
+pub async fn images_in_clusters(
+    kubectl: &str,
+    kube_contexts: &[String],
+) -> Result<HashSet<String>, String> {
+    let mut in_use = HashSet::new();
+    for context in kube_contexts {
+        debug!("Listing the images in use in {}", context);
+        let output = Command::new(kubectl)
+            .args([
+                "--context",
+                context,
+                "get",
+                "pods,replicasets,deployments,statefulsets,daemonsets,jobs,cronjobs",
+                "--all-namespaces",
+                "-o",
+                "jsonpath={..image}",
+            ])
+            .output()
+            .await
+            .map_err(|e| format!("Failed to run {}: {}", kubectl, e))?;
+        if !output.status.success() {
+            return Err(format!(
+                "Failed to list the images in use in {}: {}",
+                context,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        in_use.extend(
+            String::from_utf8_lossy(&output.stdout)
+                .split_whitespace()
+                .map(image_key),
+        );
+    }
+    Ok(in_use)
+}
+
+/// `image_name:tag` of an image reference, without its registry and digest.
+pub fn image_key(reference: &str) -> String {
+    let reference = reference.split('@').next().unwrap_or(reference);
+    reference
+        .rsplit('/')
+        .next()
+        .unwrap_or(reference)
+        .to_string()
+}
+
+/// The current tag, those in use and the `keep` most recent of `tags`, newest first.
+fn kept_tags<'a>(
+    image: &BuiltImage,
+    tags: impl Iterator<Item = &'a str>,
+    keep: usize,
+    in_use: &HashSet<String>,
+) -> HashSet<&'a str> {
+    let mut recent = HashSet::new();
+    let mut kept = HashSet::new();
+    for tag in tags {
+        if recent.len() < keep {
+            recent.insert(tag);
+        }
+        if recent.contains(tag)
+            || tag == image.tag
+            || in_use.contains(&format!("{}:{}", image.image_name, tag))
+        {
+            kept.insert(tag);
+        }
+    }
+    kept
+}
@@ -0,0 +1,192 @@
+use super::docker::DockerImage;
+use crate::builder::BuildType;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(Debug, Serialize)]
+pub struct GraphNode {
+    pub component_name: String,
+    pub build_type: String,
+    pub priority: u64,
+    /// Host and port the component is redirected to in dev mode
+    pub redirect: Option<String>,
+    pub ignored_in_devmode: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GraphEdge {
+    pub from: String,
+    pub to: String,
+    /// `depends_on` or `routes` for the components behind an ingress
+    pub kind: String,
+}
+
+/// The component dependency graph of a product, as printed by `rush describe graph`.
+#[derive(Debug, Serialize)]
+pub struct DependencyGraph {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+}
+
+impl DependencyGraph {
+    pub fn from_images(
+        images: &[DockerImage],
+        redirected_components: &HashMap<String, (String, u16)>,
+    ) -> Self {
+        let mut nodes = Vec::new();
+        let mut edges = Vec::new();
+        for image in images {
+            let spec = image.spec();
+            let build_type = match &spec.build_type {
+                BuildType::TrunkWasm { .. } => "TrunkWasm",
+                BuildType::DixiousWasm { .. } => "DixiousWasm",
+                BuildType::RustBinary { .. } => "RustBinary",
+                BuildType::Script { .. } => "Script",
+                BuildType::Zola { .. } => "Zola",
+                BuildType::Book { .. } => "Book",
+                BuildType::Ingress { .. } => "Ingress",
+                BuildType::PureDockerImage { .. } => "Image",
+                BuildType::PureKubernetes => "K8sOnly",
+                BuildType::KubernetesInstallation { .. } => "K8sInstall",
+            };
+            nodes.push(GraphNode {
+                component_name: spec.component_name.clone(),
+                build_type: build_type.to_string(),
+                priority: spec.priority,
+                redirect: redirected_components
+                    .get(&spec.component_name)
+                    .map(|(host, port)| format!("{}:{}", host, port)),
+                ignored_in_devmode: image.should_ignore_in_devmode(),
+            });
+
+            for dependency in &spec.depends_on {
+                edges.push(GraphEdge {
+                    from: spec.component_name.clone(),
+                    to: dependency.clone(),
+                    kind: "depends_on".to_string(),
+                });
+            }
+            if let BuildType::Ingress { components, .. } = &spec.build_type {
+                for component in components {
+                    edges.push(GraphEdge {
+                        from: spec.component_name.clone(),
+                        to: component.clone(),
+                        kind: "routes".to_string(),
+                    });
+                }
+            }
+        }
+        nodes.sort_by(|a, b| (a.priority, &a.component_name).cmp(&(b.priority, &b.component_name)));
+
+        DependencyGraph { nodes, edges }
+    }
+
+    fn label(node: &GraphNode) -> String {
+        let mut label = format!(
+            "{}\\n{} (priority {})",
+            node.component_name, node.build_type, node.priority
+        );
+        if let Some(redirect) = &node.redirect {
+            label.push_str(&format!("\\nredirected to {}", redirect));
+        } else if node.ignored_in_devmode {
+            label.push_str("\\nignored in dev");
+        }
+        label
+    }
+
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph components {\n    rankdir=LR;\n");
+        for node in &self.nodes {
+            let style = if node.ignored_in_devmode {
+                ", style=dashed"
+            } else {
+                ""
+            };
+            dot.push_str(&format!(
+                "    \"{}\" [shape=box, label=\"{}\"{}];\n",
+                node.component_name,
+                Self::label(node),
+                style
+            ));
+        }
+        for edge in &self.edges {
+            let style = if edge.kind == "routes" {
+                " [style=dashed]"
+            } else {
+                ""
+            };
+            dot.push_str(&format!(
+                "    \"{}\" -> \"{}\"{};\n",
+                edge.from, edge.to, style
+            ));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    pub fn to_mermaid(&self) -> String {
+        let id = |name: &str| name.replace(|c: char| !c.is_ascii_alphanumeric(), "_");
+        let mut mermaid = String::from("graph LR\n");
+        for node in &self.nodes {
+            let label = Self::label(node).replace("\\n", "<br/>");
+            mermaid.push_str(&format!(
+                "    {}[\"{}\"]\n",
+                id(&node.component_name),
+                label
+            ));
+        }
+        for edge in &self.edges {
+            let arrow = if edge.kind == "routes" { "-.->" } else { "-->" };
+            mermaid.push_str(&format!(
+                "    {} {} {}\n",
+                id(&edge.from),
+                arrow,
+                id(&edge.to)
+            ));
+        }
+        mermaid
+    }
+}
+
+/// A cycle in the `depends_on` of the components, as the components along it back to
+/// the first, e.g. `[api, cache, api]`. Dependencies that are not components are left out.
+pub fn find_cycle(dependencies: &BTreeMap<String, Vec<String>>) -> Option<Vec<String>> {
+    // Components on the path being walked are false, those fully walked true
+    let mut walked = HashMap::new();
+    for start in dependencies.keys() {
+        if walked.contains_key(start) {
+            continue;
+        }
+        walked.insert(start, false);
+        let mut path = vec![(start, 0)];
+        while let Some((component, next)) = path.last().copied() {
+            let Some(dependency) = dependencies[component].get(next) else {
+                walked.insert(component, true);
+                path.pop();
+                continue;
+            };
+            if let Some(last) = path.last_mut() {
+                last.1 += 1;
+            }
+            match walked.get(dependency) {
+                Some(false) => {
+                    let mut cycle = path
+                        .iter()
+                        .skip_while(|(component, _)| *component != dependency)
+                        .map(|(component, _)| component.to_string())
+                        .collect::<Vec<_>>();
+                    cycle.push(dependency.clone());
+                    return Some(cycle);
+                }
+                Some(true) => (),
+                None => {
+                    if let Some((dependency, _)) = dependencies.get_key_value(dependency) {
+                        walked.insert(dependency, false);
+                        path.push((dependency, 0));
+                    }
+                }
+            }
+        }
+    }
+    None
+}
@@ -1,7 +1,33 @@
 pub mod container_reactor;
 pub mod docker;
+pub mod endpoints;
+pub mod events;
+pub mod gc;
+pub mod graph;
+pub mod notifications;
+pub mod profile;
+pub mod proxy;
+pub mod registry;
 pub mod service_spec;
+pub mod snapshots;
+pub mod stats;
 pub mod status;
+pub mod status_page;
+pub mod summary;
+pub mod volumes;
+pub mod warm_start;
 
 pub use container_reactor::ContainerReactor;
+pub use events::{Event, EventSink};
+pub use graph::{find_cycle, DependencyGraph};
+pub use notifications::DeployNotification;
+pub use profile::{BuildProfiler, ProfileStep};
+pub use proxy::DevProxy;
+pub use registry::{RegistryClient, RemoteTag};
 pub use service_spec::{ServiceSpec, ServicesSpec};
+pub use snapshots::Snapshots;
+pub use stats::{ResourceMonitor, ResourceUsage};
+pub use status_page::StatusBoard;
+pub use summary::{ExecutionSummary, Step, StepStatus};
+pub use volumes::{ProductVolumes, VolumeSpec};
+pub use warm_start::WarmStart;
@@ -0,0 +1,95 @@
+use log::{debug, warn};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// What a deploy or rollout notification reports.
+#[derive(Debug, Serialize)]
+pub struct DeployNotification {
+    pub action: String,
+    pub product: String,
+    pub environment: String,
+    pub git_hash: String,
+    pub success: bool,
+    /// Tagged image name per component
+    pub images: BTreeMap<String, String>,
+    /// Components whose image differs from the previous rollout into the environment
+    pub changed_components: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl DeployNotification {
+    /// A one line summary followed by the changed components, for chat messages.
+    pub fn text(&self) -> String {
+        let short_hash = &self.git_hash[..8.min(self.git_hash.len())];
+        let mut text = match &self.error {
+            None => format!(
+                "{} of {} to {} succeeded ({})",
+                capitalize(&self.action),
+                self.product,
+                self.environment,
+                short_hash
+            ),
+            Some(error) => format!(
+                "{} of {} to {} failed ({}): {}",
+                capitalize(&self.action),
+                self.product,
+                self.environment,
+                short_hash,
+                error.lines().next().unwrap_or_default()
+            ),
+        };
+        if self.changed_components.is_empty() {
+            text.push_str("\nNo components changed");
+        }
+        for component in &self.changed_components {
+            let image = self.images.get(component).map_or("", String::as_str);
+            text.push_str(&format!("\n• {} {}", component, image));
+        }
+        text
+    }
+
+    /// Posts the notification to each webhook. Slack and Discord get a chat message,
+    /// any other URL the full notification as JSON along with the message as `text`.
+    /// Failures are logged rather than failing the deploy the notification is about.
+    pub async fn send(&self, webhooks: &[String]) {
+        let client = reqwest::Client::new();
+        for webhook in webhooks {
+            let body = if webhook.contains("hooks.slack.com") {
+                serde_json::json!({ "text": self.text() })
+            } else if webhook.contains("discord.com/api/webhooks")
+                || webhook.contains("discordapp.com/api/webhooks")
+            {
+                serde_json::json!({ "content": self.text() })
+            } else {
+                let mut body = serde_json::to_value(self).unwrap_or_default();
+                body["text"] = self.text().into();
+                body
+            };
+            let result = client
+                .post(webhook)
+                .timeout(std::time::Duration::from_secs(10))
+                .json(&body)
+                .send()
+                .await
+                .and_then(|response| response.error_for_status());
+            // Webhook URLs embed their token, so they stay out of the logs
+            match result {
+                Ok(_) => debug!("Sent {} notification", self.action),
+                Err(e) => warn!(
+                    "Failed to send {} notification: {}",
+                    self.action,
+                    e.without_url()
+                ),
+            }
+        }
+    }
+}
+
+fn capitalize(word: &str) -> String {
+    let mut characters = word.chars();
+    match characters.next() {
+        Some(first) => first.to_uppercase().chain(characters).collect(),
+        None => String::new(),
+    }
+}
@@ -0,0 +1,219 @@
+use colored::Colorize;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// What the dev loop spends a rebuild on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProfileStep {
+    /// Stopping the running containers before the rebuild
+    Stop,
+    /// Rendering the component's build artefacts
+    Artefacts,
+    /// Running the component's build script
+    BuildScript,
+    /// Building the component's image
+    DockerBuild,
+    /// Rendering the product's manifests
+    Manifests,
+    /// Starting the component's container, with the pause before the next one starts
+    ContainerStart,
+}
+
+impl ProfileStep {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ProfileStep::Stop => "stop",
+            ProfileStep::Artefacts => "artefacts",
+            ProfileStep::BuildScript => "build script",
+            ProfileStep::DockerBuild => "docker build",
+            ProfileStep::Manifests => "manifests",
+            ProfileStep::ContainerStart => "container start",
+        }
+    }
+}
+
+/// A step of a rebuild. Steps of the whole product, such as stopping the containers,
+/// have no component.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProfiledStep {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub component: Option<String>,
+    pub step: ProfileStep,
+    pub duration_ms: u128,
+}
+
+/// One turn of the dev loop, from the change of a file to the relaunch of the
+/// containers it affects.
+#[derive(Debug, Clone, Serialize)]
+pub struct Rebuild {
+    /// The components whose files changed, empty for the first build
+    pub changed: Vec<String>,
+    pub steps: Vec<ProfiledStep>,
+    /// From the change being noticed to the last container being started
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u128>,
+    #[serde(skip)]
+    started: Option<Instant>,
+}
+
+/// Records how long the rebuilds of a dev session take, see `rush profile build`.
+/// Clones share the same records, so the reactor and its images can each add theirs.
+#[derive(Debug, Clone, Default)]
+pub struct BuildProfiler {
+    rebuilds: Arc<Mutex<Vec<Rebuild>>>,
+}
+
+#[derive(Serialize)]
+struct ProfileReport<'a> {
+    rebuilds: &'a [Rebuild],
+}
+
+#[derive(Default)]
+struct Totals {
+    count: usize,
+    total: Duration,
+    max: Duration,
+}
+
+impl Totals {
+    fn add(&mut self, duration: Duration) {
+        self.count += 1;
+        self.total += duration;
+        self.max = self.max.max(duration);
+    }
+
+    fn mean(&self) -> Duration {
+        self.total / self.count.max(1) as u32
+    }
+}
+
+impl BuildProfiler {
+    /// Starts a rebuild for a change to the files of the `changed` components.
+    pub fn start_rebuild(&self, changed: Vec<String>) {
+        let mut rebuilds = self.rebuilds.lock().unwrap();
+        rebuilds.push(Rebuild {
+            changed,
+            steps: Vec::new(),
+            latency_ms: None,
+            started: Some(Instant::now()),
+        });
+    }
+
+    /// Adds a step to the current rebuild.
+    pub fn record(&self, component: Option<&str>, step: ProfileStep, duration: Duration) {
+        let mut rebuilds = self.rebuilds.lock().unwrap();
+        if let Some(rebuild) = rebuilds.last_mut() {
+            rebuild.steps.push(ProfiledStep {
+                component: component.map(str::to_string),
+                step,
+                duration_ms: duration.as_millis(),
+            });
+        }
+    }
+
+    /// Ends the current rebuild once its containers have started. Rebuilds that
+    /// failed are never finished and have no latency.
+    pub fn finish_rebuild(&self) {
+        let mut rebuilds = self.rebuilds.lock().unwrap();
+        if let Some(rebuild) = rebuilds.last_mut() {
+            if let Some(started) = rebuild.started.take() {
+                rebuild.latency_ms = Some(started.elapsed().as_millis());
+            }
+        }
+    }
+
+    pub fn rebuilds(&self) -> Vec<Rebuild> {
+        self.rebuilds.lock().unwrap().clone()
+    }
+
+    /// Prints the rebuild latencies and the steps by the time spent on them, the
+    /// first build being left out when there were later ones.
+    pub fn print(&self) {
+        let rebuilds = self.rebuilds();
+        let profiled = match rebuilds.split_first() {
+            Some((_, changes)) if !changes.is_empty() => changes,
+            _ => &rebuilds[..],
+        };
+        if profiled.is_empty() {
+            println!("No builds were profiled");
+            return;
+        }
+
+        let mut latency = Totals::default();
+        for rebuild in profiled {
+            if let Some(latency_ms) = rebuild.latency_ms {
+                latency.add(Duration::from_millis(latency_ms as u64));
+            }
+        }
+        println!(
+            "\n{} {} rebuilds, {} failed, change to relaunch {} on average, {} at most",
+            "Profile of".bold(),
+            profiled.len(),
+            profiled.len() - latency.count,
+            format_duration(latency.mean()),
+            format_duration(latency.max)
+        );
+
+        let mut steps = BTreeMap::<(String, ProfileStep), Totals>::new();
+        for step in profiled.iter().flat_map(|rebuild| &rebuild.steps) {
+            let component = step.component.clone().unwrap_or_else(|| "*".to_string());
+            steps
+                .entry((component, step.step))
+                .or_default()
+                .add(Duration::from_millis(step.duration_ms as u64));
+        }
+        let mut steps = steps.into_iter().collect::<Vec<_>>();
+        steps.sort_by_key(|(_, totals)| std::cmp::Reverse(totals.total));
+
+        let total = steps
+            .iter()
+            .map(|(_, totals)| totals.total)
+            .sum::<Duration>()
+            .max(Duration::from_millis(1));
+        let component_width = steps
+            .iter()
+            .map(|((component, _), _)| component.len())
+            .max()
+            .unwrap_or_default()
+            .max("component".len());
+        println!(
+            "  {:<width$}  {:<15}  {:>5}  {:>7}  {:>7}  {:>5}",
+            "component",
+            "step",
+            "count",
+            "mean",
+            "max",
+            "share",
+            width = component_width
+        );
+        for ((component, step), totals) in steps {
+            println!(
+                "  {:<width$}  {:<15}  {:>5}  {:>7}  {:>7}  {:>4}%",
+                component,
+                step.name(),
+                totals.count,
+                format_duration(totals.mean()),
+                format_duration(totals.max),
+                totals.total.as_millis() * 100 / total.as_millis(),
+                width = component_width
+            );
+        }
+    }
+
+    /// Writes the rebuilds as JSON to `path`.
+    pub fn write_json(&self, path: &str) -> Result<(), String> {
+        let rebuilds = self.rebuilds();
+        let json = serde_json::to_string_pretty(&ProfileReport {
+            rebuilds: &rebuilds,
+        })
+        .map_err(|e| format!("Failed to serialize profile: {}", e))?;
+        std::fs::write(path, json).map_err(|e| format!("Failed to write {}: {}", path, e))
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    format!("{:.1}s", duration.as_secs_f64())
+}
@@ -0,0 +1,562 @@
+use base64::Engine;
+use log::{debug, trace};
+use reqwest::header::{HeaderMap, ACCEPT, AUTHORIZATION, CONTENT_TYPE, WWW_AUTHENTICATE};
+use reqwest::{Method, Response, StatusCode};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+
+/// Manifest media types rush asks for, single platform ones first.
+const MANIFEST_TYPES: &str = "application/vnd.docker.distribution.manifest.v2+json, \
+     application/vnd.oci.image.manifest.v1+json, \
+     application/vnd.docker.distribution.manifest.list.v2+json, \
+     application/vnd.oci.image.index.v1+json";
+
+/// A manifest in the registry, as far as rush needs it.
+#[derive(Debug, Clone)]
+pub struct RegistryManifest {
+    pub digest: String,
+    pub media_type: String,
+    /// Digest of the image config, which holds the creation time. Manifest lists have none.
+    pub config_digest: Option<String>,
+}
+
+/// A tag pushed to the registry.
+#[derive(Debug, Clone)]
+pub struct RemoteTag {
+    pub tag: String,
+    pub digest: String,
+    /// When the image was built, if the registry has its config
+    pub created: Option<String>,
+}
+
+/// A client of the Docker Registry HTTP API v2 behind DOCKER_REGISTRY.
+///
+/// Credentials are read from REGISTRY_USERNAME and REGISTRY_PASSWORD, or else from the
+/// docker login stored in `~/.docker/config.json`, including credential helpers.
+pub struct RegistryClient {
+    client: reqwest::Client,
+    base_url: String,
+    host: String,
+    /// Path the images live under, e.g. `me` of `registry.example.com/me`
+    namespace: String,
+    credentials: Option<(String, String)>,
+    /// Bearer tokens per scope, for registries authenticating with a token service
+    tokens: Mutex<HashMap<String, String>>,
+}
+
+impl RegistryClient {
+    pub fn new(docker_registry: &str) -> Result<Self, String> {
+        let docker_registry = docker_registry.trim_end_matches('/');
+        if docker_registry.is_empty() || docker_registry == "not_set" {
+            return Err("DOCKER_REGISTRY is not set".to_string());
+        }
+        let (first, rest) = match docker_registry.split_once('/') {
+            Some((first, rest)) => (first, rest),
+            None => (docker_registry, ""),
+        };
+        // Like docker, a first segment without a dot or port is a Docker Hub user
+        let (host, namespace) =
+            if first.contains('.') || first.contains(':') || first == "localhost" {
+                (first.to_string(), rest.to_string())
+            } else {
+                ("docker.io".to_string(), docker_registry.to_string())
+            };
+        let base_url = match host.as_str() {
+            "docker.io" => "https://registry-1.docker.io".to_string(),
+            host if host.starts_with("localhost") || host.starts_with("127.0.0.1") => {
+                format!("http://{}", host)
+            }
+            host => format!("https://{}", host),
+        };
+        let credentials = match (
+            std::env::var("REGISTRY_USERNAME"),
+            std::env::var("REGISTRY_PASSWORD"),
+        ) {
+            (Ok(username), Ok(password)) => Some((username, password)),
+            _ => docker_credentials(&host),
+        };
+        Ok(RegistryClient {
+            client: reqwest::Client::new(),
+            base_url,
+            host,
+            namespace,
+            credentials,
+            tokens: Mutex::new(HashMap::new()),
+        })
+    }
+
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+
+    /// The repository of an image within the registry, e.g. `me/product-backend`.
+    fn repository(&self, image_name: &str) -> String {
+        if self.namespace.is_empty() {
+            image_name.to_string()
+        } else {
+            format!("{}/{}", self.namespace, image_name)
+        }
+    }
+
+    /// All tags pushed for an image.
+    pub async fn tags(&self, image_name: &str) -> Result<Vec<String>, String> {
+        #[derive(Deserialize)]
+        struct TagList {
+            tags: Option<Vec<String>>,
+        }
+
+        let repository = self.repository(image_name);
+        let mut tags = Vec::new();
+        let mut path = format!("/v2/{}/tags/list?n=1000", repository);
+        loop {
+            let response = self
+                .request(Method::GET, &path, &repository, HeaderMap::new(), None)
+                .await?;
+            if response.status() == StatusCode::NOT_FOUND {
+                return Ok(tags);
+            }
+            let response = check(response, &repository).await?;
+            // Further pages are linked as `</v2/...?last=tag&n=1000>; rel="next"`
+            let next = response
+                .headers()
+                .get("link")
+                .and_then(|link| link.to_str().ok())
+                .and_then(|link| link.split_once('<'))
+                .and_then(|(_, link)| link.split_once('>'))
+                .map(|(link, _)| link.to_string());
+            let list = response
+                .json::<TagList>()
+                .await
+                .map_err(|e| format!("Invalid tag list of {}: {}", repository, e))?;
+            tags.extend(list.tags.unwrap_or_default());
+            match next {
+                Some(next) => path = next,
+                None => return Ok(tags),
+            }
+        }
+    }
+
+    /// The tags of an image with their digest and age, newest first. Tags of unknown age,
+    /// e.g. of multi-platform images, come first.
+    pub async fn remote_tags(&self, image_name: &str) -> Result<Vec<RemoteTag>, String> {
+        let mut remote_tags = Vec::new();
+        for tag in self.tags(image_name).await? {
+            let Some(manifest) = self.manifest(image_name, &tag).await? else {
+                continue;
+            };
+            remote_tags.push(RemoteTag {
+                created: self.created(image_name, &manifest).await?,
+                digest: manifest.digest,
+                tag,
+            });
+        }
+        remote_tags.sort_by(|a, b| match (&a.created, &b.created) {
+            (Some(a), Some(b)) => b.cmp(a),
+            (a, b) => a.is_some().cmp(&b.is_some()),
+        });
+        Ok(remote_tags)
+    }
+
+    /// The manifest a tag or digest points at, or None when it does not exist.
+    pub async fn manifest(
+        &self,
+        image_name: &str,
+        reference: &str,
+    ) -> Result<Option<RegistryManifest>, String> {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Manifest {
+            media_type: Option<String>,
+            config: Option<Descriptor>,
+        }
+        #[derive(Deserialize)]
+        struct Descriptor {
+            digest: String,
+        }
+
+        let repository = self.repository(image_name);
+        let mut headers = HeaderMap::new();
+        headers.insert(ACCEPT, MANIFEST_TYPES.parse().unwrap());
+        let response = self
+            .request(
+                Method::GET,
+                &format!("/v2/{}/manifests/{}", repository, reference),
+                &repository,
+                headers,
+                None,
+            )
+            .await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let response = check(response, &repository).await?;
+        let header = |name: &str| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(|value| value.to_string())
+        };
+        let digest = header("docker-content-digest");
+        let content_type = header("content-type");
+        let body = response.bytes().await.map_err(|e| e.to_string())?;
+        let manifest = serde_json::from_slice::<Manifest>(&body)
+            .map_err(|e| format!("Invalid manifest of {}:{}: {}", repository, reference, e))?;
+        let digest = digest
+            .unwrap_or_else(|| format!("sha256:{}", hex::encode(openssl::sha::sha256(&body))));
+        Ok(Some(RegistryManifest {
+            digest,
+            media_type: manifest.media_type.or(content_type).unwrap_or_default(),
+            config_digest: manifest.config.map(|config| config.digest),
+        }))
+    }
+
+    /// The digest of the manifest a tag or digest points at, asked for without fetching
+    /// the manifest, or None when it does not exist.
+    pub async fn digest(
+        &self,
+        image_name: &str,
+        reference: &str,
+    ) -> Result<Option<String>, String> {
+        let repository = self.repository(image_name);
+        let mut headers = HeaderMap::new();
+        headers.insert(ACCEPT, MANIFEST_TYPES.parse().unwrap());
+        let response = self
+            .request(
+                Method::HEAD,
+                &format!("/v2/{}/manifests/{}", repository, reference),
+                &repository,
+                headers,
+                None,
+            )
+            .await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let response = check(response, &repository).await?;
+        let digest = response
+            .headers()
+            .get("docker-content-digest")
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_string());
+        match digest {
+            Some(digest) => Ok(Some(digest)),
+            // Not every registry sends it with a HEAD request
+            None => Ok(self
+                .manifest(image_name, reference)
+                .await?
+                .map(|manifest| manifest.digest)),
+        }
+    }
+
+    /// Points `tag` at the manifest `digest` of the image, which the registry already
+    /// has, without pushing the image again.
+    pub async fn tag(&self, image_name: &str, digest: &str, tag: &str) -> Result<(), String> {
+        let repository = self.repository(image_name);
+        let mut headers = HeaderMap::new();
+        headers.insert(ACCEPT, MANIFEST_TYPES.parse().unwrap());
+        let response = self
+            .request(
+                Method::GET,
+                &format!("/v2/{}/manifests/{}", repository, digest),
+                &repository,
+                headers,
+                None,
+            )
+            .await?;
+        let response = check(response, &repository).await?;
+        let content_type = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .cloned()
+            .ok_or_else(|| format!("The manifest {} of {} has no type", digest, repository))?;
+        let manifest = response.bytes().await.map_err(|e| e.to_string())?;
+
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, content_type);
+        let response = self
+            .request(
+                Method::PUT,
+                &format!("/v2/{}/manifests/{}", repository, tag),
+                &repository,
+                headers,
+                Some(manifest.to_vec()),
+            )
+            .await?;
+        check(response, &repository).await.map(|_| ())
+    }
+
+    /// When the image of a manifest was built, as recorded in its config.
+    pub async fn created(
+        &self,
+        image_name: &str,
+        manifest: &RegistryManifest,
+    ) -> Result<Option<String>, String> {
+        #[derive(Deserialize)]
+        struct ImageConfig {
+            created: Option<String>,
+        }
+
+        let Some(config_digest) = &manifest.config_digest else {
+            return Ok(None);
+        };
+        let repository = self.repository(image_name);
+        let response = self
+            .request(
+                Method::GET,
+                &format!("/v2/{}/blobs/{}", repository, config_digest),
+                &repository,
+                HeaderMap::new(),
+                None,
+            )
+            .await?;
+        let config = check(response, &repository)
+            .await?
+            .json::<ImageConfig>()
+            .await
+            .map_err(|e| format!("Invalid image config of {}: {}", repository, e))?;
+        Ok(config.created)
+    }
+
+    /// Deletes a manifest, and with it every tag pointing at it.
+    pub async fn delete(&self, image_name: &str, digest: &str) -> Result<(), String> {
+        let repository = self.repository(image_name);
+        let response = self
+            .request(
+                Method::DELETE,
+                &format!("/v2/{}/manifests/{}", repository, digest),
+                &repository,
+                HeaderMap::new(),
+                None,
+            )
+            .await?;
+        if response.status() == StatusCode::METHOD_NOT_ALLOWED {
+            return Err(format!(
+                "{} does not allow deleting images, e.g. REGISTRY_STORAGE_DELETE_ENABLED is not set",
+                self.host
+            ));
+        }
+        check(response, &repository).await.map(|_| ())
+    }
+
+    /// Sends a request, answering an authentication challenge of the registry once.
+    async fn request(
+        &self,
+        method: Method,
+        path: &str,
+        repository: &str,
+        headers: HeaderMap,
+        body: Option<Vec<u8>>,
+    ) -> Result<Response, String> {
+        let url = format!("{}{}", self.base_url, path);
+        let scope = match method {
+            Method::DELETE => format!("repository:{}:delete,pull", repository),
+            Method::PUT => format!("repository:{}:pull,push", repository),
+            _ => format!("repository:{}:pull", repository),
+        };
+        let token = self.tokens.lock().unwrap().get(&scope).cloned();
+        trace!("{} {}", method, url);
+        let send = |authorization: Option<String>| {
+            let mut request = self
+                .client
+                .request(method.clone(), &url)
+                .headers(headers.clone());
+            if let Some(authorization) = authorization {
+                request = request.header(AUTHORIZATION, authorization);
+            }
+            if let Some(body) = &body {
+                request = request.body(body.clone());
+            }
+            request.send()
+        };
+        let response = send(token.map(|token| format!("Bearer {}", token)))
+            .await
+            .map_err(|e| format!("Failed to reach {}: {}", self.host, e.without_url()))?;
+        if response.status() != StatusCode::UNAUTHORIZED {
+            return Ok(response);
+        }
+
+        let challenge = response
+            .headers()
+            .get(WWW_AUTHENTICATE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+            .to_string();
+        let authorization = if challenge.to_lowercase().starts_with("bearer") {
+            let token = self.token(&challenge, &scope).await?;
+            self.tokens
+                .lock()
+                .unwrap()
+                .insert(scope.clone(), token.clone());
+            format!("Bearer {}", token)
+        } else {
+            let (username, password) = self
+                .credentials
+                .as_ref()
+                .ok_or_else(|| self.missing_credentials())?;
+            basic_authorization(username, password)
+        };
+        send(Some(authorization))
+            .await
+            .map_err(|e| format!("Failed to reach {}: {}", self.host, e.without_url()))
+    }
+
+    /// Fetches a token from the service named in a `Bearer realm=...,service=...` challenge.
+    async fn token(&self, challenge: &str, scope: &str) -> Result<String, String> {
+        #[derive(Deserialize)]
+        struct TokenResponse {
+            token: Option<String>,
+            access_token: Option<String>,
+        }
+
+        let parameters = challenge[6..]
+            .split(',')
+            .filter_map(|parameter| parameter.trim().split_once('='))
+            .map(|(key, value)| (key.to_string(), value.trim_matches('"').to_string()))
+            .collect::<HashMap<_, _>>();
+        let realm = parameters.get("realm").ok_or_else(|| {
+            format!(
+                "{} sent an authentication challenge without a realm",
+                self.host
+            )
+        })?;
+        let mut query = vec![("scope", scope.to_string())];
+        if let Some(service) = parameters.get("service") {
+            query.push(("service", service.clone()));
+        }
+        let mut request = self.client.get(realm).query(&query);
+        if let Some((username, password)) = &self.credentials {
+            request = request.header(AUTHORIZATION, basic_authorization(username, password));
+        }
+        let response = request.send().await.map_err(|e| {
+            format!(
+                "Failed to authenticate with {}: {}",
+                self.host,
+                e.without_url()
+            )
+        })?;
+        if !response.status().is_success() {
+            return Err(if self.credentials.is_none() {
+                self.missing_credentials()
+            } else {
+                format!(
+                    "{} rejected the credentials: {}",
+                    self.host,
+                    response.status()
+                )
+            });
+        }
+        let token = response
+            .json::<TokenResponse>()
+            .await
+            .map_err(|e| format!("Invalid token response of {}: {}", self.host, e))?;
+        token
+            .token
+            .or(token.access_token)
+            .ok_or_else(|| format!("{} sent no token", self.host))
+    }
+
+    fn missing_credentials(&self) -> String {
+        format!(
+            "{} needs credentials: run `docker login {}` or set REGISTRY_USERNAME and REGISTRY_PASSWORD",
+            self.host, self.host
+        )
+    }
+}
+
+/// Turns an unsuccessful response into an error.
+async fn check(response: Response, repository: &str) -> Result<Response, String> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let body = response.text().await.unwrap_or_default();
+    Err(format!(
+        "The registry answered {} for {}: {}",
+        status,
+        repository,
+        body.trim()
+    ))
+}
+
+fn basic_authorization(username: &str, password: &str) -> String {
+    format!(
+        "Basic {}",
+        base64::engine::general_purpose::STANDARD.encode(format!("{}:{}", username, password))
+    )
+}
+
+/// The login `docker login` stored for the host, in config.json or a credential helper.
+fn docker_credentials(host: &str) -> Option<(String, String)> {
+    #[derive(Deserialize, Default)]
+    #[serde(rename_all = "camelCase")]
+    struct DockerConfig {
+        #[serde(default)]
+        auths: HashMap<String, DockerAuth>,
+        creds_store: Option<String>,
+        #[serde(default)]
+        cred_helpers: HashMap<String, String>,
+    }
+    #[derive(Deserialize)]
+    struct DockerAuth {
+        auth: Option<String>,
+    }
+    #[derive(Deserialize)]
+    #[serde(rename_all = "PascalCase")]
+    struct HelperCredentials {
+        username: String,
+        secret: String,
+    }
+
+    let path = match std::env::var_os("DOCKER_CONFIG") {
+        Some(directory) => PathBuf::from(directory).join("config.json"),
+        None => PathBuf::from(std::env::var_os("HOME")?)
+            .join(".docker")
+            .join("config.json"),
+    };
+    let config = std::fs::read_to_string(&path)
+        .ok()
+        .and_then(|contents| serde_json::from_str::<DockerConfig>(&contents).ok())
+        .unwrap_or_default();
+    // Docker Hub logins are stored under its v1 index URL
+    let keys = match host {
+        "docker.io" => vec!["https://index.docker.io/v1/".to_string()],
+        host => vec![host.to_string(), format!("https://{}", host)],
+    };
+
+    let encoded = keys
+        .iter()
+        .find_map(|key| config.auths.get(key).and_then(|auth| auth.auth.clone()));
+    if let Some(encoded) = encoded {
+        let decoded = base64::engine::general_purpose::STANDARD
+            .decode(encoded)
+            .ok()?;
+        let decoded = String::from_utf8(decoded).ok()?;
+        let (username, password) = decoded.split_once(':')?;
+        return Some((username.to_string(), password.to_string()));
+    }
+
+    let helper = keys
+        .iter()
+        .find_map(|key| config.cred_helpers.get(key))
+        .or(config.creds_store.as_ref())?;
+    let mut child = Command::new(format!("docker-credential-{}", helper))
+        .arg("get")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+    child.stdin.take()?.write_all(keys[0].as_bytes()).ok()?;
+    let output = child.wait_with_output().ok()?;
+    if !output.status.success() {
+        debug!("docker-credential-{} has no login for {}", helper, host);
+        return None;
+    }
+    let credentials = serde_json::from_slice::<HelperCredentials>(&output.stdout).ok()?;
+    Some((credentials.username, credentials.secret))
+}
@@ -0,0 +1,158 @@
+use crate::builder::DatabaseSpec;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use tokio::process::Command;
+
+/// The snapshots of the database components of a product, taken from and restored into
+/// their `rush dev` containers. Each component has a directory of them under
+/// `target/snapshots/`, which can be copied to share a dataset.
+pub struct Snapshots {
+    directory: PathBuf,
+}
+
+impl Snapshots {
+    pub fn new(product_directory: &Path) -> Self {
+        Self {
+            directory: product_directory.join("target").join("snapshots"),
+        }
+    }
+
+    /// Dumps the database of `container` into the snapshot `name`, or one named after
+    /// the current time, returning its path.
+    pub async fn snapshot(
+        &self,
+        docker: &str,
+        container: &str,
+        component_name: &str,
+        database: &DatabaseSpec,
+        name: Option<&str>,
+    ) -> Result<PathBuf, String> {
+        let command = database
+            .dump_command()
+            .ok_or_else(|| format!("{} has no dump command", component_name))?;
+        let name = name
+            .map(|name| name.to_string())
+            .unwrap_or_else(|| chrono::Local::now().format("%Y%m%d-%H%M%S").to_string());
+        let directory = self.directory.join(component_name);
+        std::fs::create_dir_all(&directory)
+            .map_err(|e| format!("Could not create {}: {}", directory.display(), e))?;
+        let path = directory.join(format!("{}.{}", name, database.extension()));
+
+        // Dumped next to the snapshot first, so that a failed dump leaves no broken one
+        let partial = path.with_extension("partial");
+        let file = std::fs::File::create(&partial)
+            .map_err(|e| format!("Could not create {}: {}", partial.display(), e))?;
+        let result = exec(
+            docker,
+            container,
+            &command,
+            Stdio::null(),
+            Stdio::from(file),
+        )
+        .await;
+        if let Err(e) = result {
+            let _ = std::fs::remove_file(&partial);
+            return Err(format!("Failed to dump {}: {}", component_name, e));
+        }
+        std::fs::rename(&partial, &path)
+            .map_err(|e| format!("Could not write {}: {}", path.display(), e))?;
+        Ok(path)
+    }
+
+    /// Loads the snapshot `name`, or the latest one, into the database of `container`,
+    /// returning its path.
+    pub async fn restore(
+        &self,
+        docker: &str,
+        container: &str,
+        component_name: &str,
+        database: &DatabaseSpec,
+        name: Option<&str>,
+    ) -> Result<PathBuf, String> {
+        let command = database
+            .restore_command()
+            .ok_or_else(|| format!("{} has no restore command", component_name))?;
+        let path = match name {
+            Some(name) => {
+                let path = self.directory.join(component_name).join(format!(
+                    "{}.{}",
+                    name,
+                    database.extension()
+                ));
+                if !path.exists() {
+                    return Err(format!("There is no snapshot {}", path.display()));
+                }
+                path
+            }
+            None => self.latest(component_name, database).ok_or_else(|| {
+                format!(
+                    "There are no snapshots of {} yet. Take one with: rush db snapshot {}",
+                    component_name, component_name
+                )
+            })?,
+        };
+
+        let file = std::fs::File::open(&path)
+            .map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+        exec(
+            docker,
+            container,
+            &command,
+            Stdio::from(file),
+            Stdio::null(),
+        )
+        .await
+        .map_err(|e| format!("Failed to restore {}: {}", component_name, e))?;
+        Ok(path)
+    }
+
+    /// The most recently written snapshot of the component.
+    fn latest(&self, component_name: &str, database: &DatabaseSpec) -> Option<PathBuf> {
+        std::fs::read_dir(self.directory.join(component_name))
+            .ok()?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|extension| extension == database.extension())
+            })
+            .max_by_key(|path| {
+                path.metadata()
+                    .and_then(|metadata| metadata.modified())
+                    .ok()
+            })
+    }
+}
+
+/// Runs `command` with `sh -c` in the container, streaming stdin and stdout, which may
+/// be binary.
+async fn exec(
+    docker: &str,
+    container: &str,
+    command: &str,
+    stdin: Stdio,
+    stdout: Stdio,
+) -> Result<(), String> {
+    let output = Command::new(docker)
+        .args(["exec", "-i", container, "sh", "-c", command])
+        .stdin(stdin)
+        .stdout(stdout)
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| e.to_string())?
+        // Unlike output(), keeps stdout going to the snapshot
+        .wait_with_output()
+        .await
+        .map_err(|e| e.to_string())?;
+    if output.status.success() {
+        return Ok(());
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+    if stderr.contains("No such container") || stderr.contains("is not running") {
+        return Err(format!(
+            "its container {} is not running. Start it with rush dev",
+            container
+        ));
+    }
+    Err(stderr)
+}
@@ -0,0 +1,161 @@
+use colored::Colorize;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use tokio::process::Command;
+
+/// What a component's container uses, as sampled by `docker stats`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct ResourceUsage {
+    pub cpu_percent: f64,
+    pub memory_bytes: u64,
+    pub memory_limit_bytes: u64,
+    /// Change of the memory since the first sample of the container, which keeps
+    /// growing when the component leaks
+    pub memory_growth_bytes: i64,
+}
+
+impl ResourceUsage {
+    /// E.g. `12.5% CPU, 180.2MiB (+96.0MiB)`.
+    pub fn describe(&self) -> String {
+        let sign = if self.memory_growth_bytes < 0 {
+            "-"
+        } else {
+            "+"
+        };
+        format!(
+            "{:.1}% CPU, {} ({}{})",
+            self.cpu_percent,
+            format_bytes(self.memory_bytes),
+            sign,
+            format_bytes(self.memory_growth_bytes.unsigned_abs())
+        )
+    }
+}
+
+/// Samples the CPU and memory of the dev containers with `docker stats`, by component.
+pub struct ResourceMonitor {
+    docker: String,
+    /// Component of each container name
+    components: BTreeMap<String, String>,
+    first_samples: HashMap<String, u64>,
+}
+
+impl ResourceMonitor {
+    /// Monitors the containers of `components`, by container name.
+    pub fn new(docker: &str, components: BTreeMap<String, String>) -> Self {
+        Self {
+            docker: docker.to_string(),
+            components,
+            first_samples: HashMap::new(),
+        }
+    }
+
+    /// The usage of the containers running now. Containers that are not running, such
+    /// as those of a component being rebuilt, are left out and start over.
+    pub async fn sample(&mut self) -> Result<BTreeMap<String, ResourceUsage>, String> {
+        let output = Command::new(&self.docker)
+            .args([
+                "stats",
+                "--no-stream",
+                "--format",
+                "{{.Name}}\t{{.CPUPerc}}\t{{.MemUsage}}",
+            ])
+            .output()
+            .await
+            .map_err(|e| format!("Failed to run {}: {}", self.docker, e))?;
+        if !output.status.success() {
+            return Err(format!(
+                "Failed to read the container stats: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+
+        let mut usage = BTreeMap::new();
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            let mut fields = line.split('\t');
+            let (Some(name), Some(cpu), Some(memory)) =
+                (fields.next(), fields.next(), fields.next())
+            else {
+                continue;
+            };
+            let Some(component) = self.components.get(name) else {
+                continue;
+            };
+            let Some((memory, limit)) = memory.split_once('/') else {
+                continue;
+            };
+            let (Some(cpu_percent), Some(memory_bytes), Some(memory_limit_bytes)) = (
+                cpu.trim().trim_end_matches('%').parse::<f64>().ok(),
+                parse_bytes(memory),
+                parse_bytes(limit),
+            ) else {
+                continue;
+            };
+            let first_sample = *self
+                .first_samples
+                .entry(component.clone())
+                .or_insert(memory_bytes);
+            usage.insert(
+                component.clone(),
+                ResourceUsage {
+                    cpu_percent,
+                    memory_bytes,
+                    memory_limit_bytes,
+                    memory_growth_bytes: memory_bytes as i64 - first_sample as i64,
+                },
+            );
+        }
+        self.first_samples
+            .retain(|component, _| usage.contains_key(component));
+        Ok(usage)
+    }
+}
+
+/// Prints one line per component, the memory in red once it has used most of its
+/// limit.
+pub fn print_usage(usage: &BTreeMap<String, ResourceUsage>) {
+    let width = usage.keys().map(String::len).max().unwrap_or_default();
+    println!("{}", "Resource usage".bold());
+    for (component, usage) in usage {
+        let line = format!("{:width$}  {}", component, usage.describe(), width = width);
+        if usage.memory_bytes * 10 >= usage.memory_limit_bytes * 9 {
+            println!("{}", line.red().bold());
+        } else {
+            println!("{}", line.dimmed());
+        }
+    }
+}
+
+/// Parses sizes as docker prints them, e.g. `180.2MiB` or `1.5GB`.
+fn parse_bytes(size: &str) -> Option<u64> {
+    let size = size.trim();
+    let split = size
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(size.len());
+    let (number, unit) = size.split_at(split);
+    let multiplier = match unit {
+        "B" | "" => 1.0,
+        "kB" | "KB" => 1e3,
+        "MB" => 1e6,
+        "GB" => 1e9,
+        "TB" => 1e12,
+        "KiB" => 1024.0,
+        "MiB" => 1024.0 * 1024.0,
+        "GiB" => 1024.0 * 1024.0 * 1024.0,
+        "TiB" => 1024.0 * 1024.0 * 1024.0 * 1024.0,
+        _ => return None,
+    };
+    number
+        .parse::<f64>()
+        .ok()
+        .map(|number| (number * multiplier) as u64)
+}
+
+fn format_bytes(bytes: u64) -> String {
+    let mebibytes = bytes as f64 / (1024.0 * 1024.0);
+    if mebibytes >= 1024.0 {
+        format!("{:.1}GiB", mebibytes / 1024.0)
+    } else {
+        format!("{:.1}MiB", mebibytes)
+    }
+}
@@ -7,3 +7,17 @@ pub enum Status {
     Finished(i32),
     Terminate,
 }
+
+impl Status {
+    /// Name of the status in the event stream.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Status::Awaiting => "awaiting",
+            Status::InProgress => "in_progress",
+            Status::StartupCompleted => "startup_completed",
+            Status::Reinitializing => "reinitializing",
+            Status::Finished(_) => "finished",
+            Status::Terminate => "terminate",
+        }
+    }
+}
@@ -0,0 +1,290 @@
+use super::stats::ResourceUsage;
+use log::{debug, trace, warn};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// Number of output lines kept per component.
+const LOG_TAIL_LINES: usize = 100;
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ComponentState {
+    pub status: String,
+    pub port: Option<u16>,
+    pub url: Option<String>,
+    /// The last sample of `docker stats`, while the container runs
+    pub resources: Option<ResourceUsage>,
+    pub logs: VecDeque<String>,
+}
+
+#[derive(Debug, Default)]
+struct Board {
+    components: BTreeMap<String, ComponentState>,
+    restart_requests: HashSet<String>,
+}
+
+/// The state of the dev stack shown by the status page of `rush dev --status-port`.
+/// Clones share the same state, so the images report their output to it directly.
+#[derive(Debug, Clone, Default)]
+pub struct StatusBoard {
+    board: Arc<Mutex<Board>>,
+}
+
+impl StatusBoard {
+    pub fn add_component(&self, component_name: &str, port: Option<u16>) {
+        let mut board = self.board.lock().unwrap();
+        let component = board
+            .components
+            .entry(component_name.to_string())
+            .or_default();
+        component.port = port;
+        component.url = port.map(|port| format!("http://localhost:{}", port));
+    }
+
+    /// Drops a component taken out of stack.spec.yaml during `rush dev`.
+    pub fn remove_component(&self, component_name: &str) {
+        let mut board = self.board.lock().unwrap();
+        board.components.remove(component_name);
+        board.restart_requests.remove(component_name);
+    }
+
+    pub fn set_status(&self, component_name: &str, status: &str) {
+        if let Some(component) = self
+            .board
+            .lock()
+            .unwrap()
+            .components
+            .get_mut(component_name)
+        {
+            component.status = status.to_string();
+        }
+    }
+
+    /// Replaces the resource usage of every component, those missing from `usage`
+    /// having none.
+    pub fn set_resources(&self, usage: &BTreeMap<String, ResourceUsage>) {
+        for (name, component) in self.board.lock().unwrap().components.iter_mut() {
+            component.resources = usage.get(name).copied();
+        }
+    }
+
+    pub fn push_log(&self, component_name: &str, line: &str) {
+        if let Some(component) = self
+            .board
+            .lock()
+            .unwrap()
+            .components
+            .get_mut(component_name)
+        {
+            if component.logs.len() == LOG_TAIL_LINES {
+                component.logs.pop_front();
+            }
+            component.logs.push_back(line.to_string());
+        }
+    }
+
+    /// Components whose restart was requested since the last call.
+    pub fn take_restart_requests(&self) -> Vec<String> {
+        self.board
+            .lock()
+            .unwrap()
+            .restart_requests
+            .drain()
+            .collect()
+    }
+
+    fn request_restart(&self, component_name: &str) -> bool {
+        let mut board = self.board.lock().unwrap();
+        if !board.components.contains_key(component_name) {
+            return false;
+        }
+        board.restart_requests.insert(component_name.to_string());
+        true
+    }
+
+    fn snapshot(&self) -> BTreeMap<String, ComponentState> {
+        self.board.lock().unwrap().components.clone()
+    }
+
+    /// Serves the page on `port` of `address` until rush exits: `/` for people, `/status`
+    /// as JSON for dashboards and `POST /restart/<component>`.
+    pub async fn serve(&self, address: IpAddr, port: u16) -> Result<(), String> {
+        let listener = TcpListener::bind((address, port))
+            .await
+            .map_err(|e| format!("Failed to serve the status page on port {}: {}", port, e))?;
+        println!("Status page on http://localhost:{}", port);
+        // Other names only reach a page on the loopback interface through DNS rebinding
+        let local_only = address.is_loopback();
+        let board = self.clone();
+        tokio::spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, _)) => {
+                        let board = board.clone();
+                        tokio::spawn(async move {
+                            if let Err(e) = board.handle(stream, local_only).await {
+                                debug!("Status page request failed: {}", e);
+                            }
+                        });
+                    }
+                    Err(e) => warn!("Status page stopped accepting connections: {}", e),
+                }
+            }
+        });
+        Ok(())
+    }
+
+    async fn handle(&self, mut stream: TcpStream, local_only: bool) -> std::io::Result<()> {
+        // Requests are small and carry no body worth reading, so one read suffices
+        let mut buffer = [0; 4096];
+        let length = stream.read(&mut buffer).await?;
+        let request = String::from_utf8_lossy(&buffer[..length]);
+        let mut request_line = request.lines().next().unwrap_or_default().split(' ');
+        let (method, path) = (
+            request_line.next().unwrap_or_default(),
+            request_line.next().unwrap_or_default(),
+        );
+        let header = |name: &str| {
+            request
+                .lines()
+                .skip(1)
+                .take_while(|line| !line.is_empty())
+                .find_map(|line| {
+                    let (key, value) = line.split_once(':')?;
+                    key.trim().eq_ignore_ascii_case(name).then(|| value.trim())
+                })
+        };
+        let host = header("Host").unwrap_or_default();
+        trace!("Status page request: {} {} for {}", method, path, host);
+
+        let response = match (method, path) {
+            _ if local_only && !is_loopback_host(host) => {
+                response("403 Forbidden", "text/plain", "Unknown host")
+            }
+            ("GET", "/") => response("200 OK", "text/html; charset=utf-8", &self.html()),
+            ("GET", "/status") => response(
+                "200 OK",
+                "application/json",
+                &serde_json::to_string_pretty(&self.snapshot()).unwrap_or_default(),
+            ),
+            ("POST", path) if path.starts_with("/restart/") => {
+                // Forms of other pages may post here too, but browsers name them in Origin
+                if !is_same_origin(header("Origin"), host) {
+                    response("403 Forbidden", "text/plain", "Cross-origin request")
+                } else if self.request_restart(&path["/restart/".len()..]) {
+                    "HTTP/1.1 303 See Other\r\nLocation: /\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string()
+                } else {
+                    response("404 Not Found", "text/plain", "Unknown component")
+                }
+            }
+            _ => response("404 Not Found", "text/plain", "Not found"),
+        };
+        stream.write_all(response.as_bytes()).await?;
+        stream.shutdown().await
+    }
+
+    fn html(&self) -> String {
+        let mut rows = String::new();
+        for (name, component) in self.snapshot() {
+            let name = escape_html(&name);
+            let port = match (&component.url, component.port) {
+                (Some(url), Some(port)) => format!("<a href=\"{}\">{}</a>", url, port),
+                _ => String::new(),
+            };
+            let resources = component
+                .resources
+                .map(|resources| resources.describe())
+                .unwrap_or_default();
+            let logs = component
+                .logs
+                .iter()
+                .map(|line| escape_html(line))
+                .collect::<Vec<_>>()
+                .join("\n");
+            rows.push_str(&format!(
+                "<tr><td><b>{name}</b></td><td>{status}</td><td>{port}</td><td>{resources}</td>\
+                 <td><form method=\"post\" action=\"/restart/{name}\"><button>Restart</button></form></td></tr>\
+                 <tr><td colspan=\"5\"><details><summary>Logs</summary><pre>{logs}</pre></details></td></tr>",
+                status = escape_html(&component.status),
+            ));
+        }
+        format!(
+            "<!DOCTYPE html><html><head><title>rush dev</title><meta http-equiv=\"refresh\" content=\"5\">\
+             <style>body{{font-family:sans-serif}}td{{padding:4px 12px}}pre{{max-height:300px;overflow:auto}}</style></head>\
+             <body><h1>rush dev</h1><table>{}</table></body></html>",
+            rows
+        )
+    }
+}
+
+fn response(status: &str, content_type: &str, body: &str) -> String {
+    format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )
+}
+
+/// Whether the Host header of a request names this machine, with or without a port.
+fn is_loopback_host(host: &str) -> bool {
+    let name = match host.strip_prefix('[') {
+        Some(rest) => rest.split(']').next().unwrap_or_default(),
+        None => host.rsplit_once(':').map_or(host, |(name, _)| name),
+    };
+    name.eq_ignore_ascii_case("localhost")
+        || name
+            .parse::<IpAddr>()
+            .is_ok_and(|address| address.is_loopback())
+}
+
+/// Whether a request comes from the page itself. Clients other than browsers send no
+/// Origin, those that do must send the Host they requested.
+fn is_same_origin(origin: Option<&str>, host: &str) -> bool {
+    match origin {
+        Some(origin) => origin
+            .split_once("://")
+            .is_some_and(|(_, authority)| authority.eq_ignore_ascii_case(host)),
+        None => true,
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_loopback_host() {
+        assert!(is_loopback_host("localhost:9999"));
+        assert!(is_loopback_host("127.0.0.1:9999"));
+        assert!(is_loopback_host("[::1]:9999"));
+        assert!(is_loopback_host("localhost"));
+        assert!(!is_loopback_host("attacker.example.com:9999"));
+        assert!(!is_loopback_host(""));
+    }
+
+    #[test]
+    fn test_is_same_origin() {
+        assert!(is_same_origin(None, "localhost:9999"));
+        assert!(is_same_origin(
+            Some("http://localhost:9999"),
+            "localhost:9999"
+        ));
+        assert!(!is_same_origin(
+            Some("https://attacker.example.com"),
+            "localhost:9999"
+        ));
+        assert!(!is_same_origin(Some("null"), "localhost:9999"));
+    }
+}
@@ -0,0 +1,132 @@
+use colored::Colorize;
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StepStatus {
+    Ok,
+    Failed,
+    Skipped,
+}
+
+/// One thing the reactor did, such as building an image or applying manifests.
+#[derive(Debug, Clone, Serialize)]
+pub struct Step {
+    /// build, push, render or apply
+    pub action: &'static str,
+    pub target: String,
+    pub status: StepStatus,
+    pub duration_ms: u128,
+}
+
+/// What a command built, pushed and applied, printed as a footer when it ends and
+/// written as JSON with `--summary-json`.
+#[derive(Debug, Clone)]
+pub struct ExecutionSummary {
+    started: Instant,
+    steps: Vec<Step>,
+}
+
+#[derive(Serialize)]
+struct SummaryReport<'a> {
+    command: &'a str,
+    success: bool,
+    duration_ms: u128,
+    steps: &'a [Step],
+}
+
+impl Default for ExecutionSummary {
+    fn default() -> Self {
+        Self {
+            started: Instant::now(),
+            steps: Vec::new(),
+        }
+    }
+}
+
+impl ExecutionSummary {
+    pub fn record(
+        &mut self,
+        action: &'static str,
+        target: &str,
+        status: StepStatus,
+        duration: Duration,
+    ) {
+        self.steps.push(Step {
+            action,
+            target: target.to_string(),
+            status,
+            duration_ms: duration.as_millis(),
+        });
+    }
+
+    pub fn skip(&mut self, action: &'static str, target: &str) {
+        self.record(action, target, StepStatus::Skipped, Duration::ZERO);
+    }
+
+    pub fn steps(&self) -> &[Step] {
+        &self.steps
+    }
+
+    /// Prints the steps with their durations and the outcome of `command`.
+    pub fn print(&self, command: &str, success: bool) {
+        let status = if success {
+            "OK".green().bold()
+        } else {
+            "FAILED".red().bold()
+        };
+        println!(
+            "\n{} {} [ {} ] in {}",
+            "Summary of".bold(),
+            command.bold(),
+            status,
+            format_duration(self.started.elapsed())
+        );
+
+        let target_width = self
+            .steps
+            .iter()
+            .map(|step| step.target.len())
+            .max()
+            .unwrap_or_default();
+        for step in &self.steps {
+            // Padded before coloring, as the escape codes would count towards the width
+            let status = match step.status {
+                StepStatus::Ok => format!("{:<7}", "OK").white().bold(),
+                StepStatus::Failed => format!("{:<7}", "FAILED").red().bold(),
+                StepStatus::Skipped => format!("{:<7}", "SKIPPED").yellow().bold(),
+            };
+            let duration = match step.status {
+                StepStatus::Skipped => String::new(),
+                _ => format_duration(Duration::from_millis(step.duration_ms as u64)),
+            };
+            let line = format!(
+                "  {:<7} {:<width$}  {}  {}",
+                step.action,
+                step.target,
+                status,
+                duration,
+                width = target_width
+            );
+            println!("{}", line.trim_end());
+        }
+    }
+
+    /// Writes the summary of `command` as JSON to `path`.
+    pub fn write_json(&self, path: &str, command: &str, success: bool) -> Result<(), String> {
+        let report = SummaryReport {
+            command,
+            success,
+            duration_ms: self.started.elapsed().as_millis(),
+            steps: &self.steps,
+        };
+        let json = serde_json::to_string_pretty(&report)
+            .map_err(|e| format!("Failed to serialize summary: {}", e))?;
+        std::fs::write(path, json).map_err(|e| format!("Failed to write {}: {}", path, e))
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    format!("{:.1}s", duration.as_secs_f64())
+}
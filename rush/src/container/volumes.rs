@@ -0,0 +1,157 @@
+use crate::utils::run_command;
+use colored::Colorize;
+use log::trace;
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+/// Label of the Docker volumes rush creates, holding the URI of their product.
+pub const PRODUCT_LABEL: &str = "rush.io/product";
+
+/// A named volume declared in the product's `stack.volumes.yaml`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VolumeSpec {
+    /// Name of the Docker volume. Defaults to the declared name after the product's URI,
+    /// e.g. `helloworld-wonop-io_pgdata`
+    pub name: Option<String>,
+    pub driver: Option<String>,
+    #[serde(default)]
+    pub driver_opts: BTreeMap<String, String>,
+    /// Created outside of rush, e.g. shared by several products. rush neither creates nor
+    /// prunes it, and its name defaults to the declared one
+    #[serde(default)]
+    pub external: bool,
+}
+
+/// The named volumes of a product, which components mount by their declared name in
+/// `volumes` instead of a host path.
+#[derive(Debug, Clone, Default)]
+pub struct ProductVolumes {
+    product_uri: String,
+    volumes: BTreeMap<String, VolumeSpec>,
+}
+
+/// Whether the source of a component's volume names a volume rather than a host path,
+/// as with Docker Compose: it has no slash and starts with neither `.` nor `~`.
+pub fn is_volume_name(source: &str) -> bool {
+    !source.contains('/') && !source.starts_with('.') && !source.starts_with('~')
+}
+
+impl ProductVolumes {
+    /// Loads `stack.volumes.yaml` of the product, if it has one.
+    pub fn load(product_dir: &Path, product_uri: &str) -> Result<Self, String> {
+        let path = product_dir.join("stack.volumes.yaml");
+        let volumes = match std::fs::read_to_string(&path) {
+            Ok(contents) => serde_yaml::from_str::<Option<BTreeMap<String, VolumeSpec>>>(&contents)
+                .map_err(|e| format!("Could not parse stack.volumes.yaml: {}", e))?
+                .unwrap_or_default(),
+            Err(_) => BTreeMap::new(),
+        };
+        Ok(Self {
+            product_uri: product_uri.to_string(),
+            volumes,
+        })
+    }
+
+    /// The name of the Docker volume declared as `name`, if it is declared.
+    pub fn docker_name(&self, name: &str) -> Option<String> {
+        let volume = self.volumes.get(name)?;
+        Some(match (&volume.name, volume.external) {
+            (Some(docker_name), _) => docker_name.clone(),
+            (None, true) => name.to_string(),
+            (None, false) => format!("{}_{}", self.product_uri, name),
+        })
+    }
+
+    /// Creates the declared volumes in `names` that do not exist yet, failing on
+    /// external ones that do not exist.
+    pub async fn create(&self, docker: &str, names: &BTreeSet<String>) -> Result<(), String> {
+        for name in names {
+            let (Some(volume), Some(docker_name)) =
+                (self.volumes.get(name), self.docker_name(name))
+            else {
+                continue;
+            };
+            let inspect = vec!["volume", "inspect", docker_name.as_str()];
+            if run_command("check".white().bold(), docker, inspect)
+                .await
+                .is_ok()
+            {
+                trace!("Docker volume '{}' already exists", docker_name);
+                continue;
+            }
+            if volume.external {
+                return Err(format!(
+                    "The external volume {} does not exist. Create it with: docker volume create {}",
+                    docker_name, docker_name
+                ));
+            }
+
+            let label = format!("{}={}", PRODUCT_LABEL, self.product_uri);
+            let mut args = vec!["volume".to_string(), "create".to_string()];
+            args.extend(["--label".to_string(), label]);
+            if let Some(driver) = &volume.driver {
+                args.extend(["--driver".to_string(), driver.clone()]);
+            }
+            for (key, value) in &volume.driver_opts {
+                args.extend(["--opt".to_string(), format!("{}={}", key, value)]);
+            }
+            args.push(docker_name.clone());
+            run_command(
+                "docker".into(),
+                docker,
+                args.iter().map(|arg| arg.as_str()).collect(),
+            )
+            .await
+            .map_err(|e| format!("Failed to create the volume {}: {}", docker_name, e))?;
+            println!("Created volume {}", docker_name);
+        }
+        Ok(())
+    }
+
+    /// Removes the volumes rush created for the product that are no longer declared, or
+    /// all of them with `all`, returning their names. With `dry_run`, only lists them.
+    pub async fn prune(
+        &self,
+        docker: &str,
+        all: bool,
+        dry_run: bool,
+    ) -> Result<Vec<String>, String> {
+        let filter = format!("label={}={}", PRODUCT_LABEL, self.product_uri);
+        let listing = run_command(
+            "docker".into(),
+            docker,
+            vec!["volume", "ls", "--quiet", "--filter", &filter],
+        )
+        .await
+        .map_err(|e| format!("Failed to list the volumes: {}", e))?;
+
+        let declared = self
+            .volumes
+            .keys()
+            .filter_map(|name| self.docker_name(name))
+            .collect::<BTreeSet<_>>();
+        let stale = listing
+            .lines()
+            .map(|line| line.trim().to_string())
+            .filter(|name| !name.is_empty() && (all || !declared.contains(name)))
+            .collect::<Vec<_>>();
+        if dry_run {
+            return Ok(stale);
+        }
+
+        let mut failures = Vec::new();
+        for name in &stale {
+            if let Err(e) =
+                run_command("docker".into(), docker, vec!["volume", "rm", name.as_str()]).await
+            {
+                failures.push(format!("Failed to remove the volume {}: {}", name, e));
+            }
+        }
+        if !failures.is_empty() {
+            return Err(failures.join("\n"));
+        }
+        Ok(stale)
+    }
+}
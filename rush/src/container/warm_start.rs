@@ -0,0 +1,101 @@
+use ignore::WalkBuilder;
+use log::{debug, trace};
+use openssl::sha::Sha256;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// The images `rush dev` built with the fingerprints of their inputs, kept in
+/// `target/warm-start.json`. The next session starts the components whose fingerprint
+/// is unchanged from these images right away and only builds the others.
+#[derive(Debug, Default)]
+pub struct WarmStart {
+    path: PathBuf,
+    images: BTreeMap<String, WarmImage>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct WarmImage {
+    fingerprint: String,
+    /// The tagged name the image was built as
+    image: String,
+}
+
+impl WarmStart {
+    pub fn load(product_directory: &Path) -> Self {
+        let path = product_directory.join("target").join("warm-start.json");
+        let images = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default();
+        Self { path, images }
+    }
+
+    /// Forgets the images of the previous session, so that every component is built.
+    pub fn forget(&mut self) {
+        self.images.clear();
+    }
+
+    /// The image built for `component_name` from inputs with this fingerprint.
+    pub fn image(&self, component_name: &str, fingerprint: &str) -> Option<&str> {
+        self.images
+            .get(component_name)
+            .filter(|image| image.fingerprint == fingerprint)
+            .map(|image| image.image.as_str())
+    }
+
+    /// Remembers the image built for `component_name` for the next session.
+    pub fn record(&mut self, component_name: &str, fingerprint: String, image: String) {
+        self.images
+            .insert(component_name.to_string(), WarmImage { fingerprint, image });
+        if let Some(parent) = self.path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        let contents = serde_json::to_string_pretty(&self.images).expect("Serializable images");
+        if let Err(e) = std::fs::write(&self.path, contents) {
+            debug!("Could not write {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+/// Hashes the inputs of an image: its build settings and the path, size and modification
+/// time of every file in `directories` that git does not ignore.
+pub fn fingerprint(settings: &str, directories: &[PathBuf]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(settings.as_bytes());
+    for directory in directories {
+        hasher.update(directory.to_string_lossy().as_bytes());
+        let mut files = WalkBuilder::new(directory)
+            .hidden(false)
+            .filter_entry(|entry| entry.file_name() != ".git")
+            .build()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| {
+                entry
+                    .file_type()
+                    .is_some_and(|file_type| file_type.is_file())
+            })
+            .filter_map(|entry| {
+                let metadata = entry.metadata().ok()?;
+                let modified = metadata
+                    .modified()
+                    .ok()?
+                    .duration_since(UNIX_EPOCH)
+                    .ok()?
+                    .as_nanos();
+                Some((entry.into_path(), metadata.len(), modified))
+            })
+            .collect::<Vec<_>>();
+        files.sort();
+        trace!(
+            "Fingerprinting {} files in {}",
+            files.len(),
+            directory.display()
+        );
+        for (path, size, modified) in files {
+            hasher.update(format!("{}:{}:{}\n", path.display(), size, modified).as_bytes());
+        }
+    }
+    hex::encode(hasher.finish())
+}
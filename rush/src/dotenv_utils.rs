@@ -20,20 +20,122 @@ pub fn load_dotenv(path: &Path) -> Result<HashMap<String, String>, std::io::Erro
 
         // Split the line into key and value
         if let Some((key, value)) = line.split_once('=') {
-            let key = key.trim().to_string();
-            let value = value.trim().to_string();
-            if value.starts_with('"') && value.ends_with('"') {
-                env_map.insert(key, value[1..value.len() - 1].to_string());
-            } else {
-                env_map.insert(key, value);
-            }
+            env_map.insert(key.trim().to_string(), parse_value(value).0);
         }
     }
 
     Ok(env_map)
 }
 
-pub fn save_dotenv(path: &Path, env_map: HashMap<String, String>) -> Result<(), std::io::Error> {
+/// The value of a `KEY=value` line from what follows the `=`, and the comment after it,
+/// with the whitespace before it, or "". Double-quoted values may escape `"`, `\` and
+/// newlines with a backslash, single-quoted values are taken as they are and unquoted
+/// values end at a `#` that follows whitespace.
+fn parse_value(raw: &str) -> (String, &str) {
+    let trimmed = raw.trim_start();
+    let quoted = match trimmed.chars().next() {
+        Some('"') => {
+            let mut value = String::new();
+            let mut chars = trimmed.char_indices().skip(1);
+            let mut end = None;
+            while let Some((i, c)) = chars.next() {
+                match c {
+                    '\\' => match chars.next() {
+                        Some((_, 'n')) => value.push('\n'),
+                        Some((_, c @ ('"' | '\\'))) => value.push(c),
+                        Some((_, c)) => {
+                            value.push('\\');
+                            value.push(c);
+                        }
+                        None => value.push('\\'),
+                    },
+                    '"' => {
+                        end = Some(i + 1);
+                        break;
+                    }
+                    c => value.push(c),
+                }
+            }
+            end.map(|end| (value, &trimmed[end..]))
+        }
+        Some('\'') => trimmed[1..]
+            .split_once('\'')
+            .map(|(value, rest)| (value.to_string(), rest)),
+        _ => None,
+    };
+    let (value, rest) = quoted.unwrap_or_else(|| {
+        let end = raw
+            .char_indices()
+            .find(|&(i, c)| c == '#' && raw[..i].ends_with(char::is_whitespace))
+            .map_or(raw.len(), |(i, _)| i);
+        let value = raw[..end].trim();
+        let value_end = raw[..end].trim_end().len();
+        (value.to_string(), &raw[value_end..])
+    });
+    let comment = if rest.trim_start().starts_with('#') {
+        rest.trim_end()
+    } else {
+        ""
+    };
+    (value, comment)
+}
+
+/// A value as `save_dotenv` writes it, double-quoted so `#`, whitespace and quotes are
+/// read back as they are.
+fn quote_value(value: &str) -> String {
+    format!(
+        "\"{}\"",
+        value
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
+    )
+}
+
+/// First line of the .env files rush writes.
+const MANAGED_HEADER: &str =
+    "# Managed by rush. Values are rewritten, comments and the order of variables are kept";
+
+/// Writes `env_map` to the .env file at `path`. Comments, including those after a value,
+/// blank lines and the order of the variables already in the file are kept, variables no
+/// longer in `env_map` are dropped and new ones are appended in alphabetical order.
+pub fn save_dotenv(
+    path: &Path,
+    mut env_map: HashMap<String, String>,
+) -> Result<(), std::io::Error> {
+    let existing = if path.exists() {
+        std::fs::read_to_string(path)?
+    } else {
+        String::new()
+    };
+
+    let mut lines = Vec::new();
+    if !existing.lines().any(|line| line == MANAGED_HEADER) {
+        lines.push(MANAGED_HEADER.to_string());
+    }
+    for line in existing.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            lines.push(line.to_string());
+            continue;
+        }
+        if let Some((key, raw)) = trimmed.split_once('=') {
+            let key = key.trim();
+            let (current, comment) = parse_value(raw);
+            match env_map.remove(key) {
+                // Unchanged lines are kept as written, e.g. without quotes
+                Some(value) if value == current => lines.push(line.to_string()),
+                Some(value) => lines.push(format!("{}={}{}", key, quote_value(&value), comment)),
+                None => {}
+            }
+        }
+    }
+    let mut new_keys: Vec<_> = env_map.into_iter().collect();
+    new_keys.sort();
+    for (key, value) in new_keys {
+        lines.push(format!("{}={}", key, quote_value(&value)));
+    }
+
     let mut file = match File::create(path) {
         Ok(file) => file,
         Err(e) => {
@@ -42,8 +144,8 @@ pub fn save_dotenv(path: &Path, env_map: HashMap<String, String>) -> Result<(),
         }
     };
 
-    for (key, value) in env_map {
-        match writeln!(file, "{}=\"{}\"", key, value) {
+    for line in lines {
+        match writeln!(file, "{}", line) {
             Ok(_) => (),
             Err(e) => {
                 error!("Failed to write to dotenv file '{}': {}", path.display(), e);
@@ -54,3 +156,90 @@ pub fn save_dotenv(path: &Path, env_map: HashMap<String, String>) -> Result<(),
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn save(existing: &str, env: &[(&str, &str)]) -> String {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join(".env");
+        std::fs::write(&path, existing).unwrap();
+        let env_map = env
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+        save_dotenv(&path, env_map).unwrap();
+        std::fs::read_to_string(&path).unwrap()
+    }
+
+    #[test]
+    fn test_save_keeps_the_order() {
+        let saved = save("B=1\nA=2\n", &[("A", "2"), ("B", "3")]);
+        assert_eq!(saved, format!("{}\nB=\"3\"\nA=2\n", MANAGED_HEADER));
+    }
+
+    #[test]
+    fn test_save_keeps_comments() {
+        let saved = save(
+            "# The database\n\nURL=old # staging only\nPORT=5432 # default\n",
+            &[("URL", "new"), ("PORT", "5432")],
+        );
+        assert_eq!(
+            saved,
+            format!(
+                "{}\n# The database\n\nURL=\"new\" # staging only\nPORT=5432 # default\n",
+                MANAGED_HEADER
+            )
+        );
+    }
+
+    #[test]
+    fn test_save_drops_removed_keys() {
+        let saved = save("A=1\nB=2\n", &[("B", "2")]);
+        assert_eq!(saved, format!("{}\nB=2\n", MANAGED_HEADER));
+    }
+
+    #[test]
+    fn test_save_appends_new_keys() {
+        let saved = save("B=1\n", &[("B", "1"), ("D", "4"), ("C", "3")]);
+        assert_eq!(
+            saved,
+            format!("{}\nB=1\nC=\"3\"\nD=\"4\"\n", MANAGED_HEADER)
+        );
+    }
+
+    #[test]
+    fn test_save_quotes_what_it_loads_back() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join(".env");
+        let env_map = HashMap::from([
+            ("QUOTE".to_string(), "say \"hi\"".to_string()),
+            ("HASH".to_string(), "a #b".to_string()),
+            ("PATH".to_string(), "C:\\rush".to_string()),
+            ("LINES".to_string(), "one\ntwo".to_string()),
+        ]);
+        save_dotenv(&path, env_map.clone()).unwrap();
+        assert_eq!(load_dotenv(&path).unwrap(), env_map);
+    }
+
+    #[test]
+    fn test_parse_value() {
+        assert_eq!(
+            parse_value("value # note"),
+            ("value".to_string(), " # note")
+        );
+        assert_eq!(parse_value("#fff"), ("#fff".to_string(), ""));
+        assert_eq!(parse_value("a#b"), ("a#b".to_string(), ""));
+        assert_eq!(
+            parse_value("\"a # b\" # note"),
+            ("a # b".to_string(), " # note")
+        );
+        assert_eq!(parse_value("'a \\n b'"), ("a \\n b".to_string(), ""));
+        assert_eq!(
+            parse_value("\"unterminated"),
+            ("\"unterminated".to_string(), "")
+        );
+    }
+}
@@ -0,0 +1,55 @@
+use thiserror::Error;
+
+/// Errors of rush, grouped by what has to be fixed. The command line tool exits with
+/// a distinct code for each group, see [`RushError::exit_code`].
+#[derive(Debug, Error)]
+pub enum RushError {
+    /// rushd.yaml, the environment settings or the command line arguments
+    #[error("{0}")]
+    Config(String),
+    /// A component definition in stack.spec.yaml
+    #[error("Invalid component {component}: {message}")]
+    Spec { component: String, message: String },
+    /// Setting up or reading a vault and its secrets definitions
+    #[error("{0}")]
+    Vault(String),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    /// Anything else, such as a failing build or rollout
+    #[error("{0}")]
+    Other(String),
+}
+
+pub type Result<T> = std::result::Result<T, RushError>;
+
+impl RushError {
+    pub fn spec(component: &str, message: impl Into<String>) -> Self {
+        RushError::Spec {
+            component: component.to_string(),
+            message: message.into(),
+        }
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            RushError::Other(_) => 1,
+            RushError::Config(_) => 2,
+            RushError::Spec { .. } => 3,
+            RushError::Vault(_) => 4,
+            RushError::Io(_) => 5,
+        }
+    }
+}
+
+// Most of rush still reports errors as strings
+impl From<String> for RushError {
+    fn from(message: String) -> Self {
+        RushError::Other(message)
+    }
+}
+
+impl From<RushError> for String {
+    fn from(error: RushError) -> Self {
+        error.to_string()
+    }
+}
@@ -0,0 +1,165 @@
+use crate::builder::{BuildType, Config};
+use crate::container::docker::DockerImage;
+use crate::toolchain::{ToolchainContext, PINNED_TOOLS};
+use serde_json::json;
+use std::collections::BTreeSet;
+use std::path::Path;
+
+/// Where the dev container installs the pinned tools, see `rush toolchain install`.
+const TOOLCHAIN_DIR: &str = "/usr/local/rush/bin";
+
+/// Writes the devcontainer.json and Dockerfile of a dev container for the product, with
+/// the cross toolchain of the target, the pinned tools and rush installed.
+///
+/// The container runs rush from the repository, so RUSHD_ROOT points at the workspace,
+/// the rushd root's .env is passed in and the components' dev ports are forwarded.
+pub fn devcontainer(
+    config: &Config,
+    toolchain: &ToolchainContext,
+    images: &[DockerImage],
+    root_directory: &Path,
+) -> Result<Vec<(&'static str, String)>, String> {
+    let build_types = images
+        .iter()
+        .map(|image| image.spec().build_type)
+        .collect::<Vec<_>>();
+
+    let mut ports = serde_json::Map::new();
+    for image in images {
+        if let Some(port) = image.port() {
+            ports.insert(
+                port.to_string(),
+                json!({ "label": image.component_name(), "onAutoForward": "silent" }),
+            );
+        }
+    }
+    let forward_ports = ports
+        .keys()
+        .filter_map(|port| port.parse::<u16>().ok())
+        .collect::<BTreeSet<_>>();
+
+    let mut devcontainer = json!({
+        "name": config.product_name(),
+        "build": { "dockerfile": "Dockerfile" },
+        "features": {
+            "ghcr.io/devcontainers/features/docker-outside-of-docker:1": {},
+        },
+        "containerEnv": {
+            "RUSHD_ROOT": "${containerWorkspaceFolder}",
+            "RUSH_TOOLCHAIN_DIR": TOOLCHAIN_DIR,
+        },
+        // The host's kube contexts, created first as bind mounts need an existing source
+        "initializeCommand": "mkdir -p \"$HOME/.kube\"",
+        "mounts": [
+            "source=${localEnv:HOME}/.kube,target=/home/vscode/.kube,type=bind,consistency=cached",
+        ],
+        "forwardPorts": forward_ports,
+        "portsAttributes": ports,
+        "postCreateCommand": format!(
+            "rush --env {} {} describe toolchain --versions",
+            config.environment(),
+            config.product_name()
+        ),
+        "customizations": {
+            "vscode": { "extensions": ["rust-lang.rust-analyzer"] },
+        },
+    });
+    if root_directory.join(".env").is_file() {
+        devcontainer["runArgs"] = json!(["--env-file", "${localWorkspaceFolder}/.env"]);
+    }
+    let devcontainer = serde_json::to_string_pretty(&devcontainer).map_err(|e| e.to_string())?;
+
+    Ok(vec![
+        ("devcontainer.json", format!("{}\n", devcontainer)),
+        ("Dockerfile", dockerfile(toolchain, &build_types)),
+    ])
+}
+
+fn dockerfile(toolchain: &ToolchainContext, build_types: &[BuildType]) -> String {
+    let target = toolchain.target();
+    let has = |matches: fn(&BuildType) -> bool| build_types.iter().any(matches);
+
+    // Debian names its cross compilers after the target, and installs them on any arch
+    let mut packages = if target.is_musl() {
+        vec!["musl-tools".to_string()]
+    } else {
+        let arch = target.arch.to_string().replace('_', "-");
+        vec![
+            format!("gcc-{}-linux-gnu", arch),
+            format!("g++-{}-linux-gnu", arch),
+        ]
+    };
+    packages.push("curl".to_string());
+
+    let mut rust_targets = vec![target.to_rust_target()];
+    let mut crates = Vec::new();
+    if has(|build_type| matches!(build_type, BuildType::TrunkWasm { .. })) {
+        crates.push("trunk");
+    }
+    if has(|build_type| matches!(build_type, BuildType::DixiousWasm { .. })) {
+        crates.push("dioxus-cli");
+    }
+    if has(|build_type| {
+        matches!(
+            build_type,
+            BuildType::TrunkWasm { .. } | BuildType::DixiousWasm { .. }
+        )
+    }) {
+        rust_targets.push("wasm32-unknown-unknown".to_string());
+    }
+    if has(|build_type| matches!(build_type, BuildType::Book { .. })) {
+        crates.push("mdbook");
+    }
+
+    let tools = PINNED_TOOLS
+        .iter()
+        .map(|tool| {
+            let url = tool.url_for("linux", "${ARCH}");
+            if tool.archive {
+                format!(
+                    "curl -fsSL \"{}\" | tar -xzO {} > {}/{}",
+                    url, tool.name, TOOLCHAIN_DIR, tool.name
+                )
+            } else {
+                format!("curl -fsSL -o {}/{} \"{}\"", TOOLCHAIN_DIR, tool.name, url)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" \\\n    && ");
+
+    let mut dockerfile = format!(
+        "# Generated by `rush export devcontainer`\n\
+         FROM mcr.microsoft.com/devcontainers/rust:1-bookworm\n\n\
+         RUN apt-get update \\\n    \
+             && apt-get install -y --no-install-recommends {} \\\n    \
+             && rm -rf /var/lib/apt/lists/*\n\n\
+         RUN rustup target add {}\n",
+        packages.join(" "),
+        rust_targets.join(" "),
+    );
+    if !crates.is_empty() {
+        dockerfile.push_str(&format!(
+            "RUN cargo install --locked {}\n",
+            crates.join(" ")
+        ));
+    }
+    dockerfile.push_str(&format!(
+        "RUN cargo install --locked rush-cli --version {version}\n\n\
+         # The tools `rush toolchain install` pins\n\
+         ENV RUSH_TOOLCHAIN_DIR={toolchain_dir}\n\
+         ENV PATH={toolchain_dir}:$PATH\n\
+         RUN ARCH=$(dpkg --print-architecture) \\\n    \
+             && mkdir -p {toolchain_dir} \\\n    \
+             && {tools} \\\n    \
+             && chmod +x {toolchain_dir}/*\n",
+        version = env!("CARGO_PKG_VERSION"),
+        toolchain_dir = TOOLCHAIN_DIR,
+        tools = tools,
+    ));
+    if target.is_musl() {
+        dockerfile.push_str(
+            "\n# musl-tools only builds for the container's own arch, use CROSS_COMPILER=zig for others\n",
+        );
+    }
+    dockerfile
+}
@@ -0,0 +1,121 @@
+pub mod devcontainer;
+pub mod skaffold;
+pub mod tilt;
+
+pub use devcontainer::devcontainer;
+pub use skaffold::skaffold_config;
+pub use tilt::tiltfile;
+
+use crate::builder::Config;
+use crate::container::docker::DockerImage;
+use crate::toolchain::ToolchainContext;
+use std::path::{Component, Path, PathBuf};
+
+/// An image rush builds, with its paths relative to the product directory.
+struct DockerArtifact {
+    component_name: String,
+    /// The name the rendered manifests refer to the image by
+    image: String,
+    context: PathBuf,
+    dockerfile: PathBuf,
+    platform: String,
+    rust_target: String,
+    watch: Vec<String>,
+}
+
+/// The images of components built from a Dockerfile. Pulled images are left out.
+fn docker_artifacts(
+    config: &Config,
+    toolchain: &ToolchainContext,
+    images: &[DockerImage],
+) -> Result<Vec<DockerArtifact>, String> {
+    let mut artifacts = Vec::new();
+    for image in images {
+        let description = image.describe();
+        let Some(dockerfile) = description.build_type.dockerfile_path() else {
+            continue;
+        };
+        // rush builds from the Dockerfile's directory, with context_dir relative to it
+        let dockerfile = normalize(Path::new(dockerfile));
+        let dockerfile_directory = dockerfile.parent().unwrap_or(Path::new(""));
+        let context = normalize(
+            &dockerfile_directory.join(description.context_dir.as_deref().unwrap_or(".")),
+        );
+        if context.starts_with("..") {
+            return Err(format!(
+                "The build context of {} lies outside the product directory",
+                description.component_name
+            ));
+        }
+
+        let watch = image
+            .spec()
+            .watch
+            .map(|watch| watch.patterns().into_iter().map(String::from).collect())
+            .unwrap_or_default();
+        artifacts.push(DockerArtifact {
+            image: image_repository(config.docker_registry(), &description.image_name),
+            component_name: description.component_name,
+            context,
+            dockerfile,
+            platform: toolchain.target().to_docker_target(),
+            rust_target: toolchain.target().to_rust_target(),
+            watch,
+        });
+    }
+    Ok(artifacts)
+}
+
+/// The image name the rendered manifests use, `{{ docker_registry }}/{{ image_name }}`
+/// without the tag, which is what skaffold and Tilt match images by.
+fn image_repository(docker_registry: &str, image_name: &str) -> String {
+    if docker_registry.is_empty() {
+        image_name.to_string()
+    } else {
+        format!("{}/{}", docker_registry.trim_end_matches('/'), image_name)
+    }
+}
+
+/// Resolves `.` and `..` without touching the file system.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if matches!(
+                    normalized.components().next_back(),
+                    None | Some(Component::ParentDir)
+                ) {
+                    normalized.push("..");
+                } else {
+                    normalized.pop();
+                }
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// `path` relative to `base`, both normalized and relative to the same directory.
+fn relative_to(path: &Path, base: &Path) -> PathBuf {
+    let path = path.components().collect::<Vec<_>>();
+    let base = base.components().collect::<Vec<_>>();
+    let common = path.iter().zip(&base).take_while(|(a, b)| a == b).count();
+    let mut relative = PathBuf::new();
+    for _ in common..base.len() {
+        relative.push("..");
+    }
+    for component in &path[common..] {
+        relative.push(component);
+    }
+    relative
+}
+
+fn display(path: &Path) -> String {
+    match path.to_string_lossy().as_ref() {
+        "" => ".".to_string(),
+        path => path.to_string(),
+    }
+}
@@ -0,0 +1,58 @@
+use super::{display, docker_artifacts, normalize, relative_to};
+use crate::builder::Config;
+use crate::container::docker::DockerImage;
+use crate::toolchain::ToolchainContext;
+use serde_json::json;
+use std::path::Path;
+
+const SKAFFOLD_API_VERSION: &str = "skaffold/v4beta11";
+
+/// Writes a skaffold.yaml for the product directory, which builds the images with their
+/// Dockerfiles and deploys the manifests rush renders into `manifests_directory`.
+///
+/// Skaffold runs neither the build scripts nor the manifest templates, so the
+/// header asks for a `rush build` first.
+pub fn skaffold_config(
+    config: &Config,
+    toolchain: &ToolchainContext,
+    images: &[DockerImage],
+    manifests_directory: &Path,
+) -> Result<String, String> {
+    let artifacts = docker_artifacts(config, toolchain, images)?
+        .into_iter()
+        .map(|artifact| {
+            json!({
+                "image": artifact.image,
+                "context": display(&artifact.context),
+                "platforms": [artifact.platform],
+                "docker": {
+                    "dockerfile": display(&relative_to(&artifact.dockerfile, &artifact.context)),
+                    "buildArgs": { "RUST_TARGET": artifact.rust_target },
+                },
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let manifests = display(&normalize(&manifests_directory.join("*").join("*.yaml")));
+    let mut skaffold = json!({
+        "apiVersion": SKAFFOLD_API_VERSION,
+        "kind": "Config",
+        "metadata": { "name": config.product_uri() },
+        "build": { "artifacts": artifacts },
+        "manifests": { "rawYaml": [manifests] },
+        "deploy": { "kubectl": {} },
+    });
+    if !config.kube_context().is_empty() {
+        skaffold["deploy"]["kubeContext"] = json!(config.kube_context());
+    }
+
+    let yaml = serde_yaml::to_string(&skaffold).map_err(|e| e.to_string())?;
+    Ok(format!(
+        "# Exported by rush from stack.spec.yaml for the {environment} environment.\n\
+         # Run `rush --env {environment} {product} build` before skaffold: it compiles the\n\
+         # artefacts the Dockerfiles copy and renders the manifests into {manifests}.\n{yaml}",
+        environment = config.environment(),
+        product = config.product_name(),
+        manifests = display(&normalize(manifests_directory)),
+    ))
+}
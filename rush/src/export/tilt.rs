@@ -0,0 +1,208 @@
+use super::{display, docker_artifacts, normalize, DockerArtifact};
+use crate::builder::Config;
+use crate::container::docker::DockerImage;
+use crate::toolchain::ToolchainContext;
+use log::debug;
+use std::path::{Path, PathBuf};
+
+/// Writes a Tiltfile for the product directory, which builds the images with their
+/// Dockerfiles and deploys the manifests rush renders into `manifests_directory`.
+///
+/// The `watch` globs of a component become live updates: the directories they name
+/// are synced into the running container wherever the Dockerfile copies them.
+pub fn tiltfile(
+    config: &Config,
+    toolchain: &ToolchainContext,
+    images: &[DockerImage],
+    product_directory: &Path,
+    manifests_directory: &Path,
+) -> Result<String, String> {
+    let mut tiltfile = format!(
+        "# Exported by rush from stack.spec.yaml for the {environment} environment.\n\
+         # Run `rush --env {environment} {product} build` before tilt: it compiles the\n\
+         # artefacts the Dockerfiles copy and renders the manifests.\n\n",
+        environment = config.environment(),
+        product = config.product_name(),
+    );
+    if !config.kube_context().is_empty() {
+        tiltfile.push_str(&format!(
+            "allow_k8s_contexts({})\n\n",
+            quote(config.kube_context())
+        ));
+    }
+    tiltfile.push_str(&format!(
+        "k8s_yaml([path for path in listdir({}, recursive=True) if path.endswith(\".yaml\")])\n",
+        quote(&display(&normalize(manifests_directory)))
+    ));
+
+    for artifact in docker_artifacts(config, toolchain, images)? {
+        tiltfile.push_str(&format!(
+            "\n# {}\ndocker_build(\n    {},\n    {},\n    dockerfile={},\n    platform={},\n    build_args={{\"RUST_TARGET\": {}}},\n",
+            artifact.component_name,
+            quote(&artifact.image),
+            quote(&display(&artifact.context)),
+            quote(&display(&artifact.dockerfile)),
+            quote(&artifact.platform),
+            quote(&artifact.rust_target),
+        ));
+        let syncs = live_update_syncs(&artifact, product_directory)?;
+        if !syncs.is_empty() {
+            tiltfile.push_str("    live_update=[\n");
+            for (local, remote) in syncs {
+                tiltfile.push_str(&format!(
+                    "        sync({}, {}),\n",
+                    quote(&display(&local)),
+                    quote(&remote)
+                ));
+            }
+            tiltfile.push_str("    ],\n");
+        }
+        tiltfile.push_str(")\n");
+    }
+    Ok(tiltfile)
+}
+
+/// Pairs each directory named by a `watch` glob with where the Dockerfile copies it to.
+fn live_update_syncs(
+    artifact: &DockerArtifact,
+    product_directory: &Path,
+) -> Result<Vec<(PathBuf, String)>, String> {
+    if artifact.watch.is_empty() {
+        return Ok(Vec::new());
+    }
+    let dockerfile_path = product_directory.join(&artifact.dockerfile);
+    let dockerfile = std::fs::read_to_string(&dockerfile_path)
+        .map_err(|e| format!("Failed to read {}: {}", dockerfile_path.display(), e))?;
+    let copies = copy_instructions(&dockerfile);
+
+    let mut syncs = Vec::new();
+    for watched in artifact
+        .watch
+        .iter()
+        .filter_map(|glob| literal_prefix(glob))
+    {
+        if !watched.starts_with(&artifact.context) {
+            debug!(
+                "Not syncing {}, it lies outside the build context of {}",
+                watched.display(),
+                artifact.component_name
+            );
+            continue;
+        }
+        for (source, destination) in &copies {
+            let source = normalize(&artifact.context.join(source));
+            let (local, remainder) = if let Ok(remainder) = watched.strip_prefix(&source) {
+                (watched.clone(), remainder.to_path_buf())
+            } else if source.starts_with(&watched) {
+                (source.clone(), PathBuf::new())
+            } else {
+                continue;
+            };
+
+            // A single file copied into a directory keeps its name
+            let remote = if product_directory.join(&source).is_file() && destination.ends_with('/')
+            {
+                format!(
+                    "{}{}",
+                    destination,
+                    display(Path::new(source.file_name().unwrap_or_default()))
+                )
+            } else if remainder.as_os_str().is_empty() {
+                destination.clone()
+            } else {
+                format!(
+                    "{}/{}",
+                    destination.trim_end_matches('/'),
+                    display(&remainder)
+                )
+            };
+            let sync = (local, remote);
+            if !syncs.contains(&sync) {
+                syncs.push(sync);
+            }
+        }
+    }
+    Ok(syncs)
+}
+
+/// The directories of a `watch` glob before its first wildcard, e.g. `webui/src` for
+/// `webui/src/**/*.rs`. Negations and globs starting with a wildcard name none.
+fn literal_prefix(glob: &str) -> Option<PathBuf> {
+    if glob.starts_with('!') {
+        return None;
+    }
+    let prefix = glob
+        .trim_start_matches('/')
+        .split('/')
+        .take_while(|part| !part.contains(['*', '?', '[']))
+        .collect::<PathBuf>();
+    let prefix = normalize(&prefix);
+    (!prefix.as_os_str().is_empty()).then_some(prefix)
+}
+
+/// The sources and absolute destinations of the COPY and ADD instructions of the
+/// Dockerfile's final stage. Copies from other stages and paths with variables are
+/// left out, as are globs.
+fn copy_instructions(dockerfile: &str) -> Vec<(PathBuf, String)> {
+    let mut copies = Vec::new();
+    let mut workdir = Some("/".to_string());
+    for instruction in dockerfile.replace("\\\n", " ").lines() {
+        let mut words = instruction.split_whitespace();
+        let keyword = words.next().unwrap_or_default().to_uppercase();
+        let arguments = words.collect::<Vec<_>>();
+        match keyword.as_str() {
+            "FROM" => {
+                copies.clear();
+                workdir = Some("/".to_string());
+            }
+            "WORKDIR" => {
+                let directory = arguments.join(" ");
+                workdir = match workdir {
+                    _ if directory.contains('$') => None,
+                    _ if directory.starts_with('/') => Some(directory),
+                    Some(workdir) => {
+                        Some(format!("{}/{}", workdir.trim_end_matches('/'), directory))
+                    }
+                    None => None,
+                };
+            }
+            "COPY" | "ADD" => {
+                if arguments
+                    .iter()
+                    .any(|argument| argument.starts_with("--from"))
+                {
+                    continue;
+                }
+                let paths = arguments
+                    .iter()
+                    .filter(|argument| !argument.starts_with("--"))
+                    .copied()
+                    .collect::<Vec<_>>();
+                let Some((destination, sources)) = paths.split_last() else {
+                    continue;
+                };
+                let destination = match (&workdir, destination.starts_with('/')) {
+                    _ if destination.contains('$') => continue,
+                    (_, true) => destination.to_string(),
+                    (Some(workdir), false) => match *destination {
+                        "." | "./" => format!("{}/", workdir.trim_end_matches('/')),
+                        destination => format!("{}/{}", workdir.trim_end_matches('/'), destination),
+                    },
+                    (None, false) => continue,
+                };
+                for source in sources {
+                    if !source.contains(['$', '*', '?', '[']) {
+                        copies.push((PathBuf::from(source), destination.clone()));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    copies
+}
+
+/// A Starlark string literal, which shares its escapes with JSON.
+fn quote(value: &str) -> String {
+    serde_json::to_string(value).unwrap_or_default()
+}
@@ -0,0 +1,74 @@
+//! The building blocks of the `rush` command line tool, for driving builds and
+//! deploys from other programs instead of shelling out to the binary.
+//!
+//! A product is described by a [`builder::Config`], which reads the settings that
+//! rushd.yaml exports as environment variables. A [`container::ContainerReactor`]
+//! then builds, runs, pushes and deploys the product's components using the tools
+//! found by a [`toolchain::ToolchainContext`] and the secrets of a [`vault::Vault`]:
+//!
+//! ```no_run
+//! use rush_core::builder::Config;
+//! use rush_core::cluster::{K8Encoder, NoopEncoder};
+//! use rush_core::container::ContainerReactor;
+//! use rush_core::error::Result;
+//! use rush_core::toolchain::{Platform, ToolchainContext};
+//! use rush_core::vault::{Base64SecretsEncoder, DotenvVault, Vault};
+//! use std::collections::HashMap;
+//! use std::sync::{Arc, Mutex};
+//!
+//! # async fn build() -> Result<()> {
+//! let config = Config::new(".", "helloworld.wonop.io", "local", "registry.example.com", 8129, HashMap::new())?;
+//! let toolchain = Arc::new(ToolchainContext::new(Platform::default(), Platform::new("linux", "x86_64")));
+//! toolchain.setup_env();
+//! let vault = Arc::new(Mutex::new(DotenvVault::new(config.product_path().into())?))
+//!     as Arc<Mutex<dyn Vault + Send>>;
+//!
+//! let mut reactor = ContainerReactor::from_product_dir(
+//!     config,
+//!     toolchain,
+//!     vault,
+//!     Arc::new(Base64SecretsEncoder),
+//!     Arc::new(NoopEncoder) as Arc<dyn K8Encoder>,
+//!     HashMap::new(),
+//!     Vec::new(),
+//! )?;
+//! reactor.build().await?;
+//! # Ok(())
+//! # }
+//! ```
+
+#[macro_use]
+extern crate tera;
+
+/// Product configuration, component specs and the build scripts rendered from them.
+pub mod builder;
+/// Kubernetes manifests, local clusters and GitOps rollouts.
+pub mod cluster;
+/// JSON Schema validation of rushd.yaml and the stack files.
+pub mod config_schema;
+/// The reactor building and running a product's containers.
+pub mod container;
+pub mod dotenv_utils;
+/// The error type of the library and the exit codes of the command line tool.
+pub mod error;
+/// Configuration for other tools, generated from the product's stack files.
+pub mod export;
+pub mod path_matcher;
+/// Progress bars for builds, pushes and manifest rendering.
+pub mod progress;
+/// Public environment variables generated into each component's .env file.
+pub mod public_env_defs;
+/// Tasks defined in rushd.yaml.
+pub mod task;
+/// Traces of rush operations, exported to an OpenTelemetry collector over OTLP.
+pub mod telemetry;
+/// Mock docker and kubectl for tests of the reactor, behind the `test-util` feature.
+#[cfg(feature = "test-util")]
+pub mod testing;
+/// Detection of the build tools for the host and target platforms.
+pub mod toolchain;
+/// Personal defaults from ~/.config/rush/config.yaml.
+pub mod user_config;
+pub mod utils;
+/// Secret storage backends and the encoders for Kubernetes secrets.
+pub mod vault;
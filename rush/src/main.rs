@@ -1,39 +1,35 @@
-#[macro_use]
-extern crate tera;
-
-mod builder;
-mod cluster;
-mod container;
-mod dotenv_utils;
-mod path_matcher;
-mod public_env_defs;
-mod toolchain;
-mod utils;
-mod vault;
-
-use crate::builder::Config;
-use crate::cluster::{K8Encoder, NoopEncoder, SealedSecretsEncoder};
-use crate::container::ContainerReactor;
-use crate::public_env_defs::PublicEnvironmentDefinitions;
-use crate::toolchain::Platform;
-use crate::toolchain::ToolchainContext;
-use crate::utils::Directory;
-use crate::vault::Base64SecretsEncoder;
-use crate::vault::SecretsDefinitions;
-use clap::{arg, value_parser, Arg, Command};
-use cluster::Minikube;
+use clap::{arg, parser::ValueSource, value_parser, Arg, ArgAction, ArgMatches, Command};
 use colored::Colorize;
 use log::warn;
 use log::{debug, error, info, trace};
-use serde::Deserialize;
-use std::collections::HashMap;
+use rush_core::builder::{load_with_local_overrides, Config};
+use rush_core::cluster::{changelog, HostMount, K3d, Kind, LocalCluster, Minikube};
+use rush_core::cluster::{compare_golden, update_golden, GoldenDifference};
+use rush_core::cluster::{K8Encoder, KubesealSettings, NoopEncoder, SealedSecretsEncoder};
+use rush_core::container::docker::DockerImage;
+use rush_core::container::{
+    BuildProfiler, ContainerReactor, DependencyGraph, EventSink, RegistryClient,
+};
+use rush_core::error::RushError;
+use rush_core::export::{devcontainer, skaffold_config, tiltfile};
+use rush_core::public_env_defs::{EnvChange, PublicEnvironmentDefinitions, RUST_MODULE_HEADER};
+use rush_core::task::Tasks;
+use rush_core::toolchain::Platform;
+use rush_core::toolchain::{ToolVersion, ToolchainContext};
+use rush_core::user_config::{UpdateCheck, UserConfig};
+use rush_core::utils::Directory;
+use rush_core::vault::Base64SecretsEncoder;
+use rush_core::vault::SecretsDefinitions;
+use rush_core::vault::{DotenvVault, MemoryVault, OnePassword, PlaceholderVault, Vault};
+use rush_core::{config_schema, task, telemetry, toolchain, utils};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 use std::env;
-use std::fs::File;
-use std::io::Read;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::sync::Mutex;
-use std::{path::Path, sync::Arc};
 use tokio::io;
-use vault::{DotenvVault, OnePassword, Vault};
 
 fn setup_environment() {
     trace!("Setting up environment");
@@ -64,60 +60,8 @@ fn setup_environment() {
     // let new_path = env::join_paths([current_path, cargo_bin.into()].iter()).unwrap();
     // env::set_var("PATH", new_path);
 
-    // Set toolchain environment variables for macOS ARM architecture
-    if cfg!(target_os = "macos") && cfg!(target_arch = "arm") {
-        trace!("Setting up toolchain for macOS ARM architecture");
-
-        let toolchain_base = "/opt/homebrew/Cellar/x86_64-unknown-linux-gnu";
-        let toolchain_path = std::fs::read_dir(toolchain_base)
-            .expect("Failed to read toolchain directory")
-            .filter_map(|entry| entry.ok())
-            .filter(|entry| entry.file_type().map(|ft| ft.is_dir()).unwrap_or(false))
-            .max_by_key(|entry| entry.file_name())
-            .map(|entry| entry.path().join("bin").to_string_lossy().into_owned())
-            .expect("No toolchain version found");
-
-        let toolchain_path = format!("{}/", toolchain_path);
-        debug!("Using toolchain path: {}", toolchain_path);
-
-        env::set_var(
-            "CC",
-            format!("{}x86_64-unknown-linux-gnu-gcc", toolchain_path),
-        );
-        env::set_var(
-            "CXX",
-            format!("{}x86_64-unknown-linux-gnu-g++", toolchain_path),
-        );
-        env::set_var(
-            "AR",
-            format!("{}x86_64-unknown-linux-gnu-ar", toolchain_path),
-        );
-        env::set_var(
-            "RANLIB",
-            format!("{}x86_64-unknown-linux-gnu-ranlib", toolchain_path),
-        );
-        env::set_var(
-            "NM",
-            format!("{}x86_64-unknown-linux-gnu-nm", toolchain_path),
-        );
-        env::set_var(
-            "STRIP",
-            format!("{}x86_64-unknown-linux-gnu-strip", toolchain_path),
-        );
-        env::set_var(
-            "OBJDUMP",
-            format!("{}x86_64-unknown-linux-gnu-objdump", toolchain_path),
-        );
-        env::set_var(
-            "OBJCOPY",
-            format!("{}x86_64-unknown-linux-gnu-objcopy", toolchain_path),
-        );
-        env::set_var(
-            "LD",
-            format!("{}x86_64-unknown-linux-gnu-ld", toolchain_path),
-        );
-        debug!("Toolchain environment variables set for macOS ARM");
-    }
+    // Tools installed with `rush toolchain install` win over the ones on PATH
+    toolchain::prefer_managed_tools();
 
     trace!("Environment setup complete");
 }
@@ -125,28 +69,517 @@ fn setup_environment() {
 #[derive(Debug, Deserialize)]
 struct RushdConfig {
     env: HashMap<String, String>,
+    #[serde(default)]
+    tasks: Tasks,
+    /// Components whose output `dev` silences, usually set in rushd.local.yaml
+    #[serde(default)]
+    silence: Vec<String>,
 }
 
-fn load_config() {
+fn load_config(user_config: &UserConfig) -> Result<RushdConfig, RushError> {
     trace!("Loading configuration");
-    let config_path = "rushd.yaml";
-    let mut file = File::open(config_path).expect("Unable to open the config file");
-    let mut contents = String::new();
-    file.read_to_string(&mut contents)
-        .expect("Unable to read the config file");
-
-    let config: RushdConfig =
-        serde_yaml::from_str(&contents).expect("Error parsing the config file");
-
-    for (key, value) in config.env {
-        debug!(
-            "Set environment variable: {}={}",
-            key.clone(),
-            value.clone()
-        );
-        std::env::set_var(key, &value);
+    // Personal defaults, overridden by the shell and by rushd.yaml below
+    for (key, value) in user_config.env() {
+        if env::var_os(&key).is_none() {
+            debug!(
+                "Set environment variable from user config: {}={}",
+                key, value
+            );
+            std::env::set_var(key, value);
+        }
+    }
+
+    // rushd.local.yaml is merged on top of rushd.yaml
+    let contents = load_with_local_overrides(Path::new("rushd.yaml")).map_err(RushError::Config)?;
+    let config: RushdConfig = serde_yaml::from_value(contents)
+        .map_err(|e| RushError::Config(format!("Failed to parse rushd.yaml: {}", e)))?;
+
+    for (key, value) in &config.env {
+        debug!("Set environment variable: {}={}", key, value);
+        std::env::set_var(key, value);
     }
     trace!("Configuration loaded successfully");
+    Ok(config)
+}
+
+/// The vault of the config's environment, or with `no_vault` the fixtures of the product
+/// in memory.
+fn create_vault(
+    config: &Config,
+    no_vault: bool,
+) -> Result<Arc<Mutex<dyn Vault + Send>>, RushError> {
+    if no_vault {
+        info!("Vault: fixtures in memory");
+        let fixtures = Path::new(config.product_path()).join("stack.env.fixtures.yaml");
+        return Ok(Arc::new(Mutex::new(MemoryVault::from_file(&fixtures)?))
+            as Arc<Mutex<dyn Vault + Send>>);
+    }
+    match config.vault_name() {
+        ".env" => {
+            info!("Vault: .env");
+            let product_path = std::path::PathBuf::from(config.product_path());
+            Ok(Arc::new(Mutex::new(DotenvVault::new(product_path)?))
+                as Arc<Mutex<dyn Vault + Send>>)
+        }
+        "1Password" => {
+            let account_name = config.one_password_account().ok_or_else(|| {
+                RushError::Vault(
+                    "1Password account not found. Please set this in rushd.yaml".to_string(),
+                )
+            })?;
+            info!(
+                "Vault: {} in {}",
+                config
+                    .one_password_vault(config.environment())
+                    .unwrap_or(config.product_name()),
+                account_name
+            );
+            Ok(Arc::new(Mutex::new(OnePassword::new(
+                account_name,
+                config.one_password_vaults().clone(),
+            ))) as Arc<Mutex<dyn Vault + Send>>)
+        }
+        vault_name => Err(RushError::Vault(format!("Invalid vault {}", vault_name))),
+    }
+}
+
+fn create_k8s_encoder(config: &Config) -> Result<Arc<dyn K8Encoder>, RushError> {
+    Ok(match config.k8s_encoder() {
+        "kubeseal" => {
+            info!("Encrypting K8s secrets with kubeseal");
+            let cache_file = format!(
+                "{}/.cache/rush/kubeseal/{}-{}.pem",
+                std::env::var("HOME").unwrap_or_default(),
+                config.product_uri(),
+                config.environment()
+            );
+            Arc::new(SealedSecretsEncoder::new(KubesealSettings {
+                certificate: config.kubeseal_cert().cloned(),
+                controller_namespace: config.kubeseal_controller_namespace().cloned(),
+                controller_name: config.kubeseal_controller_name().cloned(),
+                context: config.kube_context().to_string(),
+                cache_file: cache_file.into(),
+            })) as Arc<dyn K8Encoder>
+        }
+        "noop" => {
+            warn!("No secret encryption of secrets for K8s");
+            Arc::new(NoopEncoder) as Arc<dyn K8Encoder>
+        }
+        encoder => {
+            return Err(RushError::Config(format!(
+                "Invalid k8s encoder {}",
+                encoder
+            )))
+        }
+    })
+}
+
+/// The image of a component named on the command line, exiting when there is none.
+fn component_image<'a>(reactor: &'a ContainerReactor, component_name: &str) -> &'a DockerImage {
+    match reactor.get_image(component_name) {
+        Some(image) => image,
+        None => {
+            let e = RushError::Config(format!("Component {} not found", component_name));
+            eprintln!("{}", e);
+            exit(e.exit_code());
+        }
+    }
+}
+
+/// Loads the public environment definitions and writes the components' .env files.
+fn generate_public_environment(config: &Config) -> Result<PublicEnvironmentDefinitions, String> {
+    let public_environment = PublicEnvironmentDefinitions::for_config(config);
+    public_environment
+        .generate_dotenv_files()
+        .map_err(|e| e.to_string())?;
+    Ok(public_environment)
+}
+
+/// Fails when a variable of the .env files is missing or does not validate, as the
+/// services would otherwise only crash once they run.
+fn check_public_environment(
+    public_environment: &PublicEnvironmentDefinitions,
+) -> Result<(), String> {
+    let issues = public_environment
+        .validate_dotenv_files()
+        .map_err(|e| e.to_string())?;
+    for issue in &issues {
+        eprintln!("{}", issue.red());
+    }
+    if !issues.is_empty() {
+        return Err(format!(
+            "Found {} problem(s) in the public environment",
+            issues.len()
+        ));
+    }
+    Ok(())
+}
+
+/// Formats a component's variables for CI. GitHub env files have no comments, so secret
+/// names are only listed by the dotenv and json formats.
+fn export_environment(
+    variables: &BTreeMap<String, String>,
+    secret_names: Option<&[String]>,
+    format: &str,
+) -> String {
+    let mut output = String::new();
+    match format {
+        "json" => {
+            let mut export = serde_json::json!({ "variables": variables });
+            if let Some(secret_names) = secret_names {
+                export["secrets"] = serde_json::json!(secret_names);
+            }
+            output = serde_json::to_string_pretty(&export).unwrap_or_default();
+            output.push('\n');
+        }
+        "github" => {
+            if secret_names.is_some() {
+                warn!("Secret names are not exported in the github format");
+            }
+            for (key, value) in variables {
+                if value.contains('\n') {
+                    // Multiline values need a delimiter that does not occur in them
+                    let delimiter = format!("ghadelimiter_{}", uuid::Uuid::new_v4());
+                    output.push_str(&format!(
+                        "{}<<{}\n{}\n{}\n",
+                        key, delimiter, value, delimiter
+                    ));
+                } else {
+                    output.push_str(&format!("{}={}\n", key, value));
+                }
+            }
+        }
+        _ => {
+            for (key, value) in variables {
+                let value = value
+                    .replace('\\', "\\\\")
+                    .replace('"', "\\\"")
+                    .replace('\n', "\\n");
+                output.push_str(&format!("{}=\"{}\"\n", key, value));
+            }
+            for name in secret_names.unwrap_or_default() {
+                output.push_str(&format!("# {} is a secret\n", name));
+            }
+        }
+    }
+    output
+}
+
+/// Prints the changes to the public environment per component, like a unified diff.
+fn print_env_diff(changes: &BTreeMap<String, BTreeMap<String, EnvChange>>) {
+    for (component_name, component_changes) in changes {
+        println!("{}", component_name.bold());
+        for (name, change) in component_changes {
+            match change {
+                EnvChange::Added(value) => {
+                    println!("{}", format!("  + {}={}", name, value).green())
+                }
+                EnvChange::Removed(value) => {
+                    println!("{}", format!("  - {}={}", name, value).red())
+                }
+                EnvChange::Changed(before, after) => {
+                    println!(
+                        "{}",
+                        format!("  ~ {}: {} -> {}", name, before, after).yellow()
+                    )
+                }
+            }
+        }
+    }
+}
+
+/// Points docker at the environment's daemon when <ENV>_DOCKER_CONTEXT is set, which
+/// every docker command rush runs picks up.
+fn use_docker_context(config: &Config) {
+    if let Some(context) = config.docker_context() {
+        env::set_var("DOCKER_CONTEXT", context);
+        info!("Docker context: {}", context);
+    }
+}
+
+/// Refuses to push images built from uncommitted changes, which would otherwise only be
+/// marked by the -wip tag suffix, with --require-clean or in REQUIRE_CLEAN_ENVIRONMENTS.
+fn require_clean_tree(
+    require_clean: bool,
+    config: &Config,
+    toolchain: &ToolchainContext,
+) -> Result<(), String> {
+    if !require_clean && !config.requires_clean_tree() {
+        return Ok(());
+    }
+    let changes = toolchain.get_git_changes(config.product_path())?;
+    if changes.is_empty() {
+        return Ok(());
+    }
+    let mut message = format!(
+        "{} has uncommitted changes, commit or stash them before pushing to {}:",
+        config.product_name(),
+        config.environment()
+    );
+    for change in changes.iter().take(10) {
+        message.push_str(&format!("\n  {}", change));
+    }
+    if changes.len() > 10 {
+        message.push_str(&format!("\n  and {} more", changes.len() - 10));
+    }
+    Err(message)
+}
+
+/// Runs build, push or config validate for every product of the repository, sharing
+/// the toolchain and, unless it is a product's .env, the vault. Prints a combined summary.
+async fn run_all_products(
+    matches: &ArgMatches,
+    root_dir: &str,
+    environment: &str,
+    docker_registry: &str,
+    start_port: u16,
+    variable_overrides: HashMap<String, String>,
+    target: Platform,
+) -> Result<(), String> {
+    let command = match matches.subcommand() {
+        Some(("build", _)) => "build",
+        Some(("push", _)) => "push",
+        Some(("config", config_matches))
+            if config_matches.subcommand_matches("validate").is_some() =>
+        {
+            "validate"
+        }
+        _ => return Err("--all-products supports build, push and config validate".to_string()),
+    };
+
+    // Validating does not need the toolchain, which requires the build tools to be installed
+    let toolchain = (command != "validate").then(|| {
+        let toolchain = Arc::new(ToolchainContext::new(Platform::default(), target));
+        toolchain.setup_env();
+        toolchain
+    });
+    let events = open_events(matches)?;
+    let mut shared_vault: Option<Arc<Mutex<dyn Vault + Send>>> = None;
+    let mut results = Vec::new();
+    for product_name in Config::product_names(Path::new(root_dir)) {
+        println!("{}", format!("{} {}", command, product_name).white().bold());
+        let started = std::time::Instant::now();
+        let result = match Config::new(
+            root_dir,
+            &product_name,
+            environment,
+            docker_registry,
+            start_port,
+            variable_overrides.clone(),
+        ) {
+            Ok(config) => {
+                use_docker_context(&config);
+                run_product_command(
+                    command,
+                    config,
+                    toolchain.clone(),
+                    &mut shared_vault,
+                    &events,
+                    matches,
+                )
+                .await
+            }
+            Err(e) => Err(e.into()),
+        };
+        if let Err(e) = &result {
+            eprintln!("{}", e.red());
+        }
+        results.push((product_name, result, started.elapsed()));
+    }
+
+    println!("\n{}", "Summary".bold());
+    let mut failures = 0;
+    for (product_name, result, elapsed) in &results {
+        let status = match result {
+            Ok(_) => "ok".green(),
+            Err(_) => {
+                failures += 1;
+                "failed".red()
+            }
+        };
+        println!(
+            "  {:<40} {:<8} {:>6.1}s",
+            product_name,
+            status,
+            elapsed.as_secs_f64()
+        );
+    }
+
+    if failures > 0 {
+        return Err(format!("{} of {} products failed", failures, results.len()));
+    }
+    Ok(())
+}
+
+async fn run_product_command(
+    command: &str,
+    config: Arc<Config>,
+    toolchain: Option<Arc<ToolchainContext>>,
+    shared_vault: &mut Option<Arc<Mutex<dyn Vault + Send>>>,
+    events: &EventSink,
+    matches: &ArgMatches,
+) -> Result<(), String> {
+    if command == "validate" {
+        let issues = config_schema::validate_product(
+            Path::new(config.root_path()),
+            Path::new(config.product_path()),
+            config.environment(),
+        );
+        for issue in &issues {
+            eprintln!("{}", issue.to_string().red());
+        }
+        if !issues.is_empty() {
+            return Err(format!(
+                "Found {} problem(s) in the configuration",
+                issues.len()
+            ));
+        }
+        return Ok(());
+    }
+    let toolchain = toolchain.expect("The toolchain is set up for building");
+    if command == "push" {
+        require_clean_tree(matches.get_flag("require_clean"), &config, &toolchain)?;
+    }
+
+    // A .env vault and the fixtures live in the product directory, so only other vaults
+    // can be shared
+    let fake_secrets = matches.get_flag("fake_secrets");
+    let vault = match config.vault_name() {
+        _ if fake_secrets => Arc::new(Mutex::new(PlaceholderVault::new(SecretsDefinitions::new(
+            config.product_name().to_string(),
+            &format!("{}/stack.env.secrets.yaml", config.product_path()),
+        )?))) as Arc<Mutex<dyn Vault + Send>>,
+        _ if matches.get_flag("no_vault") => create_vault(&config, true)?,
+        ".env" => create_vault(&config, false)?,
+        _ => match shared_vault {
+            Some(vault) => vault.clone(),
+            None => shared_vault.insert(create_vault(&config, false)?).clone(),
+        },
+    };
+    check_public_environment(&generate_public_environment(&config)?)?;
+    let mut reactor = ContainerReactor::from_product_dir(
+        config.clone(),
+        toolchain,
+        vault,
+        Arc::new(Base64SecretsEncoder),
+        if fake_secrets {
+            Arc::new(NoopEncoder) as Arc<dyn K8Encoder>
+        } else {
+            create_k8s_encoder(&config)?
+        },
+        HashMap::new(),
+        Vec::new(),
+    )?;
+    if fake_secrets {
+        reactor.set_fake_secrets();
+    }
+    reactor.set_events(events.clone());
+    match command {
+        "push" => reactor.build_and_push().await,
+        _ => reactor.build().await,
+    }
+}
+
+/// Prints the execution summary of a reactor command, writing it as JSON as well with
+/// `--summary-json`, and exits with an error code when the command failed.
+fn finish_command(
+    matches: &ArgMatches,
+    command: &str,
+    reactor: &ContainerReactor,
+    result: Result<(), String>,
+) -> io::Result<()> {
+    let summary = reactor.summary();
+    if let Err(e) = &result {
+        eprintln!("{}", e);
+    }
+    if !utils::quiet() || result.is_err() {
+        summary.print(command, result.is_ok());
+    }
+    if let Some(path) = matches.get_one::<String>("summary_json") {
+        if let Err(e) = summary.write_json(path, command, result.is_ok()) {
+            eprintln!("{}", e);
+            exit(1);
+        }
+    }
+    if result.is_err() {
+        exit(1);
+    }
+    Ok(())
+}
+
+/// Starts the local cluster, creating it when it does not exist yet, and enables its
+/// ingress.
+async fn start_local_cluster(cluster: &dyn LocalCluster) -> Result<(), String> {
+    if let Err(e) = cluster.start().await {
+        debug!("Creating the {} cluster: {}", cluster.kind(), e);
+        cluster.create().await?;
+    }
+    cluster.enable_ingress().await?;
+    println!("The {} cluster is running", cluster.kind());
+    Ok(())
+}
+
+/// Runs the product in minikube until Ctrl-C, redeploying what changes. The product
+/// directory is mounted into the node at /rush/<product>, and the components' volumes
+/// become hostPath volumes there, so the pods see the files as they are edited.
+async fn minikube_dev(
+    reactor: &mut ContainerReactor,
+    minikube: &Minikube,
+    config: &Config,
+) -> Result<(), String> {
+    let host_directory = std::fs::canonicalize(reactor.product_directory())
+        .map_err(|e| format!("Failed to resolve {}: {}", reactor.product_directory(), e))?
+        .display()
+        .to_string();
+    let node_directory = format!("/rush/{}", config.product_name());
+    reactor.verify_kubernetes_context("minikube").await?;
+    reactor.set_kube_context("minikube");
+    minikube.enable_ingress().await.map_err(|e| {
+        format!(
+            "Failed to enable the ingress addon, is minikube running? Start it with rush minikube start: {}",
+            e
+        )
+    })?;
+    let mut mount = minikube.mount(&host_directory, &node_directory).await?;
+    println!(
+        "Mounted {} at {} in minikube",
+        host_directory, node_directory
+    );
+    reactor.set_host_mount(HostMount {
+        host_directory,
+        node_directory,
+    });
+
+    let result = reactor.run_in_cluster(minikube).await;
+    let _ = mount.kill().await;
+    result
+}
+
+/// Opens the event stream given with `--events`, if any.
+fn open_events(matches: &ArgMatches) -> Result<EventSink, String> {
+    match matches.get_one::<String>("events") {
+        Some(target) => EventSink::open(target),
+        None => Ok(EventSink::default()),
+    }
+}
+
+/// Picks the product from the working directory, or the only product of the repository.
+fn detect_product(root_dir: &Path, invocation_dir: &Path) -> Result<String, String> {
+    if let Some(product_name) = Config::product_name_from_path(root_dir, invocation_dir) {
+        return Ok(product_name);
+    }
+    let product_names = Config::product_names(root_dir);
+    match product_names.as_slice() {
+        [product_name] => Ok(product_name.clone()),
+        [] => Err(format!(
+            "No products found in {}",
+            root_dir.join("products").display()
+        )),
+        _ => Err(format!(
+            "Please specify a product or run rush inside its directory. Available products:\n  {}",
+            product_names.join("\n  ")
+        )),
+    }
 }
 
 #[derive(Deserialize)]
@@ -158,61 +591,150 @@ struct Release {
     prerelease: bool,
 }
 
-async fn check_version() {
-    let version = env!("CARGO_PKG_VERSION");
-    let url = format!("https://api.github.com/repos/wonop-io/rush/releases/latest");
-    let client = reqwest::Client::new();
-    let resp = client
-        .get(&url)
+async fn latest_version() -> Result<semver::Version, String> {
+    let url = "https://api.github.com/repos/wonop-io/rush/releases/latest";
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(5))
+        .build()
+        .map_err(|e| e.to_string())?;
+    let release: Release = client
+        .get(url)
         .header("User-Agent", "rush")
         .send()
         .await
-        .unwrap();
-
-    let release: Release = match resp.json().await {
-        Ok(release) => release,
-        Err(e) => {
-            panic!("Failed to get release: {}", e);
-        }
-    };
+        .map_err(|e| format!("Failed to get release: {}", e))?
+        .json()
+        .await
+        .map_err(|e| format!("Failed to get release: {}", e))?;
 
     let latest_version = release
         .tag_name
         .replace("v.", "")
-        .replace("v", "")
-        .replace(" ", "");
-    let current_version = semver::Version::parse(version).expect("Failed to parse current version");
-    let latest_version =
-        semver::Version::parse(&latest_version).expect("Failed to parse latest version");
-
-    if latest_version > current_version {
-        println!("============================================================");
-        println!("* A new version of Rush is available: {}", release.tag_name);
-        println!("* Please update it by running:");
-        println!("* ");
-        println!("* cargo install rush-cli --force");
-        println!("* ");
-        println!("============================================================");
-        println!();
-        std::process::exit(1);
+        .replace('v', "")
+        .replace(' ', "");
+    semver::Version::parse(&latest_version)
+        .map_err(|e| format!("Failed to parse latest version {}: {}", release.tag_name, e))
+}
+
+fn current_version() -> semver::Version {
+    semver::Version::parse(env!("CARGO_PKG_VERSION")).expect("Failed to parse current version")
+}
+
+/// Warns about a newer release without holding up the command.
+async fn check_version() {
+    match latest_version().await {
+        Ok(latest_version) if latest_version > current_version() => {
+            eprintln!(
+                "{}",
+                format!(
+                    "A new version of Rush is available: {}. Update it by running `rush self-update`",
+                    latest_version
+                )
+                .yellow()
+            );
+        }
+        Ok(_) => (),
+        Err(e) => debug!("Could not check for a new version: {}", e),
     }
 }
 
-#[tokio::main]
-async fn main() -> io::Result<()> {
-    check_version().await;
+async fn self_update() -> Result<(), String> {
+    let latest_version = latest_version().await?;
+    if latest_version <= current_version() {
+        println!("Rush {} is up to date", current_version());
+        return Ok(());
+    }
 
-    // Add for debugging console_subscriber::init();
-    setup_environment();
+    // Releases are published to crates.io, so installing goes through cargo
+    let cargo = utils::which("cargo")
+        .ok_or_else(|| "cargo not found. Please install Rust to update Rush.".to_string())?;
+    let version = latest_version.to_string();
+    utils::run_command(
+        "self-update".white().bold(),
+        &cargo,
+        vec!["install", "rush-cli", "--force", "--version", &version],
+    )
+    .await?;
+    println!("Updated Rush to {}", latest_version);
+    Ok(())
+}
 
-    // TODO: Get the rushd root by go levels up until you find ".git" directory
-    let root_dir = std::env::var("RUSHD_ROOT").unwrap();
-    let _guard = Directory::chdir(&root_dir);
-    debug!("Changed directory to RUSHD_ROOT: {}", root_dir);
-    load_config();
+fn mask_secret(value: &str) -> String {
+    let chars = value.chars().collect::<Vec<_>>();
+    if chars.len() >= 7 {
+        let head = chars[..2].iter().collect::<String>();
+        let tail = chars[chars.len() - 3..].iter().collect::<String>();
+        format!("{}****{}", head, tail)
+    } else {
+        "****".to_string()
+    }
+}
 
-    dotenv::dotenv().ok();
+/// Prints the result of a describe command as a debug dump, JSON or YAML.
+fn print_description<T: Serialize + std::fmt::Debug + ?Sized>(value: &T, format: &str) {
+    let output = match format {
+        "json" => serde_json::to_string_pretty(value).map_err(|e| e.to_string()),
+        "yaml" => serde_yaml::to_string(value).map_err(|e| e.to_string()),
+        _ => Ok(format!("{:#?}", value)),
+    };
+    match output {
+        Ok(output) => println!("{}", output),
+        Err(e) => {
+            eprintln!("Failed to serialize description: {}", e);
+            exit(1);
+        }
+    }
+}
+
+fn print_tool_versions(versions: &[ToolVersion]) {
+    let tool_width = versions
+        .iter()
+        .map(|v| v.tool.len())
+        .max()
+        .unwrap_or_default();
+    let version_width = versions
+        .iter()
+        .map(|v| v.version.as_deref().map_or(0, str::len))
+        .max()
+        .unwrap_or_default()
+        .max("VERSION".len());
+    println!(
+        "{}",
+        format!(
+            "{:<tool_width$}  {:<version_width$}  PATH",
+            "TOOL", "VERSION"
+        )
+        .bold()
+    );
+    for version in versions {
+        let line = match &version.path {
+            Some(path) => format!(
+                "{:<tool_width$}  {:<version_width$}  {}",
+                version.tool,
+                version.version.as_deref().unwrap_or("unknown"),
+                path
+            ),
+            None => format!("{:<tool_width$}  {}", version.tool, "not found".yellow()),
+        };
+        println!("{}", line.trim_end());
+    }
+}
+
+/// Exits after exporting the spans not exported yet, which `std::process::exit` would
+/// lose.
+fn exit(code: i32) -> ! {
+    telemetry::shutdown();
+    std::process::exit(code)
+}
+
+#[tokio::main]
+async fn main() -> io::Result<()> {
+    let result = run().await;
+    telemetry::shutdown();
+    result
+}
 
+async fn run() -> io::Result<()> {
     let version = env!("CARGO_PKG_VERSION");
     // https://api.github.com/repos/wonop-io/rush/releases
     let matches = Command::new("rush")
@@ -221,15 +743,40 @@ async fn main() -> io::Result<()> {
         .about("Rush is designed as an all-around support unit for developers, transforming the development workflow with its versatile capabilities. It offers a suite of tools for building, deploying, and managing applications, adapting to the diverse needs of projects with ease.")
         .arg(arg!(target_arch : --arch <TARGET_ARCH> "Target architecture"))
         .arg(arg!(target_os : --os <TARGET_OS> "Target OS"))
+        .arg(arg!(target_libc : --libc <LIBC> "C library of Linux targets. musl links statically, e.g. for scratch based images").value_parser(["gnu", "musl"]).default_value("gnu"))
         .arg(arg!(environment : --env <ENVIRONMENT> "Environment"))
         .arg(arg!(docker_registry : --registry <DOCKER_REGISTRY> "Docker Registry"))
         .arg(arg!(log_level : -l --loglevel <LOG_LEVEL> "Log level (trace, debug, info, warn, error)").default_value("info"))
+        .arg(arg!(verbose : -v --verbose... "Logs more: -v for debug, -vv for trace, -vvv for trace of every crate").conflicts_with_all(["log_level", "quiet"]))
+        .arg(arg!(quiet : -q --quiet "Only prints errors and the output of failing steps").conflicts_with("log_level"))
         .arg(arg!(start_port: --port <START_PORT> "Starting port for services").value_parser(value_parser!(u16)).default_value("8129"))
-        .arg(Arg::new("product_name").required(true))
+        .arg(arg!(yes : -y --yes "Skips confirmation of destructive commands"))
+        .arg(arg!(require_clean : --"require-clean" "Refuses push, deploy and rollout when the product directory has uncommitted changes. Always on in REQUIRE_CLEAN_ENVIRONMENTS"))
+        .arg(arg!(no_vault : --"no-vault" "Takes secrets from the product's stack.env.fixtures.yaml instead of the vault and keeps changes to them in memory"))
+        .arg(arg!(fake_secrets : --"fake-secrets" "Replaces every secret with a placeholder named after its component and name, for working without access to the vault. Manifests rendered with it cannot be deployed").conflicts_with("no_vault"))
+        .arg(arg!(no_ansi : --"no-ansi" "Disables colors and the rolling command output. Implied when stdout is not a terminal"))
+        .arg(arg!(vars : --var <KEY_VALUE> "Overrides a variable from variables.yaml. Format: key=value").action(ArgAction::Append))
+        .arg(arg!(all_products : --"all-products" "Runs build, push or config validate for every product"))
+        .arg(arg!(events : --events <FILE> "Writes build, launch, status and deploy events as NDJSON to a file, or - for stdout"))
+        .arg(arg!(summary_json : --"summary-json" <FILE> "Writes the summary printed at the end of a command as JSON"))
+        .arg(Arg::new("product_name").help("Product to work on. Inferred when run inside products/<product>/"))
+        .subcommand(Command::new("self-update")
+            .about("Installs the latest release of Rush")
+        )
+        .subcommand(Command::new("toolchain")
+            .about("Manages the tools rush runs")
+            .subcommand_required(true)
+            .subcommand(Command::new("install")
+                .about("Downloads pinned versions of kubectl, kubeconform, kubeseal and minikube, checked against the SHA-256 their releases publish, which rush then prefers over PATH")
+                .arg(arg!(force : --force "Downloads tools that are already installed again"))
+            )
+        )
         .subcommand(Command::new("describe")
             .about("Describes the current configuration")
+            .arg(arg!(format : --format <FORMAT> "Output format").value_parser(["text", "json", "yaml"]).default_value("text").global(true))
             .subcommand(Command::new("toolchain")
                 .about("Describes the current toolchain")
+                .arg(arg!(versions : --versions "Runs each tool to show a table of tool, version and path"))
             )
             .subcommand(Command::new("images")
                 .about("Describes the current images")
@@ -252,23 +799,168 @@ async fn main() -> io::Result<()> {
             .subcommand(Command::new("k8s")
                 .about("Describes the current k8s")
             )
+            .subcommand(Command::new("env")
+                .about("Describes the environment a component receives and where each value comes from")
+                .arg(Arg::new("component_name").required(true))
+            )
+            .subcommand(Command::new("graph")
+                .about("Prints the component dependency graph as DOT")
+                .arg(arg!(mermaid : --mermaid "Prints the graph as Mermaid instead of DOT"))
+                .arg(arg!(redirect : --redirect <COMPONENTS> ... "Shows components as redirected like in dev. Format: component@host:port").num_args(1..))
+            )
+        )
+        .subcommand(Command::new("config")
+            .about("Checks the configuration files")
+            .subcommand(Command::new("validate")
+                .about("Validates rushd.yaml and the product's stack files against their JSON Schemas")
+            )
+        )
+        .subcommand(Command::new("env")
+            .about("Inspects the public environment of stack.env.*.yaml")
+            .subcommand_required(true)
+            .subcommand(Command::new("diff")
+                .about("Shows the public variables added, removed or changed per component between two environments")
+                .arg(Arg::new("from").required(true))
+                .arg(Arg::new("to").required(true))
+                .arg(arg!(format : --format <FORMAT> "Output format").value_parser(["text", "json", "yaml"]).default_value("text"))
+            )
+            .subcommand(Command::new("codegen")
+                .about("Writes src/rush_env.rs into each RustBinary component, with a struct of its public variables")
+                .arg(arg!(dry_run : --"dry-run" "Prints the modules instead of writing them"))
+            )
+            .subcommand(Command::new("export")
+                .about("Prints the merged non-secret environment of a component for CI, e.g. to append to $GITHUB_ENV")
+                .arg(Arg::new("component_name").required(true))
+                .arg(arg!(format : --format <FORMAT> "Output format").value_parser(["github", "dotenv", "json"]).default_value("dotenv"))
+                .arg(arg!(secret_names : --"secret-names" "Also lists the names of the component's secrets, without their values"))
+            )
+            .subcommand(Command::new("render")
+                .about("Writes the components' .env files from the public environment definitions")
+                .arg(arg!(dry_run : --"dry-run" "Prints what would be written to each .env instead, without prompting"))
+            )
+        )
+        .subcommand(Command::new("export")
+            .about("Writes the configuration of other tools from the product's stack files")
+            .subcommand_required(true)
+            .subcommand(Command::new("skaffold")
+                .about("Writes a skaffold.yaml that builds the product's Dockerfiles and deploys its rendered manifests")
+                .arg(arg!(output : -o --output <FILE> "File to write, or - for stdout. Defaults to skaffold.yaml in the product directory"))
+            )
+            .subcommand(Command::new("tilt")
+                .about("Writes a Tiltfile that builds the product's Dockerfiles, live updates the paths of their watch globs and deploys the rendered manifests")
+                .arg(arg!(output : -o --output <FILE> "File to write, or - for stdout. Defaults to Tiltfile in the product directory"))
+            )
+            .subcommand(Command::new("devcontainer")
+                .about("Writes a dev container with the target's cross toolchain, the pinned tools and rush installed")
+                .arg(arg!(output : -o --output <DIRECTORY> "Directory to write devcontainer.json and the Dockerfile into, or - for stdout. Defaults to .devcontainer/<product>"))
+            )
+        )
+        .subcommand(Command::new("task")
+            .about("Runs a task from rushd.yaml, or lists the tasks when no name is given")
+            .arg(Arg::new("task_name"))
         )
         .subcommand(Command::new("dev")
             .arg(arg!(redirect : --redirect <COMPONENTS> ... "Disables component and redirects the ingress. Format: component@host:port").num_args(1..))
             .arg(arg!(silence : --silence <COMPONENTS> ... "Silence output for specific components").num_args(1..))
+            .arg(arg!(status_port : --"status-port" <PORT> "Serves a status page with the components' statuses, ports, resource usage, logs and restart buttons on this port").value_parser(value_parser!(u16)))
+            .arg(arg!(status_bind : --"status-bind" <ADDRESS> "Address the status page listens on. Only this machine can open it on the default, 0.0.0.0 shares it with the network").value_parser(value_parser!(std::net::IpAddr)).default_value("127.0.0.1"))
+            .arg(arg!(stats : --stats <SECONDS> "Prints the CPU and memory of each component's container, and its growth since the container started, every SECONDS").value_parser(value_parser!(u64).range(1..)))
+            .arg(arg!(proxy : --proxy <PORT> "Routes the product's domains and mount points to the components on this port with a built-in proxy, instead of running the ingress components").value_parser(value_parser!(u16)))
+            .arg(arg!(proxy_tls_port : --"proxy-tls-port" <PORT> "Also serves the proxy over HTTPS on this port, with a self-signed certificate for localhost and the product's domains").value_parser(value_parser!(u16)).requires("proxy"))
+            .arg(arg!(rebuild : --rebuild "Builds every component, instead of starting those unchanged since the previous rush dev from their images"))
+        )
+        .subcommand(Command::new("profile")
+            .about("Profiles the dev loop")
+            .subcommand(Command::new("build")
+                .about("Runs the product as rush dev does and, when stopped, reports how long rebuilds took from a file change to the relaunch, and which components and steps took it")
+                .arg(arg!(output : -o --output <FILE> "Also writes every rebuild with its steps as JSON"))
+            )
         )
         .subcommand(Command::new("build"))
         .subcommand(Command::new("push"))
+        .subcommand(Command::new("clean")
+            .about("Removes the product's containers left behind, e.g. by a crashed dev session")
+            .arg(arg!(images : --images "Also removes the locally built images"))
+            .arg(arg!(network : --network "Also removes the product's Docker network"))
+            .arg(arg!(k8s_output : --"k8s-output" "Also removes the rendered Kubernetes manifests"))
+        )
+        .subcommand(Command::new("kill")
+            .about("Kills and removes the product's running containers")
+        )
+        .subcommand(Command::new("volumes")
+            .about("Manages the named volumes of stack.volumes.yaml")
+            .subcommand(Command::new("prune")
+                .about("Removes the volumes rush created for the product that stack.volumes.yaml no longer declares")
+                .arg(arg!(all : --all "Removes all of the product's volumes, with their data"))
+                .arg(arg!(dry_run : --"dry-run" "Lists what would be removed without removing it"))
+            )
+        )
+        .subcommand(Command::new("db")
+            .about("Snapshots and restores the data of database components in their rush dev containers")
+            .subcommand(Command::new("snapshot")
+                .about("Dumps the database into target/snapshots/<component>/")
+                .arg(Arg::new("component_name").required(true))
+                .arg(Arg::new("name").required(false).help("Name of the snapshot, the current time by default"))
+            )
+            .subcommand(Command::new("restore")
+                .about("Loads a snapshot into the database, replacing its data")
+                .arg(Arg::new("component_name").required(true))
+                .arg(Arg::new("name").required(false).help("Name of the snapshot, the latest one by default"))
+            )
+        )
+        .subcommand(Command::new("reload-env")
+            .about("Writes the .env files again and has the running rush dev restart the containers whose environment changed, without rebuilding their images")
+            .arg(Arg::new("component_name").required(false))
+        )
+        .subcommand(Command::new("images")
+            .about("Lists the product's images")
+            .subcommand(Command::new("remote")
+                .about("Lists the tags pushed to the registry with their digests and build times")
+                .arg(Arg::new("component_name").required(false))
+            )
+        )
+        .subcommand(Command::new("gc")
+            .about("Removes stale images of the product, keeping the most recent and those in use")
+            .arg(arg!(keep : --keep <COUNT> "Number of most recent tags kept per component").value_parser(value_parser!(usize)).default_value("5"))
+            .arg(arg!(remote : --remote "Also deletes stale tags from the registry, keeping those running in any environment's clusters"))
+            .arg(arg!(dry_run : --"dry-run" "Lists what would be removed without removing it"))
+        )
         .subcommand(Command::new("minikube")
             .about("Runs tasks on minikube")
-            .subcommand(Command::new("dev"))
-            .subcommand(Command::new("start"))
-            .subcommand(Command::new("stop"))
-            .subcommand(Command::new("delete"))
+            .subcommand(Command::new("dev")
+                .about("Runs the product in minikube with the product directory mounted into it, so the components' volumes hold the files being edited")
+            )
+            .subcommand(Command::new("start")
+                .about("Starts the local cluster of LOCAL_CLUSTER, creating it on first use, and enables its ingress")
+            )
+            .subcommand(Command::new("stop")
+                .about("Stops the local cluster, keeping its state")
+            )
+            .subcommand(Command::new("delete")
+                .about("Deletes the local cluster with everything deployed to it")
+            )
         )
         .subcommand(Command::new("rollout")
             .about("Rolls out the product into staging or production")
         )
+        .subcommand(Command::new("promote")
+            .about("Publishes the images of the latest rollout into another environment, with manifests rendered for it")
+            .arg(arg!(to : --to <ENVIRONMENT> "Environment to promote to").required(true))
+            .arg(arg!(from : --from <ENVIRONMENT> "Environment to promote from").default_value("staging"))
+        )
+        .subcommand(Command::new("history")
+            .about("Lists previous rollouts of the product")
+        )
+        .subcommand(Command::new("changelog")
+            .about("Lists the commits changing each component since the last rollout into the environment")
+        )
+        .subcommand(Command::new("test")
+            .about("Tests the product without running it")
+            .subcommand(Command::new("manifests")
+                .about("Compares the rendered manifests with the golden files in tests/k8s/<environment>")
+                .arg(arg!(update : --update "Replaces the golden files with the rendered manifests"))
+            )
+        )
         .subcommand(Command::new("deploy"))
         .subcommand(Command::new("install"))
         .subcommand(Command::new("uninstall"))
@@ -294,9 +986,52 @@ async fn main() -> io::Result<()> {
         )
         .get_matches();
 
+    // Updating must work without a product or a rushd root, so it is handled before the rest
+    if matches.subcommand_matches("self-update").is_some() {
+        if let Err(e) = self_update().await {
+            eprintln!("{}", e);
+            exit(1);
+        }
+        return Ok(());
+    }
+    let user_config = match UserConfig::load() {
+        Ok(user_config) => user_config,
+        Err(e) => {
+            eprintln!("{}", e);
+            exit(e.exit_code());
+        }
+    };
+    if user_config.update_check == UpdateCheck::Always {
+        tokio::spawn(check_version());
+    }
+
+    // Add for debugging console_subscriber::init();
+    setup_environment();
+
+    // TODO: Get the rushd root by go levels up until you find ".git" directory
+    let root_dir = std::env::var("RUSHD_ROOT").unwrap();
+    // Remembered to infer the product when it is not given
+    let invocation_dir = env::current_dir().expect("Failed to get current directory");
+    let _guard = Directory::chdir(&root_dir);
+    debug!("Changed directory to RUSHD_ROOT: {}", root_dir);
+    let rushd_config = match load_config(&user_config) {
+        Ok(rushd_config) => rushd_config,
+        Err(e) => {
+            eprintln!("{}", e);
+            exit(e.exit_code());
+        }
+    };
+
+    dotenv::dotenv().ok();
+
     let start_port = *matches.get_one::<u16>("start_port").unwrap();
     let redirected_components: HashMap<String, (String, u16)> = matches
         .subcommand_matches("dev")
+        .or_else(|| {
+            matches
+                .subcommand_matches("describe")
+                .and_then(|describe_matches| describe_matches.subcommand_matches("graph"))
+        })
         .and_then(|dev_matches| dev_matches.get_many::<String>("redirect"))
         .map(|values| {
             values
@@ -322,27 +1057,68 @@ async fn main() -> io::Result<()> {
         })
         .unwrap_or_default();
 
-    let silence_components: Vec<String> = matches
+    let mut silence_components: Vec<String> = matches
         .subcommand_matches("dev")
         .and_then(|dev_matches| dev_matches.get_many::<String>("silence"))
         .map(|values| values.cloned().map(|s| s.to_string()).collect())
         .unwrap_or_default();
+    silence_components.extend(rushd_config.silence.iter().cloned());
 
-    println!("Redirecting components: {:#?}", redirected_components);
-
+    debug!("Redirecting components: {:#?}", redirected_components);
     debug!("Command line arguments parsed");
 
-    // Set log level based on command line argument
-    if let Some(log_level) = matches.get_one::<String>("log_level") {
+    // CI logs get plain lines instead of colors and overwritten windows
+    let ansi = !matches.get_flag("no_ansi") && std::io::stdout().is_terminal();
+    utils::set_ansi_output(ansi);
+    let write_style = if ansi {
+        env_logger::WriteStyle::Auto
+    } else {
+        env_logger::WriteStyle::Never
+    };
+
+    // Set log level based on command line argument. -v and -q take precedence over --loglevel
+    let quiet = matches.get_flag("quiet");
+    utils::set_quiet(quiet);
+    let log_level = match (quiet, matches.get_count("verbose")) {
+        (true, _) => Some("error"),
+        // The user config's log level replaces the default, but not an explicit --loglevel
+        (false, 0) if matches.value_source("log_level") == Some(ValueSource::DefaultValue) => {
+            user_config
+                .log_level
+                .as_deref()
+                .or(matches.get_one::<String>("log_level").map(String::as_str))
+        }
+        (false, 0) => matches.get_one::<String>("log_level").map(String::as_str),
+        (false, 1) => Some("rush=debug,rush_core=debug"),
+        (false, 2) => Some("rush=trace,rush_core=trace"),
+        (false, _) => Some("trace"),
+    };
+    if let Some(log_level) = log_level {
         env::set_var("RUST_LOG", log_level);
-        env_logger::builder().parse_env("RUST_LOG").init();
+        env_logger::builder()
+            .parse_env("RUST_LOG")
+            .write_style(write_style)
+            .init();
         trace!("Log level set to: {}", log_level);
     } else {
         // Initialize env_logger
-        env_logger::init();
+        env_logger::builder().write_style(write_style).init();
     }
     // Log the start of the application
     trace!("Starting Rush application");
+    telemetry::init();
+
+    // Installing tools needs neither a product nor an environment
+    if let Some(install_matches) = matches
+        .subcommand_matches("toolchain")
+        .and_then(|toolchain_matches| toolchain_matches.subcommand_matches("install"))
+    {
+        if let Err(e) = toolchain::install_tools(install_matches.get_flag("force")).await {
+            eprintln!("{}", e.red());
+            exit(1);
+        }
+        return Ok(());
+    }
 
     let target_arch = if let Some(target_arch) = matches.get_one::<String>("target_arch") {
         target_arch.clone()
@@ -358,23 +1134,121 @@ async fn main() -> io::Result<()> {
     };
     info!("Target OS: {}", target_os);
 
-    let environment = if let Some(environment) = matches.get_one::<String>("environment") {
+    let target_libc = matches.get_one::<String>("target_libc").unwrap();
+    let target = Platform::new(&target_os, &target_arch).with_libc(
+        target_libc
+            .parse()
+            .expect("--libc only accepts gnu and musl"),
+    );
+    info!("Target: {}", target.to_rust_target());
+
+    let environment = if let Some(environment) = matches
+        .subcommand_matches("promote")
+        .and_then(|promote_matches| promote_matches.get_one::<String>("to"))
+    {
+        environment.clone()
+    } else if let Some(environment) = matches.get_one::<String>("environment") {
         environment.clone()
     } else {
         "local".to_string()
     };
+    // An unknown environment is picked from the declared ones when there is a terminal
+    let environments = Config::environment_names();
+    let environment = if environments.contains(&environment) {
+        environment
+    } else {
+        eprintln!("{}", format!("Invalid environment: {}", environment).red());
+        match utils::select("Environment", &environments) {
+            Some(environment) => environment,
+            None => {
+                let e =
+                    RushError::Config(format!("Valid environments: {}", environments.join(", ")));
+                eprintln!("{}", e);
+                exit(e.exit_code());
+            }
+        }
+    };
     info!("Environment: {}", environment);
 
-    let docker_registry = if let Some(docker_registry) =
-        matches.get_one::<String>("docker_registry")
+    let docker_registry = match matches
+        .get_one::<String>("docker_registry")
+        .cloned()
+        .or_else(|| std::env::var("DOCKER_REGISTRY").ok())
     {
-        docker_registry.clone()
-    } else {
-        std::env::var("DOCKER_REGISTRY").expect("DOCKER_REGISTRY environment variable not found")
+        Some(docker_registry) => docker_registry,
+        None => {
+            let e = RushError::Config(
+                "DOCKER_REGISTRY not set in rushd.yaml nor given with --registry".to_string(),
+            );
+            eprintln!("{}", e);
+            exit(e.exit_code());
+        }
     };
     info!("Docker registry: {}", docker_registry);
 
-    let product_name = matches.get_one::<String>("product_name").unwrap();
+    let mut variable_overrides = HashMap::new();
+    for var in matches.get_many::<String>("vars").unwrap_or_default() {
+        match var.split_once('=') {
+            Some((key, value)) => {
+                variable_overrides.insert(key.trim().to_string(), value.to_string());
+            }
+            None => {
+                eprintln!("Invalid variable {}. Format: key=value", var);
+                exit(1);
+            }
+        }
+    }
+
+    if matches.get_flag("all_products") {
+        match run_all_products(
+            &matches,
+            &root_dir,
+            &environment,
+            &docker_registry,
+            start_port,
+            variable_overrides,
+            target.clone(),
+        )
+        .await
+        {
+            Ok(_) => return Ok(()),
+            Err(e) => {
+                eprintln!("{}", e);
+                exit(1);
+            }
+        }
+    }
+
+    // Products that cannot be resolved are picked from a list when there is a terminal
+    let product_names = Config::product_names(Path::new(&root_dir));
+    let product_name = match matches.get_one::<String>("product_name") {
+        Some(product_name) if product_names.contains(product_name) => product_name.clone(),
+        Some(product_name) => {
+            eprintln!("{}", format!("Product {} not found", product_name).red());
+            match utils::select("Product", &product_names) {
+                Some(product_name) => product_name,
+                None => {
+                    let e = RushError::Config(format!(
+                        "Available products:\n  {}",
+                        product_names.join("\n  ")
+                    ));
+                    eprintln!("{}", e);
+                    exit(e.exit_code());
+                }
+            }
+        }
+        None => match detect_product(Path::new(&root_dir), &invocation_dir) {
+            Ok(product_name) => product_name,
+            Err(e) => match utils::select("Product", &product_names) {
+                Some(product_name) => product_name,
+                None => {
+                    eprintln!("{}", e);
+                    exit(1);
+                }
+            },
+        },
+    };
+    let product_name = &product_name;
     info!("Product name: {}", product_name);
 
     let config = match Config::new(
@@ -383,118 +1257,652 @@ async fn main() -> io::Result<()> {
         &environment,
         &docker_registry,
         start_port,
+        variable_overrides,
     ) {
         Ok(config) => config,
         Err(e) => {
             error!("Failed to create config: {}", e);
             eprintln!("{}", e);
-            std::process::exit(1);
+            exit(e.exit_code());
         }
     };
 
+    // Validation has to run before the files are parsed below, as parsing panics on errors
+    if let Some(config_matches) = matches.subcommand_matches("config") {
+        if config_matches.subcommand_matches("validate").is_some() {
+            let issues = config_schema::validate_product(
+                std::path::Path::new(&root_dir),
+                std::path::Path::new(config.product_path()),
+                &environment,
+            );
+            if issues.is_empty() {
+                println!("{}", "Configuration is valid".green());
+                return Ok(());
+            }
+            for issue in &issues {
+                eprintln!("{}", issue.to_string().red());
+            }
+            eprintln!("Found {} problem(s) in the configuration", issues.len());
+            exit(1);
+        }
+    }
+
+    if let Some(env_matches) = matches.subcommand_matches("env") {
+        if let Some(diff_matches) = env_matches.subcommand_matches("diff") {
+            let mut environments = Vec::new();
+            for key in ["from", "to"] {
+                let environment = diff_matches.get_one::<String>(key).unwrap();
+                match Config::new(
+                    &root_dir,
+                    product_name,
+                    environment,
+                    &docker_registry,
+                    start_port,
+                    config.variable_overrides().clone(),
+                ) {
+                    Ok(config) => {
+                        environments.push(PublicEnvironmentDefinitions::for_config(&config))
+                    }
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        exit(e.exit_code());
+                    }
+                }
+            }
+            let changes = environments[0].diff(&environments[1]);
+            match diff_matches.get_one::<String>("format").unwrap().as_str() {
+                "text" if changes.is_empty() => println!("No differences"),
+                "text" => print_env_diff(&changes),
+                format => print_description(&changes, format),
+            }
+            return Ok(());
+        }
+
+        if let Some(codegen_matches) = env_matches.subcommand_matches("codegen") {
+            let modules = match PublicEnvironmentDefinitions::for_config(&config).rust_modules() {
+                Ok(modules) => modules,
+                Err(e) => {
+                    eprintln!("Failed to generate modules: {}", e);
+                    exit(1);
+                }
+            };
+            for (path, module) in modules {
+                let relative_path = path.strip_prefix(config.product_path()).unwrap_or(&path);
+                if codegen_matches.get_flag("dry_run") {
+                    println!("{}", relative_path.display().to_string().bold());
+                    println!("{}", module);
+                    continue;
+                }
+                // Files rush did not generate are left alone
+                let existing = std::fs::read_to_string(&path).unwrap_or_default();
+                if !existing.is_empty() && !existing.starts_with(RUST_MODULE_HEADER) {
+                    eprintln!(
+                        "{}",
+                        format!(
+                            "Skipping {}, it was not generated by rush",
+                            relative_path.display()
+                        )
+                        .yellow()
+                    );
+                    continue;
+                }
+                if let Err(e) = std::fs::write(&path, module) {
+                    eprintln!("Failed to write {}: {}", path.display(), e);
+                    exit(1);
+                }
+                println!("Wrote {}", relative_path.display());
+            }
+            return Ok(());
+        }
+
+        if let Some(render_matches) = env_matches.subcommand_matches("render") {
+            let public_environment = PublicEnvironmentDefinitions::for_config(&config);
+            if !render_matches.get_flag("dry_run") {
+                if let Err(e) = public_environment.generate_dotenv_files() {
+                    eprintln!("Failed to write .env files: {}", e);
+                    exit(1);
+                }
+                println!("{}", "Wrote .env files".green());
+                return Ok(());
+            }
+            match public_environment.render_dotenv_files(false) {
+                Ok(dotenv_files) => {
+                    for (env_path, env_map) in dotenv_files {
+                        let env_path = env_path
+                            .strip_prefix(config.product_path())
+                            .unwrap_or(&env_path);
+                        println!("{}", env_path.display().to_string().bold());
+                        for (key, value) in env_map.into_iter().collect::<BTreeMap<_, _>>() {
+                            println!("{}=\"{}\"", key, value);
+                        }
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Failed to render .env files: {}", e);
+                    exit(1);
+                }
+            }
+            return Ok(());
+        }
+    }
+
+    if let Some(task_matches) = matches.subcommand_matches("task") {
+        let task_name = match task_matches.get_one::<String>("task_name") {
+            Some(task_name) => task_name,
+            None => {
+                for (name, task) in &rushd_config.tasks {
+                    println!(
+                        "{:<24} {}",
+                        name.bold(),
+                        task.description.as_deref().unwrap_or_default()
+                    );
+                }
+                return Ok(());
+            }
+        };
+        let task = match rushd_config.tasks.get(task_name) {
+            Some(task) => task,
+            None => {
+                eprintln!(
+                    "Unknown task {}. Available tasks: {}",
+                    task_name,
+                    rushd_config
+                        .tasks
+                        .keys()
+                        .cloned()
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                );
+                exit(1);
+            }
+        };
+        match task::run_task(task_name, task, &config).await {
+            Ok(_) => return Ok(()),
+            Err(e) => {
+                eprintln!("{}", e);
+                exit(1);
+            }
+        }
+    }
+
+    // Golden files are rendered with placeholders rather than anyone's secrets, and
+    // without kubeseal, whose output differs on every run
+    let testing_manifests = matches
+        .subcommand_matches("test")
+        .and_then(|test_matches| test_matches.subcommand_matches("manifests"))
+        .is_some();
+    // Placeholders need no sealing, which would need access to the cluster
+    let fake_secrets = matches.get_flag("fake_secrets");
+
     // Loading secrets definitions and creating the vault
-    let secrets_context = SecretsDefinitions::new(
+    let (secrets_context, vault) = match SecretsDefinitions::new(
         product_name.clone(),
         &format!("{}/stack.env.secrets.yaml", config.product_path()),
-    );
-    let product_path = std::path::PathBuf::from(config.product_path());
-    let vault = match config.vault_name() {
-        ".env" => {
-            info!("Vault: .env");
-            Arc::new(Mutex::new(DotenvVault::new(product_path.clone())))
+    )
+    .and_then(|secrets_context| {
+        let vault = if testing_manifests || fake_secrets {
+            Arc::new(Mutex::new(PlaceholderVault::new(secrets_context.clone())))
                 as Arc<Mutex<dyn Vault + Send>>
+        } else {
+            create_vault(&config, matches.get_flag("no_vault"))?
+        };
+        Ok((secrets_context, vault))
+    }) {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            error!("Failed to load secrets: {}", e);
+            eprintln!("{}", e);
+            exit(e.exit_code());
         }
-        "1Password" => {
-            let account_name = config
-                .one_password_account()
-                .expect("1Password account not found. Please set this in rushd.yaml");
-            info!("Vault: {}", account_name);
-            Arc::new(Mutex::new(OnePassword::new(account_name))) as Arc<Mutex<dyn Vault + Send>>
-        }
-        _ => panic!("Invalid vault"),
     };
 
     let secrets_encoder = Arc::new(Base64SecretsEncoder);
-    let k8s_encoder = match config.k8s_encoder() {
-        "kubeseal" => {
-            info!("Encrypting K8s secrets with kubeseal");
-            Arc::new(SealedSecretsEncoder) as Arc<dyn K8Encoder>
+    let k8s_encoder = if testing_manifests || fake_secrets {
+        Arc::new(NoopEncoder) as Arc<dyn K8Encoder>
+    } else {
+        match create_k8s_encoder(&config) {
+            Ok(k8s_encoder) => k8s_encoder,
+            Err(e) => {
+                eprintln!("{}", e);
+                exit(e.exit_code());
+            }
+        }
+    };
+
+    // Creating environment
+    let public_environment = match generate_public_environment(&config) {
+        Ok(public_environment) => public_environment,
+        Err(e) => {
+            error!("Unable to generate dotenv files: {}", e);
+            eprintln!("{:#?}", e);
+            exit(1);
+        }
+    };
+    if matches!(
+        matches.subcommand_name(),
+        Some(
+            "dev"
+                | "profile"
+                | "minikube"
+                | "build"
+                | "push"
+                | "rollout"
+                | "deploy"
+                | "install"
+                | "apply"
+                | "reload-env"
+        )
+    ) {
+        if let Err(e) = check_public_environment(&public_environment) {
+            eprintln!("{}", e);
+            exit(RushError::Config(e).exit_code());
+        }
+    }
+
+    let toolchain = Arc::new(ToolchainContext::new(Platform::default(), target));
+    toolchain.setup_env();
+    use_docker_context(&config);
+    debug!("Toolchain set up");
+
+    if !utils::quiet() {
+        println!("\n\n");
+    }
+    let mut reactor = match ContainerReactor::from_product_dir(
+        config.clone(),
+        toolchain.clone(),
+        vault.clone(),
+        secrets_encoder,
+        k8s_encoder,
+        redirected_components.clone(),
+        silence_components,
+    ) {
+        Ok(reactor) => reactor,
+        Err(e) => {
+            error!("Failed to create ContainerReactor: {}", e);
+            eprintln!("{}", e);
+            exit(e.exit_code());
+        }
+    };
+    // Golden files are the same with and without the flag
+    if fake_secrets && !testing_manifests {
+        warn!("Secrets are placeholders, so the manifests cannot be deployed");
+        reactor.set_fake_secrets();
+    }
+    match open_events(&matches) {
+        Ok(events) => reactor.set_events(events),
+        Err(e) => {
+            eprintln!("{}", e);
+            exit(1);
+        }
+    }
+
+    if matches.subcommand_matches("history").is_some() {
+        match reactor.history().records() {
+            Ok(records) => {
+                for record in records {
+                    let promoted = record
+                        .promoted_from
+                        .map(|from| format!(" (promoted from {})", from))
+                        .unwrap_or_default();
+                    println!(
+                        "{}  {:<8}  {}{}",
+                        record.timestamp,
+                        record.environment.bold(),
+                        &record.git_hash[..8.min(record.git_hash.len())],
+                        promoted
+                    );
+                    let mut images = record.images.into_iter().collect::<Vec<_>>();
+                    images.sort();
+                    for (component, image) in images {
+                        println!("    {:<20} {}", component, image);
+                    }
+                }
+                return Ok(());
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                exit(1);
+            }
+        }
+    }
+
+    if let Some(manifests_matches) = matches
+        .subcommand_matches("test")
+        .and_then(|test_matches| test_matches.subcommand_matches("manifests"))
+    {
+        // A fixed tag, so the golden files do not change with every commit
+        if let Err(e) = reactor.build_manifests_with_tag("golden").await {
+            eprintln!("{}", e);
+            exit(1);
+        }
+        let rendered = Path::new(reactor.product_directory())
+            .join(reactor.cluster_manifests().output_directory());
+        let golden = Path::new(config.product_path())
+            .join("tests")
+            .join("k8s")
+            .join(&environment);
+
+        if manifests_matches.get_flag("update") {
+            if let Err(e) = update_golden(&rendered, &golden) {
+                eprintln!("{}", e);
+                exit(1);
+            }
+            println!("Updated the golden files in {}", golden.display());
+            return Ok(());
+        }
+
+        let differences = match compare_golden(&rendered, &golden) {
+            Ok(differences) => differences,
+            Err(e) => {
+                eprintln!("{}", e);
+                exit(1);
+            }
+        };
+        if differences.is_empty() {
+            println!(
+                "The manifests match the golden files in {}",
+                golden.display()
+            );
+            return Ok(());
         }
-        "noop" => {
-            warn!("No secret encryption of secrets for K8s");
-            Arc::new(NoopEncoder) as Arc<dyn K8Encoder>
+        for difference in &differences {
+            match difference {
+                GoldenDifference::Added(path) => {
+                    println!("{} {}", "Added".green().bold(), path.display())
+                }
+                GoldenDifference::Removed(path) => {
+                    println!("{} {}", "Removed".red().bold(), path.display())
+                }
+                GoldenDifference::Changed(path, lines) => {
+                    println!("{} {}", "Changed".yellow().bold(), path.display());
+                    for line in lines {
+                        if line.starts_with('+') {
+                            println!("    {}", line.green());
+                        } else {
+                            println!("    {}", line.red());
+                        }
+                    }
+                }
+            }
         }
-        _ => panic!("Invalid k8s encoder"),
-    };
+        eprintln!(
+            "The manifests differ from the golden files in {} ({} files), run with --update if that is intended",
+            golden.display(),
+            differences.len()
+        );
+        exit(1);
+    }
 
-    // Creating environment
-    let public_environment = PublicEnvironmentDefinitions::new(
-        product_name.clone(),
-        &format!("{}/stack.env.base.yaml", config.product_path()),
-        &format!("{}/stack.env.{}.yaml", config.product_path(), environment),
-    );
-    match public_environment.generate_dotenv_files() {
-        Ok(_) => (),
-        Err(e) => {
-            error!("Unable to generate dotenv files: {}", e);
-            eprintln!("{:#?}", e);
-            std::process::exit(1);
+    if matches.subcommand_matches("changelog").is_some() {
+        let previous = match reactor.history().latest(&environment) {
+            Ok(Some(previous)) => previous,
+            Ok(None) => {
+                eprintln!(
+                    "{} has not been rolled out to {} yet",
+                    product_name, environment
+                );
+                exit(1);
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                exit(1);
+            }
+        };
+        let changelogs = match changelog(
+            toolchain.git(),
+            reactor.product_directory(),
+            &previous.git_hash,
+            reactor.images(),
+        ) {
+            Ok(changelogs) => changelogs,
+            Err(e) => {
+                eprintln!("{}", e);
+                exit(1);
+            }
+        };
+        // Markdown, to paste into the description of the deploy's pull request
+        println!(
+            "Changes to {} since {} was rolled out to {} on {}",
+            product_name,
+            &previous.git_hash[..8.min(previous.git_hash.len())],
+            environment,
+            previous.timestamp
+        );
+        if changelogs.is_empty() {
+            println!("\nNo component changed.");
         }
+        for component in changelogs {
+            println!("\n### {}\n", component.component_name);
+            for commit in component.commits {
+                println!(
+                    "- {} {} ({}, {})",
+                    &commit.hash[..8.min(commit.hash.len())],
+                    commit.subject,
+                    commit.author,
+                    commit.date
+                );
+            }
+        }
+        return Ok(());
     }
 
-    let toolchain = Arc::new(ToolchainContext::new(
-        Platform::default(),
-        Platform::new(&target_os, &target_arch),
-    ));
-    toolchain.setup_env();
-    debug!("Toolchain set up");
+    // Needs the component specs, so it is handled here rather than with the other env commands
+    if let Some(export_matches) = matches
+        .subcommand_matches("env")
+        .and_then(|env_matches| env_matches.subcommand_matches("export"))
+    {
+        let component_name = export_matches.get_one::<String>("component_name").unwrap();
+        let image = match reactor.get_image(component_name) {
+            Some(image) => image,
+            None => {
+                eprintln!("Component {} not found", component_name);
+                exit(1);
+            }
+        };
+        let spec = image.spec();
+
+        // Secrets and personal .env.local overrides stay out of CI
+        let variables: BTreeMap<String, String> = spec
+            .environment_sources()
+            .into_iter()
+            .filter(|(_, _, source)| *source != ".env.secrets" && *source != ".env.local")
+            .map(|(key, value, _)| (key, value))
+            .collect();
+        let secret_names = if export_matches.get_flag("secret_names") {
+            // Read from the definitions rather than the vault, which CI may not reach
+            let mut names = secrets_context.secret_names(component_name);
+            names.extend(spec.dotenv_secrets.keys().cloned());
+            names.sort();
+            names.dedup();
+            Some(names)
+        } else {
+            None
+        };
+
+        print!(
+            "{}",
+            export_environment(
+                &variables,
+                secret_names.as_deref(),
+                export_matches.get_one::<String>("format").unwrap()
+            )
+        );
+        return Ok(());
+    }
 
-    println!("\n\n");
-    let mut reactor = match ContainerReactor::from_product_dir(
-        config.clone(),
-        toolchain.clone(),
-        vault.clone(),
-        secrets_encoder,
-        k8s_encoder,
-        redirected_components,
-        silence_components,
-    ) {
-        Ok(reactor) => reactor,
-        Err(e) => {
-            error!("Failed to create ContainerReactor: {}", e);
-            eprintln!("{}", e);
-            std::process::exit(1);
+    if let Some((exporter, export_matches)) = matches
+        .subcommand_matches("export")
+        .and_then(|export_matches| export_matches.subcommand())
+    {
+        let product_directory = Path::new(reactor.product_directory());
+        let manifests_directory = reactor.cluster_manifests().output_directory();
+        // Single file exporters take a file as output, the others a directory
+        let (exported, default_output) = match exporter {
+            "skaffold" => (
+                skaffold_config(&config, &toolchain, reactor.images(), manifests_directory)
+                    .map(|skaffold| vec![("skaffold.yaml", skaffold)]),
+                product_directory.join("skaffold.yaml"),
+            ),
+            "tilt" => (
+                tiltfile(
+                    &config,
+                    &toolchain,
+                    reactor.images(),
+                    product_directory,
+                    manifests_directory,
+                )
+                .map(|tiltfile| vec![("Tiltfile", tiltfile)]),
+                product_directory.join("Tiltfile"),
+            ),
+            "devcontainer" => (
+                devcontainer(&config, &toolchain, reactor.images(), Path::new(&root_dir)),
+                Path::new(&root_dir)
+                    .join(".devcontainer")
+                    .join(config.product_name()),
+            ),
+            _ => unreachable!("clap only accepts the exporters it declares"),
+        };
+        let exported = match exported {
+            Ok(exported) => exported,
+            Err(e) => {
+                eprintln!("{}", e);
+                exit(1);
+            }
+        };
+        let output = match export_matches
+            .get_one::<String>("output")
+            .map(String::as_str)
+        {
+            Some("-") => {
+                for (file_name, contents) in &exported {
+                    if exported.len() > 1 {
+                        println!("==> {} <==", file_name);
+                    }
+                    print!("{}", contents);
+                }
+                return Ok(());
+            }
+            Some(output) => PathBuf::from(output),
+            None => default_output,
+        };
+        let files = match exported.as_slice() {
+            [(_, contents)] => vec![(output, contents)],
+            _ => {
+                if let Err(e) = std::fs::create_dir_all(&output) {
+                    eprintln!("Failed to create {}: {}", output.display(), e);
+                    exit(1);
+                }
+                exported
+                    .iter()
+                    .map(|(file_name, contents)| (output.join(file_name), contents))
+                    .collect()
+            }
+        };
+        for (path, contents) in files {
+            if let Err(e) = std::fs::write(&path, contents) {
+                eprintln!("Failed to write {}: {}", path.display(), e);
+                exit(1);
+            }
+            println!("Wrote {}", path.display());
         }
-    };
-
-    let minikube = Minikube::new(toolchain.clone());
+        return Ok(());
+    }
 
     if let Some(matches) = matches.subcommand_matches("describe") {
         trace!("Executing 'describe' subcommand");
-        if matches.subcommand_matches("toolchain").is_some() {
-            println!("{:#?}", toolchain);
+        let format = matches.get_one::<String>("format").unwrap().as_str();
+        if let Some(toolchain_matches) = matches.subcommand_matches("toolchain") {
+            match (toolchain_matches.get_flag("versions"), format) {
+                (true, "text") => print_tool_versions(&toolchain.versions()),
+                (true, _) => print_description(&toolchain.versions(), format),
+                (false, _) => print_description(&*toolchain, format),
+            }
             debug!("Described toolchain");
-            std::process::exit(0);
+            exit(0);
         }
 
         if matches.subcommand_matches("images").is_some() {
-            println!("{:#?}", reactor.images());
+            let images = reactor
+                .images()
+                .iter()
+                .map(|image| image.describe())
+                .collect::<Vec<_>>();
+            print_description(&images, format);
             debug!("Described images");
-            std::process::exit(0);
+            exit(0);
         }
 
         if matches.subcommand_matches("services").is_some() {
-            println!("{:#?}", reactor.services());
+            print_description(reactor.services(), format);
             debug!("Described services");
-            std::process::exit(0);
+            exit(0);
+        }
+
+        if let Some(graph_matches) = matches.subcommand_matches("graph") {
+            let graph = DependencyGraph::from_images(reactor.images(), &redirected_components);
+            match format {
+                "text" if graph_matches.get_flag("mermaid") => print!("{}", graph.to_mermaid()),
+                "text" => print!("{}", graph.to_dot()),
+                _ => print_description(&graph, format),
+            }
+            debug!("Described dependency graph");
+            exit(0);
+        }
+
+        if let Some(env_matches) = matches.subcommand_matches("env") {
+            let component_name = env_matches.get_one::<String>("component_name").unwrap();
+            let image = component_image(&reactor, component_name);
+            let spec = image.spec();
+
+            let mut variables = BTreeMap::new();
+            for (key, value, source) in spec.environment_sources() {
+                let source = if source == ".env" && public_environment.defines(component_name, &key)
+                {
+                    "stack.env (generated)"
+                } else {
+                    source
+                };
+                let value = if source == ".env.secrets" {
+                    mask_secret(&value)
+                } else {
+                    value
+                };
+                variables.insert(key, serde_json::json!({ "value": value, "source": source }));
+            }
+
+            let secrets = vault
+                .lock()
+                .unwrap()
+                .get(product_name, component_name, &environment)
+                .await
+                .unwrap_or_default();
+            for (key, value) in secrets {
+                // Vault secrets only reach the cluster, the containers use .env.secrets
+                variables.entry(key).or_insert_with(
+                    || serde_json::json!({ "value": mask_secret(&value), "source": "vault (k8s)" }),
+                );
+            }
+
+            if format == "text" {
+                for (key, variable) in &variables {
+                    println!(
+                        "{:<32} {:<40} {}",
+                        key.bold(),
+                        variable["value"].as_str().unwrap_or_default(),
+                        variable["source"].as_str().unwrap_or_default().dimmed()
+                    );
+                }
+            } else {
+                print_description(&variables, format);
+            }
+            debug!("Described environment for component: {}", component_name);
+            exit(0);
         }
 
         if matches.subcommand_matches("build-script").is_some() {
             let component_name = matches.get_one::<String>("component_name").unwrap();
             trace!("Describing build script for component: {}", component_name);
-            let image = reactor
-                .get_image(component_name)
-                .expect("Component not found");
+            let image = component_image(&reactor, component_name);
             let secrets = vault
                 .lock()
                 .unwrap()
@@ -503,17 +1911,23 @@ async fn main() -> io::Result<()> {
                 .unwrap_or_default();
             let ctx = image.generate_build_context(secrets);
 
-            println!("{}", image.build_script(&ctx).unwrap());
+            let script = image.build_script(&ctx).unwrap();
+            if format == "text" {
+                println!("{}", script);
+            } else {
+                print_description(
+                    &serde_json::json!({ "component": component_name, "script": script }),
+                    format,
+                );
+            }
             debug!("Described build script for component: {}", component_name);
-            std::process::exit(0);
+            exit(0);
         }
 
         if matches.subcommand_matches("build-context").is_some() {
             let component_name = matches.get_one::<String>("component_name").unwrap();
             trace!("Describing build context for component: {}", component_name);
-            let image = reactor
-                .get_image(component_name)
-                .expect("Component not found");
+            let image = component_image(&reactor, component_name);
             let secrets = vault
                 .lock()
                 .unwrap()
@@ -521,18 +1935,16 @@ async fn main() -> io::Result<()> {
                 .await
                 .unwrap_or_default();
             let ctx = image.generate_build_context(secrets);
-            println!("{:#?}", ctx);
+            print_description(&ctx, format);
             debug!("Described build context for component: {}", component_name);
-            std::process::exit(0);
+            exit(0);
         }
 
         if matches.subcommand_matches("artefacts").is_some() {
             let _pop_dir = Directory::chdir(reactor.product_directory());
             let component_name = matches.get_one::<String>("component_name").unwrap();
             trace!("Describing artefacts for component: {}", component_name);
-            let image = reactor
-                .get_image(component_name)
-                .expect("Component not found");
+            let image = component_image(&reactor, component_name);
             let secrets = vault
                 .lock()
                 .unwrap()
@@ -540,25 +1952,38 @@ async fn main() -> io::Result<()> {
                 .await
                 .unwrap_or_default();
             let ctx = image.generate_build_context(secrets);
-            for (k, v) in image.spec().build_artefacts() {
-                let message = format!("{} {}", "Artefact".green(), k.white());
-                println!("{}\n", &message.bold());
-
-                println!("{}\n", v.render(&ctx));
+            let artefacts = image
+                .spec()
+                .build_artefacts()
+                .into_iter()
+                .map(|(k, v)| (k, v.render(&ctx)))
+                .collect::<BTreeMap<_, _>>();
+            if format == "text" {
+                for (k, rendered) in artefacts {
+                    let message = format!("{} {}", "Artefact".green(), k.white());
+                    println!("{}\n", &message.bold());
+
+                    println!("{}\n", rendered);
+                }
+            } else {
+                print_description(&artefacts, format);
             }
             debug!("Described artefacts for component: {}", component_name);
-            std::process::exit(0);
+            exit(0);
         }
 
         if matches.subcommand_matches("k8s").is_some() {
             trace!("Describing Kubernetes manifests");
             let manifests = reactor.cluster_manifests();
+            let mut described = Vec::new();
             for component in manifests.components() {
-                println!(
-                    "{} -> {}",
-                    component.input_directory().display(),
-                    component.output_directory().display()
-                );
+                if format == "text" {
+                    println!(
+                        "{} -> {}",
+                        component.input_directory().display(),
+                        component.output_directory().display()
+                    );
+                }
                 let spec = component.spec();
                 let secrets = vault
                     .lock()
@@ -567,13 +1992,35 @@ async fn main() -> io::Result<()> {
                     .await
                     .unwrap_or_default();
                 let ctx = spec.generate_build_context(Some(toolchain.clone()), secrets);
-                for manifest in component.manifests() {
-                    println!("{}", manifest.render(&ctx));
+                if format == "text" {
+                    for manifest in component.manifests() {
+                        println!("{}", manifest.render(&ctx));
+                    }
+                    println!();
+                } else {
+                    let rendered = component
+                        .manifests()
+                        .iter()
+                        .map(|manifest| {
+                            serde_json::json!({
+                                "path": manifest.artefact.input_path,
+                                "contents": manifest.render(&ctx),
+                            })
+                        })
+                        .collect::<Vec<_>>();
+                    described.push(serde_json::json!({
+                        "component": spec.component_name,
+                        "input_directory": component.input_directory(),
+                        "output_directory": component.output_directory(),
+                        "manifests": rendered,
+                    }));
                 }
-                println!();
+            }
+            if format != "text" {
+                print_description(&described, format);
             }
             debug!("Described Kubernetes manifests");
-            std::process::exit(0);
+            exit(0);
         }
     }
 
@@ -582,7 +2029,12 @@ async fn main() -> io::Result<()> {
 
         if matches.subcommand_matches("create").is_some() {
             trace!("Creating vault");
-            match vault.lock().unwrap().create_vault(product_name).await {
+            match vault
+                .lock()
+                .unwrap()
+                .create_vault(product_name, &environment)
+                .await
+            {
                 Ok(_) => {
                     trace!("Vault created successfully");
                     return Ok(());
@@ -590,7 +2042,7 @@ async fn main() -> io::Result<()> {
                 Err(e) => {
                     error!("Failed to create vault: {}", e);
                     eprintln!("{}", e);
-                    std::process::exit(1);
+                    exit(1);
                 }
             }
         }
@@ -599,8 +2051,17 @@ async fn main() -> io::Result<()> {
             let component_name = matches.get_one::<String>("component_name").unwrap();
             let secrets = matches.get_one::<String>("secrets").unwrap();
             trace!("Adding: {}", secrets);
-            let secrets: HashMap<String, String> =
-                serde_json::from_str(secrets).expect("Invalid secrets format");
+            let secrets: HashMap<String, String> = match serde_json::from_str(secrets) {
+                Ok(secrets) => secrets,
+                Err(e) => {
+                    let e = RushError::Config(format!(
+                        "Invalid secrets {}: {} (expected a JSON object of strings)",
+                        secrets, e
+                    ));
+                    eprintln!("{}", e);
+                    exit(e.exit_code());
+                }
+            };
 
             trace!("Adding secrets to vault");
             match vault
@@ -616,7 +2077,7 @@ async fn main() -> io::Result<()> {
                 Err(e) => {
                     error!("Failed to add secrets: {}", e);
                     eprintln!("{}", e);
-                    std::process::exit(1);
+                    exit(1);
                 }
             }
         }
@@ -639,7 +2100,7 @@ async fn main() -> io::Result<()> {
                 Err(e) => {
                     error!("Failed to remove secrets: {}", e);
                     eprintln!("{}", e);
-                    std::process::exit(1);
+                    exit(1);
                 }
             }
         }
@@ -649,12 +2110,17 @@ async fn main() -> io::Result<()> {
         trace!("Executing 'secrets' subcommand");
 
         if matches.subcommand_matches("init").is_some() {
-            match vault.lock().unwrap().create_vault(product_name).await {
+            match vault
+                .lock()
+                .unwrap()
+                .create_vault(product_name, &environment)
+                .await
+            {
                 Ok(_) => (),
                 Err(e) => {
                     error!("Failed to create vault: {}", e);
                     eprintln!("{}", e);
-                    std::process::exit(1);
+                    exit(1);
                 }
             }
             trace!("Initializing secrets");
@@ -666,10 +2132,82 @@ async fn main() -> io::Result<()> {
                 Err(e) => {
                     error!("Failed to initialize secrets: {}", e);
                     eprintln!("{}", e);
-                    std::process::exit(1);
+                    exit(1);
+                }
+            }
+        }
+    }
+
+    // Cleaning up needs no secrets, so it works with a vault that is missing some
+    if let Some(clean_matches) = matches.subcommand_matches("clean") {
+        reactor.clean().await;
+        if clean_matches.get_flag("images") {
+            reactor.remove_images().await;
+        }
+        let mut result = Ok(());
+        if clean_matches.get_flag("network") {
+            result = result.and(reactor.delete_network().await);
+        }
+        if clean_matches.get_flag("k8s_output") {
+            result = result.and(reactor.remove_k8s_output());
+        }
+        match result {
+            Ok(_) => return Ok(()),
+            Err(e) => {
+                eprintln!("{}", e);
+                exit(1);
+            }
+        }
+    }
+
+    if let Some(gc_matches) = matches.subcommand_matches("gc") {
+        let remote = gc_matches.get_flag("remote");
+        // The registry is shared by the environments, so none of their clusters may lose an image
+        let mut kube_contexts = Vec::new();
+        if remote {
+            for environment in config.environments() {
+                if environment == "local" {
+                    continue;
+                }
+                let environment_config = match Config::new(
+                    &root_dir,
+                    product_name,
+                    environment,
+                    &docker_registry,
+                    start_port,
+                    config.variable_overrides().clone(),
+                ) {
+                    Ok(environment_config) => environment_config,
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        exit(e.exit_code());
+                    }
+                };
+                for context in environment_config.kube_contexts() {
+                    if context != "not_set" && !kube_contexts.contains(context) {
+                        kube_contexts.push(context.clone());
+                    }
                 }
             }
         }
+        let result = reactor
+            .collect_garbage(
+                *gc_matches.get_one::<usize>("keep").unwrap(),
+                remote,
+                &kube_contexts,
+                gc_matches.get_flag("dry_run"),
+            )
+            .await;
+        if let Err(e) = result {
+            eprintln!("{}", e);
+            exit(1);
+        }
+        return Ok(());
+    }
+
+    if matches.subcommand_matches("kill").is_some() {
+        reactor.kill_and_clean(true).await;
+        return Ok(());
     }
 
     // Validate secrets
@@ -679,12 +2217,38 @@ async fn main() -> io::Result<()> {
     {
         error!("Missing secrets in vault: {}", e);
         eprintln!("{}", e);
-        std::process::exit(1);
+        exit(1);
     }
 
     // Run and deploy Operations
-    if matches.subcommand_matches("dev").is_some() {
+    if let Some(dev_matches) = matches.subcommand_matches("dev") {
         trace!("Launching development environment");
+        reactor.enable_warm_start(dev_matches.get_flag("rebuild"));
+        if let Some(port) = dev_matches.get_one::<u16>("proxy") {
+            let tls = dev_matches
+                .get_one::<u16>("proxy_tls_port")
+                .map(|tls_port| {
+                    let certificate_file = format!(
+                        "{}/.cache/rush/proxy/{}.pem",
+                        std::env::var("HOME").unwrap_or_default(),
+                        config.product_uri()
+                    );
+                    (*tls_port, PathBuf::from(certificate_file))
+                });
+            reactor.set_proxy(*port, tls);
+        }
+        if let Some(port) = dev_matches.get_one::<u16>("status_port") {
+            let address = *dev_matches
+                .get_one::<std::net::IpAddr>("status_bind")
+                .unwrap();
+            if let Err(e) = reactor.serve_status_page(address, *port).await {
+                eprintln!("{}", e);
+                exit(1);
+            }
+        }
+        if let Some(seconds) = dev_matches.get_one::<u64>("stats") {
+            reactor.set_resource_usage_interval(std::time::Duration::from_secs(*seconds));
+        }
         match reactor.launch().await {
             Ok(_) => {
                 trace!("Development environment launched successfully");
@@ -693,122 +2257,357 @@ async fn main() -> io::Result<()> {
             Err(e) => {
                 error!("Failed to launch development environment: {}", e);
                 eprintln!("{}", e);
-                std::process::exit(1);
+                exit(1);
             }
         }
     }
 
-    if matches.subcommand_matches("build").is_some() {
-        match reactor.build().await {
-            Ok(_) => {
-                return Ok(());
+    if let Some(build_matches) = matches
+        .subcommand_matches("profile")
+        .and_then(|profile_matches| profile_matches.subcommand_matches("build"))
+    {
+        let profiler = BuildProfiler::default();
+        reactor.set_profiler(profiler.clone());
+        println!(
+            "{}",
+            "Profiling rebuilds: change the components' files, then stop with Ctrl-C".bold()
+        );
+        if let Err(e) = reactor.launch().await {
+            error!("Failed to launch development environment: {}", e);
+            eprintln!("{}", e);
+            exit(1);
+        }
+        profiler.print();
+        if let Some(output) = build_matches.get_one::<String>("output") {
+            if let Err(e) = profiler.write_json(output) {
+                eprintln!("{}", e);
+                exit(1);
+            }
+        }
+        return Ok(());
+    }
+
+    if let Some(remote_matches) = matches
+        .subcommand_matches("images")
+        .and_then(|images_matches| images_matches.subcommand_matches("remote"))
+    {
+        let component_name = remote_matches.get_one::<String>("component_name");
+        if let Some(component_name) = component_name {
+            if reactor.get_image(component_name).is_none() {
+                eprintln!("Component {} not found", component_name);
+                exit(1);
             }
+        }
+        let registry = match RegistryClient::new(config.docker_registry()) {
+            Ok(registry) => registry,
             Err(e) => {
                 eprintln!("{}", e);
-                std::process::exit(1);
+                exit(1);
+            }
+        };
+        // Marks the tags the latest rollout into each environment uses
+        let mut rolled_out: HashMap<String, Vec<String>> = HashMap::new();
+        for environment in config.environments() {
+            if let Ok(Some(record)) = reactor.history().latest(environment) {
+                for image in record.images.values() {
+                    rolled_out
+                        .entry(image.rsplit('/').next().unwrap_or(image).to_string())
+                        .or_default()
+                        .push(environment.clone());
+                }
+            }
+        }
+        for image in reactor.images() {
+            let description = image.describe();
+            if description.build_type.dockerfile_path().is_none()
+                || component_name.is_some_and(|name| *name != description.component_name)
+            {
+                continue;
+            }
+            let remote_tags = match registry.remote_tags(&description.image_name).await {
+                Ok(remote_tags) => remote_tags,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    exit(1);
+                }
+            };
+            println!(
+                "{}  {}/{}",
+                description.component_name.bold(),
+                config.docker_registry(),
+                description.image_name
+            );
+            if remote_tags.is_empty() {
+                println!("    No tags pushed");
+            }
+            for remote in remote_tags {
+                let mut notes = rolled_out
+                    .get(&format!("{}:{}", description.image_name, remote.tag))
+                    .cloned()
+                    .unwrap_or_default();
+                if description.tag.as_deref() == Some(remote.tag.as_str()) {
+                    notes.insert(0, "current".to_string());
+                }
+                println!(
+                    "    {:<20} {}  {:<30} {}",
+                    remote.tag,
+                    remote.digest,
+                    remote.created.as_deref().unwrap_or("-"),
+                    notes.join(", ").green()
+                );
             }
         }
+        return Ok(());
     }
 
-    if matches.subcommand_matches("push").is_some() {
-        match reactor.build_and_push().await {
-            Ok(_) => {
-                return Ok(());
+    if let Some(prune_matches) = matches
+        .subcommand_matches("volumes")
+        .and_then(|volumes_matches| volumes_matches.subcommand_matches("prune"))
+    {
+        let dry_run = prune_matches.get_flag("dry_run");
+        match reactor
+            .prune_volumes(prune_matches.get_flag("all"), dry_run)
+            .await
+        {
+            Ok(volumes) if volumes.is_empty() => println!("No volumes to remove"),
+            Ok(volumes) => {
+                for volume in volumes {
+                    if dry_run {
+                        println!("Would remove volume {}", volume);
+                    } else {
+                        println!("Removed volume {}", volume);
+                    }
+                }
             }
             Err(e) => {
                 eprintln!("{}", e);
-                std::process::exit(1);
+                exit(1);
+            }
+        }
+        return Ok(());
+    }
+
+    if let Some(db_matches) = matches.subcommand_matches("db") {
+        let result = match db_matches.subcommand() {
+            Some(("snapshot", snapshot_matches)) => {
+                let component_name = snapshot_matches
+                    .get_one::<String>("component_name")
+                    .unwrap();
+                let name = snapshot_matches.get_one::<String>("name");
+                reactor
+                    .snapshot_database(component_name, name.map(|name| name.as_str()))
+                    .await
+                    .map(|path| println!("Wrote snapshot {}", path.display()))
             }
+            Some(("restore", restore_matches)) => {
+                let component_name = restore_matches.get_one::<String>("component_name").unwrap();
+                let name = restore_matches.get_one::<String>("name");
+                reactor
+                    .restore_database(component_name, name.map(|name| name.as_str()))
+                    .await
+                    .map(|path| println!("Restored snapshot {}", path.display()))
+            }
+            _ => Err("Use rush db snapshot or rush db restore".to_string()),
+        };
+        if let Err(e) = result {
+            eprintln!("{}", e);
+            exit(1);
+        }
+        return Ok(());
+    }
+
+    if let Some(reload_matches) = matches.subcommand_matches("reload-env") {
+        let component_name = reload_matches.get_one::<String>("component_name");
+        if let Err(e) = reactor.request_env_reload(component_name.map(|name| name.as_str())) {
+            eprintln!("{}", e);
+            exit(1);
+        }
+        match component_name {
+            Some(component_name) => println!(
+                "Wrote the .env files. A running rush dev restarts '{}' if its environment changed",
+                component_name
+            ),
+            None => println!(
+                "Wrote the .env files. A running rush dev restarts the components whose environment changed"
+            ),
+        }
+        return Ok(());
+    }
+
+    if matches.subcommand_matches("build").is_some() {
+        let result = reactor.build().await;
+        return finish_command(&matches, "build", &reactor, result);
+    }
+
+    // Every command pushing images, checked before the build rather than after it
+    if ["push", "deploy", "rollout"]
+        .into_iter()
+        .any(|command| matches.subcommand_matches(command).is_some())
+    {
+        if let Err(e) = require_clean_tree(matches.get_flag("require_clean"), &config, &toolchain) {
+            eprintln!("{}", e);
+            exit(1);
         }
     }
 
+    if matches.subcommand_matches("push").is_some() {
+        let result = reactor.build_and_push().await;
+        return finish_command(&matches, "push", &reactor, result);
+    }
+
+    let cluster_command = matches
+        .subcommand_matches("minikube")
+        .and_then(|minikube_matches| minikube_matches.subcommand_name());
+    if let Some(command @ ("start" | "stop" | "delete")) = cluster_command {
+        // Config::new only accepts the local clusters rush knows
+        let local_cluster = match config.local_cluster() {
+            "kind" => Arc::new(Kind::new(toolchain.clone(), config.local_cluster_name()))
+                as Arc<dyn LocalCluster>,
+            "k3d" => Arc::new(K3d::new(toolchain.clone(), config.local_cluster_name()))
+                as Arc<dyn LocalCluster>,
+            _ => Arc::new(Minikube::new(toolchain.clone())) as Arc<dyn LocalCluster>,
+        };
+        let result = match command {
+            "start" => start_local_cluster(local_cluster.as_ref()).await,
+            "stop" => local_cluster
+                .stop()
+                .await
+                .map(|_| println!("Stopped the {} cluster", local_cluster.kind())),
+            _ => local_cluster
+                .delete()
+                .await
+                .map(|_| println!("Deleted the {} cluster", local_cluster.kind())),
+        };
+        if let Err(e) = result {
+            eprintln!("{}", e);
+            exit(1);
+        }
+        return Ok(());
+    }
+
+    if matches
+        .subcommand_matches("minikube")
+        .and_then(|minikube_matches| minikube_matches.subcommand_matches("dev"))
+        .is_some()
+    {
+        if !toolchain.has_kubectl() {
+            eprintln!("kubectl not found");
+            exit(1);
+        }
+        let minikube = Minikube::new(toolchain.clone());
+        if let Err(e) = minikube_dev(&mut reactor, &minikube, &config).await {
+            eprintln!("{}", e);
+            exit(1);
+        }
+        return Ok(());
+    }
+
     // Setting the context
     if !toolchain.has_kubectl() {
         eprintln!("kubectl not found");
-        std::process::exit(1);
+        exit(1);
     }
 
-    match reactor
-        .select_kubernetes_context(config.kube_context())
+    // Every kubectl command passes the context, the user's current one is left alone
+    if let Err(e) = reactor
+        .verify_kubernetes_context(config.kube_context())
         .await
     {
-        Ok(_) => (),
-        Err(e) => {
-            eprintln!("{}", e);
-            std::process::exit(1);
+        eprintln!("{}", e);
+        exit(1);
+    }
+
+    // Changes to protected environments, listed in PROTECTED_ENVIRONMENTS, must be confirmed
+    let destructive_command = [
+        "apply",
+        "deploy",
+        "uninstall",
+        "unapply",
+        "rollout",
+        "promote",
+    ]
+    .into_iter()
+    .find(|command| matches.subcommand_matches(command).is_some());
+    if let (Some(command), true) = (destructive_command, config.is_protected()) {
+        if !matches.get_flag("yes")
+            && !utils::confirm_typed(
+                &format!(
+                    "You are about to {} {} in {} using {}.",
+                    command,
+                    product_name,
+                    environment.bold(),
+                    config.kube_contexts().join(", ").bold()
+                ),
+                &environment,
+            )
+        {
+            eprintln!("Aborted");
+            exit(1);
         }
     }
 
     if matches.subcommand_matches("rollout").is_some() {
-        match reactor.rollout().await {
-            Ok(_) => {
-                return Ok(());
+        let previous = reactor.history().latest(&environment).ok().flatten();
+        let result = reactor.rollout().await;
+        reactor
+            .notify_deploy("rollout", previous.as_ref(), &result)
+            .await;
+        return finish_command(&matches, "rollout", &reactor, result);
+    }
+
+    if let Some(promote_matches) = matches.subcommand_matches("promote") {
+        let from = promote_matches.get_one::<String>("from").unwrap();
+        if !config.environments().contains(from) {
+            eprintln!(
+                "Invalid environment: {}. Valid environments: {}",
+                from,
+                config.environments().join(", ")
+            );
+            exit(1);
+        }
+        let record = match reactor.history().latest(from) {
+            Ok(Some(record)) => record,
+            Ok(None) => {
+                eprintln!("No rollout to {} found in the deployment history", from);
+                exit(1);
             }
             Err(e) => {
                 eprintln!("{}", e);
-                std::process::exit(1);
+                exit(1);
             }
-        }
+        };
+
+        let result = reactor.promote(&record).await;
+        return finish_command(&matches, "promote", &reactor, result);
     }
 
     if matches.subcommand_matches("install").is_some() {
-        match reactor.install_manifests().await {
-            Ok(_) => {
-                return Ok(());
-            }
-            Err(e) => {
-                eprintln!("{}", e);
-                std::process::exit(1);
-            }
-        }
+        let result = reactor.install_manifests().await;
+        return finish_command(&matches, "install", &reactor, result);
     }
 
     if matches.subcommand_matches("uninstall").is_some() {
-        match reactor.uninstall_manifests().await {
-            Ok(_) => {
-                return Ok(());
-            }
-            Err(e) => {
-                eprintln!("{}", e);
-                std::process::exit(1);
-            }
-        }
+        let result = reactor.uninstall_manifests().await;
+        return finish_command(&matches, "uninstall", &reactor, result);
     }
 
     if matches.subcommand_matches("deploy").is_some() {
-        match reactor.deploy().await {
-            Ok(_) => {
-                return Ok(());
-            }
-            Err(e) => {
-                eprintln!("{}", e);
-                std::process::exit(1);
-            }
-        }
+        let previous = reactor.history().latest(&environment).ok().flatten();
+        let result = reactor.deploy().await;
+        reactor
+            .notify_deploy("deploy", previous.as_ref(), &result)
+            .await;
+        return finish_command(&matches, "deploy", &reactor, result);
     }
 
     if matches.subcommand_matches("apply").is_some() {
-        match reactor.apply().await {
-            Ok(_) => {
-                return Ok(());
-            }
-            Err(e) => {
-                eprintln!("{}", e);
-                std::process::exit(1);
-            }
-        }
+        let result = reactor.apply().await;
+        return finish_command(&matches, "apply", &reactor, result);
     }
 
     if matches.subcommand_matches("unapply").is_some() {
-        match reactor.unapply().await {
-            Ok(_) => {
-                return Ok(());
-            }
-            Err(e) => {
-                eprintln!("{}", e);
-                std::process::exit(1);
-            }
-        }
+        let result = reactor.unapply().await;
+        return finish_command(&matches, "unapply", &reactor, result);
     }
 
     Ok(())
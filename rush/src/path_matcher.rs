@@ -1,12 +1,17 @@
-use glob::Pattern as GlobPattern;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::{Match, WalkBuilder};
+use log::warn;
+use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
 
-/// Represents a .gitignore file and its patterns
+/// Matches paths against patterns with the semantics of .gitignore files, compiled once
 #[derive(Debug)]
 pub struct PathMatcher {
-    /// List of ignore patterns parsed from .gitignore files
-    match_patterns: Vec<Pattern>,
+    /// The patterns as written, in the order they were read
+    patterns: Vec<String>,
+    /// One compiled matcher per .gitignore file, by the directory it is relative to
+    matchers: HashMap<PathBuf, Gitignore>,
     /// Root path where the PathMatcher instance was created
     root_path: PathBuf,
 }
@@ -14,8 +19,8 @@ pub struct PathMatcher {
 /// Represents a single pattern from a .gitignore file
 #[derive(Debug)]
 pub struct Pattern {
-    /// Compiled glob pattern
-    pattern: GlobPattern,
+    /// Compiled pattern, matching relative paths
+    matcher: Gitignore,
     /// Original pattern string from .gitignore
     original_pattern: String,
     /// Indicates if this is a negation pattern (starts with !)
@@ -31,28 +36,34 @@ impl Pattern {
     ///
     /// * `pattern` - A string slice that holds the pattern from .gitignore
     pub fn new(pattern: String) -> Self {
-        let is_negation = pattern.starts_with('!');
-        let is_directory_only = pattern.ends_with('/');
-        let cleaned_pattern = pattern
-            .trim_start_matches('!')
-            .trim_end_matches('/')
-            .to_string();
-
-        let glob_pattern = if cleaned_pattern.starts_with('/') {
-            GlobPattern::new(&cleaned_pattern).expect("Failed to compile glob pattern")
-        } else {
-            GlobPattern::new(&format!("**/{}", cleaned_pattern))
-                .expect("Failed to compile glob pattern")
-        };
+        let mut builder = GitignoreBuilder::new("");
+        builder
+            .add_line(None, &pattern)
+            .expect("Failed to compile glob pattern");
 
         Pattern {
-            pattern: glob_pattern,
+            matcher: builder.build().expect("Failed to compile glob pattern"),
+            is_negation: pattern.starts_with('!'),
+            is_directory_only: pattern.ends_with('/'),
             original_pattern: pattern,
-            is_negation,
-            is_directory_only,
         }
     }
 
+    /// The pattern as written
+    pub fn as_str(&self) -> &str {
+        &self.original_pattern
+    }
+
+    /// Whether a match re-includes the path (the pattern starts with !)
+    pub fn is_negation(&self) -> bool {
+        self.is_negation
+    }
+
+    /// Whether the pattern only matches directories (it ends with /)
+    pub fn is_directory_only(&self) -> bool {
+        self.is_directory_only
+    }
+
     /// Checks if the given path matches this pattern
     ///
     /// # Arguments
@@ -60,14 +71,7 @@ impl Pattern {
     /// * `path` - The path to check
     /// * `is_dir` - Whether the path is a directory
     pub fn matches(&self, path: &Path, is_dir: bool) -> bool {
-        if self.is_directory_only && !is_dir {
-            return false;
-        }
-
-        let path_str = path
-            .to_str()
-            .expect("Path could not be converted to string");
-        self.pattern.matches(path_str)
+        !self.matcher.matched(path, is_dir).is_none()
     }
 }
 
@@ -76,21 +80,35 @@ impl PathMatcher {
     ///
     /// # Arguments
     ///
-    /// * `start_path` - The path to start searching for .gitignore files
+    /// * `start_path` - The directory the patterns are relative to
+    /// * `paths` - The patterns, e.g. the `watch` list of a component
     pub fn new(start_path: &Path, paths: Vec<String>) -> Self {
-        let match_patterns = paths.into_iter().map(Pattern::new).collect();
+        let root_path = absolute(start_path);
+        let mut builder = GitignoreBuilder::new(&root_path);
+        for path in &paths {
+            builder
+                .add_line(None, path)
+                .expect("Failed to compile glob pattern");
+        }
 
         PathMatcher {
-            match_patterns,
-            root_path: start_path.to_path_buf(),
+            matchers: HashMap::from([(
+                root_path.clone(),
+                builder.build().expect("Failed to compile glob pattern"),
+            )]),
+            patterns: paths,
+            root_path,
         }
     }
 
+    /// Reads the .gitignore files of `start_path`, its parents and the directories below
+    /// it that are not ignored themselves.
     pub fn from_gitignore(start_path: &Path) -> Self {
-        let mut current_path = start_path.to_path_buf();
+        let root_path = absolute(start_path);
         let mut gitignore_paths = Vec::new();
 
         // Walk up the directory tree to find all .gitignore files
+        let mut current_path = root_path.clone();
         loop {
             let gitignore_path = current_path.join(".gitignore");
             if gitignore_path.exists() {
@@ -100,57 +118,101 @@ impl PathMatcher {
                 break;
             }
         }
-
-        // Read all .gitignore files and collect patterns
-        let mut match_patterns = Vec::new();
-        for path in gitignore_paths.into_iter().rev() {
-            let gitignore_content =
-                fs::read_to_string(&path).expect("Failed to read .gitignore file");
-            match_patterns.extend(
+        gitignore_paths.reverse();
+
+        // The walk skips ignored directories, so target/ and node_modules/ are not read
+        let nested = WalkBuilder::new(&root_path)
+            .hidden(false)
+            .require_git(false)
+            .filter_entry(|entry| entry.file_name() != ".git")
+            .build()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.depth() > 1 && entry.file_name() == ".gitignore")
+            .map(|entry| entry.into_path());
+        gitignore_paths.extend(nested);
+
+        let mut patterns = Vec::new();
+        let mut matchers = HashMap::new();
+        for path in gitignore_paths {
+            let (matcher, error) = Gitignore::new(&path);
+            if let Some(e) = error {
+                warn!("Invalid patterns in {}: {}", path.display(), e);
+            }
+            let gitignore_content = fs::read_to_string(&path).unwrap_or_default();
+            patterns.extend(
                 gitignore_content
                     .lines()
                     .map(|line| line.trim().to_string())
-                    .filter(|line| !line.is_empty() && !line.starts_with('#'))
-                    .map(Pattern::new),
+                    .filter(|line| !line.is_empty() && !line.starts_with('#')),
             );
+            matchers.insert(matcher.path().to_path_buf(), matcher);
         }
 
         PathMatcher {
-            match_patterns,
-            root_path: start_path.to_path_buf(),
+            patterns,
+            matchers,
+            root_path,
+        }
+    }
+
+    /// The patterns as written, e.g. in the `watch` list of a component
+    pub fn patterns(&self) -> Vec<&str> {
+        self.patterns.iter().map(String::as_str).collect()
+    }
+
+    /// The directories, or files, holding every path the patterns of [`new`](Self::new)
+    /// can match: the root for a pattern matching at any depth, such as `*.rs`, and the
+    /// part before the first wildcard for anchored ones, such as `src/**/*.rs`.
+    pub fn directories(&self) -> Vec<PathBuf> {
+        let mut directories = Vec::new();
+        for pattern in &self.patterns {
+            // Re-including a path does not widen what can match
+            if pattern.starts_with('!') {
+                continue;
+            }
+            let pattern = pattern.trim_end_matches('/');
+            // Patterns without a slash but at the end match at any depth, as in git
+            if !pattern.contains('/') {
+                directories.push(self.root_path.clone());
+                continue;
+            }
+            let prefix = pattern
+                .trim_start_matches('/')
+                .split('/')
+                .take_while(|part| !part.contains(['*', '?', '[']))
+                .collect::<PathBuf>();
+            directories.push(self.root_path.join(prefix));
         }
+        directories
     }
 
-    /// Checks if a given path should be matched
+    /// Checks if a given path should be matched. A path matches when it or one of its
+    /// parents does, and the .gitignore closest to it decides.
     ///
     /// # Arguments
     ///
-    /// * `path` - The path to check
+    /// * `path` - The path to check, relative to the root path or absolute
     pub fn matches(&self, path: &Path) -> bool {
-        let relative_path = path.strip_prefix(&self.root_path).unwrap_or(path);
+        let path = self.root_path.join(path);
         let is_dir = path.is_dir();
 
-        let mut matched = false;
-        for ancestor in relative_path.ancestors() {
-            for pattern in &self.match_patterns {
-                if pattern.matches(ancestor, true) {
-                    matched = !pattern.is_negation;
-                }
+        for directory in path.ancestors().skip(1) {
+            let Some(matcher) = self.matchers.get(directory) else {
+                continue;
+            };
+            match matcher.matched_path_or_any_parents(&path, is_dir) {
+                Match::None => continue,
+                decision => return decision.is_ignore(),
             }
         }
-
-        if !matched {
-            for pattern in &self.match_patterns {
-                if pattern.matches(relative_path, is_dir) {
-                    matched = !pattern.is_negation;
-                }
-            }
-        }
-
-        matched
+        false
     }
 }
 
+fn absolute(path: &Path) -> PathBuf {
+    std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -286,6 +348,66 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_gitignore_below_start_path() {
+        let temp_dir = TempDir::new().unwrap();
+        create_gitignore(&temp_dir, "target/\n");
+        fs::create_dir_all(temp_dir.path().join("frontend/dist")).unwrap();
+        fs::write(temp_dir.path().join("frontend/.gitignore"), "dist\n").unwrap();
+
+        let gitignore = PathMatcher::from_gitignore(temp_dir.path());
+
+        assert!(gitignore.matches(&temp_dir.path().join("frontend/dist/index.html")));
+        assert!(!gitignore.matches(&temp_dir.path().join("dist/index.html")));
+        assert!(!gitignore.matches(&temp_dir.path().join("src/main.rs")));
+    }
+
+    #[test]
+    fn test_patterns_with_a_slash_are_anchored() {
+        let temp_dir = TempDir::new().unwrap();
+        let matcher = PathMatcher::new(
+            temp_dir.path(),
+            vec!["src/*.rs".to_string(), "assets".to_string()],
+        );
+
+        assert!(matcher.matches(&temp_dir.path().join("src/main.rs")));
+        assert!(!matcher.matches(&temp_dir.path().join("src/bin/main.rs")));
+        assert!(!matcher.matches(&temp_dir.path().join("backend/src/main.rs")));
+        assert!(matcher.matches(&temp_dir.path().join("frontend/assets/logo.png")));
+        assert!(matcher.matches(Path::new("src/lib.rs")));
+    }
+
+    #[test]
+    fn test_directories() {
+        let root = Path::new("/product");
+        let matcher = PathMatcher::new(
+            root,
+            vec![
+                "frontend/webui/dist/".to_string(),
+                "/backend/src/**/*.rs".to_string(),
+                "config/app.toml".to_string(),
+                "!frontend/webui/dist/tmp".to_string(),
+            ],
+        );
+        assert_eq!(
+            matcher.directories(),
+            vec![
+                root.join("frontend/webui/dist"),
+                root.join("backend/src"),
+                root.join("config/app.toml"),
+            ]
+        );
+
+        let matcher = PathMatcher::new(
+            root,
+            vec!["*.sql".to_string(), "**/migrations/*".to_string()],
+        );
+        assert_eq!(
+            matcher.directories(),
+            vec![root.to_path_buf(), root.to_path_buf()]
+        );
+    }
+
     #[test]
     fn test_nonexistent_paths() {
         let temp_dir = TempDir::new().unwrap();
@@ -0,0 +1,103 @@
+use crate::utils::{ansi_output, quiet};
+use colored::{ColoredString, Colorize};
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// The bar currently shown at the bottom of the terminal. Output printed while it is
+/// shown has to go through [`suspend`] so the bar is redrawn below it.
+static ACTIVE: Mutex<Option<ProgressBar>> = Mutex::new(None);
+
+/// Reports progress through a list of components, such as the images of a build:
+///
+/// ```text
+/// Building frontend  ..... [  OK  ]
+/// ⠙ [2/5] 00:00:41 Building backend
+/// ```
+///
+/// Without ANSI output the bar is left out and each step is printed on its own line.
+/// In quiet mode only failures are printed.
+pub struct Progress {
+    bar: ProgressBar,
+    verb: String,
+    total: usize,
+}
+
+impl Progress {
+    pub fn new(verb: &str, total: usize) -> Self {
+        let bar = if ansi_output() && !quiet() {
+            // Drawn to stdout, where the command windows it is interleaved with go
+            let bar =
+                ProgressBar::with_draw_target(Some(total as u64), ProgressDrawTarget::stdout());
+            bar.set_style(
+                ProgressStyle::with_template("{spinner} [{pos}/{len}] {elapsed_precise} {msg}")
+                    .expect("Progress template is valid"),
+            );
+            bar.enable_steady_tick(Duration::from_millis(100));
+            *ACTIVE.lock().unwrap() = Some(bar.clone());
+            bar
+        } else {
+            ProgressBar::hidden()
+        };
+
+        Self {
+            bar,
+            verb: verb.to_string(),
+            total,
+        }
+    }
+
+    /// Marks the start of the work on `item`.
+    pub fn start(&self, item: &str) {
+        if self.bar.is_hidden() && !quiet() {
+            println!(
+                "{} {} ({}/{})",
+                self.verb,
+                item,
+                self.bar.position() + 1,
+                self.total
+            );
+        }
+        self.bar.set_message(format!("{} {}", self.verb, item));
+    }
+
+    /// Prints the outcome of `item` above the bar and advances it.
+    pub fn finish(&self, item: &str, status: ColoredString) {
+        if !quiet() {
+            suspend(|| println!("{} {}  ..... [  {}  ]", self.verb, item, status));
+        }
+        self.bar.inc(1);
+    }
+
+    /// Prints that `item` failed, also in quiet mode.
+    pub fn fail(&self, item: &str) {
+        suspend(|| println!("{} {}  ..... [ {} ]", self.verb, item, "FAIL".red().bold()));
+        self.bar.inc(1);
+    }
+
+    /// Advances the bar past `item` with a status such as SKIPPED, without the verb.
+    pub fn skip(&self, item: &str, status: ColoredString) {
+        if !quiet() {
+            suspend(|| println!("{}  ..... [  {}  ]", item, status));
+        }
+        self.bar.inc(1);
+    }
+}
+
+impl Drop for Progress {
+    fn drop(&mut self) {
+        if !self.bar.is_hidden() {
+            ACTIVE.lock().unwrap().take();
+        }
+        self.bar.finish_and_clear();
+    }
+}
+
+/// Runs `print` with the active progress bar, if any, cleared from the terminal.
+pub fn suspend<R>(print: impl FnOnce() -> R) -> R {
+    let bar = ACTIVE.lock().unwrap().clone();
+    match bar {
+        Some(bar) => bar.suspend(print),
+        None => print(),
+    }
+}
@@ -1,25 +1,216 @@
+use crate::builder::{load_stack_spec, shared_file, Config};
 use crate::dotenv_utils::load_dotenv;
 use crate::dotenv_utils::save_dotenv;
 use chrono::Local;
 use colored::Colorize;
 use log::{error, trace, warn};
+use rand::{distributions::Alphanumeric, Rng};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
-use serde_yaml::Value;
-use std::collections::HashMap;
-use std::fs::{self, File};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
 use std::io::{Read, Write};
 use std::path::PathBuf;
+use std::process::Command;
+use tera::{Context, Tera};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PublicEnvironmentDefinitions {
     product_name: String,
     components: HashMap<String, ComponentEnvironment>,
     product_dir: PathBuf,
+    /// Config values `Static` definitions can reference, e.g. `{{ domain }}`
+    template_variables: HashMap<String, String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ComponentEnvironment {
     environment_variables: HashMap<String, GenerationMethod>,
+    #[serde(default)]
+    constraints: HashMap<String, VariableConstraint>,
+}
+
+/// File name of the modules written by `rush env codegen`.
+const RUST_MODULE_FILE: &str = "rush_env.rs";
+
+/// First line of the generated modules, which marks files rush may overwrite.
+pub const RUST_MODULE_HEADER: &str =
+    "// Generated by `rush env codegen` from stack.env.*.yaml. Do not edit.";
+
+impl ComponentEnvironment {
+    /// The Rust module of `rust_modules`. Variables that are generated or required are
+    /// `String`s, the ones that are only constrained are optional.
+    fn rust_module(&self, component_name: &str) -> String {
+        let names: BTreeMap<&String, bool> = self
+            .environment_variables
+            .keys()
+            .map(|name| (name, false))
+            .chain(self.constraints.iter().map(|(name, constraint)| {
+                let optional =
+                    !constraint.required && !self.environment_variables.contains_key(name);
+                (name, optional)
+            }))
+            .collect();
+
+        let mut fields = String::new();
+        let mut reads = String::new();
+        for (name, optional) in names {
+            let field = rust_field_name(name);
+            let allowed = match self.constraints.get(name).and_then(|c| c.one_of.as_ref()) {
+                Some(values) => format!(", one of {}", values.join(", ")),
+                None => String::new(),
+            };
+            fields.push_str(&format!("    /// `{}`{}\n", name, allowed));
+            if optional {
+                fields.push_str(&format!("    pub {}: Option<String>,\n", field));
+                reads.push_str(&format!(
+                    "            {}: std::env::var(\"{}\").ok(),\n",
+                    field, name
+                ));
+            } else {
+                fields.push_str(&format!("    pub {}: String,\n", field));
+                reads.push_str(&format!("            {}: var(\"{}\")?,\n", field, name));
+            }
+        }
+
+        format!(
+            r#"{header}
+
+/// The public environment of the {component_name} component.
+#[derive(Debug, Clone)]
+pub struct Env {{
+{fields}}}
+
+impl Env {{
+    /// Reads the variables from the environment of the process.
+    pub fn from_env() -> Result<Self, String> {{
+        Ok(Self {{
+{reads}        }})
+    }}
+}}
+
+fn var(name: &str) -> Result<String, String> {{
+    std::env::var(name).map_err(|e| format!("{{}}: {{}}", name, e))
+}}
+"#,
+            header = RUST_MODULE_HEADER,
+        )
+    }
+}
+
+/// The snake case field for a variable, e.g. `api_url` for `API_URL`.
+fn rust_field_name(variable_name: &str) -> String {
+    const KEYWORDS: &[&str] = &[
+        "as", "async", "await", "box", "break", "const", "continue", "dyn", "else", "enum",
+        "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move",
+        "mut", "pub", "ref", "return", "static", "struct", "trait", "true", "type", "unsafe",
+        "use", "where", "while", "yield",
+    ];
+    let mut field: String = variable_name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if field.starts_with(|c: char| c.is_ascii_digit()) {
+        field.insert(0, '_');
+    }
+    if KEYWORDS.contains(&field.as_str()) {
+        field.insert_str(0, "r#");
+    } else if ["crate", "self", "super"].contains(&field.as_str()) {
+        // Not allowed as raw identifiers
+        field.push('_');
+    }
+    field
+}
+
+/// Requirements on the final value of a variable, checked before build and dev so
+/// services do not crash on a missing or malformed value at runtime.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VariableConstraint {
+    #[serde(default)]
+    required: bool,
+    /// Regular expression the whole value has to match
+    pattern: Option<String>,
+    /// Values the variable may take
+    one_of: Option<Vec<String>>,
+}
+
+impl VariableConstraint {
+    /// Describes why `value` violates the constraint, if it does.
+    fn check(&self, value: Option<&String>) -> Option<String> {
+        let value = match value {
+            Some(value) if !value.is_empty() => value,
+            _ if self.required => return Some("is required but not set".to_string()),
+            _ => return None,
+        };
+        if let Some(pattern) = &self.pattern {
+            match Regex::new(&format!("^(?:{})$", pattern)) {
+                Ok(regex) if regex.is_match(value) => {}
+                Ok(_) => return Some(format!("'{}' does not match {}", value, pattern)),
+                Err(e) => return Some(format!("has an invalid pattern {}: {}", pattern, e)),
+            }
+        }
+        match &self.one_of {
+            Some(values) if !values.contains(value) => {
+                Some(format!("'{}' is not one of {}", value, values.join(", ")))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// A component the public environment is written for.
+struct ComponentDir {
+    name: String,
+    path: PathBuf,
+    build_type: Option<String>,
+}
+
+/// A variable of stack.env.*.yaml: either just its generation method, e.g.
+/// `!Static "info"`, or a mapping with `generate`, `required`, `pattern` and `one_of`.
+#[derive(Debug, Clone)]
+struct VariableDefinition {
+    generate: Option<GenerationMethod>,
+    constraint: Option<VariableConstraint>,
+}
+
+impl<'de> Deserialize<'de> for VariableDefinition {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct Detailed {
+            generate: Option<GenerationMethod>,
+            #[serde(default)]
+            required: bool,
+            pattern: Option<String>,
+            one_of: Option<Vec<String>>,
+        }
+
+        // Goes through a Value, as untagged enums lose the YAML tags of the methods
+        let value = serde_yaml::Value::deserialize(deserializer)?;
+        if value.is_mapping() {
+            let detailed: Detailed =
+                serde_yaml::from_value(value).map_err(serde::de::Error::custom)?;
+            Ok(Self {
+                generate: detailed.generate,
+                constraint: Some(VariableConstraint {
+                    required: detailed.required,
+                    pattern: detailed.pattern,
+                    one_of: detailed.one_of,
+                }),
+            })
+        } else {
+            Ok(Self {
+                generate: Some(serde_yaml::from_value(value).map_err(serde::de::Error::custom)?),
+                constraint: None,
+            })
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -28,27 +219,119 @@ pub enum GenerationMethod {
     Ask(String),
     AskWithDefault(String, String),
     Timestamp(String),
+    /// Output of a shell command run in the product directory, e.g. `git rev-parse HEAD`
+    FromCommand(String),
+    /// Contents of a file relative to the product directory
+    FromFile(String),
+    /// Random alphanumeric string of the given length, generated once
+    Random(usize),
+}
+
+/// A variable that differs between two environments' public definitions.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum EnvChange {
+    Added(String),
+    Removed(String),
+    Changed(String, String),
+}
+
+impl GenerationMethod {
+    /// Whether generating the value prompts for it.
+    fn is_interactive(&self) -> bool {
+        matches!(
+            self,
+            GenerationMethod::Ask(_) | GenerationMethod::AskWithDefault(_, _)
+        )
+    }
+
+    /// The value without prompting or running anything, so environments can be compared.
+    /// Values that are only known when generated are shown as their definition.
+    fn preview(&self) -> String {
+        match self {
+            GenerationMethod::Static(value) => value.clone(),
+            GenerationMethod::Ask(prompt) => format!("<ask: {}>", prompt),
+            GenerationMethod::AskWithDefault(_, default) => default.clone(),
+            GenerationMethod::Timestamp(format) => format!("<timestamp: {}>", format),
+            GenerationMethod::FromCommand(command) => format!("<command: {}>", command),
+            GenerationMethod::FromFile(path) => format!("<file: {}>", path),
+            GenerationMethod::Random(length) => format!("<random: {}>", length),
+        }
+    }
+
+    /// Whether the value is regenerated every time rather than kept once in the .env file.
+    fn is_derived(&self) -> bool {
+        matches!(
+            self,
+            GenerationMethod::Static(_)
+                | GenerationMethod::FromCommand(_)
+                | GenerationMethod::FromFile(_)
+        )
+    }
 }
 
 impl PublicEnvironmentDefinitions {
-    pub fn new(product_name: String, base_yaml: &str, specialisation_yaml: &str) -> Self {
+    /// Loads the product's public environment definitions for the config's environment.
+    pub fn for_config(config: &Config) -> Self {
+        Self::new(
+            config.product_name().to_string(),
+            &format!("{}/stack.env.base.yaml", config.product_path()),
+            &format!(
+                "{}/stack.env.{}.yaml",
+                config.product_path(),
+                config.environment()
+            ),
+            HashMap::from([
+                (
+                    "product_name".to_string(),
+                    config.product_name().to_string(),
+                ),
+                ("product_uri".to_string(), config.product_uri().to_string()),
+                ("environment".to_string(), config.environment().to_string()),
+                ("domain".to_string(), config.product_domain().to_string()),
+                (
+                    "docker_registry".to_string(),
+                    config.docker_registry().to_string(),
+                ),
+            ]),
+        )
+    }
+
+    pub fn new(
+        product_name: String,
+        base_yaml: &str,
+        specialisation_yaml: &str,
+        template_variables: HashMap<String, String>,
+    ) -> Self {
         let product_dir = PathBuf::from(base_yaml).parent().unwrap().to_path_buf();
 
-        let base_components = Self::load_components(base_yaml, true);
-        let specialisation_components = Self::load_components(specialisation_yaml, false);
-        let components = Self::merge_components(base_components, specialisation_components);
+        // Definitions shared between products come first, so the product's own take precedence
+        let mut layers = Vec::new();
+        for yaml_path in [base_yaml, specialisation_yaml] {
+            let file_name = PathBuf::from(yaml_path);
+            let shared_path = shared_file(
+                &product_dir,
+                &file_name.file_name().unwrap().to_string_lossy(),
+            );
+            if shared_path.exists() {
+                layers.push(Self::load_components(&shared_path.to_string_lossy(), false));
+            }
+        }
+        layers.push(Self::load_components(base_yaml, true));
+        layers.push(Self::load_components(specialisation_yaml, false));
+        let components = Self::merge_components(layers);
 
         Self {
             product_name,
             components,
             product_dir,
+            template_variables,
         }
     }
 
     fn load_components(
         yaml_path: &str,
         is_base: bool,
-    ) -> HashMap<String, HashMap<String, GenerationMethod>> {
+    ) -> HashMap<String, HashMap<String, VariableDefinition>> {
         match File::open(yaml_path) {
             Ok(mut file) => {
                 let mut contents = String::new();
@@ -94,29 +377,34 @@ impl PublicEnvironmentDefinitions {
         }
     }
 
+    /// Merges the definitions of each layer into the previous ones, variable by variable.
+    /// A layer that only constrains a variable keeps its generation method and vice versa.
     fn merge_components(
-        mut base: HashMap<String, HashMap<String, GenerationMethod>>,
-        specialisation: HashMap<String, HashMap<String, GenerationMethod>>,
+        layers: Vec<HashMap<String, HashMap<String, VariableDefinition>>>,
     ) -> HashMap<String, ComponentEnvironment> {
-        for (component_name, env_vars) in specialisation {
-            match base.get_mut(&component_name) {
-                Some(base_env_vars) => base_env_vars.extend(env_vars),
-                None => {
-                    base.insert(component_name, env_vars);
+        let mut components: HashMap<String, ComponentEnvironment> = HashMap::new();
+        for layer in layers {
+            for (component_name, env_vars) in layer {
+                let component =
+                    components
+                        .entry(component_name)
+                        .or_insert_with(|| ComponentEnvironment {
+                            environment_variables: HashMap::new(),
+                            constraints: HashMap::new(),
+                        });
+                for (name, definition) in env_vars {
+                    if let Some(generate) = definition.generate {
+                        component
+                            .environment_variables
+                            .insert(name.clone(), generate);
+                    }
+                    if let Some(constraint) = definition.constraint {
+                        component.constraints.insert(name, constraint);
+                    }
                 }
             }
         }
-
-        base.into_iter()
-            .map(|(component_name, environment_variables)| {
-                (
-                    component_name,
-                    ComponentEnvironment {
-                        environment_variables,
-                    },
-                )
-            })
-            .collect()
+        components
     }
 
     pub fn add_component(&mut self, component_name: String) {
@@ -124,6 +412,7 @@ impl PublicEnvironmentDefinitions {
             component_name,
             ComponentEnvironment {
                 environment_variables: HashMap::new(),
+                constraints: HashMap::new(),
             },
         );
     }
@@ -143,6 +432,13 @@ impl PublicEnvironmentDefinitions {
         }
     }
 
+    /// Whether the variable is generated from the public environment definitions.
+    pub fn defines(&self, component_name: &str, variable_name: &str) -> bool {
+        self.components
+            .get(component_name)
+            .is_some_and(|component| component.environment_variables.contains_key(variable_name))
+    }
+
     pub fn generate_value(&self, component_name: &str, variable_name: &str) -> Option<String> {
         if let Some(component) = self.components.get(component_name) {
             if let Some(generation_method) = component.environment_variables.get(variable_name) {
@@ -177,6 +473,46 @@ impl PublicEnvironmentDefinitions {
                     GenerationMethod::Timestamp(format) => {
                         Some(Local::now().format(format).to_string())
                     }
+                    GenerationMethod::FromCommand(command) => {
+                        match Command::new("sh")
+                            .args(["-c", command])
+                            .current_dir(&self.product_dir)
+                            .output()
+                        {
+                            Ok(output) if output.status.success() => {
+                                Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+                            }
+                            Ok(output) => {
+                                error!(
+                                    "Command '{}' for {} failed: {}",
+                                    command,
+                                    variable_name,
+                                    String::from_utf8_lossy(&output.stderr).trim()
+                                );
+                                None
+                            }
+                            Err(e) => {
+                                error!("Failed to run '{}' for {}: {}", command, variable_name, e);
+                                None
+                            }
+                        }
+                    }
+                    GenerationMethod::FromFile(path) => {
+                        match std::fs::read_to_string(self.product_dir.join(path)) {
+                            Ok(contents) => Some(contents.trim().to_string()),
+                            Err(e) => {
+                                error!("Failed to read {} for {}: {}", path, variable_name, e);
+                                None
+                            }
+                        }
+                    }
+                    GenerationMethod::Random(length) => Some(
+                        rand::thread_rng()
+                            .sample_iter(&Alphanumeric)
+                            .take(*length)
+                            .map(char::from)
+                            .collect(),
+                    ),
                 }
             } else {
                 None
@@ -186,19 +522,119 @@ impl PublicEnvironmentDefinitions {
         }
     }
 
-    pub fn generate_dotenv_files(&self) -> Result<(), std::io::Error> {
+    /// The merged variables of each component with their templates rendered, without
+    /// prompting, running commands or touching the .env files.
+    pub fn preview(&self) -> BTreeMap<String, BTreeMap<String, String>> {
+        self.components
+            .iter()
+            .map(|(component_name, component)| {
+                let mut env_map = component
+                    .environment_variables
+                    .iter()
+                    .map(|(name, method)| (name.clone(), method.preview()))
+                    .collect();
+                self.render_templates(component, &mut env_map);
+                (component_name.clone(), env_map.into_iter().collect())
+            })
+            .collect()
+    }
+
+    /// The variables added, removed or changed per component when going from `self` to
+    /// `other`. Components without changes are left out.
+    pub fn diff(&self, other: &Self) -> BTreeMap<String, BTreeMap<String, EnvChange>> {
+        let from = self.preview();
+        let to = other.preview();
+        let empty = BTreeMap::new();
+
+        let mut changes = BTreeMap::new();
+        for component_name in from.keys().chain(to.keys()) {
+            let from_vars = from.get(component_name).unwrap_or(&empty);
+            let to_vars = to.get(component_name).unwrap_or(&empty);
+            let mut component_changes = BTreeMap::new();
+            for name in from_vars.keys().chain(to_vars.keys()) {
+                let change = match (from_vars.get(name), to_vars.get(name)) {
+                    (None, Some(value)) => EnvChange::Added(value.clone()),
+                    (Some(value), None) => EnvChange::Removed(value.clone()),
+                    (Some(before), Some(after)) if before != after => {
+                        EnvChange::Changed(before.clone(), after.clone())
+                    }
+                    _ => continue,
+                };
+                component_changes.insert(name.clone(), change);
+            }
+            if !component_changes.is_empty() {
+                changes.insert(component_name.clone(), component_changes);
+            }
+        }
+        changes
+    }
+
+    /// Renders the `Static` values that are Tera templates with the config values and
+    /// the component's other variables. Templates may reference one another, so they are
+    /// rendered until the values no longer change.
+    fn render_templates(
+        &self,
+        component: &ComponentEnvironment,
+        env_map: &mut HashMap<String, String>,
+    ) {
+        let templates: Vec<(&String, &String)> = component
+            .environment_variables
+            .iter()
+            .filter_map(|(name, method)| match method {
+                GenerationMethod::Static(value) if value.contains("{{") || value.contains("{%") => {
+                    Some((name, value))
+                }
+                _ => None,
+            })
+            .collect();
+
+        let mut errors = HashMap::new();
+        for _ in 0..=templates.len() {
+            let mut context = Context::new();
+            for (name, value) in self.template_variables.iter().chain(env_map.iter()) {
+                context.insert(name, value);
+            }
+
+            errors.clear();
+            let mut changed = false;
+            for (name, template) in &templates {
+                match Tera::one_off(template, &context, false) {
+                    Ok(value) => {
+                        if env_map.get(*name) != Some(&value) {
+                            env_map.insert((*name).clone(), value);
+                            changed = true;
+                        }
+                    }
+                    Err(e) => {
+                        errors.insert(*name, e);
+                    }
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+
+        for (name, e) in errors {
+            let cause = std::error::Error::source(&e)
+                .map(|cause| cause.to_string())
+                .unwrap_or_default();
+            error!("Failed to render template of {}: {} {}", name, e, cause);
+        }
+    }
+
+    /// The directory of each component of the stack spec that has definitions and exists.
+    fn component_dirs(&self) -> Result<Vec<ComponentDir>, std::io::Error> {
         // TODO: Get from config
-        let stack_yaml_path = self.product_dir.join("stack.spec.yaml");
-        let stack_yaml_content = match fs::read_to_string(&stack_yaml_path) {
-            Ok(content) => content,
+        let stack_yaml = match load_stack_spec(&self.product_dir) {
+            Ok(stack_yaml) => stack_yaml,
             Err(e) => {
-                error!("Failed to read stack.spec.yaml: {}", e);
-                return Err(e);
+                error!("{}", e);
+                return Err(std::io::Error::other(e));
             }
         };
-        let stack_yaml: Value =
-            serde_yaml::from_str(&stack_yaml_content).expect("Unable to parse stack.spec.yaml");
 
+        let mut dirs = Vec::new();
         if let Some(components_map) = stack_yaml.as_mapping() {
             for (component_name, component_info) in components_map {
                 if let (Some(component_name), Some(location)) = (
@@ -210,32 +646,119 @@ impl PublicEnvironmentDefinitions {
                         trace!("Component {} directory not found, skipping", component_name);
                         continue;
                     }
-                    let env_path = component_dir.join(".env");
+                    if self.components.contains_key(component_name) {
+                        dirs.push(ComponentDir {
+                            name: component_name.to_string(),
+                            path: component_dir,
+                            build_type: component_info
+                                .get("build_type")
+                                .and_then(|v| v.as_str())
+                                .map(str::to_string),
+                        });
+                    }
+                }
+            }
+        }
+        Ok(dirs)
+    }
 
-                    if let Some(component) = self.components.get(component_name) {
-                        let mut env_map = if env_path.exists() {
-                            load_dotenv(&env_path)?
-                        } else {
-                            HashMap::new()
-                        };
+    /// Writes the .env file of each component, keeping the values of variables that
+    /// are only generated once.
+    pub fn generate_dotenv_files(&self) -> Result<(), std::io::Error> {
+        for (env_path, env_map) in self.render_dotenv_files(true)? {
+            save_dotenv(&env_path, env_map)?;
+        }
+        Ok(())
+    }
 
-                        for (var_name, generation_method) in &component.environment_variables {
-                            if !env_map.contains_key(var_name)
-                                || matches!(generation_method, GenerationMethod::Static(_))
-                            {
-                                if let Some(value) = self.generate_value(component_name, var_name) {
-                                    env_map.insert(var_name.clone(), value);
-                                } else {
-                                    error!("Failed to generate value for {}", var_name);
-                                }
-                            }
-                        }
+    /// The .env files `generate_dotenv_files` writes and their contents, without touching
+    /// the disk. Unless `interactive`, variables that would be asked for are shown as
+    /// their prompt or default instead.
+    pub fn render_dotenv_files(
+        &self,
+        interactive: bool,
+    ) -> Result<BTreeMap<PathBuf, HashMap<String, String>>, std::io::Error> {
+        let mut dotenv_files = BTreeMap::new();
+        for ComponentDir {
+            name: component_name,
+            path,
+            ..
+        } in self.component_dirs()?
+        {
+            let env_path = path.join(".env");
+            let component = &self.components[&component_name];
+            let mut env_map = if env_path.exists() {
+                load_dotenv(&env_path)?
+            } else {
+                HashMap::new()
+            };
 
-                        save_dotenv(&env_path, env_map)?;
-                    }
+            for (var_name, generation_method) in &component.environment_variables {
+                if env_map.contains_key(var_name) && !generation_method.is_derived() {
+                    continue;
+                }
+                if !interactive && generation_method.is_interactive() {
+                    env_map.insert(var_name.clone(), generation_method.preview());
+                } else if let Some(value) = self.generate_value(&component_name, var_name) {
+                    env_map.insert(var_name.clone(), value);
+                } else {
+                    error!("Failed to generate value for {}", var_name);
                 }
             }
+
+            self.render_templates(component, &mut env_map);
+            dotenv_files.insert(env_path, env_map);
         }
-        Ok(())
+        Ok(dotenv_files)
+    }
+
+    /// Checks the .env files, with their .env.local overrides, against the constraints of
+    /// their variables, returning a message per violation. Components without a .env file are checked as empty.
+    pub fn validate_dotenv_files(&self) -> Result<Vec<String>, std::io::Error> {
+        let mut issues = Vec::new();
+        for ComponentDir {
+            name: component_name,
+            path,
+            ..
+        } in self.component_dirs()?
+        {
+            let env_path = path.join(".env");
+            let component = &self.components[&component_name];
+            if component.constraints.is_empty() {
+                continue;
+            }
+            // The containers get .env.local on top of .env, so its values are the final ones
+            let mut env_map = HashMap::new();
+            for path in [env_path.clone(), env_path.with_extension("local")] {
+                if path.exists() {
+                    env_map.extend(load_dotenv(&path)?);
+                }
+            }
+            let constraints: BTreeMap<_, _> = component.constraints.iter().collect();
+            for (name, constraint) in constraints {
+                if let Some(issue) = constraint.check(env_map.get(name)) {
+                    issues.push(format!("{}: {} {}", component_name, name, issue));
+                }
+            }
+        }
+        Ok(issues)
+    }
+
+    /// A Rust module per RustBinary component with a struct of its public variables, so
+    /// services read their environment through fields instead of `std::env::var`.
+    /// Returns the path of each module next to the component's `src/main.rs`.
+    pub fn rust_modules(&self) -> Result<BTreeMap<PathBuf, String>, std::io::Error> {
+        let mut modules = BTreeMap::new();
+        for component_dir in self.component_dirs()? {
+            if component_dir.build_type.as_deref() != Some("RustBinary") {
+                continue;
+            }
+            let component = &self.components[&component_dir.name];
+            modules.insert(
+                component_dir.path.join("src").join(RUST_MODULE_FILE),
+                component.rust_module(&component_dir.name),
+            );
+        }
+        Ok(modules)
     }
 }
@@ -0,0 +1,97 @@
+use crate::builder::Config;
+use colored::Colorize;
+use log::trace;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use tokio::process::Command;
+
+/// A named sequence of commands from the `tasks:` section of rushd.yaml:
+///
+/// ```yaml
+/// tasks:
+///   release:
+///     description: Builds, pushes and deploys to staging
+///     steps:
+///       - rush: push
+///       - rush: --env staging deploy
+///       - run: ./scripts/notify.sh "$RUSH_PRODUCT"
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Task {
+    #[serde(default)]
+    pub description: Option<String>,
+    pub steps: Vec<TaskStep>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum TaskStep {
+    /// A shell command, run with `sh -c` from the repository root
+    Run { run: String },
+    /// Arguments of a rush command, run for the same product. Split on whitespace.
+    Rush { rush: String },
+}
+
+pub type Tasks = BTreeMap<String, Task>;
+
+/// Runs the steps of a task in order, stopping at the first failing one. The product
+/// and environment are exported as RUSH_PRODUCT, RUSH_PRODUCT_PATH, RUSH_PRODUCT_URI
+/// and RUSH_ENVIRONMENT.
+pub async fn run_task(name: &str, task: &Task, config: &Config) -> Result<(), String> {
+    let rush = std::env::current_exe()
+        .map_err(|e| format!("Could not find the rush executable: {}", e))?;
+    let context = [
+        ("RUSH_PRODUCT", config.product_name()),
+        ("RUSH_PRODUCT_PATH", config.product_path()),
+        ("RUSH_PRODUCT_URI", config.product_uri()),
+        ("RUSH_ENVIRONMENT", config.environment()),
+    ];
+
+    for (index, step) in task.steps.iter().enumerate() {
+        let mut command = match step {
+            TaskStep::Run { run: script } => {
+                println!(
+                    "{} {}",
+                    format!("[{}:{}]", name, index + 1).white().bold(),
+                    script
+                );
+                let mut command = Command::new("sh");
+                command.arg("-c").arg(script);
+                command
+            }
+            TaskStep::Rush { rush: args } => {
+                println!(
+                    "{} rush {}",
+                    format!("[{}:{}]", name, index + 1).white().bold(),
+                    args
+                );
+                let args = args.split_whitespace().collect::<Vec<_>>();
+                let mut command = Command::new(&rush);
+                // A step may pick another environment, otherwise it inherits the task's
+                if !args.contains(&"--env") {
+                    command.arg("--env").arg(config.environment());
+                }
+                // Run from the product directory, from which the product is inferred
+                command.args(args).current_dir(config.product_path());
+                command
+            }
+        };
+        command.envs(context);
+        trace!("Running task step: {:?}", command);
+
+        let status = command
+            .status()
+            .await
+            .map_err(|e| format!("Failed to run step {} of task {}: {}", index + 1, name, e))?;
+        if !status.success() {
+            return Err(format!(
+                "Step {} of task {} failed with {}",
+                index + 1,
+                name,
+                status
+            ));
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,337 @@
+use log::{debug, trace, warn};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Condvar, Mutex, OnceLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::Subscriber;
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+/// The spans recorded and not exported yet.
+static SPANS: OnceLock<Arc<Recorder>> = OnceLock::new();
+
+/// How often the closed spans are exported, unless a full batch is ready earlier.
+const EXPORT_INTERVAL: Duration = Duration::from_secs(5);
+/// Spans per export request.
+const MAX_EXPORT_BATCH_SIZE: usize = 512;
+/// Closed spans kept while the endpoint cannot keep up; further ones are dropped.
+const MAX_QUEUE_SIZE: usize = 2048;
+
+#[derive(Default)]
+struct Recorder {
+    spans: Mutex<Spans>,
+    /// Notified when a batch is ready and when an export finishes
+    changed: Condvar,
+}
+
+#[derive(Default)]
+struct Spans {
+    open: HashMap<u64, SpanData>,
+    closed: Vec<SpanData>,
+    dropped: usize,
+    /// Whether the exporter thread is sending a batch
+    exporting: bool,
+}
+
+struct SpanData {
+    trace_id: u128,
+    span_id: u64,
+    parent_span_id: Option<u64>,
+    name: &'static str,
+    start: SystemTime,
+    end: Option<SystemTime>,
+    attributes: Vec<(String, String)>,
+}
+
+/// Records the spans of the reactor, builds, vault calls and commands rush runs.
+struct RecordingLayer {
+    recorder: Arc<Recorder>,
+}
+
+struct AttributeVisitor<'a>(&'a mut Vec<(String, String)>);
+
+impl Visit for AttributeVisitor<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.push((field.name().to_string(), value.to_string()));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0
+            .push((field.name().to_string(), format!("{:?}", value)));
+    }
+}
+
+impl<S> Layer<S> for RecordingLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        // Spans of tokio, hyper and the like are left out
+        if !attrs.metadata().target().starts_with("rush") {
+            return;
+        }
+        let mut attributes = Vec::new();
+        attrs.record(&mut AttributeVisitor(&mut attributes));
+
+        let mut spans = self.recorder.spans.lock().unwrap();
+        let parent = ctx.span(id).and_then(|span| {
+            span.scope()
+                .skip(1)
+                .find_map(|ancestor| spans.open.get(&ancestor.id().into_u64()))
+        });
+        let span = SpanData {
+            trace_id: parent.map_or_else(rand::random, |parent| parent.trace_id),
+            span_id: rand::random(),
+            parent_span_id: parent.map(|parent| parent.span_id),
+            name: attrs.metadata().name(),
+            start: SystemTime::now(),
+            end: None,
+            attributes,
+        };
+        spans.open.insert(id.into_u64(), span);
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, _ctx: Context<'_, S>) {
+        if let Some(span) = self
+            .recorder
+            .spans
+            .lock()
+            .unwrap()
+            .open
+            .get_mut(&id.into_u64())
+        {
+            values.record(&mut AttributeVisitor(&mut span.attributes));
+        }
+    }
+
+    fn on_close(&self, id: Id, _ctx: Context<'_, S>) {
+        let mut spans = self.recorder.spans.lock().unwrap();
+        let Some(mut span) = spans.open.remove(&id.into_u64()) else {
+            return;
+        };
+        if spans.closed.len() >= MAX_QUEUE_SIZE {
+            spans.dropped += 1;
+            return;
+        }
+        span.end = Some(SystemTime::now());
+        spans.closed.push(span);
+        if spans.closed.len() >= MAX_EXPORT_BATCH_SIZE {
+            self.recorder.changed.notify_all();
+        }
+    }
+}
+
+/// The OTLP/HTTP endpoint for traces from the standard OpenTelemetry variables.
+fn traces_endpoint() -> Option<String> {
+    match std::env::var("OTEL_EXPORTER_OTLP_TRACES_ENDPOINT") {
+        Ok(endpoint) if !endpoint.is_empty() => Some(endpoint),
+        _ => match std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT") {
+            Ok(endpoint) if !endpoint.is_empty() => {
+                Some(format!("{}/v1/traces", endpoint.trim_end_matches('/')))
+            }
+            _ => None,
+        },
+    }
+}
+
+/// Starts recording spans when OTEL_EXPORTER_OTLP_ENDPOINT or
+/// OTEL_EXPORTER_OTLP_TRACES_ENDPOINT is set. They are exported in batches while rush
+/// runs; call `shutdown` to export the rest.
+pub fn init() {
+    let Some(endpoint) = traces_endpoint() else {
+        return;
+    };
+    let recorder = SPANS.get_or_init(Default::default).clone();
+    let subscriber = tracing_subscriber::registry().with(RecordingLayer {
+        recorder: recorder.clone(),
+    });
+    match tracing::subscriber::set_global_default(subscriber) {
+        Ok(_) => debug!("Recording traces for OTLP"),
+        Err(e) => {
+            warn!("Failed to set up tracing: {}", e);
+            return;
+        }
+    }
+    if let Err(e) = std::thread::Builder::new()
+        .name("otlp-exporter".to_string())
+        .spawn(move || export_batches(&recorder, &endpoint))
+    {
+        warn!("Failed to start exporting traces: {}", e);
+    }
+}
+
+/// Exports the closed spans every `EXPORT_INTERVAL`, or once a batch is full.
+fn export_batches(recorder: &Recorder, endpoint: &str) {
+    let runtime = match tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+    {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            warn!("Failed to start exporting traces: {}", e);
+            return;
+        }
+    };
+    loop {
+        let batch = {
+            let spans = recorder.spans.lock().unwrap();
+            let (mut spans, _) = recorder
+                .changed
+                .wait_timeout_while(spans, EXPORT_INTERVAL, |spans| {
+                    spans.closed.len() < MAX_EXPORT_BATCH_SIZE
+                })
+                .unwrap();
+            if spans.closed.is_empty() {
+                continue;
+            }
+            spans.exporting = true;
+            take_batch(&mut spans)
+        };
+        if let Err(e) = runtime.block_on(send(endpoint, &batch)) {
+            warn!("{}", e);
+        }
+        recorder.spans.lock().unwrap().exporting = false;
+        recorder.changed.notify_all();
+    }
+}
+
+fn take_batch(spans: &mut Spans) -> Vec<SpanData> {
+    if spans.dropped > 0 {
+        warn!(
+            "Dropped {} spans, as they were recorded faster than exported",
+            spans.dropped
+        );
+        spans.dropped = 0;
+    }
+    let size = spans.closed.len().min(MAX_EXPORT_BATCH_SIZE);
+    spans.closed.drain(..size).collect()
+}
+
+/// Exports the spans not exported yet over OTLP/HTTP with JSON encoding. Spans that are
+/// still open, e.g. when rush exits from within an operation, end now.
+pub fn shutdown() {
+    let (Some(recorder), Some(endpoint)) = (SPANS.get(), traces_endpoint()) else {
+        return;
+    };
+    let batches = {
+        let spans = recorder.spans.lock().unwrap();
+        // The batch being sent would be lost when rush exits right after this
+        let (mut spans, _) = recorder
+            .changed
+            .wait_timeout_while(spans, Duration::from_secs(10), |spans| spans.exporting)
+            .unwrap();
+        let open = std::mem::take(&mut spans.open);
+        spans.closed.extend(open.into_values());
+        let mut batches = Vec::new();
+        while !spans.closed.is_empty() {
+            batches.push(take_batch(&mut spans));
+        }
+        batches
+    };
+    if batches.is_empty() {
+        return;
+    }
+
+    // Runs on its own runtime, as this is called from synchronous code within the
+    // async main as well as right before exiting
+    let result = std::thread::spawn(move || {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| e.to_string())?;
+        runtime.block_on(async {
+            for batch in &batches {
+                send(&endpoint, batch).await?;
+            }
+            Ok::<_, String>(())
+        })
+    })
+    .join();
+    match result {
+        Ok(Ok(())) => debug!("Exported the remaining spans"),
+        Ok(Err(e)) => warn!("{}", e),
+        Err(_) => warn!("Failed to export traces"),
+    }
+}
+
+async fn send(endpoint: &str, spans: &[SpanData]) -> Result<(), String> {
+    trace!("Exporting {} spans to {}", spans.len(), endpoint);
+    let mut request = reqwest::Client::new()
+        .post(endpoint)
+        .timeout(Duration::from_secs(10))
+        .json(&export_request(spans));
+    for (key, value) in exporter_headers() {
+        request = request.header(key, value);
+    }
+    request
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map(|_| ())
+        .map_err(|e| format!("Failed to export traces to {}: {}", endpoint, e))
+}
+
+/// Headers from OTEL_EXPORTER_OTLP_HEADERS, e.g. `api-key=secret,team=infra`.
+fn exporter_headers() -> Vec<(String, String)> {
+    std::env::var("OTEL_EXPORTER_OTLP_HEADERS")
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(|header| header.split_once('='))
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .collect()
+}
+
+fn unix_nanos(time: SystemTime) -> String {
+    time.duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_nanos())
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// An ExportTraceServiceRequest in the JSON encoding of OTLP.
+fn export_request(spans: &[SpanData]) -> serde_json::Value {
+    let now = SystemTime::now();
+    let spans = spans
+        .iter()
+        .map(|span| {
+            let attributes = span
+                .attributes
+                .iter()
+                .map(|(key, value)| serde_json::json!({ "key": key, "value": { "stringValue": value } }))
+                .collect::<Vec<_>>();
+            let mut json = serde_json::json!({
+                "traceId": format!("{:032x}", span.trace_id),
+                "spanId": format!("{:016x}", span.span_id),
+                "name": span.name,
+                // SPAN_KIND_INTERNAL
+                "kind": 1,
+                "startTimeUnixNano": unix_nanos(span.start),
+                "endTimeUnixNano": unix_nanos(span.end.unwrap_or(now)),
+                "attributes": attributes,
+            });
+            if let Some(parent_span_id) = span.parent_span_id {
+                json["parentSpanId"] = format!("{:016x}", parent_span_id).into();
+            }
+            json
+        })
+        .collect::<Vec<_>>();
+
+    let service_name = std::env::var("OTEL_SERVICE_NAME").unwrap_or_else(|_| "rush".to_string());
+    serde_json::json!({
+        "resourceSpans": [{
+            "resource": {
+                "attributes": [
+                    { "key": "service.name", "value": { "stringValue": service_name } },
+                    { "key": "service.version", "value": { "stringValue": env!("CARGO_PKG_VERSION") } },
+                ]
+            },
+            "scopeSpans": [{
+                "scope": { "name": "rush" },
+                "spans": spans,
+            }]
+        }]
+    })
+}
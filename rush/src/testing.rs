@@ -0,0 +1,295 @@
+//! Mocks of the commands rush runs, for tests of the reactor without docker or a cluster.
+//!
+//! With the `test-util` feature, [`run_command`](crate::utils::run_command),
+//! [`run_command_in_window`](crate::utils::run_command_in_window) and the containers
+//! started by the dev loop go through the [`CommandRunner`] installed with
+//! [`set_command_runner`]. A [`MockRunner`] records the calls and answers them from
+//! rules, and a [`TestProduct`] sets up a product to drive a reactor on:
+//!
+//! ```no_run
+//! use rush_core::testing::{set_command_runner, MockRunner, Response, TestProduct};
+//!
+//! # async fn test() {
+//! let runner = MockRunner::new();
+//! runner.on("docker run", Response::Process("echo ready; sleep 1".to_string()));
+//! set_command_runner(runner.clone());
+//!
+//! let product = TestProduct::new("database:\n  build_type: PureDockerImage\n  image: postgres:16\n");
+//! let mut reactor = product.reactor();
+//! reactor.launch().await.unwrap();
+//! assert_eq!(runner.calls_matching("docker run").len(), 1);
+//! # }
+//! ```
+
+use crate::builder::Config;
+use crate::cluster::{K8Encoder, NoopEncoder};
+use crate::container::ContainerReactor;
+use crate::error::RushError;
+use crate::toolchain::{Platform, ToolchainContext};
+use crate::vault::{Base64SecretsEncoder, MemoryVault, Vault};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::{Arc, Mutex, RwLock};
+use tempfile::TempDir;
+use tokio::process::{Child, Command};
+
+/// Runs the commands of rush in place of the processes themselves.
+#[async_trait]
+pub trait CommandRunner: Send + Sync {
+    /// Runs a command to completion, returning its output, or its output and the
+    /// command when it fails.
+    async fn run(&self, command: &str, args: &[String]) -> Result<String, String>;
+
+    /// Starts a command that runs until it exits or is killed, such as `docker run`,
+    /// with its stdout and stderr piped.
+    fn spawn(&self, command: &str, args: &[String]) -> std::io::Result<Child>;
+}
+
+static COMMAND_RUNNER: RwLock<Option<Arc<dyn CommandRunner>>> = RwLock::new(None);
+
+/// Routes the commands of rush through `runner` until [`clear_command_runner`].
+pub fn set_command_runner(runner: Arc<dyn CommandRunner>) {
+    *COMMAND_RUNNER.write().unwrap() = Some(runner);
+}
+
+/// Runs the commands of rush as processes again.
+pub fn clear_command_runner() {
+    COMMAND_RUNNER.write().unwrap().take();
+}
+
+pub(crate) fn command_runner() -> Option<Arc<dyn CommandRunner>> {
+    COMMAND_RUNNER.read().unwrap().clone()
+}
+
+/// A command run through a [`MockRunner`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Call {
+    pub command: String,
+    pub args: Vec<String>,
+}
+
+impl Call {
+    /// The command line, with the command by its file name, e.g. `docker run --rm ...`.
+    pub fn line(&self) -> String {
+        let program = Path::new(&self.command)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| self.command.clone());
+        std::iter::once(program)
+            .chain(self.args.iter().cloned())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// How a [`MockRunner`] answers a call.
+#[derive(Debug, Clone)]
+pub enum Response {
+    /// The command succeeds with this output
+    Output(String),
+    /// The command fails with this output
+    Fail(String),
+    /// A started command runs this `sh` script, e.g. `echo ready; sleep 1; exit 3`
+    Process(String),
+}
+
+/// A [`CommandRunner`] recording every call. Calls succeed without output and started
+/// commands run until they are killed, unless a rule says otherwise.
+#[derive(Default)]
+pub struct MockRunner {
+    calls: Mutex<Vec<Call>>,
+    rules: Mutex<Vec<(String, Response)>>,
+}
+
+impl MockRunner {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    /// Answers the calls whose [command line](Call::line) contains `pattern`. Rules
+    /// added later take precedence.
+    pub fn on(&self, pattern: &str, response: Response) {
+        self.rules
+            .lock()
+            .unwrap()
+            .push((pattern.to_string(), response));
+    }
+
+    /// The calls so far, in the order they were made.
+    pub fn calls(&self) -> Vec<Call> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    /// The calls whose command line contains `pattern`.
+    pub fn calls_matching(&self, pattern: &str) -> Vec<Call> {
+        self.calls()
+            .into_iter()
+            .filter(|call| call.line().contains(pattern))
+            .collect()
+    }
+
+    fn record(&self, command: &str, args: &[String]) -> Option<Response> {
+        let call = Call {
+            command: command.to_string(),
+            args: args.to_vec(),
+        };
+        let line = call.line();
+        self.calls.lock().unwrap().push(call);
+        self.rules
+            .lock()
+            .unwrap()
+            .iter()
+            .rev()
+            .find(|(pattern, _)| line.contains(pattern.as_str()))
+            .map(|(_, response)| response.clone())
+    }
+}
+
+#[async_trait]
+impl CommandRunner for MockRunner {
+    async fn run(&self, command: &str, args: &[String]) -> Result<String, String> {
+        match self.record(command, args) {
+            Some(Response::Fail(output)) => Err(output),
+            Some(Response::Output(output)) => Ok(output),
+            Some(Response::Process(script)) => {
+                let output = Command::new("sh")
+                    .args(["-c", &script])
+                    .output()
+                    .await
+                    .map_err(|e| e.to_string())?;
+                let text = String::from_utf8_lossy(&output.stdout)
+                    .trim_end()
+                    .to_string();
+                if output.status.success() {
+                    Ok(text)
+                } else {
+                    Err(text)
+                }
+            }
+            None => Ok(String::new()),
+        }
+    }
+
+    fn spawn(&self, command: &str, args: &[String]) -> std::io::Result<Child> {
+        let script = match self.record(command, args) {
+            Some(Response::Process(script)) => script,
+            Some(Response::Output(output)) => format!("printf '%s\\n' '{}'", output),
+            Some(Response::Fail(output)) => format!("printf '%s\\n' '{}' >&2; exit 1", output),
+            None => "exec sleep 3600".to_string(),
+        };
+        // Killed with the task waiting for it, as `docker kill` only reaches the mock
+        Command::new("sh")
+            .args(["-c", &script])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+    }
+}
+
+/// A product with the given stack.spec.yaml in a temporary rush root, committed to a
+/// git repository of its own, in the `local` environment.
+pub struct TestProduct {
+    root: TempDir,
+    product_name: String,
+}
+
+impl TestProduct {
+    pub fn new(stack_spec: &str) -> Self {
+        // What rushd.yaml would export; the kube context is never used with a mock runner
+        for (key, value) in [
+            ("LOCAL_CTX", "test"),
+            ("LOCAL_VAULT", ".env"),
+            ("LOCAL_DOMAIN", "{{ product_uri }}.localhost"),
+            ("K8S_ENCODER_LOCAL", "noop"),
+            (
+                "INFRASTRUCTURE_REPOSITORY",
+                "git@example.com:test/infrastructure.git",
+            ),
+        ] {
+            std::env::set_var(key, value);
+        }
+
+        let root = TempDir::new().expect("Failed to create a temporary directory");
+        let product = Self {
+            root,
+            product_name: "test.example.io".to_string(),
+        };
+        product.write("stack.spec.yaml", stack_spec);
+        product.git(&["init", "-q"]);
+        product.commit();
+        product
+    }
+
+    /// The directory of the product.
+    pub fn path(&self) -> PathBuf {
+        self.root.path().join("products").join("io.example.test")
+    }
+
+    /// Writes a file relative to the product directory.
+    pub fn write(&self, path: &str, contents: &str) {
+        let path = self.path().join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).expect("Failed to create a directory");
+        std::fs::write(&path, contents).expect("Failed to write a file");
+    }
+
+    /// Commits everything in the product directory, which changes the image tags.
+    pub fn commit(&self) {
+        self.git(&["add", "-A"]);
+        self.git(&[
+            "-c",
+            "user.name=rush",
+            "-c",
+            "user.email=rush@example.com",
+            "commit",
+            "-q",
+            "--allow-empty",
+            "-m",
+            "test",
+        ]);
+    }
+
+    /// A reactor for the product with a [stub toolchain](ToolchainContext::stub), so
+    /// no tool other than git has to be installed, and an empty [`MemoryVault`].
+    pub fn reactor(&self) -> ContainerReactor {
+        self.try_reactor().expect("Failed to create the reactor")
+    }
+
+    /// Like [`reactor`](Self::reactor), returning the error of an invalid product.
+    pub fn try_reactor(&self) -> Result<ContainerReactor, RushError> {
+        let config = Config::new(
+            &self.root.path().display().to_string(),
+            &self.product_name,
+            "local",
+            "registry.example.com",
+            8129,
+            HashMap::new(),
+        )
+        .expect("Invalid test configuration");
+        let toolchain = Arc::new(ToolchainContext::stub(
+            Platform::default(),
+            Platform::default(),
+        ));
+        let vault = Arc::new(Mutex::new(MemoryVault::new())) as Arc<Mutex<dyn Vault + Send>>;
+        ContainerReactor::from_product_dir(
+            config,
+            toolchain,
+            vault,
+            Arc::new(Base64SecretsEncoder),
+            Arc::new(NoopEncoder) as Arc<dyn K8Encoder>,
+            HashMap::new(),
+            Vec::new(),
+        )
+    }
+
+    fn git(&self, args: &[&str]) {
+        let status = std::process::Command::new("git")
+            .args(args)
+            .current_dir(self.path())
+            .status()
+            .expect("Failed to run git");
+        assert!(status.success(), "git {} failed", args.join(" "));
+    }
+}
@@ -0,0 +1,287 @@
+use crate::toolchain::platform::{ArchType, OperatingSystem};
+use crate::toolchain::Platform;
+use colored::Colorize;
+use log::{debug, trace};
+use openssl::sha::Sha256;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+
+/// A tool `rush toolchain install` downloads, pinned to a version known to work with rush.
+pub struct PinnedTool {
+    pub name: &'static str,
+    pub version: &'static str,
+    /// Download URL with `{version}`, `{os}` and `{arch}` placeholders
+    url: &'static str,
+    /// Whether the download is a .tar.gz holding the binary rather than the binary itself
+    pub archive: bool,
+    /// Checksums the release publishes, with the placeholders of `url`, which downloads
+    /// are checked against
+    checksums_url: &'static str,
+}
+
+pub const PINNED_TOOLS: &[PinnedTool] = &[
+    PinnedTool {
+        name: "kubectl",
+        version: "1.30.2",
+        url: "https://dl.k8s.io/release/v{version}/bin/{os}/{arch}/kubectl",
+        archive: false,
+        checksums_url: "https://dl.k8s.io/release/v{version}/bin/{os}/{arch}/kubectl.sha256",
+    },
+    PinnedTool {
+        name: "kubeconform",
+        version: "0.6.7",
+        url: "https://github.com/yannh/kubeconform/releases/download/v{version}/kubeconform-{os}-{arch}.tar.gz",
+        archive: true,
+        checksums_url: "https://github.com/yannh/kubeconform/releases/download/v{version}/CHECKSUMS",
+    },
+    PinnedTool {
+        name: "kubeseal",
+        version: "0.27.1",
+        url: "https://github.com/bitnami-labs/sealed-secrets/releases/download/v{version}/kubeseal-{version}-{os}-{arch}.tar.gz",
+        archive: true,
+        checksums_url: "https://github.com/bitnami-labs/sealed-secrets/releases/download/v{version}/sealed-secrets_{version}_checksums.txt",
+    },
+    PinnedTool {
+        name: "minikube",
+        version: "1.33.1",
+        url: "https://github.com/kubernetes/minikube/releases/download/v{version}/minikube-{os}-{arch}",
+        archive: false,
+        checksums_url: "https://github.com/kubernetes/minikube/releases/download/v{version}/minikube-{os}-{arch}.sha256",
+    },
+];
+
+impl PinnedTool {
+    /// The `os` and `arch` of a platform as the releases name them.
+    fn release_platform(platform: &Platform) -> (&'static str, &'static str) {
+        let os = match platform.os {
+            OperatingSystem::Linux => "linux",
+            OperatingSystem::MacOS => "darwin",
+        };
+        let arch = match platform.arch {
+            ArchType::X86_64 => "amd64",
+            ArchType::AARCH64 => "arm64",
+        };
+        (os, arch)
+    }
+
+    /// The download URL for an `os` and `arch` as the releases name them, e.g. `linux`
+    /// and `arm64`. Shell variables pass through, for scripts that pick the arch.
+    pub fn url_for(&self, os: &str, arch: &str) -> String {
+        fill(self.url, self.version, os, arch)
+    }
+
+    /// The SHA-256 the download for `os` and `arch` must have, as its release publishes
+    /// it next to the download.
+    async fn expected_digest(
+        &self,
+        client: &reqwest::Client,
+        os: &str,
+        arch: &str,
+    ) -> Result<String, String> {
+        let url = fill(self.checksums_url, self.version, os, arch);
+        let checksums = download(client, &url).await?;
+        let file_name = self.url_for(os, arch);
+        let file_name = file_name.rsplit('/').next().unwrap_or_default();
+        published_digest(&String::from_utf8_lossy(&checksums), file_name)
+            .ok_or_else(|| format!("No checksum of {} in {}", file_name, url))
+    }
+}
+
+fn fill(template: &str, version: &str, os: &str, arch: &str) -> String {
+    template
+        .replace("{version}", version)
+        .replace("{os}", os)
+        .replace("{arch}", arch)
+}
+
+/// The digest of `file_name` in a checksums file, with either a line per file, as
+/// `sha256sum` writes them, or just the digest of a single file.
+fn published_digest(checksums: &str, file_name: &str) -> Option<String> {
+    checksums.lines().find_map(|line| {
+        let mut fields = line.split_whitespace();
+        let digest = fields.next()?;
+        match fields.next() {
+            Some(name) if name.trim_start_matches('*') != file_name => None,
+            _ => Some(digest.to_lowercase()),
+        }
+    })
+}
+
+async fn download(client: &reqwest::Client, url: &str) -> Result<bytes::Bytes, String> {
+    client
+        .get(url)
+        .header("User-Agent", "rush")
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| format!("Failed to download {}: {}", url, e))?
+        .bytes()
+        .await
+        .map_err(|e| format!("Failed to download {}: {}", url, e))
+}
+
+/// Where the version of an installed tool is recorded, so a changed pin installs it again.
+fn version_file(bin_dir: &Path, name: &str) -> PathBuf {
+    bin_dir.join(".versions").join(name)
+}
+
+/// Directory rush installs tools into, `$RUSH_TOOLCHAIN_DIR` or `~/.rush/bin`.
+pub fn managed_bin_dir() -> Option<PathBuf> {
+    match std::env::var_os("RUSH_TOOLCHAIN_DIR") {
+        Some(dir) if !dir.is_empty() => Some(PathBuf::from(dir)),
+        _ => Some(
+            PathBuf::from(std::env::var_os("HOME")?)
+                .join(".rush")
+                .join("bin"),
+        ),
+    }
+}
+
+/// Puts the managed tools ahead of the ones on PATH, so both the lookups of the
+/// toolchain and commands run by name pick them up.
+pub fn prefer_managed_tools() {
+    let bin_dir = match managed_bin_dir() {
+        Some(bin_dir) if bin_dir.is_dir() => bin_dir,
+        _ => return,
+    };
+    let path = std::env::var_os("PATH").unwrap_or_default();
+    let paths = std::iter::once(bin_dir.clone()).chain(std::env::split_paths(&path));
+    match std::env::join_paths(paths) {
+        Ok(path) => {
+            std::env::set_var("PATH", path);
+            debug!("Preferring tools in {}", bin_dir.display());
+        }
+        Err(e) => debug!("Could not add {} to PATH: {}", bin_dir.display(), e),
+    }
+}
+
+/// Downloads the pinned tools for the host into the managed directory, failing on
+/// downloads whose SHA-256 differs from the one their release publishes. Tools already
+/// installed in their pinned version are kept unless `force` is set.
+pub async fn install_tools(force: bool) -> Result<(), String> {
+    let bin_dir = managed_bin_dir().ok_or_else(|| "HOME is not set".to_string())?;
+    std::fs::create_dir_all(&bin_dir)
+        .map_err(|e| format!("Failed to create {}: {}", bin_dir.display(), e))?;
+
+    let (os, arch) = PinnedTool::release_platform(&Platform::default());
+    let client = reqwest::Client::new();
+    for tool in PINNED_TOOLS {
+        let destination = bin_dir.join(tool.name);
+        let version_file = version_file(&bin_dir, tool.name);
+        let installed = std::fs::read_to_string(&version_file).unwrap_or_default();
+        if destination.exists() && installed.trim() == tool.version && !force {
+            println!(
+                "{} {}  ..... [  {}  ]",
+                tool.name,
+                tool.version,
+                "INSTALLED".yellow().bold()
+            );
+            continue;
+        }
+
+        let url = tool.url_for(os, arch);
+        trace!("Downloading {} from {}", tool.name, url);
+        let bytes = download(&client, &url).await?;
+        let expected = tool.expected_digest(&client, os, arch).await?;
+        let mut hasher = Sha256::new();
+        hasher.update(&bytes);
+        let digest = hex::encode(hasher.finish());
+        if digest != expected {
+            return Err(format!(
+                "The SHA-256 of {} is {}, expected {}",
+                url, digest, expected
+            ));
+        }
+
+        if tool.archive {
+            extract_binary(&bytes, tool.name, &destination)?;
+        } else {
+            std::fs::write(&destination, &bytes)
+                .map_err(|e| format!("Failed to write {}: {}", destination.display(), e))?;
+        }
+        make_executable(&destination)?;
+        std::fs::create_dir_all(version_file.parent().unwrap_or(&bin_dir))
+            .and_then(|_| std::fs::write(&version_file, tool.version))
+            .map_err(|e| format!("Failed to write {}: {}", version_file.display(), e))?;
+        println!(
+            "{} {}  ..... [  {}  ]",
+            tool.name,
+            tool.version,
+            "OK".white().bold()
+        );
+    }
+
+    println!(
+        "Installed into {}, which rush prefers over PATH",
+        bin_dir.display()
+    );
+    Ok(())
+}
+
+/// Extracts the binary `name` from a .tar.gz archive using the system's tar.
+fn extract_binary(archive: &[u8], name: &str, destination: &Path) -> Result<(), String> {
+    let mut child = std::process::Command::new("tar")
+        .args(["-xzOf", "-", name])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run tar: {}", e))?;
+    // Written from a thread, as tar only drains stdin while its stdout is read
+    let mut stdin = child.stdin.take().expect("tar stdin is piped");
+    let archive = archive.to_vec();
+    let writer = std::thread::spawn(move || stdin.write_all(&archive));
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("Failed to run tar: {}", e))?;
+    let _ = writer.join();
+    if !output.status.success() || output.stdout.is_empty() {
+        return Err(format!(
+            "Failed to extract {} from its archive: {}",
+            name,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    std::fs::write(destination, output.stdout)
+        .map_err(|e| format!("Failed to write {}: {}", destination.display(), e))
+}
+
+#[cfg(unix)]
+fn make_executable(path: &Path) -> Result<(), String> {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755))
+        .map_err(|e| format!("Failed to make {} executable: {}", path.display(), e))
+}
+
+#[cfg(not(unix))]
+fn make_executable(_path: &Path) -> Result<(), String> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_published_digest() {
+        let checksums = "0a1b  kubeseal-0.27.1-linux-amd64.tar.gz\n\
+                         2C3D *kubeseal-0.27.1-linux-arm64.tar.gz\n";
+        assert_eq!(
+            published_digest(checksums, "kubeseal-0.27.1-linux-amd64.tar.gz").as_deref(),
+            Some("0a1b")
+        );
+        assert_eq!(
+            published_digest(checksums, "kubeseal-0.27.1-linux-arm64.tar.gz").as_deref(),
+            Some("2c3d")
+        );
+        assert_eq!(
+            published_digest(checksums, "kubeseal-0.27.1-darwin-arm64.tar.gz"),
+            None
+        );
+        assert_eq!(
+            published_digest("4e5f\n", "kubectl").as_deref(),
+            Some("4e5f")
+        );
+    }
+}
@@ -1,7 +1,10 @@
+mod install;
 mod platform;
-use crate::toolchain::platform::{ArchType, OperatingSystem};
+use crate::toolchain::platform::OperatingSystem;
 use crate::utils::{first_which, resolve_toolchain_path};
-pub use platform::Platform;
+pub use install::{install_tools, managed_bin_dir, prefer_managed_tools, PinnedTool, PINNED_TOOLS};
+use log::{debug, warn};
+pub use platform::{Libc, Platform};
 use serde::{Deserialize, Serialize};
 use std::process::Command;
 use std::str;
@@ -18,6 +21,10 @@ pub struct ToolchainContext {
     kubectl: Option<String>,
     kubectx: Option<String>,
     minikube: Option<String>,
+    kind: Option<String>,
+    k3d: Option<String>,
+    #[serde(default)]
+    sccache: Option<String>,
 
     // Secondary
     cc: String,
@@ -49,6 +56,9 @@ impl ToolchainContext {
             kubectl: first_which(vec!["kubectl"]),
             kubectx: first_which(vec!["kubectx"]),
             minikube: first_which(vec!["minikube"]),
+            kind: first_which(vec!["kind"]),
+            k3d: first_which(vec!["k3d"]),
+            sccache: first_which(vec!["sccache"]),
 
             cc: first_which(vec!["clang", "gcc"])
                 .expect("None of the default toolchains are availablefor this architecture"),
@@ -120,6 +130,9 @@ impl ToolchainContext {
                 kubectl: first_which(vec!["kubectl"]),
                 kubectx: first_which(vec!["kubectx"]),
                 minikube: first_which(vec!["minikube"]),
+                kind: first_which(vec!["kind"]),
+                k3d: first_which(vec!["k3d"]),
+                sccache: first_which(vec!["sccache"]),
 
                 cc,
                 cxx,
@@ -136,6 +149,191 @@ impl ToolchainContext {
         }
     }
 
+    /// Uses `zig cc` as the C compiler and linker, which cross-compiles to Linux from any
+    /// host without a target specific gcc. Cargo and the cc crate take a single program, so
+    /// the zig subcommands are wrapped in scripts passing the zig target.
+    pub fn from_zig(target: &Platform) -> Option<Self> {
+        let zig = first_which(vec!["zig"])?;
+        let zig_target = format!("{}-linux-{}", target.arch.to_string(), target.libc);
+        let directory = std::path::PathBuf::from(std::env::var_os("HOME")?)
+            .join(".rush")
+            .join("zig")
+            .join(&zig_target);
+        let wrapper = |name: &str, args: &str| match write_wrapper(&directory, name, &zig, args) {
+            Ok(path) => Some(path),
+            Err(e) => {
+                warn!("Failed to write zig wrapper '{}': {}", name, e);
+                None
+            }
+        };
+        let cc = wrapper("cc", &format!("cc -target {}", zig_target))?;
+        let cxx = wrapper("c++", &format!("c++ -target {}", zig_target))?;
+        let ar = wrapper("ar", "ar")?;
+        let ranlib = wrapper("ranlib", "ranlib")?;
+        let objcopy = wrapper("objcopy", "objcopy")?;
+        let ld = wrapper("ld", "ld.lld")?;
+
+        Some(ToolchainContext {
+            host: Platform::default(),
+            target: target.clone(),
+
+            git: first_which(vec!["git"]).expect("git not found."),
+            docker: first_which(vec!["docker"]).expect("docker not found."),
+            trunk: first_which(vec![
+                "$HOME/.cargo/bin/wasm-trunk",
+                "$HOME/.cargo/bin/trunk",
+                "wasm-trunk",
+                "trunk",
+            ])
+            .expect("trunk not found."),
+            kubectl: first_which(vec!["kubectl"]),
+            kubectx: first_which(vec!["kubectx"]),
+            minikube: first_which(vec!["minikube"]),
+            kind: first_which(vec!["kind"]),
+            k3d: first_which(vec!["k3d"]),
+            sccache: first_which(vec!["sccache"]),
+
+            cc,
+            cxx,
+            ar,
+            ranlib,
+            // zig has no nm, strip or objdump, but the host's llvm tools read any target
+            nm: first_which(vec!["llvm-nm", "nm"]).expect("nm not found."),
+            strip: first_which(vec!["llvm-strip", "strip"]).expect("strip not found."),
+            objdump: first_which(vec!["llvm-objdump", "objdump"]).expect("objdump not found."),
+            objcopy,
+            ld,
+        })
+    }
+
+    /// Names every tool without looking it up, for tests routing the commands through a
+    /// [mock runner](crate::testing::MockRunner). Only git is run for real.
+    #[cfg(feature = "test-util")]
+    pub fn stub(host: Platform, target: Platform) -> Self {
+        ToolchainContext {
+            host,
+            target,
+
+            git: "git".to_string(),
+            docker: "docker".to_string(),
+            trunk: "trunk".to_string(),
+            kubectl: Some("kubectl".to_string()),
+            kubectx: Some("kubectx".to_string()),
+            minikube: Some("minikube".to_string()),
+            kind: Some("kind".to_string()),
+            k3d: Some("k3d".to_string()),
+            sccache: None,
+
+            cc: "cc".to_string(),
+            cxx: "c++".to_string(),
+            ar: "ar".to_string(),
+            ranlib: "ranlib".to_string(),
+            nm: "nm".to_string(),
+            strip: "strip".to_string(),
+            objdump: "objdump".to_string(),
+            objcopy: "objcopy".to_string(),
+            ld: "ld".to_string(),
+        }
+    }
+
+    /// The musl cross toolchain, e.g. x86_64-linux-musl-gcc from musl-cross, or musl-gcc
+    /// when building for the host's architecture on Linux.
+    pub fn from_musl(host: &Platform, target: &Platform) -> Option<Self> {
+        let prefix = format!("{}-linux-musl-", target.arch.to_string());
+        let native = host.os == OperatingSystem::Linux && host.arch == target.arch;
+        Self::from_cross_prefix(host, target, &prefix, native.then_some("musl-gcc"))
+    }
+
+    /// A cross toolchain whose tools are on PATH with a target prefix, e.g.
+    /// aarch64-linux-gnu-gcc from Debian's gcc-aarch64-linux-gnu. Tools the toolchain
+    /// lacks, apart from the compiler, fall back to the host's.
+    pub fn from_cross_prefix(
+        host: &Platform,
+        target: &Platform,
+        prefix: &str,
+        fallback_cc: Option<&str>,
+    ) -> Option<Self> {
+        let tool = |name: &str, fallback: &str| {
+            first_which(vec![&format!("{}{}", prefix, name), fallback])
+        };
+        let cc = match (first_which(vec![&format!("{}gcc", prefix)]), fallback_cc) {
+            (Some(cc), _) => cc,
+            (None, Some(fallback_cc)) => first_which(vec![fallback_cc])?,
+            (None, None) => return None,
+        };
+
+        Some(ToolchainContext {
+            host: host.clone(),
+            target: target.clone(),
+
+            git: first_which(vec!["git"]).expect("git not found."),
+            docker: first_which(vec!["docker"]).expect("docker not found."),
+            trunk: first_which(vec![
+                "$HOME/.cargo/bin/wasm-trunk",
+                "$HOME/.cargo/bin/trunk",
+                "wasm-trunk",
+                "trunk",
+            ])
+            .expect("trunk not found."),
+            kubectl: first_which(vec!["kubectl"]),
+            kubectx: first_which(vec!["kubectx"]),
+            minikube: first_which(vec!["minikube"]),
+            kind: first_which(vec!["kind"]),
+            k3d: first_which(vec!["k3d"]),
+            sccache: first_which(vec!["sccache"]),
+
+            cc,
+            cxx: tool("g++", "g++")?,
+            ar: tool("ar", "ar")?,
+            ranlib: tool("ranlib", "ranlib")?,
+            nm: tool("nm", "nm")?,
+            strip: tool("strip", "strip")?,
+            objdump: tool("objdump", "objdump")?,
+            objcopy: tool("objcopy", "objcopy")?,
+            ld: tool("ld", "ld")?,
+        })
+    }
+
+    /// Finds the gcc cross toolchain for `target`. A bin directory configured with
+    /// <ARCH>_CROSS_TOOLCHAIN_PATH or CROSS_TOOLCHAIN_PATH is used as is. Otherwise the
+    /// newest Homebrew install is used, then tools with the target prefix on PATH or in
+    /// MacPorts, e.g. aarch64-linux-gnu-gcc.
+    pub fn find_cross_toolchain(host: &Platform, target: &Platform) -> Option<Self> {
+        let arch = target.arch.to_string();
+        let configured = std::env::var(format!("{}_CROSS_TOOLCHAIN_PATH", arch.to_uppercase()))
+            .or_else(|_| std::env::var("CROSS_TOOLCHAIN_PATH"))
+            .ok()
+            .filter(|path| !path.is_empty());
+        if let Some(path) = configured {
+            debug!("Using the cross toolchain in {}", path);
+            return Self::from_path(&path);
+        }
+
+        let homebrew = ["/opt/homebrew/Cellar", "/usr/local/Cellar"]
+            .iter()
+            .filter_map(|cellar| {
+                std::fs::read_dir(format!("{}/{}-unknown-linux-gnu", cellar, arch)).ok()
+            })
+            .filter_map(|versions| {
+                versions
+                    .filter_map(|entry| entry.ok())
+                    .filter(|entry| entry.file_type().map(|ft| ft.is_dir()).unwrap_or(false))
+                    .max_by_key(|entry| entry.file_name())
+            })
+            .find_map(|version| Self::from_path(&version.path().join("bin").to_string_lossy()));
+        if homebrew.is_some() {
+            return homebrew;
+        }
+
+        [
+            format!("{}-linux-gnu-", arch),
+            format!("{}-unknown-linux-gnu-", arch),
+            format!("/opt/local/bin/{}-linux-gnu-", arch),
+        ]
+        .iter()
+        .find_map(|prefix| Self::from_cross_prefix(host, target, prefix, None))
+    }
+
     pub fn setup_env(&self) {
         std::env::set_var("CC", self.cc.clone());
         std::env::set_var("CXX", self.cxx.clone());
@@ -146,6 +344,23 @@ impl ToolchainContext {
         std::env::set_var("OBJDUMP", self.objdump.clone());
         std::env::set_var("OBJCOPY", self.objcopy.clone());
         std::env::set_var("LD", self.ld.clone());
+
+        if self.target.is_musl() {
+            // The cc crate, which ring and vendored openssl build with, reads the compiler
+            // for the target from CC_<target>, and cargo the linker from its own variable
+            let rust_target = self.target.to_rust_target();
+            let target_var = rust_target.replace('-', "_");
+            std::env::set_var(format!("CC_{}", target_var), self.cc.clone());
+            std::env::set_var(format!("CXX_{}", target_var), self.cxx.clone());
+            std::env::set_var(format!("AR_{}", target_var), self.ar.clone());
+            std::env::set_var(
+                format!("CARGO_TARGET_{}_LINKER", target_var.to_uppercase()),
+                self.cc.clone(),
+            );
+            // Statically link openssl and anything else found through pkg-config
+            std::env::set_var("OPENSSL_STATIC", "1");
+            std::env::set_var("PKG_CONFIG_ALL_STATIC", "1");
+        }
     }
 
     pub fn host(&self) -> &Platform {
@@ -165,31 +380,72 @@ impl ToolchainContext {
         None
     }
 
+    /// The toolchain building for `target` on `host`. Cross and musl builds use the gcc
+    /// cross toolchain, or zig when CROSS_COMPILER is set to zig.
     pub fn new(host: Platform, target: Platform) -> Self {
-        let mut ret = if host.arch == target.arch && host.os == target.os {
+        let cross_compiler = std::env::var("CROSS_COMPILER").unwrap_or_else(|_| "gnu".to_string());
+        let mut ret = if target.is_musl() && cross_compiler == "zig" {
+            Self::from_zig(&target).expect("zig not found. Install it or set CROSS_COMPILER to gnu")
+        } else if target.is_musl() {
+            Self::from_musl(&host, &target).expect(
+                "No musl toolchain found. Install musl-cross (or musl-tools on Linux) or set CROSS_COMPILER to zig",
+            )
+        } else if host.arch == target.arch && host.os == target.os {
             Self::default()
-        } else if host.os == OperatingSystem::MacOS {
-            if target.arch == ArchType::X86_64 {
-                Self::from_first_path(vec![
-                    "/opt/homebrew/Cellar/x86_64-unknown-linux-gnu/7.2.0/bin/",
-                ])
-                .expect("No suitable toolchain found")
-            } else if target.arch == ArchType::AARCH64 {
-                Self::from_first_path(vec![
-                    "/opt/homebrew/Cellar/aarch64-unknown-linux-gnu/7.2.0/bin/",
-                ])
-                .expect("No suitable toolchain found")
-            } else {
-                panic!("Unsupported target architecture: {}", target.to_string());
-            }
+        } else if cross_compiler == "zig" {
+            Self::from_zig(&target).expect("zig not found. Install it or set CROSS_COMPILER to gnu")
         } else {
-            panic!("Unsupported host OS: {}", host.to_string());
+            // Components built with cross or from Dockerfiles alone need no cross toolchain,
+            // so only cargo builds fail without one
+            Self::find_cross_toolchain(&host, &target).unwrap_or_else(|| {
+                warn!(
+                    "No {} cross toolchain found. Set CROSS_TOOLCHAIN_PATH to its bin directory, CROSS_COMPILER to zig or build with cross",
+                    target.to_rust_target()
+                );
+                Self::default()
+            })
         };
         ret.host = host;
         ret.target = target;
         ret
     }
 
+    /// The resolved tools by name. Optional tools that were not found have no path.
+    pub fn tools(&self) -> Vec<(&'static str, Option<&str>)> {
+        vec![
+            ("git", Some(self.git.as_str())),
+            ("docker", Some(self.docker.as_str())),
+            ("trunk", Some(self.trunk.as_str())),
+            ("kubectl", self.kubectl.as_deref()),
+            ("kubectx", self.kubectx.as_deref()),
+            ("minikube", self.minikube.as_deref()),
+            ("kind", self.kind.as_deref()),
+            ("k3d", self.k3d.as_deref()),
+            ("sccache", self.sccache.as_deref()),
+            ("cc", Some(self.cc.as_str())),
+            ("cxx", Some(self.cxx.as_str())),
+            ("ar", Some(self.ar.as_str())),
+            ("ranlib", Some(self.ranlib.as_str())),
+            ("nm", Some(self.nm.as_str())),
+            ("strip", Some(self.strip.as_str())),
+            ("objdump", Some(self.objdump.as_str())),
+            ("objcopy", Some(self.objcopy.as_str())),
+            ("ld", Some(self.ld.as_str())),
+        ]
+    }
+
+    /// Runs each resolved tool to ask for its version.
+    pub fn versions(&self) -> Vec<ToolVersion> {
+        self.tools()
+            .into_iter()
+            .map(|(tool, path)| ToolVersion {
+                tool,
+                path: path.map(str::to_string),
+                version: path.and_then(|path| tool_version(tool, path)),
+            })
+            .collect()
+    }
+
     pub fn has_minikube(&self) -> bool {
         self.minikube.is_some()
     }
@@ -198,6 +454,18 @@ impl ToolchainContext {
         self.minikube.clone()
     }
 
+    pub fn kind(&self) -> Option<String> {
+        self.kind.clone()
+    }
+
+    pub fn k3d(&self) -> Option<String> {
+        self.k3d.clone()
+    }
+
+    pub fn sccache(&self) -> Option<&str> {
+        self.sccache.as_deref()
+    }
+
     pub fn docker(&self) -> &str {
         &self.docker
     }
@@ -251,6 +519,27 @@ impl ToolchainContext {
         Ok(hash)
     }
 
+    /// Files under the subdirectory that are modified, staged or untracked.
+    pub fn get_git_changes(&self, subdirectory_path: &str) -> Result<Vec<String>, String> {
+        let status_output = Command::new(&self.git)
+            .args(["status", "--porcelain", "--", subdirectory_path])
+            .output()
+            .map_err(|e| e.to_string())?;
+        if !status_output.status.success() {
+            return Err(format!(
+                "Failed to get the git status of {}: {}",
+                subdirectory_path,
+                String::from_utf8_lossy(&status_output.stderr).trim()
+            ));
+        }
+
+        Ok(String::from_utf8_lossy(&status_output.stdout)
+            .lines()
+            .map(|line| line.trim().to_string())
+            .filter(|line| !line.is_empty())
+            .collect())
+    }
+
     pub fn get_git_wip(&self, subdirectory_path: &str) -> Result<String, String> {
         let dirty_output = Command::new(&self.git)
             .args(["diff", subdirectory_path])
@@ -269,3 +558,54 @@ impl ToolchainContext {
         Ok("".to_string())
     }
 }
+
+#[derive(Serialize, Debug)]
+pub struct ToolVersion {
+    pub tool: &'static str,
+    pub path: Option<String>,
+    pub version: Option<String>,
+}
+
+/// The first line a tool prints about its version, if it has a way to print one.
+fn tool_version(tool: &str, path: &str) -> Option<String> {
+    let args: &[&str] = match tool {
+        "kubectl" => &["version", "--client"],
+        "minikube" => &["version", "--short"],
+        "kind" | "k3d" => &["version"],
+        _ => &["--version"],
+    };
+    let output = Command::new(path).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let first_line = |stream: &[u8]| {
+        String::from_utf8_lossy(stream)
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty())
+            .map(str::to_string)
+    };
+    // Some tools, e.g. ld on macOS, print their version to stderr
+    first_line(&output.stdout).or_else(|| first_line(&output.stderr))
+}
+
+/// Writes an executable script running `zig <args>` with the arguments it is given.
+fn write_wrapper(
+    directory: &std::path::Path,
+    name: &str,
+    zig: &str,
+    args: &str,
+) -> std::io::Result<String> {
+    std::fs::create_dir_all(directory)?;
+    let path = directory.join(name);
+    std::fs::write(
+        &path,
+        format!("#!/bin/sh\nexec \"{}\" {} \"$@\"\n", zig, args),
+    )?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))?;
+    }
+    Ok(path.to_string_lossy().into_owned())
+}
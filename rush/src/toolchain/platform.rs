@@ -67,17 +67,49 @@ impl ArchType {
 
     pub fn from_str(s: &str) -> Self {
         match s {
-            "x86_64" => Self::X86_64,
-            "aarch64" => Self::AARCH64,
+            "x86_64" | "amd64" => Self::X86_64,
+            "aarch64" | "arm64" => Self::AARCH64,
             _ => panic!("Invalid architecture type: {}", s),
         }
     }
 }
 
+/// The C library Linux binaries link against. musl binaries are linked statically, so
+/// they run in scratch based images.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub enum Libc {
+    #[default]
+    Gnu,
+    Musl,
+}
+
+impl std::fmt::Display for Libc {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Libc::Gnu => write!(f, "gnu"),
+            Libc::Musl => write!(f, "musl"),
+        }
+    }
+}
+
+impl std::str::FromStr for Libc {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "gnu" => Ok(Self::Gnu),
+            "musl" => Ok(Self::Musl),
+            _ => Err(format!("Invalid libc: {}", s)),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct Platform {
     pub os: OperatingSystem,
     pub arch: ArchType,
+    #[serde(default)]
+    pub libc: Libc,
 }
 
 impl Platform {
@@ -85,21 +117,36 @@ impl Platform {
         let os = OperatingSystem::default();
         let arch = ArchType::default();
 
-        Self { os, arch }
+        Self {
+            os,
+            arch,
+            libc: Libc::default(),
+        }
     }
 
     pub fn new(os: &str, arch: &str) -> Self {
         Self {
             os: OperatingSystem::from_str(os),
             arch: ArchType::from_str(arch),
+            libc: Libc::default(),
         }
     }
 
+    pub fn with_libc(mut self, libc: Libc) -> Self {
+        self.libc = libc;
+        self
+    }
+
+    pub fn is_musl(&self) -> bool {
+        self.libc == Libc::Musl
+    }
+
     pub fn to_rust_target(&self) -> String {
         format!(
-            "{}-unknown-{}-gnu",
+            "{}-unknown-{}-{}",
             self.arch.to_string(),
-            self.os.to_string()
+            self.os.to_string(),
+            self.libc
         )
     }
 
@@ -0,0 +1,75 @@
+use crate::error::RushError;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// Personal defaults read from `~/.config/rush/config.yaml`, or from
+/// `$XDG_CONFIG_HOME/rush/config.yaml` when that is set:
+///
+/// ```yaml
+/// docker_registry: registry.example.com/me
+/// log_level: debug
+/// update_check: never
+/// one_password_account: me.1password.com
+/// # Any other rushd.yaml env default
+/// env:
+///   LOCAL_CLUSTER: kind
+/// ```
+///
+/// The values are merged under the repository's rushd.yaml and variables exported in
+/// the shell, so those always win, as do command line arguments.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct UserConfig {
+    pub docker_registry: Option<String>,
+    pub log_level: Option<String>,
+    #[serde(default)]
+    pub update_check: UpdateCheck,
+    pub one_password_account: Option<String>,
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+}
+
+/// Whether rush looks for a newer release on start.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UpdateCheck {
+    #[default]
+    Always,
+    Never,
+}
+
+impl UserConfig {
+    pub fn path() -> Option<PathBuf> {
+        let config_home = match std::env::var_os("XDG_CONFIG_HOME") {
+            Some(config_home) if !config_home.is_empty() => PathBuf::from(config_home),
+            _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+        };
+        Some(config_home.join("rush").join("config.yaml"))
+    }
+
+    /// Loads the user config, which is empty when the file does not exist.
+    pub fn load() -> Result<Self, RushError> {
+        let path = match Self::path() {
+            Some(path) if path.exists() => path,
+            _ => return Ok(Self::default()),
+        };
+        let contents = std::fs::read_to_string(&path)
+            .map_err(|e| RushError::Config(format!("Failed to read {}: {}", path.display(), e)))?;
+        serde_yaml::from_str::<Option<Self>>(&contents)
+            .map(Option::unwrap_or_default)
+            .map_err(|e| RushError::Config(format!("Failed to parse {}: {}", path.display(), e)))
+    }
+
+    /// The environment variables the user config provides defaults for.
+    pub fn env(&self) -> HashMap<String, String> {
+        let mut env = self.env.clone();
+        if let Some(docker_registry) = &self.docker_registry {
+            env.insert("DOCKER_REGISTRY".to_string(), docker_registry.clone());
+        }
+        if let Some(account) = &self.one_password_account {
+            env.insert("ONE_PASSWORD_ACCOUNT".to_string(), account.clone());
+        }
+        env
+    }
+}
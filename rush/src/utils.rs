@@ -1,5 +1,6 @@
 use std::sync::mpsc::{self, Receiver, Sender};
 
+use crate::progress::suspend;
 use colored::ColoredString;
 use colored::Colorize;
 use log::{debug, error, info, trace, warn};
@@ -8,12 +9,41 @@ use std::path::Path;
 use std::path::PathBuf;
 use std::process::Command;
 use std::str;
+use std::sync::atomic::{AtomicBool, Ordering};
 use tokio::io::AsyncRead;
 use tokio::{
     io::{self, AsyncBufReadExt},
     process::Command as TokioCommand,
 };
 
+static ANSI_OUTPUT: AtomicBool = AtomicBool::new(true);
+
+/// Turns colors and the rolling command window on or off. Turned off when stdout is
+/// not a terminal or with `--no-ansi`, so CI logs get plain lines without escape codes.
+pub fn set_ansi_output(enabled: bool) {
+    ANSI_OUTPUT.store(enabled, Ordering::Relaxed);
+    if enabled {
+        colored::control::unset_override();
+    } else {
+        colored::control::set_override(false);
+    }
+}
+
+pub fn ansi_output() -> bool {
+    ANSI_OUTPUT.load(Ordering::Relaxed)
+}
+
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// With `-q`, the output of commands and steps is only printed when they fail.
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+pub fn quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
 pub struct DockerCrossCompileGuard {
     cross_container_opts: Option<String>,
     docker_default_platform: Option<String>,
@@ -172,6 +202,31 @@ pub fn first_which(candidates: Vec<&str>) -> Option<String> {
     None
 }
 
+/// The candidate `name` is most likely a typo of, if any is close enough.
+pub fn closest_match<'a>(name: &str, candidates: &[&'a str]) -> Option<&'a str> {
+    candidates
+        .iter()
+        .map(|candidate| (edit_distance(name, candidate), *candidate))
+        .filter(|(distance, candidate)| *distance <= 2.max(candidate.len() / 3))
+        .min()
+        .map(|(_, candidate)| candidate)
+}
+
+/// Levenshtein distance, counting in characters.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+    for (i, a) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a != *b);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
 pub fn resolve_toolchain_path(path: &str, tool: &str) -> Option<String> {
     debug!(
         "Resolving toolchain path for '{}' in directory: {}",
@@ -195,6 +250,59 @@ pub fn resolve_toolchain_path(path: &str, tool: &str) -> Option<String> {
     result
 }
 
+/// Asks a yes/no question on the terminal, defaulting to no.
+pub fn confirm(question: &str) -> bool {
+    print!("{} (y/N) ", question);
+    if std::io::Write::flush(&mut std::io::stdout()).is_err() {
+        return false;
+    }
+    let mut input = String::new();
+    match std::io::stdin().read_line(&mut input) {
+        Ok(_) => matches!(input.trim().to_lowercase().as_str(), "y" | "yes"),
+        Err(e) => {
+            warn!("Failed to read confirmation: {}", e);
+            false
+        }
+    }
+}
+
+/// Asks the user to type `expected` to go ahead, for changes that are hard to undo.
+pub fn confirm_typed(question: &str, expected: &str) -> bool {
+    print!("{} Type {} to continue: ", question, expected);
+    if std::io::Write::flush(&mut std::io::stdout()).is_err() {
+        return false;
+    }
+    let mut input = String::new();
+    match std::io::stdin().read_line(&mut input) {
+        Ok(_) => input.trim() == expected,
+        Err(e) => {
+            warn!("Failed to read confirmation: {}", e);
+            false
+        }
+    }
+}
+
+/// Lets the user pick one of `items` with the arrow keys. Returns `None` without a
+/// terminal to ask on, such as in CI, or when the user cancels with Esc.
+pub fn select(prompt: &str, items: &[String]) -> Option<String> {
+    use std::io::IsTerminal;
+    if items.is_empty() || !std::io::stdin().is_terminal() || !std::io::stderr().is_terminal() {
+        return None;
+    }
+    match dialoguer::Select::new()
+        .with_prompt(prompt)
+        .items(items)
+        .default(0)
+        .interact_opt()
+    {
+        Ok(selection) => selection.map(|index| items[index].clone()),
+        Err(e) => {
+            warn!("Failed to read selection: {}", e);
+            None
+        }
+    }
+}
+
 pub async fn handle_stream<R: AsyncRead + Unpin>(reader: R, sender: Sender<String>) {
     let mut reader = io::BufReader::new(reader);
     let mut line = String::new();
@@ -232,6 +340,8 @@ pub async fn handle_stream<R: AsyncRead + Unpin>(reader: R, sender: Sender<Strin
     }
 }
 
+// Only the subcommand is recorded, as the other arguments may hold secrets
+#[tracing::instrument(skip_all, fields(command = %command, subcommand = args.first().copied().unwrap_or_default()))]
 pub async fn run_command_in_window(
     window_size: usize,
     formatted_label: &str,
@@ -241,9 +351,22 @@ pub async fn run_command_in_window(
     let debug_args = args.join(" ");
     trace!("Running command in window: {} {}", command, debug_args);
 
+    #[cfg(feature = "test-util")]
+    if let Some(runner) = crate::testing::command_runner() {
+        let args = args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        return runner.run(command, &args).await;
+    }
+
+    // Without ANSI support the output is printed line by line instead of in a window
+    let ansi = ansi_output() && !quiet();
+
     // Creating a clear space for the window
-    for _ in 0..=window_size {
-        println!();
+    if ansi {
+        suspend(|| {
+            for _ in 0..=window_size {
+                println!();
+            }
+        });
     }
 
     let debug_args = args.join(" ");
@@ -264,11 +387,28 @@ pub async fn run_command_in_window(
 
     let mut lines = Vec::new();
     let mut lines_in_window = Vec::new();
-    print!("{}", format!("\x1B[?7l"));
+    if ansi {
+        print!("{}", format!("\x1B[?7l"));
+    }
     while let Ok(line) = rx.recv() {
         trace!("Received line: {}", line.trim_end());
         lines.push(line.trim_end().to_string());
 
+        if quiet() {
+            continue;
+        }
+        if !ansi {
+            let clean_line = line.trim_end().replace(['\x1B', '\r', '\n'], "");
+            suspend(|| {
+                println!(
+                    "       {}  |   {}",
+                    formatted_label.bold().color("white"),
+                    clean_line
+                )
+            });
+            continue;
+        }
+
         // Printing the last ten lines
         let skip = if lines.len() < window_size {
             0
@@ -277,15 +417,17 @@ pub async fn run_command_in_window(
         };
 
         lines_in_window = lines.iter().skip(skip).cloned().collect::<Vec<_>>();
-        print!("{}", format!("\r\x1B[{}A", lines_in_window.len()));
-        for line in lines_in_window.iter() {
-            let clean_line = line.trim_end().replace(['\x1B', '\r', '\n'], "");
-            println!(
-                "       {}  |   {}",
-                formatted_label.bold().color("white"),
-                clean_line
-            );
-        }
+        suspend(|| {
+            print!("{}", format!("\r\x1B[{}A", lines_in_window.len()));
+            for line in lines_in_window.iter() {
+                let clean_line = line.trim_end().replace(['\x1B', '\r', '\n'], "");
+                println!(
+                    "       {}  |   {}",
+                    formatted_label.bold().color("white"),
+                    clean_line
+                );
+            }
+        });
     }
 
     let _ = tokio::join!(stdout_task, stderr_task);
@@ -304,12 +446,16 @@ pub async fn run_command_in_window(
         ),
     );
 
-    print!("{}", format!("\r\x1B[{}A", lines_in_window.len()));
-    for _ in lines_in_window.iter() {
-        println!("{}", format!("\r\x1B[2K"));
+    if ansi {
+        suspend(|| {
+            print!("{}", format!("\r\x1B[{}A", lines_in_window.len()));
+            for _ in lines_in_window.iter() {
+                println!("{}", format!("\r\x1B[2K"));
+            }
+            print!("{}", format!("\r\x1B[{}A", lines_in_window.len() + 1));
+            print!("{}", format!("\x1B[?7h"));
+        });
     }
-    print!("{}", format!("\r\x1B[{}A", lines_in_window.len() + 1));
-    print!("{}", format!("\x1B[?7h"));
     if let Some(code) = child.wait().await.unwrap().code() {
         if code != 0 {
             error!("Command failed with exit code: {}", code);
@@ -324,6 +470,22 @@ pub async fn run_command_in_window(
     }
 }
 
+/// Starts a command that keeps running, such as a container, with its output piped.
+pub fn spawn_command(command: &str, args: &[String]) -> std::io::Result<tokio::process::Child> {
+    #[cfg(feature = "test-util")]
+    if let Some(runner) = crate::testing::command_runner() {
+        return runner.spawn(command, args);
+    }
+
+    TokioCommand::new(command)
+        .args(args)
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+}
+
+// Only the subcommand is recorded, as the other arguments may hold secrets
+#[tracing::instrument(skip_all, fields(command = %command, subcommand = args.first().copied().unwrap_or_default()))]
 pub async fn run_command(
     formatted_label: ColoredString,
     command: &str,
@@ -332,6 +494,12 @@ pub async fn run_command(
     let debug_args = args.join(" ");
     trace!("Running command: {} {}", command, debug_args);
 
+    #[cfg(feature = "test-util")]
+    if let Some(runner) = crate::testing::command_runner() {
+        let args = args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        return runner.run(command, &args).await;
+    }
+
     // Settting process up
     let (tx, rx): (Sender<String>, Receiver<String>) = mpsc::channel();
     let mut child = TokioCommand::new(command)
@@ -351,7 +519,9 @@ pub async fn run_command(
         trace!("Received line: {}", line.trim_end());
         lines.push(line.trim_end().to_string());
         let clean_line = line.trim_end().replace(['\x1B', '\r', '\n'], "");
-        println!("       {}  |   {}", formatted_label, clean_line);
+        if !quiet() {
+            suspend(|| println!("       {}  |   {}", formatted_label, clean_line));
+        }
     }
 
     let _ = tokio::join!(stdout_task, stderr_task);
@@ -1,26 +1,24 @@
+use crate::builder::load_stack_spec;
 use crate::dotenv_utils::{load_dotenv, save_dotenv};
+use crate::error::RushError;
 use crate::vault::vault_trait::Vault;
 use async_trait::async_trait;
 use log::warn;
-use serde_yaml::Value;
 use std::collections::HashMap;
 use std::error::Error;
 use std::fs;
 use std::path::PathBuf;
 
+#[derive(Clone)]
 pub struct DotenvVault {
     product_dir: PathBuf,
     components: HashMap<String, PathBuf>,
 }
 
 impl DotenvVault {
-    pub fn new(product_dir: PathBuf) -> Self {
+    pub fn new(product_dir: PathBuf) -> Result<Self, RushError> {
         // TODO: It shouldn't read that here, but rather et it from the config
-        let stack_yaml_path = product_dir.join("stack.spec.yaml");
-        let stack_yaml_content =
-            fs::read_to_string(&stack_yaml_path).expect("Unable to read stack.spec.yaml");
-        let stack_yaml: Value =
-            serde_yaml::from_str(&stack_yaml_content).expect("Unable to parse stack.spec.yaml");
+        let stack_yaml = load_stack_spec(&product_dir).map_err(RushError::Vault)?;
 
         let mut components = HashMap::new();
         if let Some(components_map) = stack_yaml.as_mapping() {
@@ -29,21 +27,27 @@ impl DotenvVault {
                     component_name.as_str(),
                     component_info.get("location").and_then(|v| v.as_str()),
                 ) {
-                    let absolute_path = product_dir.join(location).canonicalize().expect(&format!(
-                        "Failed to get absolute path for component: {}",
-                        component_name
-                    ));
+                    let absolute_path = product_dir.join(location).canonicalize().map_err(|e| {
+                        RushError::Vault(format!(
+                            "Failed to get absolute path for component {}: {}",
+                            component_name, e
+                        ))
+                    })?;
                     components.insert(component_name.to_string(), absolute_path);
                 }
             }
         }
 
-        Self {
-            product_dir: product_dir
-                .canonicalize()
-                .expect("Failed to get absolute path for product_dir"),
+        Ok(Self {
+            product_dir: product_dir.canonicalize().map_err(|e| {
+                RushError::Vault(format!(
+                    "Failed to get absolute path for {}: {}",
+                    product_dir.display(),
+                    e
+                ))
+            })?,
             components,
-        }
+        })
     }
 
     fn get_env_path(&self, component_name: &str) -> Option<PathBuf> {
@@ -55,6 +59,7 @@ impl DotenvVault {
 
 #[async_trait]
 impl Vault for DotenvVault {
+    #[tracing::instrument(name = "vault.get", skip_all, fields(vault = ".env", component = %component_name))]
     async fn get(
         &self,
         _product_name: &str,
@@ -78,6 +83,7 @@ impl Vault for DotenvVault {
         }
     }
 
+    #[tracing::instrument(name = "vault.set", skip_all, fields(vault = ".env", component = %component_name))]
     async fn set(
         &mut self,
         _product_name: &str,
@@ -98,11 +104,16 @@ impl Vault for DotenvVault {
         }
     }
 
-    async fn create_vault(&mut self, _product_name: &str) -> Result<(), Box<dyn Error>> {
+    async fn create_vault(
+        &mut self,
+        _product_name: &str,
+        _environment: &str,
+    ) -> Result<(), Box<dyn Error>> {
         // No-op for dotenv vault
         Ok(())
     }
 
+    #[tracing::instrument(name = "vault.remove", skip_all, fields(vault = ".env", component = %component_name))]
     async fn remove(
         &mut self,
         _product_name: &str,
@@ -124,8 +135,16 @@ impl Vault for DotenvVault {
         }
     }
 
-    async fn check_if_vault_exists(&self, _product_name: &str) -> Result<bool, Box<dyn Error>> {
+    async fn check_if_vault_exists(
+        &self,
+        _product_name: &str,
+        _environment: &str,
+    ) -> Result<bool, Box<dyn Error>> {
         // No-op for dotenv vault
         Ok(true)
     }
+
+    fn boxed_clone(&self) -> Box<dyn Vault + Send + Sync> {
+        Box::new(self.clone())
+    }
 }
@@ -0,0 +1,121 @@
+use crate::error::RushError;
+use crate::vault::Vault;
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::Path;
+
+/// Secrets of each component, by name.
+pub type SecretFixtures = HashMap<String, HashMap<String, String>>;
+
+/// A vault holding secrets in memory, for tests and for running with `--no-vault`. Each
+/// component starts out with its fixtures in every product and environment. What is set
+/// or removed afterwards replaces them and is gone when the vault is dropped.
+#[derive(Clone, Default)]
+pub struct MemoryVault {
+    fixtures: SecretFixtures,
+    secrets: HashMap<(String, String, String), HashMap<String, String>>,
+}
+
+impl MemoryVault {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_fixtures(fixtures: SecretFixtures) -> Self {
+        Self {
+            fixtures,
+            secrets: HashMap::new(),
+        }
+    }
+
+    /// Reads the fixtures from a YAML file mapping each component to its secrets. A
+    /// missing file means no fixtures.
+    pub fn from_file(path: &Path) -> Result<Self, RushError> {
+        if !path.exists() {
+            return Ok(Self::new());
+        }
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| RushError::Vault(format!("Failed to read {}: {}", path.display(), e)))?;
+        let fixtures = serde_yaml::from_str::<Option<SecretFixtures>>(&contents)
+            .map_err(|e| {
+                RushError::Vault(format!("Invalid fixtures in {}: {}", path.display(), e))
+            })?
+            .unwrap_or_default();
+        Ok(Self::with_fixtures(fixtures))
+    }
+
+    fn key(
+        product_name: &str,
+        component_name: &str,
+        environment: &str,
+    ) -> (String, String, String) {
+        (
+            product_name.to_string(),
+            component_name.to_string(),
+            environment.to_string(),
+        )
+    }
+}
+
+#[async_trait]
+impl Vault for MemoryVault {
+    async fn get(
+        &self,
+        product_name: &str,
+        component_name: &str,
+        environment: &str,
+    ) -> Result<HashMap<String, String>, Box<dyn Error>> {
+        let key = Self::key(product_name, component_name, environment);
+        Ok(self
+            .secrets
+            .get(&key)
+            .or_else(|| self.fixtures.get(component_name))
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    async fn set(
+        &mut self,
+        product_name: &str,
+        component_name: &str,
+        environment: &str,
+        secrets: HashMap<String, String>,
+    ) -> Result<(), Box<dyn Error>> {
+        let key = Self::key(product_name, component_name, environment);
+        self.secrets.insert(key, secrets);
+        Ok(())
+    }
+
+    async fn create_vault(
+        &mut self,
+        _product_name: &str,
+        _environment: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+
+    async fn remove(
+        &mut self,
+        product_name: &str,
+        component_name: &str,
+        environment: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        // Kept as empty, so the fixtures do not come back
+        let key = Self::key(product_name, component_name, environment);
+        self.secrets.insert(key, HashMap::new());
+        Ok(())
+    }
+
+    async fn check_if_vault_exists(
+        &self,
+        _product_name: &str,
+        _environment: &str,
+    ) -> Result<bool, Box<dyn Error>> {
+        Ok(true)
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Vault + Send + Sync> {
+        Box::new(self.clone())
+    }
+}
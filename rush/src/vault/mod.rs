@@ -1,11 +1,15 @@
 mod dotenv_vault;
+mod memory_vault;
 mod one_password;
+mod placeholder_vault;
 mod secrets_adapter;
 mod secrets_definitions;
 mod vault_trait;
 
 pub use dotenv_vault::DotenvVault;
+pub use memory_vault::{MemoryVault, SecretFixtures};
 pub use one_password::OnePassword;
+pub use placeholder_vault::PlaceholderVault;
 pub use secrets_adapter::{Base64SecretsEncoder, EncodeSecrets, NoopEncoder};
 pub use secrets_definitions::SecretsDefinitions;
-pub use vault_trait::Vault;
+pub use vault_trait::{get_secrets, Vault};
@@ -6,18 +6,31 @@ use std::collections::HashMap;
 use std::error::Error;
 use std::process::Command;
 
+#[derive(Clone)]
 pub struct OnePassword {
     account: String,
+    /// The vault of each environment, see [`Config::one_password_vault`](crate::builder::Config::one_password_vault)
+    vaults: HashMap<String, String>,
 }
 
 impl OnePassword {
-    pub fn new(account: &str) -> Self {
+    pub fn new(account: &str, vaults: HashMap<String, String>) -> Self {
         trace!("Creating new OnePassword instance");
         OnePassword {
             account: account.to_string(),
+            vaults,
         }
     }
 
+    /// The vault holding the secrets of the environment, named after the product
+    /// unless configured otherwise.
+    fn vault<'a>(&'a self, product_name: &'a str, environment: &str) -> &'a str {
+        self.vaults
+            .get(environment)
+            .map(|vault| vault.as_str())
+            .unwrap_or(product_name)
+    }
+
     fn run_op_command(&self, args: Vec<String>) -> Result<String, Box<dyn Error>> {
         debug!("Running 1Password CLI command with args: {:?}", args);
         let output = Command::new("op").args(&args).output()?;
@@ -39,17 +52,19 @@ impl OnePassword {
 
 #[async_trait]
 impl Vault for OnePassword {
+    #[tracing::instrument(name = "vault.get", skip_all, fields(vault = "1Password", component = %component_name))]
     async fn get(
         &self,
         product_name: &str,
         component_name: &str,
         environment: &str,
     ) -> Result<HashMap<String, String>, Box<dyn Error>> {
+        let vault = self.vault(product_name, environment);
         trace!(
             "Getting secrets for {}-{} in vault {}",
             component_name,
             environment,
-            product_name
+            vault
         );
         let item_name = format!("{}-{}", component_name, environment);
         let output = self.run_op_command(
@@ -60,7 +75,7 @@ impl Vault for OnePassword {
                 "--account",
                 &self.account,
                 "--vault",
-                product_name,
+                vault,
                 "--format",
                 "json",
             ]
@@ -84,6 +99,7 @@ impl Vault for OnePassword {
         Ok(secrets)
     }
 
+    #[tracing::instrument(name = "vault.set", skip_all, fields(vault = "1Password", component = %component_name))]
     async fn set(
         &mut self,
         product_name: &str,
@@ -91,11 +107,12 @@ impl Vault for OnePassword {
         environment: &str,
         secrets: HashMap<String, String>,
     ) -> Result<(), Box<dyn Error>> {
+        let vault = self.vault(product_name, environment).to_string();
         trace!(
             "Setting secrets for {}-{} in vault {}",
             component_name,
             environment,
-            product_name
+            vault
         );
         let item_name = format!("{}-{}", component_name, environment);
 
@@ -107,7 +124,7 @@ impl Vault for OnePassword {
                 "--account",
                 &self.account,
                 "--vault",
-                product_name,
+                &vault,
                 "--format",
                 "json",
             ]
@@ -137,7 +154,7 @@ impl Vault for OnePassword {
         args.push("--account".to_string());
         args.push(self.account.clone());
         args.push("--vault".to_string());
-        args.push(product_name.to_string());
+        args.push(vault);
 
         for (key, value) in &secrets {
             args.push(format!("{}={}", key, value));
@@ -154,8 +171,13 @@ impl Vault for OnePassword {
         Ok(())
     }
 
-    async fn create_vault(&mut self, product_name: &str) -> Result<(), Box<dyn Error>> {
-        trace!("Checking if vault exists: {}", product_name);
+    async fn create_vault(
+        &mut self,
+        product_name: &str,
+        environment: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        let vault_name = self.vault(product_name, environment).to_string();
+        trace!("Checking if vault exists: {}", vault_name);
         let list_args = vec![
             "vault".to_string(),
             "list".to_string(),
@@ -169,37 +191,39 @@ impl Vault for OnePassword {
 
         if vaults
             .iter()
-            .any(|vault| vault["name"].as_str() == Some(product_name))
+            .any(|vault| vault["name"].as_str() == Some(vault_name.as_str()))
         {
-            trace!("Vault '{}' already exists", product_name);
+            trace!("Vault '{}' already exists", vault_name);
             return Ok(());
         }
 
-        trace!("Creating vault: {}", product_name);
+        trace!("Creating vault: {}", vault_name);
         let create_args = vec![
             "vault".to_string(),
             "create".to_string(),
-            product_name.to_string(),
+            vault_name.clone(),
             "--account".to_string(),
             self.account.clone(),
         ];
         let create_output = self.run_op_command(create_args)?;
 
-        trace!("Successfully created vault: {}", product_name);
+        trace!("Successfully created vault: {}", vault_name);
         Ok(())
     }
 
+    #[tracing::instrument(name = "vault.remove", skip_all, fields(vault = "1Password", component = %component_name))]
     async fn remove(
         &mut self,
         product_name: &str,
         component_name: &str,
         environment: &str,
     ) -> Result<(), Box<dyn Error>> {
+        let vault = self.vault(product_name, environment).to_string();
         trace!(
             "Removing secrets for {}-{} in vault {}",
             component_name,
             environment,
-            product_name
+            vault
         );
         let item_name = format!("{}-{}", component_name, environment);
 
@@ -210,7 +234,7 @@ impl Vault for OnePassword {
             "--account".to_string(),
             self.account.clone(),
             "--vault".to_string(),
-            product_name.to_string(),
+            vault,
         ];
         let output = self.run_op_command(args)?;
 
@@ -218,8 +242,13 @@ impl Vault for OnePassword {
         Ok(())
     }
 
-    async fn check_if_vault_exists(&self, product_name: &str) -> Result<bool, Box<dyn Error>> {
-        trace!("Checking if vault exists: {}", product_name);
+    async fn check_if_vault_exists(
+        &self,
+        product_name: &str,
+        environment: &str,
+    ) -> Result<bool, Box<dyn Error>> {
+        let vault_name = self.vault(product_name, environment);
+        trace!("Checking if vault exists: {}", vault_name);
         let list_args = vec![
             "vault".to_string(),
             "list".to_string(),
@@ -233,8 +262,12 @@ impl Vault for OnePassword {
 
         let exists = vaults
             .iter()
-            .any(|vault| vault["name"].as_str() == Some(product_name));
-        trace!("Vault '{}' exists: {}", product_name, exists);
+            .any(|vault| vault["name"].as_str() == Some(vault_name));
+        trace!("Vault '{}' exists: {}", vault_name, exists);
         Ok(exists)
     }
+
+    fn boxed_clone(&self) -> Box<dyn Vault + Send + Sync> {
+        Box::new(self.clone())
+    }
 }
@@ -0,0 +1,77 @@
+use crate::vault::{SecretsDefinitions, Vault};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::error::Error;
+
+/// A vault handing out a fixed placeholder, `placeholder-<component>-<SECRET>`, for each
+/// secret defined in stack.env.secrets.yaml. Manifests rendered with it are the same on
+/// every machine and hold no real secrets. Nothing can be stored in it.
+#[derive(Clone)]
+pub struct PlaceholderVault {
+    definitions: SecretsDefinitions,
+}
+
+impl PlaceholderVault {
+    pub fn new(definitions: SecretsDefinitions) -> Self {
+        Self { definitions }
+    }
+}
+
+#[async_trait]
+impl Vault for PlaceholderVault {
+    async fn get(
+        &self,
+        _product_name: &str,
+        component_name: &str,
+        _environment: &str,
+    ) -> Result<HashMap<String, String>, Box<dyn Error>> {
+        Ok(self
+            .definitions
+            .secret_names(component_name)
+            .into_iter()
+            .map(|name| {
+                let value = format!("placeholder-{}-{}", component_name, name);
+                (name, value)
+            })
+            .collect())
+    }
+
+    async fn set(
+        &mut self,
+        _product_name: &str,
+        _component_name: &str,
+        _environment: &str,
+        _secrets: HashMap<String, String>,
+    ) -> Result<(), Box<dyn Error>> {
+        Err("Secrets cannot be stored in the placeholder vault".into())
+    }
+
+    async fn create_vault(
+        &mut self,
+        _product_name: &str,
+        _environment: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+
+    async fn remove(
+        &mut self,
+        _product_name: &str,
+        _component_name: &str,
+        _environment: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        Err("Secrets cannot be removed from the placeholder vault".into())
+    }
+
+    async fn check_if_vault_exists(
+        &self,
+        _product_name: &str,
+        _environment: &str,
+    ) -> Result<bool, Box<dyn Error>> {
+        Ok(true)
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Vault + Send + Sync> {
+        Box::new(self.clone())
+    }
+}
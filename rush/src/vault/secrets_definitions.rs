@@ -1,3 +1,5 @@
+use crate::builder::shared_file;
+use crate::error::RushError;
 use crate::vault::Vault;
 use base64;
 use chrono::Utc;
@@ -15,6 +17,7 @@ use std::error::Error;
 use std::fs::File;
 use std::io::Read;
 use std::io::Write;
+use std::path::Path;
 use std::sync::Arc;
 use std::sync::Mutex;
 use uuid::Uuid;
@@ -60,45 +63,55 @@ pub enum GenerationResult {
 }
 
 impl SecretsDefinitions {
-    pub fn new(product_name: String, yaml_filename: &str) -> Self {
-        let components = match File::open(yaml_filename) {
+    pub fn new(product_name: String, yaml_filename: &str) -> Result<Self, RushError> {
+        // Secrets shared between products are overridden by the product's own definitions
+        let yaml_path = Path::new(yaml_filename);
+        let shared_path = shared_file(
+            yaml_path.parent().unwrap_or(Path::new(".")),
+            "stack.env.secrets.yaml",
+        );
+        let mut components = HashMap::new();
+        if shared_path.exists() {
+            components = Self::load_components(&shared_path.to_string_lossy())?;
+        }
+        for (component_name, secrets) in Self::load_components(yaml_filename)? {
+            components
+                .entry(component_name)
+                .or_default()
+                .extend(secrets);
+        }
+
+        let components = components
+            .into_iter()
+            .map(|(component_name, secrets)| (component_name, ComponentSecrets { secrets }))
+            .collect();
+
+        Ok(Self {
+            product_name,
+            components,
+        })
+    }
+
+    fn load_components(
+        yaml_filename: &str,
+    ) -> Result<HashMap<String, HashMap<String, GenerationMethod>>, RushError> {
+        match File::open(yaml_filename) {
             Ok(mut file) => {
                 let mut contents = String::new();
-                match file.read_to_string(&mut contents) {
-                    Ok(_) => match serde_yaml::from_str(&contents) {
-                        Ok(parsed_components) => parsed_components,
-                        Err(e) => {
-                            panic!(
-                                "Unable to parse YAML file: {}. Returning empty definition.",
-                                e
-                            );
-                        }
-                    },
-                    Err(e) => {
-                        panic!(
-                            "Unable to read YAML file: {}. Returning empty definition.",
-                            e
-                        );
-                    }
-                }
+                file.read_to_string(&mut contents).map_err(|e| {
+                    RushError::Vault(format!("Unable to read {}: {}", yaml_filename, e))
+                })?;
+                serde_yaml::from_str(&contents).map_err(|e| {
+                    RushError::Vault(format!("Unable to parse {}: {}", yaml_filename, e))
+                })
             }
             Err(e) => {
                 warn!(
                     "Unable to open YAML file '{}': {}. Returning empty definition.",
                     yaml_filename, e
                 );
-                HashMap::new()
+                Ok(HashMap::new())
             }
-        };
-
-        let components = components
-            .into_iter()
-            .map(|(component_name, secrets)| (component_name, ComponentSecrets { secrets }))
-            .collect();
-
-        Self {
-            product_name,
-            components,
         }
     }
 
@@ -124,6 +137,14 @@ impl SecretsDefinitions {
         }
     }
 
+    /// Names of the secrets defined for the component.
+    pub fn secret_names(&self, component_name: &str) -> Vec<String> {
+        self.components
+            .get(component_name)
+            .map(|component| component.secrets.keys().cloned().collect())
+            .unwrap_or_default()
+    }
+
     pub async fn validate_vault(
         &self,
         vault: Arc<Mutex<dyn Vault + Send>>,
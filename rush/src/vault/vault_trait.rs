@@ -2,6 +2,7 @@ use async_trait::async_trait;
 use core::fmt::Debug;
 use std::collections::HashMap;
 use std::error::Error;
+use std::sync::Mutex;
 
 impl Debug for dyn Vault {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
@@ -34,8 +35,12 @@ pub trait Vault {
         secrets: HashMap<String, String>,
     ) -> Result<(), Box<dyn Error>>;
 
-    /// Creates a vault (product) if it does not exist.
-    async fn create_vault(&mut self, product_name: &str) -> Result<(), Box<dyn Error>>;
+    /// Creates the vault of a product's environment if it does not exist.
+    async fn create_vault(
+        &mut self,
+        product_name: &str,
+        environment: &str,
+    ) -> Result<(), Box<dyn Error>>;
 
     /// Removes secrets from the vault for a specific product, component, and environment.
     async fn remove(
@@ -45,6 +50,26 @@ pub trait Vault {
         environment: &str,
     ) -> Result<(), Box<dyn Error>>;
 
-    /// Checks if a vault (product) exists.
-    async fn check_if_vault_exists(&self, product_name: &str) -> Result<bool, Box<dyn Error>>;
+    /// Checks if the vault of a product's environment exists.
+    async fn check_if_vault_exists(
+        &self,
+        product_name: &str,
+        environment: &str,
+    ) -> Result<bool, Box<dyn Error>>;
+
+    /// A copy of the vault, which secrets can be read from once the lock of a shared
+    /// vault is released.
+    fn boxed_clone(&self) -> Box<dyn Vault + Send + Sync>;
+}
+
+/// Retrieves secrets from a vault shared behind a lock, which is released before they
+/// are fetched, so other tasks can use the vault meanwhile.
+pub async fn get_secrets(
+    vault: &Mutex<dyn Vault + Send>,
+    product_name: &str,
+    component_name: &str,
+    environment: &str,
+) -> Result<HashMap<String, String>, Box<dyn Error>> {
+    let vault = vault.lock().unwrap().boxed_clone();
+    vault.get(product_name, component_name, environment).await
 }
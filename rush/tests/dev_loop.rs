@@ -0,0 +1,662 @@
+//! The dev loop of `rush dev` against a mock docker.
+
+use rush_core::cluster::Minikube;
+use rush_core::container::{BuildProfiler, ProfileStep};
+use rush_core::testing::{set_command_runner, MockRunner, Response, TestProduct};
+use rush_core::toolchain::{Platform, ToolchainContext};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+// The command runner and the environment are global, so one test runs at a time
+static SERIAL: Mutex<()> = Mutex::const_new(());
+
+// Listed in reverse, so the order of the spec does not start them in the right order
+const STACK_SPEC: &str = r#"
+api:
+  build_type: "Image"
+  image: "busybox:1"
+  depends_on:
+    - cache
+
+cache:
+  build_type: "Image"
+  image: "redis:7"
+  depends_on:
+    - database
+
+database:
+  build_type: "Image"
+  image: "postgres:16"
+"#;
+
+fn position(runner: &MockRunner, pattern: &str) -> usize {
+    runner
+        .calls()
+        .iter()
+        .position(|call| call.line().starts_with("docker run") && call.line().contains(pattern))
+        .unwrap_or_else(|| panic!("No container of {} was started", pattern))
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn starts_dependencies_first() {
+    let _serial = SERIAL.lock().await;
+    let runner = MockRunner::new();
+    runner.on("busybox:1", Response::Process("sleep 1".to_string()));
+    set_command_runner(runner.clone() as Arc<_>);
+
+    let product = TestProduct::new(STACK_SPEC);
+    product.reactor().launch().await.unwrap();
+
+    let database = position(&runner, "postgres:16");
+    let cache = position(&runner, "redis:7");
+    let api = position(&runner, "busybox:1");
+    assert!(database < cache, "cache was started before the database");
+    assert!(cache < api, "api was started before the cache");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn rejects_dependency_cycles() {
+    let _serial = SERIAL.lock().await;
+    let product = TestProduct::new(&STACK_SPEC.replace(
+        "  image: \"postgres:16\"",
+        "  image: \"postgres:16\"\n  depends_on:\n    - api",
+    ));
+
+    let error = product.try_reactor().err().expect("The cycle was accepted");
+    assert_eq!(
+        error.to_string(),
+        "Invalid component api: depends_on forms a cycle: api -> cache -> database -> api"
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn attaches_network_aliases_and_extra_networks() {
+    let _serial = SERIAL.lock().await;
+    let runner = MockRunner::new();
+    runner.on("busybox:1", Response::Process("sleep 1".to_string()));
+    set_command_runner(runner.clone() as Arc<_>);
+
+    let product = TestProduct::new(&STACK_SPEC.replace(
+        "  image: \"postgres:16\"",
+        "  image: \"postgres:16\"\n  network_aliases:\n    - db\n  extra_networks:\n    - shared",
+    ));
+    product.reactor().launch().await.unwrap();
+
+    let database = runner.calls()[position(&runner, "postgres:16")].line();
+    assert!(
+        database.contains("--network name=net-test-example-io,alias=db --network shared"),
+        "{}",
+        database
+    );
+
+    runner.on(
+        "docker network inspect shared",
+        Response::Fail(String::new()),
+    );
+    let error = product.reactor().launch().await.unwrap_err();
+    assert!(error.contains("docker network create shared"), "{}", error);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn creates_and_mounts_named_volumes() {
+    let _serial = SERIAL.lock().await;
+    let runner = MockRunner::new();
+    runner.on("busybox:1", Response::Process("sleep 1".to_string()));
+    runner.on("docker volume inspect", Response::Fail(String::new()));
+    set_command_runner(runner.clone() as Arc<_>);
+
+    let stack_spec = STACK_SPEC.replace(
+        "  image: \"postgres:16\"",
+        "  image: \"postgres:16\"\n  volumes:\n    pgdata: /var/lib/postgresql/data",
+    );
+    let product = TestProduct::new(&stack_spec);
+    let error = product
+        .try_reactor()
+        .err()
+        .expect("An undeclared volume was accepted");
+    assert!(error.to_string().contains("volume pgdata is not declared"));
+
+    product.write("stack.volumes.yaml", "pgdata: {}\n");
+    product.reactor().launch().await.unwrap();
+
+    assert_eq!(
+        runner.calls_matching("docker volume create")[0].line(),
+        "docker volume create --label rush.io/product=test-example-io test-example-io_pgdata"
+    );
+    let database = runner.calls()[position(&runner, "postgres:16")].line();
+    assert!(
+        database.contains("-v test-example-io_pgdata:/var/lib/postgresql/data"),
+        "{}",
+        database
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn removes_all_containers_when_one_exits() {
+    let _serial = SERIAL.lock().await;
+    let runner = MockRunner::new();
+    runner.on("docker ps", Response::Output("0123456789ab".to_string()));
+    runner.on(
+        "busybox:1",
+        Response::Process("echo done; exit 3".to_string()),
+    );
+    set_command_runner(runner.clone() as Arc<_>);
+
+    let product = TestProduct::new(STACK_SPEC);
+    product.reactor().launch().await.unwrap();
+
+    let calls = runner.calls();
+    let exited = position(&runner, "busybox:1");
+    let after_exit = calls[exited..]
+        .iter()
+        .map(|call| call.line())
+        .collect::<Vec<_>>();
+    for component in ["database", "cache", "api"] {
+        assert!(
+            after_exit
+                .iter()
+                .any(|line| line.starts_with("docker rm -f") && line.ends_with(component)),
+            "The container of {} was not removed: {:#?}",
+            component,
+            after_exit
+        );
+    }
+    assert!(after_exit
+        .iter()
+        .any(|line| line.starts_with("docker network rm")));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn restarts_only_for_changes_in_the_context() {
+    let _serial = SERIAL.lock().await;
+    let runner = MockRunner::new();
+    set_command_runner(runner.clone() as Arc<_>);
+
+    let product = TestProduct::new(
+        r#"
+app:
+  build_type: "Script"
+  location: "app"
+  dockerfile: "app/Dockerfile"
+
+database:
+  build_type: "Image"
+  image: "postgres:16"
+"#,
+    );
+    product.write("app/Dockerfile", "FROM scratch\n");
+    product.write("app/build.sh", "echo build\n");
+    product.write("target/build.log", "\n");
+    product.commit();
+
+    // Changes files from a thread of its own, as the dev loop blocks the test's
+    let product_path = product.path();
+    let changes = {
+        let runner = runner.clone();
+        std::thread::spawn(move || {
+            let started = || {
+                runner
+                    .calls_matching("test.example.io-app:")
+                    .iter()
+                    .filter(|call| call.line().starts_with("docker run"))
+                    .count()
+            };
+            while started() == 0 {
+                std::thread::sleep(Duration::from_millis(50));
+            }
+            std::fs::write(product_path.join("target/build.log"), "changed\n").unwrap();
+            std::thread::sleep(Duration::from_secs(1));
+            let started_after_unrelated_change = started();
+
+            std::fs::write(product_path.join("app/build.sh"), "echo changed\n").unwrap();
+            for _ in 0..300 {
+                if started() > started_after_unrelated_change {
+                    break;
+                }
+                std::thread::sleep(Duration::from_millis(100));
+            }
+            (started_after_unrelated_change, started())
+        })
+    };
+
+    // The dev loop runs until it is interrupted, so it is dropped once the thread is done
+    let mut reactor = product.reactor();
+    let (after_unrelated_change, after_change) = tokio::select! {
+        result = reactor.launch() => panic!("The dev loop ended: {:?}", result),
+        changes = tokio::task::spawn_blocking(move || changes.join()) => changes.unwrap().unwrap(),
+    };
+    assert_eq!(
+        after_unrelated_change, 1,
+        "A change outside the context restarted app"
+    );
+    assert_eq!(after_change, 2, "A change to app did not restart it");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn restarts_for_files_in_new_directories() {
+    let _serial = SERIAL.lock().await;
+    let runner = MockRunner::new();
+    set_command_runner(runner.clone() as Arc<_>);
+
+    let product = TestProduct::new(
+        r#"
+app:
+  build_type: "Script"
+  location: "app"
+  dockerfile: "app/Dockerfile"
+"#,
+    );
+    product.write("app/Dockerfile", "FROM scratch\n");
+    product.write("app/build.sh", "echo build\n");
+    product.commit();
+
+    let product_path = product.path();
+    let changes = {
+        let runner = runner.clone();
+        std::thread::spawn(move || {
+            let started = || {
+                runner
+                    .calls_matching("test.example.io-app:")
+                    .iter()
+                    .filter(|call| call.line().starts_with("docker run"))
+                    .count()
+            };
+            while started() == 0 {
+                std::thread::sleep(Duration::from_millis(50));
+            }
+            // Each directory has a watch of its own, so one created later needs one too
+            std::fs::create_dir_all(product_path.join("app/src/handlers")).unwrap();
+            std::thread::sleep(Duration::from_secs(1));
+            std::fs::write(product_path.join("app/src/handlers/api.sh"), "echo api\n").unwrap();
+            for _ in 0..300 {
+                if started() > 1 {
+                    break;
+                }
+                std::thread::sleep(Duration::from_millis(100));
+            }
+            started()
+        })
+    };
+
+    let mut reactor = product.reactor();
+    let started = tokio::select! {
+        result = reactor.launch() => panic!("The dev loop ended: {:?}", result),
+        changes = tokio::task::spawn_blocking(move || changes.join()) => changes.unwrap().unwrap(),
+    };
+    assert_eq!(started, 2, "A file in a new directory did not restart app");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn starts_unchanged_components_from_the_previous_images() {
+    let _serial = SERIAL.lock().await;
+    let product = TestProduct::new(
+        r#"
+app:
+  build_type: "Script"
+  location: "app"
+  dockerfile: "app/Dockerfile"
+
+worker:
+  build_type: "Script"
+  location: "worker"
+  dockerfile: "worker/Dockerfile"
+"#,
+    );
+    for component in ["app", "worker"] {
+        product.write(&format!("{}/Dockerfile", component), "FROM scratch\n");
+        product.write(&format!("{}/build.sh", component), "echo build\n");
+    }
+    product.commit();
+    let builds = |runner: &MockRunner, component: &str| {
+        runner
+            .calls_matching(&format!("test.example.io-{}:", component))
+            .iter()
+            .filter(|call| call.line().starts_with("docker build"))
+            .count()
+    };
+
+    let runner = MockRunner::new();
+    // The dev loop ends once a container exits
+    runner.on("docker run", Response::Process("sleep 1".to_string()));
+    set_command_runner(runner.clone() as Arc<_>);
+    let mut reactor = product.reactor();
+    reactor.enable_warm_start(false);
+    reactor.launch().await.unwrap();
+    assert_eq!(builds(&runner, "app"), 1);
+    assert_eq!(builds(&runner, "worker"), 1);
+
+    product.write("worker/build.sh", "echo changed\n");
+    let runner = MockRunner::new();
+    runner.on("docker run", Response::Process("sleep 1".to_string()));
+    set_command_runner(runner.clone() as Arc<_>);
+    let mut reactor = product.reactor();
+    reactor.enable_warm_start(false);
+    reactor.launch().await.unwrap();
+    assert_eq!(builds(&runner, "app"), 0, "The unchanged app was rebuilt");
+    assert_eq!(
+        builds(&runner, "worker"),
+        1,
+        "The changed worker was not rebuilt"
+    );
+    // Panics unless app was started from its previous image
+    position(&runner, "test.example.io-app:");
+
+    let runner = MockRunner::new();
+    runner.on("docker run", Response::Process("sleep 1".to_string()));
+    set_command_runner(runner.clone() as Arc<_>);
+    let mut reactor = product.reactor();
+    reactor.enable_warm_start(true);
+    reactor.launch().await.unwrap();
+    assert_eq!(builds(&runner, "app"), 1, "--rebuild did not rebuild app");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn restarts_for_a_changed_environment_without_a_rebuild() {
+    let _serial = SERIAL.lock().await;
+    let runner = MockRunner::new();
+    set_command_runner(runner.clone() as Arc<_>);
+
+    let product = TestProduct::new(
+        r#"
+app:
+  build_type: "Script"
+  location: "app"
+  dockerfile: "app/Dockerfile"
+
+database:
+  build_type: "Image"
+  image: "postgres:16"
+"#,
+    );
+    product.write("app/Dockerfile", "FROM scratch\n");
+    product.write("app/build.sh", "echo build\n");
+    product.write("app/.env", "GREETING=hello\n");
+    product.commit();
+
+    let product_path = product.path();
+    let restarted = {
+        let runner = runner.clone();
+        std::thread::spawn(move || {
+            let started = || {
+                runner
+                    .calls_matching("test.example.io-app:")
+                    .iter()
+                    .filter(|call| call.line().starts_with("docker run"))
+                    .count()
+            };
+            while started() == 0 {
+                std::thread::sleep(Duration::from_millis(50));
+            }
+            std::fs::write(product_path.join("app/.env"), "GREETING=hi\n").unwrap();
+            for _ in 0..300 {
+                if started() > 1 {
+                    break;
+                }
+                std::thread::sleep(Duration::from_millis(100));
+            }
+        })
+    };
+
+    let mut reactor = product.reactor();
+    tokio::select! {
+        result = reactor.launch() => panic!("The dev loop ended: {:?}", result),
+        restarted = tokio::task::spawn_blocking(move || restarted.join()) => restarted.unwrap().unwrap(),
+    };
+    let count = |pattern: &str, command: &str| {
+        runner
+            .calls_matching(pattern)
+            .iter()
+            .filter(|call| call.line().starts_with(command))
+            .count()
+    };
+    let app_runs = runner
+        .calls_matching("test.example.io-app:")
+        .into_iter()
+        .filter(|call| call.line().starts_with("docker run"))
+        .collect::<Vec<_>>();
+    assert_eq!(app_runs.len(), 2, "The new environment did not restart app");
+    assert!(app_runs[1].line().contains("GREETING=hi"));
+    assert_eq!(
+        count("test.example.io-app:", "docker build"),
+        1,
+        "app was rebuilt"
+    );
+    assert_eq!(
+        count("postgres:16", "docker run"),
+        1,
+        "database was restarted"
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn reconciles_the_components_with_a_changed_stack_spec() {
+    let _serial = SERIAL.lock().await;
+    let runner = MockRunner::new();
+    set_command_runner(runner.clone() as Arc<_>);
+
+    let app = r#"
+app:
+  build_type: "Script"
+  location: "app"
+  dockerfile: "app/Dockerfile"
+"#;
+    let product = TestProduct::new(&format!(
+        "{}{}",
+        app,
+        r#"
+cache:
+  build_type: "Image"
+  image: "redis:7"
+
+database:
+  build_type: "Image"
+  image: "postgres:16"
+"#
+    ));
+    product.write("app/Dockerfile", "FROM scratch\n");
+    product.write("app/build.sh", "echo build\n");
+    product.commit();
+
+    let product_path = product.path();
+    let reloaded = {
+        let runner = runner.clone();
+        std::thread::spawn(move || {
+            let started = |pattern: &str| {
+                runner
+                    .calls_matching(pattern)
+                    .iter()
+                    .any(|call| call.line().starts_with("docker run"))
+            };
+            while !started("postgres:16") {
+                std::thread::sleep(Duration::from_millis(50));
+            }
+            let stack_spec = format!(
+                "{}{}",
+                app,
+                r#"
+database:
+  build_type: "Image"
+  image: "postgres:17"
+
+worker:
+  build_type: "Image"
+  image: "busybox:1"
+"#
+            );
+            std::fs::write(product_path.join("stack.spec.yaml"), stack_spec).unwrap();
+            for _ in 0..300 {
+                if started("postgres:17") && started("busybox:1") {
+                    break;
+                }
+                std::thread::sleep(Duration::from_millis(100));
+            }
+        })
+    };
+
+    let mut reactor = product.reactor();
+    tokio::select! {
+        result = reactor.launch() => panic!("The dev loop ended: {:?}", result),
+        reloaded = tokio::task::spawn_blocking(move || reloaded.join()) => reloaded.unwrap().unwrap(),
+    };
+    let count = |pattern: &str, command: &str| {
+        runner
+            .calls_matching(pattern)
+            .iter()
+            .filter(|call| call.line().starts_with(command))
+            .count()
+    };
+    assert_eq!(
+        count("postgres:17", "docker run"),
+        1,
+        "database was not reconfigured"
+    );
+    assert_eq!(count("busybox:1", "docker run"), 1, "worker was not added");
+    assert_eq!(count("redis:7", "docker run"), 1, "cache was restarted");
+    assert!(
+        count("name=test.example.io-cache", "docker ps") > 0,
+        "cache was not stopped"
+    );
+    assert_eq!(
+        count("test.example.io-app:", "docker run"),
+        1,
+        "The unchanged app was restarted"
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn profiles_the_steps_of_a_rebuild() {
+    let _serial = SERIAL.lock().await;
+    let runner = MockRunner::new();
+    set_command_runner(runner.clone() as Arc<_>);
+
+    let product = TestProduct::new(
+        r#"
+app:
+  build_type: "Script"
+  location: "app"
+  dockerfile: "app/Dockerfile"
+"#,
+    );
+    product.write("app/Dockerfile", "FROM scratch\n");
+    product.write("app/build.sh", "echo build\n");
+    product.commit();
+
+    let profiler = BuildProfiler::default();
+    let mut reactor = product.reactor();
+    reactor.set_profiler(profiler.clone());
+
+    let product_path = product.path();
+    let rebuilt = {
+        let profiler = profiler.clone();
+        std::thread::spawn(move || {
+            let finished = |count: usize| {
+                let rebuilds = profiler.rebuilds();
+                rebuilds.len() == count && rebuilds[count - 1].latency_ms.is_some()
+            };
+            for _ in 0..300 {
+                if finished(1) {
+                    break;
+                }
+                std::thread::sleep(Duration::from_millis(100));
+            }
+            std::fs::write(product_path.join("app/build.sh"), "echo changed\n").unwrap();
+            for _ in 0..300 {
+                if finished(2) {
+                    break;
+                }
+                std::thread::sleep(Duration::from_millis(100));
+            }
+        })
+    };
+
+    tokio::select! {
+        result = reactor.launch() => panic!("The dev loop ended: {:?}", result),
+        rebuilt = tokio::task::spawn_blocking(move || rebuilt.join()) => rebuilt.unwrap().unwrap(),
+    };
+
+    let rebuilds = profiler.rebuilds();
+    assert_eq!(rebuilds.len(), 2, "The change was not profiled");
+    let rebuild = &rebuilds[1];
+    assert_eq!(rebuild.changed, vec!["app".to_string()]);
+    assert!(rebuild.latency_ms.is_some());
+    let steps = rebuild
+        .steps
+        .iter()
+        .map(|step| (step.component.as_deref(), step.step))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        steps,
+        vec![
+            (None, ProfileStep::Stop),
+            (Some("app"), ProfileStep::DockerBuild),
+            (None, ProfileStep::Manifests),
+            (Some("app"), ProfileStep::ContainerStart),
+        ]
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn deletes_removed_components_from_the_cluster() {
+    let _serial = SERIAL.lock().await;
+    let runner = MockRunner::new();
+    set_command_runner(runner.clone() as Arc<_>);
+
+    let component = |name: &str| {
+        format!(
+            r#"
+{name}:
+  build_type: "Image"
+  image: "busybox:1"
+  k8s: "{name}/infrastructure"
+"#
+        )
+    };
+    let product = TestProduct::new(&format!("{}{}", component("api"), component("worker")));
+    for name in ["api", "worker"] {
+        product.write(
+            &format!("{}/infrastructure/deployment.yaml", name),
+            &format!("kind: Deployment\nmetadata:\n  name: {}\n", name),
+        );
+    }
+    product.commit();
+
+    let product_path = product.path();
+    let render_dir = product_path.join("target/k8s/100_worker");
+    let deleted = |runner: &MockRunner| {
+        runner
+            .calls_matching("k8s/100_worker/deployment.yaml")
+            .iter()
+            .any(|call| call.line().starts_with("kubectl --context test delete"))
+    };
+    let removed = {
+        let runner = runner.clone();
+        let stack_spec = component("api");
+        let render_dir = render_dir.clone();
+        std::thread::spawn(move || {
+            while !render_dir.exists() || runner.calls_matching("kubectl").is_empty() {
+                std::thread::sleep(Duration::from_millis(50));
+            }
+            std::fs::write(product_path.join("stack.spec.yaml"), stack_spec).unwrap();
+            for _ in 0..300 {
+                if deleted(&runner) {
+                    break;
+                }
+                std::thread::sleep(Duration::from_millis(100));
+            }
+        })
+    };
+
+    let mut reactor = product.reactor();
+    let toolchain = Arc::new(ToolchainContext::stub(
+        Platform::default(),
+        Platform::default(),
+    ));
+    let cluster = Minikube::new(toolchain);
+    tokio::select! {
+        result = reactor.run_in_cluster(&cluster) => panic!("The dev loop ended: {:?}", result),
+        removed = tokio::task::spawn_blocking(move || removed.join()) => removed.unwrap().unwrap(),
+    };
+    assert!(deleted(&runner), "worker was not deleted from the cluster");
+    assert!(!render_dir.exists(), "The manifests of worker were kept");
+}